@@ -58,6 +58,8 @@ void setup() {
 // The format is as follows:
 // time, flow rate fuel, flow rate oxi, pulse count fuel, pulse count oxi,
 // desired position fuel, desired position oxi, is_emergency
+// Firmware with closed-loop flow control may append three optional fields:
+// controller error, controller integrator, controller output
 void loop() {
   currentMillis = millis();
 
@@ -0,0 +1,190 @@
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::thread;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::applog::diag;
+
+/// Events worth an operator's attention when they're watching the stand, not the screen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlertEvent {
+    Disconnect,
+    Emergency,
+    Redline,
+    Dropout,
+}
+
+impl AlertEvent {
+    pub fn title(&self) -> &'static str {
+        match self {
+            AlertEvent::Disconnect => "Serial link lost",
+            AlertEvent::Emergency => "Firmware emergency",
+            AlertEvent::Redline => "Redline tripped",
+            AlertEvent::Dropout => "Telemetry dropout",
+        }
+    }
+}
+
+/// Sounds and notifications raised for each alert event.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AlertSettings {
+    /// Raise an OS desktop notification for every alert
+    pub desktop_notifications: bool,
+    /// Sound files played per event; none plays nothing
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub disconnect_sound: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub emergency_sound: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redline_sound: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dropout_sound: Option<PathBuf>,
+    /// Seconds without telemetry on a live link before a dropout alert
+    pub dropout_s: f64,
+}
+
+impl Default for AlertSettings {
+    fn default() -> Self {
+        Self {
+            desktop_notifications: true,
+            disconnect_sound: None,
+            emergency_sound: None,
+            redline_sound: None,
+            dropout_sound: None,
+            dropout_s: 2.0,
+        }
+    }
+}
+
+impl AlertSettings {
+    fn sound(&self, event: AlertEvent) -> Option<&Path> {
+        match event {
+            AlertEvent::Disconnect => self.disconnect_sound.as_deref(),
+            AlertEvent::Emergency => self.emergency_sound.as_deref(),
+            AlertEvent::Redline => self.redline_sound.as_deref(),
+            AlertEvent::Dropout => self.dropout_sound.as_deref(),
+        }
+    }
+}
+
+/// Watches the link and telemetry timing, and raises alerts through the OS.
+pub struct Alerter {
+    // Whether the serial link was up at the last check; None without a link
+    link_up: Option<bool>,
+    last_data: Instant,
+    dropout_alerted: bool,
+}
+
+impl Default for Alerter {
+    fn default() -> Self {
+        Self {
+            link_up: None,
+            last_data: Instant::now(),
+            dropout_alerted: false,
+        }
+    }
+}
+
+impl Alerter {
+    /// Plays the event's sound and raises a desktop notification, without waiting for either.
+    pub fn raise(&self, settings: &AlertSettings, event: AlertEvent, detail: &str) {
+        diag!("Alert: {}: {}", event.title(), detail);
+        if let Some(sound) = settings.sound(event) {
+            spawn(play_sound_command(sound));
+        }
+        if settings.desktop_notifications {
+            spawn(notification_command(event.title(), detail));
+        }
+    }
+
+    pub fn data_received(&mut self) {
+        self.last_data = Instant::now();
+        self.dropout_alerted = false;
+    }
+
+    /// Called every frame with the link state; an operator disconnect passes None
+    /// and raises nothing.
+    pub fn watch(&mut self, settings: &AlertSettings, link_up: Option<bool>) {
+        match (self.link_up, link_up) {
+            (Some(true), Some(false)) => {
+                self.raise(
+                    settings,
+                    AlertEvent::Disconnect,
+                    "Reconnecting to the serial port",
+                );
+            }
+            // A fresh link gets a full dropout interval to start streaming
+            (previous, Some(true)) if previous != Some(true) => self.data_received(),
+            _ => {}
+        }
+        self.link_up = link_up;
+
+        let silent = self.last_data.elapsed().as_secs_f64();
+        if link_up == Some(true)
+            && settings.dropout_s > 0.0
+            && silent > settings.dropout_s
+            && !self.dropout_alerted
+        {
+            self.dropout_alerted = true;
+            self.raise(
+                settings,
+                AlertEvent::Dropout,
+                &format!("No telemetry for {:.1} s", silent),
+            );
+        }
+    }
+}
+
+fn play_sound_command(path: &Path) -> Command {
+    let mut command;
+    if cfg!(target_os = "macos") {
+        command = Command::new("afplay");
+        command.arg(path);
+    } else if cfg!(target_os = "windows") {
+        command = Command::new("powershell");
+        command.args(["-NoProfile", "-Command"]).arg(format!(
+            "(New-Object Media.SoundPlayer '{}').PlaySync()",
+            path.display().to_string().replace('\'', "''")
+        ));
+    } else {
+        command = Command::new("paplay");
+        command.arg(path);
+    }
+    command
+}
+
+fn notification_command(title: &str, body: &str) -> Command {
+    let mut command;
+    if cfg!(target_os = "macos") {
+        let escape = |s: &str| s.replace('\\', "\\\\").replace('"', "\\\"");
+        command = Command::new("osascript");
+        command.arg("-e").arg(format!(
+            "display notification \"{}\" with title \"{}\"",
+            escape(body),
+            escape(title)
+        ));
+    } else {
+        // libnotify; elsewhere the spawn fails and the alert is only logged
+        command = Command::new("notify-send");
+        command.args(["--urgency=critical", title, body]);
+    }
+    command
+}
+
+/// Runs an alert command in the background, reaping it when it exits.
+fn spawn(mut command: Command) {
+    let child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    match child {
+        Ok(mut child) => {
+            thread::spawn(move || child.wait());
+        }
+        Err(e) => diag!("Failed to run {:?}: {}", command.get_program(), e),
+    }
+}
@@ -0,0 +1,36 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+static APP_LOG: OnceLock<Mutex<File>> = OnceLock::new();
+
+/// Starts mirroring diagnostics into app.log inside the session log directory.
+pub fn init(log_dir: &Path) -> std::io::Result<()> {
+    let file = File::create(log_dir.join("app.log"))?;
+    let _ = APP_LOG.set(Mutex::new(file));
+    Ok(())
+}
+
+/// Writes a timestamped diagnostic line to stderr and, once initialized, app.log.
+pub fn write(message: std::fmt::Arguments) {
+    let line = format!(
+        "[{}] {}",
+        chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+        message
+    );
+    eprintln!("{}", line);
+    if let Some(file) = APP_LOG.get() {
+        let mut file = file.lock().unwrap();
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+/// Logs a diagnostic message, like eprintln! but timestamped and saved with the session.
+macro_rules! diag {
+    ($($arg:tt)*) => {
+        $crate::applog::write(format_args!($($arg)*))
+    };
+}
+
+pub(crate) use diag;
@@ -0,0 +1,191 @@
+use std::fmt;
+use std::time::Instant;
+
+use serde::{Deserialize, Serialize};
+
+use crate::applog::diag;
+
+/// Why and when the abort latched.
+#[derive(Debug, Clone)]
+pub struct AbortRecord {
+    pub reason: String,
+    pub at: Instant,
+}
+
+/// Where the test stand is in its arming sequence.
+///
+/// Valves can only be opened while ARMED or FIRING; an abort latches until the
+/// operator resets to SAFE, and arming again is a separate, deliberate step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum ArmState {
+    Safe,
+    Armed,
+    Firing,
+    Aborted,
+}
+
+impl fmt::Display for ArmState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ArmState::Safe => "SAFE",
+            ArmState::Armed => "ARMED",
+            ArmState::Firing => "FIRING",
+            ArmState::Aborted => "ABORTED",
+        })
+    }
+}
+
+/// Arming state machine that every valve command goes through.
+pub struct Arming {
+    state: ArmState,
+    since: Instant,
+    aborted: Option<AbortRecord>,
+    // Number of aborts this session
+    abort_count: u32,
+}
+
+impl Default for Arming {
+    fn default() -> Self {
+        Self {
+            state: ArmState::Safe,
+            since: Instant::now(),
+            aborted: None,
+            abort_count: 0,
+        }
+    }
+}
+
+impl Arming {
+    pub fn state(&self) -> ArmState {
+        self.state
+    }
+
+    /// When the current state was entered.
+    pub fn since(&self) -> Instant {
+        self.since
+    }
+
+    /// The latched abort, while ABORTED.
+    pub fn abort_record(&self) -> Option<&AbortRecord> {
+        self.aborted.as_ref()
+    }
+
+    pub fn abort_count(&self) -> u32 {
+        self.abort_count
+    }
+
+    /// SAFE -> ARMED.
+    pub fn arm(&mut self) -> Result<ArmState, String> {
+        match self.state {
+            ArmState::Safe => Ok(self.enter(ArmState::Armed)),
+            state => Err(format!("Can't arm while {}", state)),
+        }
+    }
+
+    /// ARMED -> SAFE; valves must be closed first.
+    pub fn disarm(&mut self) -> Result<ArmState, String> {
+        match self.state {
+            ArmState::Armed => Ok(self.enter(ArmState::Safe)),
+            state => Err(format!("Can't disarm while {}", state)),
+        }
+    }
+
+    /// Whether a valve command with any valve open may be sent.
+    pub fn permits(&self, any_open: bool) -> Result<(), String> {
+        match self.state {
+            _ if !any_open => Ok(()),
+            ArmState::Armed | ArmState::Firing => Ok(()),
+            ArmState::Safe => Err("SAFE: arm before opening valves".to_string()),
+            ArmState::Aborted => Err("ABORTED: reset and arm before opening valves".to_string()),
+        }
+    }
+
+    /// Records an accepted valve command: opening moves ARMED to FIRING and
+    /// closing both moves FIRING back to ARMED. Returns the new state on a transition.
+    pub fn valves_commanded(&mut self, any_open: bool) -> Option<ArmState> {
+        match (self.state, any_open) {
+            (ArmState::Armed, true) => Some(self.enter(ArmState::Firing)),
+            (ArmState::Firing, false) => Some(self.enter(ArmState::Armed)),
+            _ => None,
+        }
+    }
+
+    /// Any state -> ABORTED. Returns false if already aborted.
+    pub fn abort(&mut self, reason: &str) -> bool {
+        if self.state == ArmState::Aborted {
+            return false;
+        }
+        diag!("ABORT: {}", reason);
+        self.aborted = Some(AbortRecord {
+            reason: reason.to_string(),
+            at: Instant::now(),
+        });
+        self.abort_count += 1;
+        self.enter(ArmState::Aborted);
+        true
+    }
+
+    /// ABORTED -> SAFE, clearing the latch.
+    pub fn reset(&mut self) -> Result<ArmState, String> {
+        if self.state != ArmState::Aborted {
+            return Err(format!("Nothing to reset while {}", self.state));
+        }
+        if let Some(record) = self.aborted.take() {
+            diag!(
+                "Reset {:.1} s after abort ({})",
+                record.at.elapsed().as_secs_f64(),
+                record.reason
+            );
+        }
+        Ok(self.enter(ArmState::Safe))
+    }
+
+    fn enter(&mut self, state: ArmState) -> ArmState {
+        diag!("Arming: {} -> {}", self.state, state);
+        self.state = state;
+        self.since = Instant::now();
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn arms_fires_and_disarms() {
+        let mut arming = Arming::default();
+        assert!(arming.permits(true).is_err());
+        assert!(arming.disarm().is_err());
+        assert_eq!(arming.arm(), Ok(ArmState::Armed));
+        assert!(arming.arm().is_err());
+        assert_eq!(arming.valves_commanded(true), Some(ArmState::Firing));
+        assert!(arming.disarm().is_err(), "valves still open");
+        assert_eq!(arming.valves_commanded(false), Some(ArmState::Armed));
+        assert_eq!(arming.valves_commanded(false), None);
+        assert_eq!(arming.disarm(), Ok(ArmState::Safe));
+    }
+
+    #[test]
+    fn abort_latches_until_reset() {
+        let mut arming = Arming::default();
+        arming.arm().unwrap();
+        arming.valves_commanded(true);
+        assert!(arming.abort("Operator abort"));
+        assert!(!arming.abort("Redline"), "already aborted");
+        assert_eq!(arming.state(), ArmState::Aborted);
+        assert_eq!(arming.abort_record().unwrap().reason, "Operator abort");
+        assert_eq!(arming.abort_count(), 1);
+
+        assert!(arming.permits(true).is_err());
+        assert!(arming.permits(false).is_ok(), "closing is always allowed");
+        assert!(arming.arm().is_err());
+        assert_eq!(arming.valves_commanded(true), None);
+
+        assert_eq!(arming.reset(), Ok(ArmState::Safe));
+        assert!(arming.abort_record().is_none());
+        assert!(arming.permits(true).is_err(), "reset doesn't arm");
+        assert!(arming.reset().is_err());
+    }
+}
@@ -0,0 +1,269 @@
+//! Per-meter flow calibration and the units flows are displayed in.
+//!
+//! Calibration is applied where telemetry is decoded, so the plots, the logs and
+//! remote viewers all see the same calibrated flow in L/min. The display unit
+//! only scales what the operator sees; logs stay in L/min so sessions compare
+//! whatever unit was on screen. The calibration in effect is recorded in the
+//! session's metadata.json.
+//!
+//! K-factors are measured with the calibration wizard: a known volume of water
+//! is run through a meter while its pulses are counted.
+
+use eframe::egui;
+use ksi_telemetry::EngineDataPoint;
+use serde::{Deserialize, Serialize};
+
+use crate::decoding::{FLOW_K_FACTOR_FUEL, FLOW_K_FACTOR_OXI};
+
+/// Unit flows are displayed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FlowUnit {
+    #[default]
+    LitersPerMinute,
+    /// Mass flow, from the meter's volumetric flow and the propellant density
+    KilogramsPerSecond,
+}
+
+impl FlowUnit {
+    pub const ALL: [FlowUnit; 2] = [FlowUnit::LitersPerMinute, FlowUnit::KilogramsPerSecond];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            FlowUnit::LitersPerMinute => "L/min",
+            FlowUnit::KilogramsPerSecond => "kg/s",
+        }
+    }
+}
+
+/// Calibration of one flow meter.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowCalibration {
+    /// Pulses per second per L/min, used when the channel carries pulse frequency
+    pub k_factor: f64,
+    /// Added to the flow in L/min, e.g. to zero a meter that reads with no flow
+    pub offset: f64,
+    pub unit: FlowUnit,
+    /// Propellant density in kg/L, for mass flow
+    pub density_kg_per_l: f64,
+    /// When the K-factor was last measured, in local time, and its standard uncertainty
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub calibrated_at: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k_uncertainty: Option<f64>,
+}
+
+impl Default for FlowCalibration {
+    fn default() -> Self {
+        Self {
+            k_factor: FLOW_K_FACTOR_FUEL,
+            offset: 0.0,
+            unit: FlowUnit::LitersPerMinute,
+            density_kg_per_l: 1.0,
+            calibrated_at: None,
+            k_uncertainty: None,
+        }
+    }
+}
+
+impl FlowCalibration {
+    /// Displayed value per L/min.
+    pub fn display_scale(&self) -> f64 {
+        match self.unit {
+            FlowUnit::LitersPerMinute => 1.0,
+            FlowUnit::KilogramsPerSecond => self.density_kg_per_l / 60.0,
+        }
+    }
+}
+
+/// Calibration of both flow meters, in the settings and the session metadata.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct FlowCalibrations {
+    pub fuel: FlowCalibration,
+    pub oxi: FlowCalibration,
+}
+
+impl Default for FlowCalibrations {
+    fn default() -> Self {
+        Self {
+            fuel: FlowCalibration::default(),
+            oxi: FlowCalibration {
+                k_factor: FLOW_K_FACTOR_OXI,
+                ..FlowCalibration::default()
+            },
+        }
+    }
+}
+
+/// One of the two flow meters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FlowMeter {
+    Fuel,
+    Oxi,
+}
+
+impl FlowMeter {
+    pub fn name(self) -> &'static str {
+        match self {
+            FlowMeter::Fuel => "Fuel",
+            FlowMeter::Oxi => "Oxidizer",
+        }
+    }
+
+    fn pulses(self, dp: &EngineDataPoint) -> i32 {
+        match self {
+            FlowMeter::Fuel => dp.pulse_count_fuel,
+            FlowMeter::Oxi => dp.pulse_count_oxi,
+        }
+    }
+
+    pub fn calibration(self, calibrations: &FlowCalibrations) -> &FlowCalibration {
+        match self {
+            FlowMeter::Fuel => &calibrations.fuel,
+            FlowMeter::Oxi => &calibrations.oxi,
+        }
+    }
+
+    pub fn calibration_mut(self, calibrations: &mut FlowCalibrations) -> &mut FlowCalibration {
+        match self {
+            FlowMeter::Fuel => &mut calibrations.fuel,
+            FlowMeter::Oxi => &mut calibrations.oxi,
+        }
+    }
+}
+
+/// A K-factor measured from a known-volume run.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KFactorResult {
+    pub k_factor: f64,
+    /// Standard uncertainty, combining the volume's with one pulse of counting
+    pub uncertainty: f64,
+}
+
+/// K-factor in pulses per second per L/min from `pulses` counted over `volume_l`.
+///
+/// Q = F / K, so a volume V = N / (60 K) and K = N / (60 V). The run can start
+/// and end partway through a counting window, which can add or lose a pulse.
+pub fn k_factor(pulses: u64, volume_l: f64, volume_uncertainty_l: f64) -> Option<KFactorResult> {
+    if pulses == 0 || volume_l <= 0.0 {
+        return None;
+    }
+    let k_factor = pulses as f64 / (60.0 * volume_l);
+    let relative =
+        ((1.0 / pulses as f64).powi(2) + (volume_uncertainty_l / volume_l).powi(2)).sqrt();
+    Some(KFactorResult {
+        k_factor,
+        uncertainty: k_factor * relative,
+    })
+}
+
+/// Guided K-factor measurement: the operator enters a known volume, starts a
+/// run, passes that volume of water through the meter, and stops the run.
+pub struct CalibrationWizard {
+    meter: FlowMeter,
+    volume_l: f64,
+    volume_uncertainty_l: f64,
+    // Whether a run is in progress, and the pulses it has counted
+    running: bool,
+    pulses: u64,
+    // Firmware time of the first and latest data point in the run
+    span: Option<(f64, f64)>,
+}
+
+impl Default for CalibrationWizard {
+    fn default() -> Self {
+        Self {
+            meter: FlowMeter::Fuel,
+            volume_l: 1.0,
+            volume_uncertainty_l: 0.01,
+            running: false,
+            pulses: 0,
+            span: None,
+        }
+    }
+}
+
+impl CalibrationWizard {
+    /// Counts a live data point's pulses while a run is in progress.
+    pub fn update(&mut self, dp: &EngineDataPoint) {
+        if !self.running {
+            return;
+        }
+        self.pulses += self.meter.pulses(dp).max(0) as u64;
+        let start = self.span.map_or(dp.time, |(start, _)| start);
+        self.span = Some((start, dp.time));
+    }
+
+    /// Draws the steps; returns the meter and result once the operator applies it.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        calibrations: &FlowCalibrations,
+    ) -> Option<(FlowMeter, KFactorResult)> {
+        ui.label("1. Choose the meter and enter the volume to run through it.");
+        ui.add_enabled_ui(!self.running, |ui| {
+            ui.horizontal(|ui| {
+                for meter in [FlowMeter::Fuel, FlowMeter::Oxi] {
+                    ui.selectable_value(&mut self.meter, meter, meter.name());
+                }
+                ui.label("Volume");
+                ui.add(
+                    egui::DragValue::new(&mut self.volume_l)
+                        .speed(0.01)
+                        .range(0.001..=1000.0)
+                        .suffix(" L"),
+                );
+                ui.label("±");
+                ui.add(
+                    egui::DragValue::new(&mut self.volume_uncertainty_l)
+                        .speed(0.001)
+                        .range(0.0..=100.0)
+                        .suffix(" L"),
+                );
+            });
+        });
+        let current = self.meter.calibration(calibrations);
+        ui.label(match &current.calibrated_at {
+            Some(at) => format!(
+                "Current K-factor: {:.3} ± {:.3}, measured {}",
+                current.k_factor,
+                current.k_uncertainty.unwrap_or(0.0),
+                at
+            ),
+            None => format!("Current K-factor: {:.3} (not measured)", current.k_factor),
+        });
+
+        ui.separator();
+        ui.label("2. Start the run, pass the volume through the meter, then stop.");
+        ui.horizontal(|ui| {
+            if self.running {
+                if ui.button("Stop run").clicked() {
+                    self.running = false;
+                }
+            } else if ui.button("Start run").clicked() {
+                self.running = true;
+                self.pulses = 0;
+                self.span = None;
+            }
+            let duration_s = self.span.map_or(0.0, |(start, end)| (end - start) / 1000.0);
+            ui.label(format!("{} pulses over {:.1} s", self.pulses, duration_s));
+        });
+
+        if self.running {
+            return None;
+        }
+        let result = k_factor(self.pulses, self.volume_l, self.volume_uncertainty_l)?;
+        ui.separator();
+        ui.label("3. Check the result and apply it to the calibration.");
+        ui.label(format!(
+            "K-factor: {:.3} ± {:.3} pulses/s per L/min",
+            result.k_factor, result.uncertainty
+        ));
+        ui.button("Apply")
+            .on_hover_text("Saves the K-factor to the settings with the time and uncertainty")
+            .clicked()
+            .then_some((self.meter, result))
+    }
+}
@@ -0,0 +1,455 @@
+use rusqlite::{params, Connection};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Sender, TryRecvError};
+use std::sync::Mutex;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::applog::diag;
+use crate::events::EventMarker;
+use ksi_telemetry::EngineDataPoint;
+
+/// Campaign index shared by every session, stored next to the session folders.
+pub const CAMPAIGN_DB_NAME: &str = "campaign.sqlite";
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id INTEGER PRIMARY KEY,
+    log_dir TEXT NOT NULL UNIQUE,
+    started_at INTEGER,
+    ended_at INTEGER
+);
+CREATE TABLE IF NOT EXISTS metrics (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    name TEXT NOT NULL,
+    value REAL NOT NULL,
+    PRIMARY KEY (session_id, name)
+);
+CREATE TABLE IF NOT EXISTS events (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    timestamp INTEGER NOT NULL,
+    time REAL NOT NULL,
+    kind TEXT NOT NULL,
+    label TEXT NOT NULL
+);
+CREATE TABLE IF NOT EXISTS tags (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    tag TEXT NOT NULL,
+    PRIMARY KEY (session_id, tag)
+);
+CREATE TABLE IF NOT EXISTS samples (
+    session_id INTEGER NOT NULL REFERENCES sessions(id) ON DELETE CASCADE,
+    timestamp INTEGER NOT NULL,
+    time REAL NOT NULL,
+    flow_rate_fuel REAL NOT NULL,
+    flow_rate_oxi REAL NOT NULL,
+    pulse_count_fuel INTEGER NOT NULL,
+    pulse_count_oxi INTEGER NOT NULL,
+    desired_pos_fuel INTEGER NOT NULL,
+    desired_pos_oxi INTEGER NOT NULL,
+    fuel_valve_open INTEGER NOT NULL,
+    oxi_valve_open INTEGER NOT NULL,
+    controller_error REAL,
+    controller_integrator REAL,
+    controller_output REAL,
+    temperature_nozzle REAL,
+    temperature_tank REAL,
+    temperature_ambient REAL,
+    thrust REAL,
+    quality TEXT NOT NULL,
+    reported_fuel_valve INTEGER,
+    reported_oxi_valve INTEGER,
+    pressures TEXT NOT NULL
+);
+CREATE INDEX IF NOT EXISTS samples_session ON samples(session_id, time);
+CREATE TABLE IF NOT EXISTS lab_notes (
+    id INTEGER PRIMARY KEY,
+    folder TEXT NOT NULL,
+    transcript TEXT NOT NULL,
+    date INTEGER,
+    experiment_type TEXT,
+    objective TEXT,
+    summary_path TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    UNIQUE (folder, transcript)
+);
+";
+
+/// How long a writer waits for the database while another connection holds it.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+/// Most samples inserted in one transaction.
+const SAMPLES_PER_TRANSACTION: usize = 500;
+
+/// Sample columns the session search can test, as (column, display name).
+pub const SEARCHABLE_COLUMNS: [(&str, &str); 7] = [
+    ("flow_rate_fuel", "Fuel flow (L/min)"),
+    ("flow_rate_oxi", "Oxidizer flow (L/min)"),
+    ("thrust", "Thrust (N)"),
+    ("temperature_nozzle", "Nozzle temperature (°C)"),
+    ("temperature_tank", "Tank temperature (°C)"),
+    ("temperature_ambient", "Ambient temperature (°C)"),
+    ("controller_error", "Controller error"),
+];
+
+/// Running statistics for the current session, updated per data point.
+#[derive(Debug, Clone, Default)]
+pub struct SessionStats {
+    pub samples: u64,
+    pub started_at: Option<u64>, // Unix time of the first data point
+    pub ended_at: Option<u64>,   // Unix time of the latest data point
+    first_time: Option<f64>,
+    last_time: Option<f64>,
+    pub peak_flow_fuel: f64,
+    pub peak_flow_oxi: f64,
+    sum_flow_fuel: f64,
+    sum_flow_oxi: f64,
+    pub volume_fuel: f64, // Integrated volume in L
+    pub volume_oxi: f64,
+}
+
+impl SessionStats {
+    pub fn update(&mut self, dp: &EngineDataPoint) {
+        if let Some(last_time) = self.last_time {
+            // Flow is in L/min and firmware time in ms
+            let dt_min = (dp.time - last_time).max(0.0) / 60_000.0;
+            self.volume_fuel += dp.flow_rate_fuel * dt_min;
+            self.volume_oxi += dp.flow_rate_oxi * dt_min;
+        }
+        self.samples += 1;
+        self.started_at.get_or_insert(dp.timestamp);
+        self.ended_at = Some(dp.timestamp);
+        self.first_time.get_or_insert(dp.time);
+        self.last_time = Some(dp.time);
+        self.peak_flow_fuel = self.peak_flow_fuel.max(dp.flow_rate_fuel);
+        self.peak_flow_oxi = self.peak_flow_oxi.max(dp.flow_rate_oxi);
+        self.sum_flow_fuel += dp.flow_rate_fuel;
+        self.sum_flow_oxi += dp.flow_rate_oxi;
+    }
+
+    pub fn duration_s(&self) -> f64 {
+        match (self.first_time, self.last_time) {
+            (Some(first), Some(last)) => (last - first) / 1000.0,
+            _ => 0.0,
+        }
+    }
+
+    /// Named metrics stored in the campaign database.
+    pub fn metrics(&self) -> Vec<(&'static str, f64)> {
+        let samples = self.samples.max(1) as f64;
+        let mut metrics = vec![
+            ("samples", self.samples as f64),
+            ("duration_s", self.duration_s()),
+            ("peak_flow_fuel", self.peak_flow_fuel),
+            ("peak_flow_oxi", self.peak_flow_oxi),
+            ("mean_flow_fuel", self.sum_flow_fuel / samples),
+            ("mean_flow_oxi", self.sum_flow_oxi / samples),
+            ("volume_fuel", self.volume_fuel),
+            ("volume_oxi", self.volume_oxi),
+        ];
+        // Volumetric: there are no propellant densities to convert to mass
+        if self.volume_fuel > 0.0 {
+            metrics.push(("mean_of_ratio", self.volume_oxi / self.volume_fuel));
+        }
+        metrics
+    }
+}
+
+/// Everything recorded about a session when it closes.
+pub struct SessionRecord<'a> {
+    pub log_dir: &'a Path,
+    pub stats: &'a SessionStats,
+    pub extra_metrics: Vec<(&'static str, f64)>,
+    pub markers: &'a [EventMarker],
+}
+
+/// A session's stored metrics, for campaign-level trends.
+pub struct SessionMetrics {
+    pub log_dir: String,
+    pub started_at: Option<i64>,
+    pub metrics: HashMap<String, f64>,
+}
+
+/// An experiment summarized by lab_assist from a day's transcripts.
+pub struct LabNote {
+    pub folder: String,
+    pub transcript: String,
+    pub date: Option<i64>, // Unix time of the experiment day
+    pub experiment_type: Option<String>,
+    pub objective: Option<String>,
+}
+
+/// A session whose samples peaked above a searched threshold.
+pub struct SessionMatch {
+    pub log_dir: String,
+    pub started_at: Option<i64>,
+    pub peak: f64,
+}
+
+/// Opens (creating if needed) the campaign database.
+pub fn open(db_path: &Path) -> rusqlite::Result<Connection> {
+    let conn = Connection::open(db_path)?;
+    conn.busy_timeout(BUSY_TIMEOUT)?;
+    conn.execute_batch("PRAGMA foreign_keys = ON;")?;
+    conn.execute_batch(SCHEMA)?;
+    Ok(conn)
+}
+
+/// Inserts or replaces a session and its metrics and events in the campaign database.
+pub fn record_session(db_path: &Path, record: &SessionRecord) -> rusqlite::Result<()> {
+    let mut conn = open(db_path)?;
+    let tx = conn.transaction()?;
+    let log_dir = record.log_dir.display().to_string();
+
+    tx.execute(
+        "INSERT INTO sessions (log_dir, started_at, ended_at) VALUES (?1, ?2, ?3)
+         ON CONFLICT(log_dir) DO UPDATE SET started_at = ?2, ended_at = ?3",
+        params![log_dir, record.stats.started_at, record.stats.ended_at],
+    )?;
+    let session_id: i64 = tx.query_row(
+        "SELECT id FROM sessions WHERE log_dir = ?1",
+        params![log_dir],
+        |row| row.get(0),
+    )?;
+
+    tx.execute(
+        "DELETE FROM metrics WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    for (name, value) in record
+        .stats
+        .metrics()
+        .into_iter()
+        .chain(record.extra_metrics.iter().copied())
+    {
+        tx.execute(
+            "INSERT INTO metrics (session_id, name, value) VALUES (?1, ?2, ?3)",
+            params![session_id, name, value],
+        )?;
+    }
+
+    tx.execute(
+        "DELETE FROM events WHERE session_id = ?1",
+        params![session_id],
+    )?;
+    for marker in record.markers {
+        tx.execute(
+            "INSERT INTO events (session_id, timestamp, time, kind, label)
+             VALUES (?1, ?2, ?3, 'marker', ?4)",
+            params![session_id, marker.timestamp, marker.time, marker.label],
+        )?;
+    }
+
+    tx.commit()
+}
+
+/// Every indexed session with its metrics, oldest first.
+pub fn session_metrics(db_path: &Path) -> rusqlite::Result<Vec<SessionMetrics>> {
+    let conn = open(db_path)?;
+    let mut sessions_query =
+        conn.prepare("SELECT id, log_dir, started_at FROM sessions ORDER BY started_at, id")?;
+    let mut metrics_query =
+        conn.prepare("SELECT name, value FROM metrics WHERE session_id = ?1")?;
+    let rows = sessions_query.query_map([], |row| {
+        Ok((
+            row.get::<_, i64>(0)?,
+            row.get::<_, String>(1)?,
+            row.get::<_, Option<i64>>(2)?,
+        ))
+    })?;
+    let mut sessions = Vec::new();
+    for row in rows {
+        let (id, log_dir, started_at) = row?;
+        let metrics = metrics_query
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<HashMap<String, f64>>>()?;
+        sessions.push(SessionMetrics {
+            log_dir,
+            started_at,
+            metrics,
+        });
+    }
+    Ok(sessions)
+}
+
+/// Every lab note indexed by lab_assist, oldest first.
+pub fn lab_notes(db_path: &Path) -> rusqlite::Result<Vec<LabNote>> {
+    let conn = open(db_path)?;
+    let mut query = conn.prepare(
+        "SELECT folder, transcript, date, experiment_type, objective
+         FROM lab_notes ORDER BY date, folder, transcript",
+    )?;
+    let notes = query
+        .query_map([], |row| {
+            Ok(LabNote {
+                folder: row.get(0)?,
+                transcript: row.get(1)?,
+                date: row.get(2)?,
+                experiment_type: row.get(3)?,
+                objective: row.get(4)?,
+            })
+        })?
+        .collect();
+    notes
+}
+
+/// Sessions whose samples of `column`, one of `SEARCHABLE_COLUMNS`, exceeded
+/// `threshold`, with their peak value. Only sessions recorded with sample
+/// storage on have samples to search.
+pub fn sessions_exceeding(
+    db_path: &Path,
+    column: &str,
+    threshold: f64,
+) -> Result<Vec<SessionMatch>, String> {
+    // The column is interpolated into the SQL, so only known names are allowed
+    if !SEARCHABLE_COLUMNS.iter().any(|(name, _)| *name == column) {
+        return Err(format!("Unknown sample column: {}", column));
+    }
+    let conn = open(db_path).map_err(|e| e.to_string())?;
+    let mut query = conn
+        .prepare(&format!(
+            "SELECT sessions.log_dir, sessions.started_at, MAX(samples.{column}) AS peak
+             FROM samples JOIN sessions ON sessions.id = samples.session_id
+             GROUP BY samples.session_id
+             HAVING peak > ?1
+             ORDER BY sessions.started_at, sessions.id"
+        ))
+        .map_err(|e| e.to_string())?;
+    let matches = query
+        .query_map(params![threshold], |row| {
+            Ok(SessionMatch {
+                log_dir: row.get(0)?,
+                started_at: row.get(1)?,
+                peak: row.get(2)?,
+            })
+        })
+        .and_then(|rows| rows.collect::<rusqlite::Result<Vec<_>>>())
+        .map_err(|e| e.to_string())?;
+    Ok(matches)
+}
+
+/// Stores every sample of the running session in the campaign database's
+/// samples table, from its own thread.
+pub struct SampleWriter {
+    sender: Mutex<Option<Sender<EngineDataPoint>>>,
+    writer_thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl SampleWriter {
+    /// Registers the session, so its samples have a session row to belong to.
+    pub fn start(db_path: &Path, log_dir: &Path) -> Result<Self, String> {
+        let failed =
+            |e: rusqlite::Error| format!("Failed to store samples in {}: {}", db_path.display(), e);
+        let conn = open(db_path).map_err(failed)?;
+        let log_dir = log_dir.display().to_string();
+        conn.execute(
+            "INSERT INTO sessions (log_dir) VALUES (?1) ON CONFLICT(log_dir) DO NOTHING",
+            params![log_dir],
+        )
+        .map_err(failed)?;
+        let session_id: i64 = conn
+            .query_row(
+                "SELECT id FROM sessions WHERE log_dir = ?1",
+                params![log_dir],
+                |row| row.get(0),
+            )
+            .map_err(failed)?;
+
+        let (sender, receiver) = mpsc::channel::<EngineDataPoint>();
+        let writer_thread = {
+            let db_path = db_path.to_path_buf();
+            thread::spawn(move || write_samples(conn, session_id, receiver, db_path))
+        };
+        diag!("Storing samples in {}", db_path.display());
+        Ok(Self {
+            sender: Mutex::new(Some(sender)),
+            writer_thread: Mutex::new(Some(writer_thread)),
+        })
+    }
+
+    pub fn write(&self, data_point: &EngineDataPoint) {
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            let _ = sender.send(data_point.clone());
+        }
+    }
+
+    /// Commits the remaining samples; later data points are ignored.
+    pub fn finish(&self) {
+        self.sender.lock().unwrap().take();
+        if let Some(writer_thread) = self.writer_thread.lock().unwrap().take() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+/// Inserts samples as they arrive, batching whatever is queued into one transaction.
+fn write_samples(
+    mut conn: Connection,
+    session_id: i64,
+    receiver: mpsc::Receiver<EngineDataPoint>,
+    db_path: PathBuf,
+) {
+    // Ends when `finish` drops the sender
+    while let Ok(first) = receiver.recv() {
+        let mut batch = vec![first];
+        while batch.len() < SAMPLES_PER_TRANSACTION {
+            match receiver.try_recv() {
+                Ok(data_point) => batch.push(data_point),
+                Err(TryRecvError::Empty | TryRecvError::Disconnected) => break,
+            }
+        }
+        if let Err(e) = insert_samples(&mut conn, session_id, &batch) {
+            diag!("Stopped storing samples in {}: {}", db_path.display(), e);
+            return;
+        }
+    }
+}
+
+fn insert_samples(
+    conn: &mut Connection,
+    session_id: i64,
+    batch: &[EngineDataPoint],
+) -> rusqlite::Result<()> {
+    let tx = conn.transaction()?;
+    {
+        let mut insert = tx.prepare_cached(
+            "INSERT INTO samples (
+                session_id, timestamp, time, flow_rate_fuel, flow_rate_oxi,
+                pulse_count_fuel, pulse_count_oxi, desired_pos_fuel, desired_pos_oxi,
+                fuel_valve_open, oxi_valve_open,
+                controller_error, controller_integrator, controller_output,
+                temperature_nozzle, temperature_tank, temperature_ambient, thrust,
+                quality, reported_fuel_valve, reported_oxi_valve, pressures
+            ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14,
+                      ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22)",
+        )?;
+        for dp in batch {
+            let pressures: Vec<String> = dp.pressures.iter().map(|p| p.to_string()).collect();
+            insert.execute(params![
+                session_id,
+                dp.timestamp,
+                dp.time,
+                dp.flow_rate_fuel,
+                dp.flow_rate_oxi,
+                dp.pulse_count_fuel,
+                dp.pulse_count_oxi,
+                dp.desired_pos_fuel,
+                dp.desired_pos_oxi,
+                dp.fuel_valve_open,
+                dp.oxi_valve_open,
+                dp.controller.map(|c| c.error),
+                dp.controller.map(|c| c.integrator),
+                dp.controller.map(|c| c.output),
+                dp.temperatures.map(|t| t.nozzle),
+                dp.temperatures.map(|t| t.tank),
+                dp.temperatures.map(|t| t.ambient),
+                dp.thrust,
+                dp.quality.as_str(),
+                dp.valve_echo.map(|e| e.fuel_open),
+                dp.valve_echo.map(|e| e.oxi_open),
+                pressures.join(","),
+            ])?;
+        }
+    }
+    tx.commit()
+}
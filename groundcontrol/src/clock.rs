@@ -0,0 +1,60 @@
+/// T-0 reference for displaying firmware time relative to ignition.
+///
+/// Only the display changes; logs keep absolute firmware time so the
+/// reference can be moved after the fact.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MissionClock {
+    t_zero: Option<f64>, // Firmware time of T-0 in ms
+}
+
+impl MissionClock {
+    pub fn set_t_zero(&mut self, time: f64) {
+        self.t_zero = Some(time);
+    }
+
+    pub fn clear_t_zero(&mut self) {
+        self.t_zero = None;
+    }
+
+    pub fn t_zero(&self) -> Option<f64> {
+        self.t_zero
+    }
+
+    /// X coordinate for plotting: T-relative seconds once T-0 is set, firmware ms before.
+    pub fn plot_x(&self, time: f64) -> f64 {
+        match self.t_zero {
+            Some(t_zero) => (time - t_zero) / 1000.0,
+            None => time,
+        }
+    }
+
+    /// Firmware time at a plot X coordinate; the inverse of `plot_x`.
+    pub fn time_at(&self, x: f64) -> f64 {
+        match self.t_zero {
+            Some(t_zero) => x * 1000.0 + t_zero,
+            None => x,
+        }
+    }
+
+    pub fn axis_label(&self) -> &'static str {
+        match self.t_zero {
+            Some(_) => "T (s)",
+            None => "Time (ms)",
+        }
+    }
+
+    /// Formats a firmware time as "T-1.5 s" / "T+2.0 s", or plain ms without T-0.
+    pub fn format(&self, time: f64) -> String {
+        match self.t_zero {
+            Some(t_zero) => {
+                let t = (time - t_zero) / 1000.0;
+                if t < 0.0 {
+                    format!("T-{:.1} s", -t)
+                } else {
+                    format!("T+{:.1} s", t)
+                }
+            }
+            None => format!("{} ms", time),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use ksi_telemetry::Relays;
+
+/// Turns commanded valve and relay states into the bytes a particular firmware expects.
+pub trait CommandEncoder: Send {
+    fn encode(&self, fuel_open: bool, oxi_open: bool) -> String;
+    fn encode_relays(&self, relays: Relays) -> String;
+    /// Positions in percent that open valves are driven to, for proportional firmware
+    fn encode_positions(&self, fuel_pct: f64, oxi_pct: f64) -> String;
+    fn encode_igniter(&self, energized: bool) -> String;
+}
+
+/// "1,0\n" - the original groundcontrol.ino format.
+pub struct CsvEncoder;
+
+impl CommandEncoder for CsvEncoder {
+    fn encode(&self, fuel_open: bool, oxi_open: bool) -> String {
+        format!("{},{}\n", fuel_open as u8, oxi_open as u8)
+    }
+
+    /// "R101\n" for lighting, camera power, and beacon. It has no comma, so
+    /// firmware without the relay board ignores it rather than reading valve states.
+    fn encode_relays(&self, relays: Relays) -> String {
+        format!(
+            "R{}{}{}\n",
+            relays.lighting as u8, relays.camera_power as u8, relays.beacon as u8
+        )
+    }
+
+    /// "P40 100\n" in whole percent, also without a comma.
+    fn encode_positions(&self, fuel_pct: f64, oxi_pct: f64) -> String {
+        format!("P{:.0} {:.0}\n", fuel_pct, oxi_pct)
+    }
+
+    /// "I1\n", also without a comma.
+    fn encode_igniter(&self, energized: bool) -> String {
+        format!("I{}\n", energized as u8)
+    }
+}
+
+/// "V F1 O0\r\n" - keyword format used by newer boards.
+pub struct VerboseEncoder;
+
+impl CommandEncoder for VerboseEncoder {
+    fn encode(&self, fuel_open: bool, oxi_open: bool) -> String {
+        format!("V F{} O{}\r\n", fuel_open as u8, oxi_open as u8)
+    }
+
+    /// "R L1 C0 B1\r\n"
+    fn encode_relays(&self, relays: Relays) -> String {
+        format!(
+            "R L{} C{} B{}\r\n",
+            relays.lighting as u8, relays.camera_power as u8, relays.beacon as u8
+        )
+    }
+
+    /// "P F40 O100\r\n"
+    fn encode_positions(&self, fuel_pct: f64, oxi_pct: f64) -> String {
+        format!("P F{:.0} O{:.0}\r\n", fuel_pct, oxi_pct)
+    }
+
+    /// "IGN 1\r\n"
+    fn encode_igniter(&self, energized: bool) -> String {
+        format!("IGN {}\r\n", energized as u8)
+    }
+}
+
+/// Firmware command dialects selectable from the GUI.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CommandDialect {
+    #[default]
+    Csv,
+    Verbose,
+}
+
+impl CommandDialect {
+    pub const ALL: [CommandDialect; 2] = [CommandDialect::Csv, CommandDialect::Verbose];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            CommandDialect::Csv => "CSV (1,0)",
+            CommandDialect::Verbose => "Verbose (V F1 O0)",
+        }
+    }
+
+    pub fn encoder(self) -> Box<dyn CommandEncoder> {
+        match self {
+            CommandDialect::Csv => Box::new(CsvEncoder),
+            CommandDialect::Verbose => Box::new(VerboseEncoder),
+        }
+    }
+}
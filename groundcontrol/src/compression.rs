@@ -0,0 +1,123 @@
+//! Optional zstd compression of network telemetry, to fit more channels through
+//! slow radio links.
+//!
+//! Each message is compressed on its own, so a lost datagram costs nothing more
+//! than itself. A dictionary trained on logged telemetry carries the field names
+//! and typical values that repeat between messages; without one a short JSON
+//! message barely shrinks. Receivers tell compressed messages from plain JSON by
+//! the zstd frame magic, so compressing senders and plain ones can share a link.
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::replay;
+
+/// Command-line flag, followed by the dictionary to write and the data_log.csv
+/// files to train it on, that trains a link dictionary and exits.
+pub const TRAIN_DICTIONARY_FLAG: &str = "--train-dictionary";
+
+// Trained dictionary size; larger ones gain little on messages of a few hundred bytes
+const DICTIONARY_SIZE: usize = 16 * 1024;
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+// Largest message a receiver decompresses, matching the largest UDP datagram
+const MAX_MESSAGE: usize = 65_507;
+
+/// Compression of WebSocket and UDP telemetry; raw TCP viewers always get plain JSON lines.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct LinkCompression {
+    /// zstd level from 1 to 22; messages are small, so high levels stay cheap
+    pub level: i32,
+    /// Dictionary written by --train-dictionary; every station on the link needs the same file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dictionary: Option<PathBuf>,
+}
+
+impl Default for LinkCompression {
+    fn default() -> Self {
+        Self {
+            level: 9,
+            dictionary: None,
+        }
+    }
+}
+
+/// Compresses outgoing messages and decompresses incoming ones with the configured dictionary.
+pub struct LinkCodec {
+    compressor: Mutex<zstd::bulk::Compressor<'static>>,
+    decompressor: Mutex<zstd::bulk::Decompressor<'static>>,
+}
+
+impl LinkCodec {
+    pub fn load(settings: &LinkCompression) -> Result<Self, String> {
+        let dictionary = match &settings.dictionary {
+            Some(path) => fs::read(path)
+                .map_err(|e| format!("Failed to read link dictionary {}: {}", path.display(), e))?,
+            None => Vec::new(),
+        };
+        let compressor = zstd::bulk::Compressor::with_dictionary(settings.level, &dictionary)
+            .map_err(|e| format!("Failed to set up link compression: {}", e))?;
+        let decompressor = zstd::bulk::Decompressor::with_dictionary(&dictionary)
+            .map_err(|e| format!("Failed to set up link decompression: {}", e))?;
+        Ok(Self {
+            compressor: Mutex::new(compressor),
+            decompressor: Mutex::new(decompressor),
+        })
+    }
+
+    pub fn compress(&self, message: &[u8]) -> Result<Vec<u8>, String> {
+        self.compressor
+            .lock()
+            .unwrap()
+            .compress(message)
+            .map_err(|e| format!("Failed to compress data point: {}", e))
+    }
+}
+
+/// A received message as JSON, decompressing it first if it is a zstd frame.
+pub fn decode<'a>(codec: Option<&LinkCodec>, message: &'a [u8]) -> Result<Cow<'a, [u8]>, String> {
+    if !message.starts_with(&ZSTD_MAGIC) {
+        return Ok(Cow::Borrowed(message));
+    }
+    let codec = codec.ok_or("compressed message, but link compression isn't configured")?;
+    codec
+        .decompressor
+        .lock()
+        .unwrap()
+        .decompress(message, MAX_MESSAGE)
+        .map(Cow::Owned)
+        .map_err(|e| {
+            format!(
+                "Failed to decompress message (is the dictionary the sender's?): {}",
+                e
+            )
+        })
+}
+
+/// Trains a dictionary on the data points in session logs, encoded as they are sent.
+pub fn train_dictionary(output: &Path, logs: &[PathBuf]) -> Result<(), String> {
+    let mut samples = Vec::new();
+    for log in logs {
+        for data_point in replay::load(log)? {
+            samples.push(serde_json::to_vec(&data_point).map_err(|e| e.to_string())?);
+        }
+    }
+    if samples.is_empty() {
+        return Err("No data points to train on".to_string());
+    }
+    let dictionary = zstd::dict::from_samples(&samples, DICTIONARY_SIZE)
+        .map_err(|e| format!("Failed to train dictionary: {}", e))?;
+    fs::write(output, &dictionary)
+        .map_err(|e| format!("Failed to write {}: {}", output.display(), e))?;
+    println!(
+        "Trained a {} byte dictionary on {} data points into {}",
+        dictionary.len(),
+        samples.len(),
+        output.display()
+    );
+    Ok(())
+}
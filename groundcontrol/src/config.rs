@@ -0,0 +1,417 @@
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+
+use ksi_telemetry::packet::Framing;
+use ksi_telemetry::Protocol;
+
+use crate::serial::TelemetryFormat;
+use crate::startup::StartupGate;
+
+use crate::alerts::AlertSettings;
+use crate::applog::diag;
+use crate::calibration::FlowCalibrations;
+use crate::compression::LinkCompression;
+use crate::deadman::DeadmanInput;
+use crate::igniter::IgniterSettings;
+use crate::limits::ChannelLimits;
+use crate::mqtt::MqttSettings;
+use crate::query::Quantity;
+use crate::timesync::TimeSource;
+use crate::widgets::{ChannelGroup, DashboardLayout};
+use crate::{
+    BAUD_RATE, BROADCAST_INTERVAL_MS, MAX_DATA_POINTS, MAX_VALVE_CYCLES_PER_MINUTE, PORT_NAME,
+};
+
+const SETTINGS_FILE_NAME: &str = "settings.toml";
+const CONFIG_DIR_NAME: &str = "ksi-groundcontrol";
+
+/// Ports a viewer server starts on when enabled; distinct, so all can run at once.
+pub const DEFAULT_WEBSOCKET_PORT: u16 = 8080;
+pub const DEFAULT_TCP_PORT: u16 = 8081;
+pub const DEFAULT_UDP_BROADCAST_PORT: u16 = 8082;
+
+/// A pressure transducer streamed after the other firmware values.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PressureChannel {
+    pub name: String,
+    pub unit: String,
+    /// Subsystem the transducer is listed under
+    #[serde(default)]
+    pub group: ChannelGroup,
+}
+
+/// A board read alongside the engine controller, such as the pressurization
+/// skid. It only streams; nothing is written to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SerialDevice {
+    /// Namespace for its channels, e.g. "skid" for "skid.Tank"
+    pub name: String,
+    pub port: String,
+    pub baud_rate: u32,
+    /// Sends binary frames, whose pressures are its channels, instead of CSV
+    /// lines of bare channel values
+    pub binary_telemetry: bool,
+    pub framing: Framing,
+    /// Channels in the order the board sends them
+    pub channels: Vec<PressureChannel>,
+    /// The board's channels are redlined: while armed, its going silent trips a
+    /// redline, as its missing values never breach a limit
+    pub safe_when_silent: bool,
+}
+
+impl Default for SerialDevice {
+    fn default() -> Self {
+        Self {
+            name: "skid".to_string(),
+            port: String::new(),
+            baud_rate: BAUD_RATE,
+            binary_telemetry: false,
+            framing: Framing::Delimited,
+            channels: Vec::new(),
+            safe_when_silent: false,
+        }
+    }
+}
+
+impl SerialDevice {
+    pub fn telemetry_format(&self) -> TelemetryFormat {
+        TelemetryFormat {
+            protocol: if self.binary_telemetry {
+                Protocol::Binary
+            } else {
+                Protocol::Csv
+            },
+            pressure_channels: self.channels.len(),
+            framing: checked_framing(self.framing),
+            channel_values: true,
+        }
+    }
+}
+
+/// A slow environmental sensor the firmware reads when polled.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EnvironmentSensor {
+    pub name: String,
+    pub unit: String,
+    /// Sensor number in the poll request and reply
+    pub id: u8,
+    pub poll_interval_s: f64,
+}
+
+/// Operator-adjustable settings, persisted as TOML.
+///
+/// Missing keys fall back to the built-in defaults, so older files keep loading.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Port selected at startup when present
+    pub port: String,
+    pub baud_rate: u32,
+    /// Interval between valve command broadcasts
+    pub broadcast_interval_ms: u64,
+    /// The firmware takes proportional valve positions, not just open and close
+    pub position_control: bool,
+    /// Fastest a commanded valve position moves, in percent per second
+    pub position_ramp_pct_per_s: f64,
+    /// Opens either valve may make in a rolling minute before commands are refused
+    pub max_valve_cycles_per_minute: usize,
+    /// Data points kept at full rate for plotting; as many older ones are kept thinned
+    pub max_data_points: usize,
+    /// Folder holding session folders and the campaign database
+    pub log_dir: PathBuf,
+    /// Second folder (e.g. a USB drive or network share) that also receives each data log
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mirror_log_dir: Option<PathBuf>,
+    /// Read COBS-framed binary telemetry instead of CSV lines
+    pub binary_telemetry: bool,
+    /// Accept CSV lines and binary frames on the same port, told apart per message,
+    /// so ground and firmware can be upgraded independently; overrides binary_telemetry
+    pub mixed_telemetry: bool,
+    /// How messages are cut from the stream, for boards that send fixed-length or
+    /// length-prefixed packets instead of delimited lines and frames
+    pub framing: Framing,
+    /// Also write each session's data log as typed, columnar data_log.parquet
+    pub parquet_log: bool,
+    /// Also store every sample in the campaign database, for searching across sessions
+    pub sqlite_samples: bool,
+    /// Pressure channels ending each telemetry line, in order
+    pub pressure_channels: Vec<PressureChannel>,
+    /// Other boards streaming on their own ports; their channels follow the
+    /// pressure channels in every sample, named "<board>.<channel>"
+    pub devices: Vec<SerialDevice>,
+    /// Thermocouple redlines in °C; traces above them are drawn red
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redline_nozzle_c: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redline_tank_c: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redline_ambient_c: Option<f64>,
+    /// Warning and critical limits, drawn on the plots and checked on every data point
+    pub limits: Vec<ChannelLimits>,
+    /// Consecutive critical samples before a safing limit closes the valves
+    pub redline_samples: u32,
+    /// Environment sensors, each polled on its own interval
+    pub environment_sensors: Vec<EnvironmentSensor>,
+    /// Seconds without telemetry before the stale-data banner shows
+    pub stale_after_s: f64,
+    /// Length of the countdown to T-0; it holds itself on a redline or lost telemetry
+    pub countdown_s: f64,
+    /// Side panel of large numeric readouts beside the plots
+    pub readout_panel: bool,
+    /// The stand has the auxiliary relay board: lighting, camera power, and the pad beacon
+    pub stand_relays: bool,
+    /// Warning beacon on while ARMED or FIRING, with the all-clear pattern on return to SAFE
+    pub beacon_automation: bool,
+    /// Igniter channel timing and interlock; none when the stand has no igniter
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub igniter: Option<IgniterSettings>,
+    /// Flow meter K-factors, offsets, and display units
+    pub flow_calibration: FlowCalibrations,
+    /// Sounds and desktop notifications for critical events
+    pub alerts: AlertSettings,
+    /// Port serving live telemetry to remote viewers over WebSocket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub websocket_port: Option<u16>,
+    /// Port serving the same JSON lines over raw TCP
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tcp_port: Option<u16>,
+    /// Port that every live data point is broadcast to over UDP on the LAN
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub udp_broadcast_port: Option<u16>,
+    /// zstd compression of WebSocket and UDP telemetry, sent and received
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub link_compression: Option<LinkCompression>,
+    /// Broker that every live data point is published to
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mqtt: Option<MqttSettings>,
+    /// Reference clock that data point timestamps are disciplined against
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub time_source: Option<TimeSource>,
+    /// Dashboard plot arrangement; the standard layout when unset
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layout: Option<DashboardLayout>,
+    /// Bring-up gates in the order they must pass before arming; omitted gates aren't enforced
+    pub startup_gates: Vec<StartupGate>,
+    /// Items the operator ticks off for the checklist gate
+    pub checklist: Vec<String>,
+    /// Valves open only while the deadman input is held
+    pub deadman_enabled: bool,
+    pub deadman_input: DeadmanInput,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            port: PORT_NAME.to_string(),
+            baud_rate: BAUD_RATE,
+            broadcast_interval_ms: BROADCAST_INTERVAL_MS,
+            position_control: false,
+            position_ramp_pct_per_s: 50.0,
+            max_valve_cycles_per_minute: MAX_VALVE_CYCLES_PER_MINUTE,
+            max_data_points: MAX_DATA_POINTS,
+            log_dir: PathBuf::from("logs"),
+            mirror_log_dir: None,
+            binary_telemetry: false,
+            mixed_telemetry: false,
+            framing: Framing::Delimited,
+            parquet_log: false,
+            sqlite_samples: false,
+            pressure_channels: Vec::new(),
+            devices: Vec::new(),
+            redline_nozzle_c: None,
+            redline_tank_c: None,
+            redline_ambient_c: None,
+            limits: Vec::new(),
+            redline_samples: 3,
+            environment_sensors: Vec::new(),
+            stale_after_s: 1.0,
+            countdown_s: 10.0,
+            readout_panel: true,
+            stand_relays: false,
+            beacon_automation: true,
+            igniter: None,
+            flow_calibration: FlowCalibrations::default(),
+            alerts: AlertSettings::default(),
+            websocket_port: None,
+            tcp_port: None,
+            udp_broadcast_port: None,
+            link_compression: None,
+            mqtt: None,
+            time_source: None,
+            layout: None,
+            startup_gates: StartupGate::ALL.to_vec(),
+            checklist: [
+                "Area clear",
+                "Propellant lines connected",
+                "Igniter safe",
+                "Cameras recording",
+            ]
+            .map(String::from)
+            .to_vec(),
+            deadman_enabled: false,
+            deadman_input: DeadmanInput::default(),
+        }
+    }
+}
+
+impl Settings {
+    /// Loads the settings file, writing the defaults there on first run.
+    ///
+    /// An invalid file falls back to the defaults, returned with the error.
+    pub fn load() -> (Self, Option<String>) {
+        let path = settings_path();
+        match fs::read_to_string(&path) {
+            Ok(contents) => match toml::from_str::<Self>(&contents) {
+                Ok(mut settings) => {
+                    diag!("Loaded settings from {}", path.display());
+                    let error = settings.reset_out_of_range().map(|field| {
+                        let error = format!("Invalid {} in {}", field, path.display());
+                        diag!("{}, using the default", error);
+                        error
+                    });
+                    (settings, error)
+                }
+                Err(e) => {
+                    let error = format!("Invalid settings in {}: {}", path.display(), e);
+                    diag!("{}, using defaults", error);
+                    (Self::default(), Some(error))
+                }
+            },
+            Err(_) => {
+                let settings = Self::default();
+                match settings.save() {
+                    Ok(()) => diag!("Wrote default settings to {}", path.display()),
+                    Err(e) => diag!("{}", e),
+                }
+                (settings, None)
+            }
+        }
+    }
+
+    pub fn telemetry_format(&self) -> TelemetryFormat {
+        TelemetryFormat {
+            protocol: if self.mixed_telemetry {
+                Protocol::Mixed
+            } else if self.binary_telemetry {
+                Protocol::Binary
+            } else {
+                Protocol::Csv
+            },
+            pressure_channels: self.pressure_channels.len(),
+            framing: checked_framing(self.framing),
+            channel_values: false,
+        }
+    }
+
+    /// The pressure channels every sample carries: the engine controller's, then
+    /// each board's under its name.
+    pub fn all_pressure_channels(&self) -> Cow<'_, [PressureChannel]> {
+        if self.devices.is_empty() {
+            return Cow::Borrowed(&self.pressure_channels);
+        }
+        let device_channels = self.devices.iter().flat_map(|device| {
+            device.channels.iter().map(|channel| PressureChannel {
+                name: format!("{}.{}", device.name, channel.name),
+                ..channel.clone()
+            })
+        });
+        Cow::Owned(
+            self.pressure_channels
+                .iter()
+                .cloned()
+                .chain(device_channels)
+                .collect(),
+        )
+    }
+
+    /// The configured limits for a quantity, if any.
+    pub fn limits_for(&self, quantity: Quantity) -> Option<ChannelLimits> {
+        self.limits.iter().find(|l| l.quantity == quantity).cloned()
+    }
+
+    /// Why these settings can't be saved, if they can't.
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.position_ramp_pct_per_s.is_finite() && self.position_ramp_pct_per_s >= 0.0) {
+            return Err("The valve position ramp rate must be a number of at least 0".to_string());
+        }
+        if self.max_valve_cycles_per_minute == 0 {
+            return Err("The valve duty limit must allow at least 1 cycle a minute".to_string());
+        }
+        if let Some(igniter) = &self.igniter {
+            if !(igniter.arm_timeout_s.is_finite() && igniter.arm_timeout_s >= 0.0) {
+                return Err("The igniter arm timeout must be a number of at least 0".to_string());
+            }
+        }
+        if let (Some(websocket), Some(tcp)) = (self.websocket_port, self.tcp_port) {
+            if websocket == tcp {
+                return Err(format!(
+                    "WebSocket and TCP viewers can't share port {}",
+                    websocket
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Puts numbers the stand can't run with back to their defaults, returning
+    /// the name of the first one reset.
+    fn reset_out_of_range(&mut self) -> Option<&'static str> {
+        let mut reset = None;
+        let defaults = Self::default();
+        if !(self.position_ramp_pct_per_s.is_finite() && self.position_ramp_pct_per_s >= 0.0) {
+            self.position_ramp_pct_per_s = defaults.position_ramp_pct_per_s;
+            reset = reset.or(Some("position_ramp_pct_per_s"));
+        }
+        if self.max_valve_cycles_per_minute == 0 {
+            self.max_valve_cycles_per_minute = defaults.max_valve_cycles_per_minute;
+            reset = reset.or(Some("max_valve_cycles_per_minute"));
+        }
+        if let Some(igniter) = &mut self.igniter {
+            if !(igniter.arm_timeout_s.is_finite() && igniter.arm_timeout_s >= 0.0) {
+                igniter.arm_timeout_s = IgniterSettings::default().arm_timeout_s;
+                reset = reset.or(Some("igniter arm_timeout_s"));
+            }
+        }
+        reset
+    }
+
+    pub fn save(&self) -> Result<(), String> {
+        let path = settings_path();
+        let contents = toml::to_string_pretty(self).map_err(|e| e.to_string())?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)
+                .map_err(|e| format!("Failed to create {}: {}", dir.display(), e))?;
+        }
+        fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// A settings.toml next to the binary wins; otherwise the user config directory.
+pub fn settings_path() -> PathBuf {
+    let portable = env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join(SETTINGS_FILE_NAME)));
+    if let Some(portable) = portable.filter(|path| path.is_file()) {
+        return portable;
+    }
+    let config_dir = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))
+        .unwrap_or_default();
+    config_dir.join(CONFIG_DIR_NAME).join(SETTINGS_FILE_NAME)
+}
+
+/// The framing if it can frame anything, otherwise the protocol's delimiters.
+fn checked_framing(framing: Framing) -> Framing {
+    match framing.validate() {
+        Ok(()) => framing,
+        Err(e) => {
+            diag!("{}; reading delimited messages", e);
+            Framing::Delimited
+        }
+    }
+}
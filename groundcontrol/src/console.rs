@@ -0,0 +1,77 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+
+use chrono::{DateTime, Local};
+
+use crate::applog::diag;
+
+const CONSOLE_LOG_NAME: &str = "firmware_console.log";
+// Lines kept for display; the log file keeps everything
+const MAX_CONSOLE_LINES: usize = 2000;
+
+struct ConsoleLine {
+    received: DateTime<Local>,
+    text: String,
+}
+
+/// Debug text printed by the firmware, shown in its own window and saved with the session.
+pub struct FirmwareConsole {
+    lines: Mutex<VecDeque<ConsoleLine>>,
+    file: Mutex<File>,
+}
+
+impl FirmwareConsole {
+    pub fn new(log_dir: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            lines: Mutex::new(VecDeque::new()),
+            file: Mutex::new(File::create(log_dir.join(CONSOLE_LOG_NAME))?),
+        })
+    }
+
+    pub fn push(&self, text: &str) {
+        let line = ConsoleLine {
+            received: Local::now(),
+            text: text.to_string(),
+        };
+        let entry = format!("[{}] {}\n", line.received.format("%H:%M:%S%.3f"), line.text);
+        if let Err(e) = self.file.lock().unwrap().write_all(entry.as_bytes()) {
+            diag!("Failed to write firmware console line: {}", e);
+        }
+        let mut lines = self.lines.lock().unwrap();
+        lines.push_back(line);
+        if lines.len() > MAX_CONSOLE_LINES {
+            lines.pop_front();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.lines.lock().unwrap().len()
+    }
+
+    pub fn clear(&self) {
+        self.lines.lock().unwrap().clear();
+    }
+
+    /// Scrolling list of lines, following new output while scrolled to the bottom.
+    pub fn show(&self, ui: &mut egui::Ui) {
+        let lines = self.lines.lock().unwrap();
+        egui::ScrollArea::vertical()
+            .stick_to_bottom(true)
+            .auto_shrink([false, false])
+            .max_height(360.0)
+            .show(ui, |ui| {
+                for line in lines.iter() {
+                    ui.horizontal(|ui| {
+                        ui.weak(
+                            egui::RichText::new(line.received.format("%H:%M:%S%.3f").to_string())
+                                .monospace(),
+                        );
+                        ui.label(egui::RichText::new(&line.text).monospace());
+                    });
+                }
+            });
+    }
+}
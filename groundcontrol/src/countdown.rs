@@ -0,0 +1,115 @@
+//! Countdown to T-0 with holds, shared with the serial thread and the event log
+//! so every logged sample and event carries its T-time.
+
+use std::time::{Duration, Instant};
+
+use crate::applog::diag;
+
+/// The countdown clock: idle, counting down to T-0, holding, or counting up after it.
+///
+/// Holds only apply before T-0; they freeze the time left, which resuming
+/// counts down from again.
+#[derive(Debug, Default)]
+pub struct Countdown {
+    // When T-0 falls while counting, or fell once reached
+    t_zero_at: Option<Instant>,
+    // Time left and why, while holding
+    hold: Option<(Duration, String)>,
+    // Whether passing T-0 has been reported by `take_t_zero`
+    t_zero_taken: bool,
+}
+
+impl Countdown {
+    /// Starts counting down from T-`length`, replacing any earlier count.
+    pub fn start(&mut self, length: Duration) {
+        diag!("Countdown started at T-{:.1} s", length.as_secs_f64());
+        *self = Self {
+            t_zero_at: Some(Instant::now() + length),
+            ..Self::default()
+        };
+    }
+
+    /// Marks T-0 now, for a T-0 set without counting down.
+    pub fn mark_t_zero(&mut self) {
+        *self = Self {
+            t_zero_at: Some(Instant::now()),
+            t_zero_taken: true,
+            ..Self::default()
+        };
+    }
+
+    /// Back to idle, without a T-0.
+    pub fn recycle(&mut self) {
+        *self = Self::default();
+    }
+
+    /// Whether the clock is counting down to T-0, not holding or past it.
+    pub fn counting(&self, now: Instant) -> bool {
+        self.hold.is_none() && self.t_zero_at.is_some_and(|t_zero| now < t_zero)
+    }
+
+    /// Why the count is holding, while it is.
+    pub fn hold_reason(&self) -> Option<&str> {
+        self.hold.as_ref().map(|(_, reason)| reason.as_str())
+    }
+
+    /// Holds the count; returns false if it wasn't counting down.
+    pub fn hold(&mut self, reason: &str) -> bool {
+        let now = Instant::now();
+        if !self.counting(now) {
+            return false;
+        }
+        let Some(t_zero) = self.t_zero_at.take() else {
+            return false;
+        };
+        diag!("Countdown hold: {}", reason);
+        self.hold = Some((t_zero - now, reason.to_string()));
+        true
+    }
+
+    /// Resumes a held count from where it stopped; returns false if it wasn't holding.
+    pub fn resume(&mut self) -> bool {
+        let Some((remaining, _)) = self.hold.take() else {
+            return false;
+        };
+        diag!("Countdown resumed at T-{:.1} s", remaining.as_secs_f64());
+        self.t_zero_at = Some(Instant::now() + remaining);
+        true
+    }
+
+    /// Seconds from T-0 at `now`, negative before it, or None while idle.
+    pub fn t_time(&self, now: Instant) -> Option<f64> {
+        if let Some((remaining, _)) = &self.hold {
+            return Some(-remaining.as_secs_f64());
+        }
+        let t_zero = self.t_zero_at?;
+        Some(if now < t_zero {
+            -(t_zero - now).as_secs_f64()
+        } else {
+            (now - t_zero).as_secs_f64()
+        })
+    }
+
+    /// True once, the first time the count is seen past T-0.
+    pub fn take_t_zero(&mut self, now: Instant) -> bool {
+        let reached = self.t_zero_at.is_some_and(|t_zero| now >= t_zero);
+        if reached && !self.t_zero_taken {
+            self.t_zero_taken = true;
+            return true;
+        }
+        false
+    }
+}
+
+/// Formats a T-time as "T-00:08.3" / "T+01:02.0".
+pub fn format_t_time(t_time: f64) -> String {
+    let sign = if t_time < 0.0 { '-' } else { '+' };
+    let tenths = (t_time.abs() * 10.0).round() as u64;
+    format!(
+        "T{}{:02}:{:02}.{}",
+        sign,
+        tenths / 600,
+        tenths / 10 % 60,
+        tenths % 10
+    )
+}
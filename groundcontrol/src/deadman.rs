@@ -0,0 +1,108 @@
+//! Deadman switch inputs: a keyboard key or a gamepad trigger that must be held
+//! for the valves to open.
+
+use gilrs::{Button, Gilrs};
+use serde::{Deserialize, Serialize};
+
+use crate::applog::diag;
+
+/// Input held as the deadman switch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeadmanInput {
+    #[default]
+    KeyD,
+    KeyF,
+    KeySpace,
+    KeyF12,
+    LeftTrigger,
+    RightTrigger,
+}
+
+impl DeadmanInput {
+    pub const ALL: [DeadmanInput; 6] = [
+        DeadmanInput::KeyD,
+        DeadmanInput::KeyF,
+        DeadmanInput::KeySpace,
+        DeadmanInput::KeyF12,
+        DeadmanInput::LeftTrigger,
+        DeadmanInput::RightTrigger,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DeadmanInput::LeftTrigger => "Left trigger",
+            DeadmanInput::RightTrigger => "Right trigger",
+            _ => self.key().map_or("", |key| key.name()),
+        }
+    }
+
+    /// The keyboard key, when this input is one.
+    pub fn key(self) -> Option<egui::Key> {
+        match self {
+            DeadmanInput::KeyD => Some(egui::Key::D),
+            DeadmanInput::KeyF => Some(egui::Key::F),
+            DeadmanInput::KeySpace => Some(egui::Key::Space),
+            DeadmanInput::KeyF12 => Some(egui::Key::F12),
+            DeadmanInput::LeftTrigger | DeadmanInput::RightTrigger => None,
+        }
+    }
+
+    /// The gamepad trigger, when this input is one.
+    pub fn trigger(self) -> Option<Button> {
+        match self {
+            DeadmanInput::LeftTrigger => Some(Button::LeftTrigger2),
+            DeadmanInput::RightTrigger => Some(Button::RightTrigger2),
+            _ => None,
+        }
+    }
+}
+
+/// Connected gamepads, read for a held trigger. A trigger reads released when
+/// no gamepad is connected or gamepad support couldn't start.
+pub struct Gamepads {
+    gilrs: Option<Gilrs>,
+}
+
+impl Gamepads {
+    pub fn new() -> Self {
+        let gilrs = match Gilrs::new() {
+            Ok(gilrs) => Some(gilrs),
+            Err(e) => {
+                diag!("Gamepads unavailable: {}", e);
+                None
+            }
+        };
+        Self { gilrs }
+    }
+
+    /// Whether `button` is pressed on any connected gamepad, after taking in the
+    /// events since the last call.
+    pub fn held(&mut self, button: Button) -> bool {
+        let Some(gilrs) = &mut self.gilrs else {
+            return false;
+        };
+        while gilrs.next_event().is_some() {}
+        gilrs
+            .gamepads()
+            .any(|(_, gamepad)| gamepad.is_connected() && gamepad.is_pressed(button))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_input_is_a_key_or_a_trigger() {
+        for input in DeadmanInput::ALL {
+            assert!(
+                input.key().is_some() != input.trigger().is_some(),
+                "{:?}",
+                input
+            );
+            assert!(!input.name().is_empty(), "{:?}", input);
+        }
+        assert_eq!(DeadmanInput::KeySpace.name(), "Space");
+    }
+}
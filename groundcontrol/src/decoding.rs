@@ -0,0 +1,53 @@
+use crate::calibration::FlowCalibrations;
+
+/// Default flow meter K-factor in pulses per second per L/min (Q = F / K).
+pub const FLOW_K_FACTOR_FUEL: f64 = 7.5;
+pub const FLOW_K_FACTOR_OXI: f64 = 7.5;
+
+/// How a flow channel's telemetry value should be interpreted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlowDecoding {
+    /// The firmware already reports flow in L/min
+    #[default]
+    Flow,
+    /// The firmware reports raw pulse frequency in Hz; convert with the K-factor
+    PulseFrequency,
+}
+
+impl FlowDecoding {
+    pub const ALL: [FlowDecoding; 2] = [FlowDecoding::Flow, FlowDecoding::PulseFrequency];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            FlowDecoding::Flow => "Firmware flow",
+            FlowDecoding::PulseFrequency => "Pulse frequency",
+        }
+    }
+
+    /// Converts a raw channel value to flow in L/min.
+    pub fn decode(self, raw: f64, k_factor: f64) -> f64 {
+        match self {
+            FlowDecoding::Flow => raw,
+            FlowDecoding::PulseFrequency => raw / k_factor,
+        }
+    }
+}
+
+/// Decoding mode selected for each flow channel, and the meters' calibration.
+#[derive(Debug, Clone, Default)]
+pub struct FlowDecodingConfig {
+    pub fuel: FlowDecoding,
+    pub oxi: FlowDecoding,
+    pub calibration: FlowCalibrations,
+}
+
+impl FlowDecodingConfig {
+    /// Converts raw (fuel, oxi) channel values to calibrated flow in L/min.
+    pub fn decode(&self, raw_fuel: f64, raw_oxi: f64) -> (f64, f64) {
+        let (fuel, oxi) = (&self.calibration.fuel, &self.calibration.oxi);
+        (
+            self.fuel.decode(raw_fuel, fuel.k_factor) + fuel.offset,
+            self.oxi.decode(raw_oxi, oxi.k_factor) + oxi.offset,
+        )
+    }
+}
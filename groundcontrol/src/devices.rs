@@ -0,0 +1,228 @@
+//! Boards read alongside the engine controller, such as the pressurization skid.
+//!
+//! Each board has its own port, protocol, framing, and channel list, and only
+//! streams; nothing is written to it. Every sample a board sends is logged at its
+//! own rate to board_<board>.csv in the session folder, stamped on the same clock as
+//! data_log.csv, whether or not the engine controller is streaming. Its latest
+//! values are also appended to every engine controller sample as extra pressure
+//! channels named "<board>.<channel>", so they plot on the one timeline. A board
+//! that goes quiet leaves its channels as NaN, drawn as gaps.
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::EngineDataPoint;
+
+use crate::applog::diag;
+use crate::config::SerialDevice;
+use crate::serial::{SerialLink, SerialShared};
+
+/// How long a board's values are carried onto samples after it last reported.
+pub const DEVICE_STALE_MS: u64 = 1_000;
+
+/// The latest values from one board.
+pub struct DeviceReadings {
+    pub name: String,
+    channels: usize,
+    // Going silent trips a redline while armed
+    safe_when_silent: bool,
+    latest: Mutex<Option<(Instant, Vec<f64>)>>,
+    log: Mutex<Option<File>>,
+}
+
+impl DeviceReadings {
+    /// Keeps and logs a board's values, if it sent one per configured channel.
+    pub fn record(&self, values: Vec<f64>, unix_ms: u64, now: Instant) {
+        if values.len() != self.channels {
+            diag!(
+                "{}: expected {} channel values, got {}",
+                self.name,
+                self.channels,
+                values.len()
+            );
+            return;
+        }
+        if let Some(file) = self.log.lock().unwrap().as_mut() {
+            let values: String = values.iter().map(|v| format!(",{}", v)).collect();
+            if let Err(e) = writeln!(file, "{}{}", unix_ms, values) {
+                diag!("Failed to write {} sample: {}", self.name, e);
+            }
+        }
+        *self.latest.lock().unwrap() = Some((now, values));
+    }
+
+    /// Time since the board last reported, if it has.
+    pub fn age(&self) -> Option<Duration> {
+        self.latest
+            .lock()
+            .unwrap()
+            .as_ref()
+            .map(|(received, _)| received.elapsed())
+    }
+
+    fn fresh_values(&self, now: Instant) -> Option<Vec<f64>> {
+        let latest = self.latest.lock().unwrap();
+        let (received, values) = latest.as_ref()?;
+        (now.saturating_duration_since(*received) < Duration::from_millis(DEVICE_STALE_MS))
+            .then(|| values.clone())
+    }
+}
+
+/// Every configured board's latest values, in settings order.
+#[derive(Default)]
+pub struct DeviceHub {
+    devices: Vec<Arc<DeviceReadings>>,
+}
+
+impl DeviceHub {
+    /// Readings for every board, each logging to board_<board>.csv in `log_dir` when given.
+    pub fn new(devices: &[SerialDevice], log_dir: Option<&Path>) -> std::io::Result<Self> {
+        let mut readings = Vec::new();
+        for device in devices {
+            let log = match log_dir {
+                Some(log_dir) => {
+                    let mut file =
+                        File::create(log_dir.join(format!("board_{}.csv", device.name)))?;
+                    let header: String = device
+                        .channels
+                        .iter()
+                        .map(|channel| format!(",{}.{}", device.name, channel.name))
+                        .collect();
+                    writeln!(file, "unix_ms{}", header)?;
+                    Some(file)
+                }
+                None => None,
+            };
+            readings.push(Arc::new(DeviceReadings {
+                name: device.name.clone(),
+                channels: device.channels.len(),
+                safe_when_silent: device.safe_when_silent,
+                latest: Mutex::new(None),
+                log: Mutex::new(log),
+            }));
+        }
+        Ok(Self { devices: readings })
+    }
+
+    pub fn readings(&self) -> &[Arc<DeviceReadings>] {
+        &self.devices
+    }
+
+    /// Appends each board's latest values to the sample's pressures.
+    pub fn merge(&self, data_point: &mut EngineDataPoint, now: Instant) {
+        for device in &self.devices {
+            match device.fresh_values(now) {
+                Some(values) => data_point.pressures.extend(values),
+                None => data_point
+                    .pressures
+                    .extend(std::iter::repeat_n(f64::NAN, device.channels)),
+            }
+        }
+    }
+
+    /// Names of the boards without fresh values at `now`, including any that
+    /// never reported, and whether each trips a redline while silent.
+    pub fn silent(&self, now: Instant) -> Vec<(&str, bool)> {
+        self.devices
+            .iter()
+            .filter(|device| device.fresh_values(now).is_none())
+            .map(|device| (device.name.as_str(), device.safe_when_silent))
+            .collect()
+    }
+}
+
+/// Opens every configured board; each link reconnects by itself and closes when dropped.
+///
+/// A board that can't be opened now is left out until the next start.
+pub fn connect_all(devices: &[SerialDevice], shared: &SerialShared) -> Vec<SerialLink> {
+    devices
+        .iter()
+        .zip(shared.devices.readings())
+        .filter_map(|(device, readings)| {
+            if device.port.is_empty() {
+                diag!("{}: no port configured", device.name);
+                return None;
+            }
+            SerialLink::connect_device(
+                &device.port,
+                device.baud_rate,
+                device.telemetry_format(),
+                readings.clone(),
+                shared.time_sync.clone(),
+            )
+            .map_err(|e| diag!("{}: {}", device.name, e))
+            .ok()
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::PressureChannel;
+    use crate::widgets::ChannelGroup;
+
+    fn board(name: &str, channels: usize, safe_when_silent: bool) -> SerialDevice {
+        SerialDevice {
+            name: name.to_string(),
+            channels: (0..channels)
+                .map(|i| PressureChannel {
+                    name: format!("P{}", i),
+                    unit: "bar".to_string(),
+                    group: ChannelGroup::default(),
+                })
+                .collect(),
+            safe_when_silent,
+            ..SerialDevice::default()
+        }
+    }
+
+    fn merged(hub: &DeviceHub, now: Instant) -> Vec<f64> {
+        let mut data_point = ksi_telemetry::parse_line("0,0,0,0,0,0,0,0").unwrap();
+        data_point.pressures = vec![1.0];
+        hub.merge(&mut data_point, now);
+        data_point.pressures
+    }
+
+    #[test]
+    fn boards_append_in_settings_order() {
+        let hub =
+            DeviceHub::new(&[board("skid", 2, false), board("tank", 1, false)], None).unwrap();
+        let now = Instant::now();
+        hub.readings()[1].record(vec![5.0], 0, now);
+        hub.readings()[0].record(vec![3.0, 4.0], 0, now);
+        assert_eq!(merged(&hub, now), [1.0, 3.0, 4.0, 5.0]);
+        assert!(hub.silent(now).is_empty());
+    }
+
+    #[test]
+    fn silent_boards_merge_as_nan_and_are_reported() {
+        let hub = DeviceHub::new(&[board("skid", 2, true), board("tank", 1, false)], None).unwrap();
+        let now = Instant::now();
+        // Never reported
+        assert_eq!(hub.silent(now), [("skid", true), ("tank", false)]);
+        hub.readings()[0].record(vec![3.0, 4.0], 0, now);
+        hub.readings()[1].record(vec![5.0], 0, now);
+        let later = now + Duration::from_millis(DEVICE_STALE_MS);
+        hub.readings()[1].record(vec![6.0], 0, later);
+        let pressures = merged(&hub, later);
+        assert_eq!(pressures.len(), 4);
+        assert!(pressures[1].is_nan() && pressures[2].is_nan());
+        assert_eq!(pressures[3], 6.0);
+        assert_eq!(hub.silent(later), [("skid", true)]);
+    }
+
+    #[test]
+    fn wrong_channel_counts_are_dropped() {
+        let hub = DeviceHub::new(&[board("skid", 2, false)], None).unwrap();
+        let now = Instant::now();
+        hub.readings()[0].record(vec![3.0, 4.0], 0, now);
+        // Neither replaces the last good values nor shifts the channels after them
+        hub.readings()[0].record(vec![7.0], 0, now);
+        hub.readings()[0].record(vec![7.0, 8.0, 9.0], 0, now);
+        assert_eq!(merged(&hub, now), [1.0, 3.0, 4.0]);
+    }
+}
@@ -0,0 +1,139 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use ksi_telemetry::environment::EnvironmentReading;
+
+use crate::applog::diag;
+use crate::config::EnvironmentSensor;
+
+const ENVIRONMENT_LOG_NAME: &str = "environment.csv";
+// A reading older than this many poll intervals is shown as stale
+const STALE_POLL_INTERVALS: f64 = 3.0;
+
+#[derive(Debug, Clone, Copy)]
+struct Sample {
+    value: f64,
+    received: Instant,
+}
+
+/// Latest readings of the polled environment sensors, logged at their native rate.
+///
+/// Readings go to environment.csv in the session folder rather than data_log.csv,
+/// so a sensor polled every few seconds doesn't repeat on every telemetry row.
+pub struct Environment {
+    sensors: Vec<EnvironmentSensor>,
+    latest: Mutex<Vec<Option<Sample>>>,
+    log: Mutex<Option<File>>,
+}
+
+impl Environment {
+    /// Opens environment.csv in the session folder when any sensor is configured.
+    pub fn new(sensors: Vec<EnvironmentSensor>, log_dir: &Path) -> std::io::Result<Self> {
+        let log = if sensors.is_empty() {
+            None
+        } else {
+            let mut file = File::create(log_dir.join(ENVIRONMENT_LOG_NAME))?;
+            file.write_all(b"timestamp_ms,sensor,name,value\n")?;
+            Some(file)
+        };
+        Ok(Self {
+            latest: Mutex::new(vec![None; sensors.len()]),
+            sensors,
+            log: Mutex::new(log),
+        })
+    }
+
+    /// Stores and logs a reply; readings from unconfigured sensors are dropped.
+    pub fn record(&self, reading: EnvironmentReading) {
+        let Some(index) = self.sensors.iter().position(|s| s.id == reading.sensor) else {
+            diag!("Reading from unconfigured sensor {}", reading.sensor);
+            return;
+        };
+        self.latest.lock().unwrap()[index] = Some(Sample {
+            value: reading.value,
+            received: Instant::now(),
+        });
+        let timestamp_ms = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis();
+        if let Some(file) = self.log.lock().unwrap().as_mut() {
+            let line = format!(
+                "{},{},{},{}\n",
+                timestamp_ms, reading.sensor, self.sensors[index].name, reading.value
+            );
+            if let Err(e) = file.write_all(line.as_bytes()) {
+                diag!("Failed to write environment reading: {}", e);
+            }
+        }
+    }
+
+    /// A fresh schedule that polls every sensor right away, then at its own interval.
+    pub fn schedule(&self) -> PollSchedule {
+        let now = Instant::now();
+        PollSchedule {
+            entries: self
+                .sensors
+                .iter()
+                .map(|s| {
+                    (
+                        s.id,
+                        Duration::from_secs_f64(s.poll_interval_s.max(0.1)),
+                        now,
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Compact one-line display of every sensor, instead of a plot per sensor.
+    pub fn show_strip(&self, ui: &mut egui::Ui) {
+        if self.sensors.is_empty() {
+            return;
+        }
+        let latest = self.latest.lock().unwrap().clone();
+        ui.horizontal(|ui| {
+            ui.label("Environment:");
+            for (sensor, sample) in self.sensors.iter().zip(latest) {
+                match sample {
+                    Some(sample) => {
+                        let age = sample.received.elapsed().as_secs_f64();
+                        let text = format!("{} {:.1} {}", sensor.name, sample.value, sensor.unit);
+                        if age > sensor.poll_interval_s * STALE_POLL_INTERVALS {
+                            ui.colored_label(egui::Color32::YELLOW, text)
+                                .on_hover_text(format!("Last reading {:.0} s ago", age));
+                        } else {
+                            ui.label(text);
+                        }
+                    }
+                    None => {
+                        ui.weak(format!("{} --", sensor.name));
+                    }
+                }
+                ui.separator();
+            }
+        });
+    }
+}
+
+/// When each sensor is next due to be polled.
+pub struct PollSchedule {
+    entries: Vec<(u8, Duration, Instant)>, // Sensor id, interval, next due
+}
+
+impl PollSchedule {
+    /// Ids of sensors due at `now`, advancing each to its next slot.
+    pub fn take_due(&mut self, now: Instant) -> Vec<u8> {
+        let mut due = Vec::new();
+        for (id, interval, next) in &mut self.entries {
+            if now >= *next {
+                due.push(*id);
+                *next = now + *interval;
+            }
+        }
+        due
+    }
+}
@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::applog::diag;
+use crate::countdown::Countdown;
+
+/// Pre-defined markers dropped by the number keys.
+pub const QUICK_MARKERS: [(egui::Key, &str); 4] = [
+    (egui::Key::Num1, "anomaly"),
+    (egui::Key::Num2, "hold"),
+    (egui::Key::Num3, "resume"),
+    (egui::Key::Num4, "visual check OK"),
+];
+/// Opens the event log to type a free-text annotation, timed at the key press.
+pub const ANNOTATION_KEY: egui::Key = egui::Key::N;
+
+#[derive(Debug, Clone)]
+pub struct EventMarker {
+    pub timestamp: u64,      // Real date timestamp in Unix time
+    pub time: f64,           // Firmware time of the latest data point
+    pub t_time: Option<f64>, // Seconds from T-0 on the countdown clock, once one is set
+    pub label: String,
+}
+
+/// Operator event markers, persisted to events.csv in the log directory.
+pub struct EventLog {
+    markers: Vec<EventMarker>,
+    file: File,
+    countdown: Arc<Mutex<Countdown>>,
+}
+
+impl EventLog {
+    /// Creates the events file inside the given log directory; markers are
+    /// stamped with the T-time on the shared countdown clock.
+    pub fn new(log_dir: &Path, countdown: Arc<Mutex<Countdown>>) -> std::io::Result<Self> {
+        let file = File::create(log_dir.join("events.csv"))?;
+        Ok(Self {
+            markers: Vec::new(),
+            file,
+            countdown,
+        })
+    }
+
+    /// Records a marker at the given firmware time and appends it to the log.
+    pub fn add(&mut self, time: f64, label: &str) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+        let marker = EventMarker {
+            timestamp,
+            time,
+            t_time: self.countdown.lock().unwrap().t_time(Instant::now()),
+            label: label.to_string(),
+        };
+        let log_line = format!(
+            "{},{},{},{}\n",
+            marker.timestamp,
+            marker.time,
+            marker.t_time.map(|t| t.to_string()).unwrap_or_default(),
+            csv_field(&marker.label)
+        );
+        if let Err(e) = self.file.write_all(log_line.as_bytes()) {
+            diag!("Failed to write event marker: {}", e);
+        }
+        self.markers.push(marker);
+    }
+
+    pub fn markers(&self) -> &[EventMarker] {
+        &self.markers
+    }
+}
+
+/// Quotes a free-text label if it would otherwise break the CSV row.
+pub fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
@@ -0,0 +1,92 @@
+use ksi_telemetry::EngineDataPoint;
+
+/// How long a channel may hold a constant value before it is flagged (firmware ms).
+const FROZEN_CHANNEL_MS: f64 = 2000.0;
+
+/// Measured channels that should never sit at a constant non-zero value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Channel {
+    FlowFuel,
+    FlowOxi,
+    PulseFuel,
+    PulseOxi,
+}
+
+impl Channel {
+    pub const ALL: [Channel; 4] = [
+        Channel::FlowFuel,
+        Channel::FlowOxi,
+        Channel::PulseFuel,
+        Channel::PulseOxi,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::FlowFuel => "Fuel Flow",
+            Channel::FlowOxi => "Oxidizer Flow",
+            Channel::PulseFuel => "Fuel Pulses",
+            Channel::PulseOxi => "Oxidizer Pulses",
+        }
+    }
+
+    fn value(self, dp: &EngineDataPoint) -> f64 {
+        match self {
+            Channel::FlowFuel => dp.flow_rate_fuel,
+            Channel::FlowOxi => dp.flow_rate_oxi,
+            Channel::PulseFuel => dp.pulse_count_fuel as f64,
+            Channel::PulseOxi => dp.pulse_count_oxi as f64,
+        }
+    }
+}
+
+#[derive(Default, Clone, Copy)]
+struct ChannelState {
+    last_value: f64,
+    last_change: f64,
+}
+
+/// Detects individual channels that stop updating while the rest of the link is alive.
+///
+/// A channel is frozen when it has held the same non-zero value for longer than
+/// FROZEN_CHANNEL_MS and some other channel has changed since. Zero is excluded
+/// because a closed valve legitimately reads a constant zero flow.
+#[derive(Default)]
+pub struct FrozenChannelDetector {
+    states: [ChannelState; 4],
+    latest_time: f64,
+}
+
+impl FrozenChannelDetector {
+    pub fn update(&mut self, dp: &EngineDataPoint) {
+        for (state, channel) in self.states.iter_mut().zip(Channel::ALL) {
+            let value = channel.value(dp);
+            if value != state.last_value {
+                state.last_value = value;
+                state.last_change = dp.time;
+            }
+        }
+        self.latest_time = dp.time;
+    }
+
+    pub fn is_frozen(&self, channel: Channel) -> bool {
+        let index = Channel::ALL.iter().position(|&c| c == channel).unwrap();
+        let state = self.states[index];
+        let others_changed = self
+            .states
+            .iter()
+            .enumerate()
+            .any(|(i, other)| i != index && other.last_change > state.last_change);
+        state.last_value != 0.0
+            && self.latest_time - state.last_change > FROZEN_CHANNEL_MS
+            && others_changed
+    }
+
+    /// Names of the given channels that are currently frozen.
+    pub fn frozen_names(&self, channels: &[Channel]) -> Vec<&'static str> {
+        channels
+            .iter()
+            .filter(|&&c| self.is_frozen(c))
+            .map(|c| c.name())
+            .collect()
+    }
+}
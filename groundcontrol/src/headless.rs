@@ -0,0 +1,139 @@
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::EngineDataPoint;
+
+use crate::applog::diag;
+use crate::config::Settings;
+use crate::serial::{self, ConnectionState, SerialLink, SerialShared};
+use crate::simulator::Simulator;
+
+/// Command-line flag that records a session without opening a window.
+pub const HEADLESS_FLAG: &str = "--headless";
+// How often a status line is printed
+const STATUS_INTERVAL: Duration = Duration::from_secs(5);
+// Delay between attempts to open the port while no link exists
+const CONNECT_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Runs the serial link and logging with no GUI until Ctrl-C or SIGTERM,
+/// printing a status line to stdout every few seconds.
+///
+/// Valves are never commanded open; the write thread keeps broadcasting them closed.
+pub fn run(
+    settings: &Settings,
+    shared: &SerialShared,
+    data_receiver: Receiver<EngineDataPoint>,
+    log_dir: &Path,
+    simulator: Option<Simulator>,
+) {
+    println!("Recording to {}", log_dir.display());
+    let stop = Arc::new(AtomicBool::new(false));
+    {
+        let stop = stop.clone();
+        if let Err(e) = ctrlc::set_handler(move || stop.store(true, Ordering::Relaxed)) {
+            diag!("Failed to install the stop handler: {}", e);
+        }
+    }
+    let mut port: Option<String> = None;
+    let mut link: Option<SerialLink> = None;
+    let mut last_connect_attempt: Option<Instant> = None;
+    let mut last_status = Instant::now();
+    let (mut samples, mut samples_since_status) = (0u64, 0u64);
+    let mut latest: Option<EngineDataPoint> = None;
+    while !stop.load(Ordering::Relaxed) {
+        let connect_due =
+            last_connect_attempt.is_none_or(|t| t.elapsed() >= CONNECT_RETRY_INTERVAL);
+        if link.is_none() && simulator.is_none() && connect_due {
+            last_connect_attempt = Some(Instant::now());
+            // Same choice as the GUI: the configured adapter if it's plugged in, else the first found
+            port = Some(crate::default_port(
+                &serial::available_ports(),
+                &settings.port,
+            ))
+            .filter(|port| !port.is_empty());
+            if let Some(port) = &port {
+                match SerialLink::connect(
+                    port,
+                    settings.baud_rate,
+                    settings.telemetry_format(),
+                    shared,
+                ) {
+                    Ok(opened) => {
+                        println!("Connected to {} at {} baud", port, settings.baud_rate);
+                        link = Some(opened);
+                    }
+                    Err(e) => {
+                        diag!("{}", e);
+                        println!("{}; retrying", e);
+                    }
+                }
+            }
+        }
+
+        match data_receiver.recv_timeout(Duration::from_millis(100)) {
+            Ok(data_point) => {
+                samples += 1;
+                samples_since_status += 1;
+                latest = Some(data_point);
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if last_status.elapsed() >= STATUS_INTERVAL {
+            let rate = samples_since_status as f64 / last_status.elapsed().as_secs_f64();
+            let source = match (&link, simulator.is_some()) {
+                (_, true) => "simulated".to_string(),
+                (Some(link), _) => match link.state() {
+                    ConnectionState::Connected => format!("{} connected", link.port_name),
+                    ConnectionState::Reconnecting { attempt } => {
+                        format!("{} reconnecting (attempt {})", link.port_name, attempt)
+                    }
+                    ConnectionState::Disconnected => format!("{} disconnected", link.port_name),
+                },
+                (None, false) => match &port {
+                    Some(port) => format!("{} not open", port),
+                    None => "no serial port found".to_string(),
+                },
+            };
+            let flows = latest.as_ref().map_or_else(
+                || "no data".to_string(),
+                |dp| {
+                    format!(
+                        "fuel {:.3} L/min, oxi {:.3} L/min",
+                        dp.flow_rate_fuel, dp.flow_rate_oxi
+                    )
+                },
+            );
+            let unhealthy = shared
+                .recorder
+                .destinations()
+                .iter()
+                .filter(|d| !d.is_healthy())
+                .count();
+            println!(
+                "[{}] {} | {} samples ({:.1}/s) | {} | corrupt frames {}{}",
+                chrono::Local::now().format("%H:%M:%S"),
+                source,
+                samples,
+                rate,
+                flows,
+                shared.corrupt_frames.load(Ordering::Relaxed),
+                if unhealthy > 0 {
+                    format!(" | {} log destination(s) failed", unhealthy)
+                } else {
+                    String::new()
+                },
+            );
+            last_status = Instant::now();
+            samples_since_status = 0;
+        }
+    }
+    // Closing the link first lets its last data points reach the logs
+    drop(link);
+    shared.recorder.finish();
+    println!("Stopped after {} samples", samples);
+}
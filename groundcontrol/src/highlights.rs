@@ -0,0 +1,146 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::clock::MissionClock;
+
+const HIGHLIGHTS_FILE_NAME: &str = "highlights.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HighlightKind {
+    Marker,
+    Phase,
+    Alarm,
+}
+
+impl HighlightKind {
+    fn name(self) -> &'static str {
+        match self {
+            HighlightKind::Marker => "marker",
+            HighlightKind::Phase => "phase",
+            HighlightKind::Alarm => "alarm",
+        }
+    }
+}
+
+/// A moment or span of a session worth jumping to, in firmware ms.
+#[derive(Debug, Clone)]
+pub struct Highlight {
+    pub kind: HighlightKind,
+    pub label: String,
+    pub time: f64,
+    pub end: Option<f64>, // Phases only; None while the phase is still running
+}
+
+/// Session markers, phases, and alarms, kept in highlights.json for external tools.
+///
+/// The file is rewritten whenever a highlight or T-0 changes, so editing tools can
+/// read chapter markers during a session as well as after it. Times are given both
+/// in firmware ms and in seconds relative to T-0, when set.
+pub struct Highlights {
+    session: String,
+    path: PathBuf,
+    entries: Vec<Highlight>,
+    open_phase: Option<usize>,
+    dirty: bool,
+}
+
+impl Highlights {
+    pub fn new(log_dir: &Path) -> Self {
+        Self {
+            session: log_dir
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            path: log_dir.join(HIGHLIGHTS_FILE_NAME),
+            entries: Vec::new(),
+            open_phase: None,
+            dirty: true,
+        }
+    }
+
+    pub fn add(&mut self, kind: HighlightKind, time: f64, label: &str) {
+        self.entries.push(Highlight {
+            kind,
+            label: label.to_string(),
+            time,
+            end: None,
+        });
+        self.dirty = true;
+    }
+
+    /// Starts a phase unless one is already running.
+    pub fn start_phase(&mut self, time: f64, label: &str) {
+        if self.open_phase.is_none() {
+            self.open_phase = Some(self.entries.len());
+            self.add(HighlightKind::Phase, time, label);
+        }
+    }
+
+    pub fn end_phase(&mut self, time: f64) {
+        if let Some(index) = self.open_phase.take() {
+            self.entries[index].end = Some(time);
+            self.dirty = true;
+        }
+    }
+
+    /// Marks the file for rewriting, e.g. after T-0 moved.
+    pub fn touch(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Rewrites highlights.json if anything changed since the last write.
+    pub fn flush(&mut self, clock: &MissionClock) -> Result<(), String> {
+        if !self.dirty {
+            return Ok(());
+        }
+        self.dirty = false;
+        // Replace the file in one step so readers never see a partial write
+        let temp = self.path.with_extension("json.tmp");
+        fs::write(&temp, self.to_json(clock))
+            .and_then(|()| fs::rename(&temp, &self.path))
+            .map_err(|e| format!("Failed to write {}: {}", self.path.display(), e))
+    }
+
+    fn to_json(&self, clock: &MissionClock) -> String {
+        let number = |value: Option<f64>| value.map_or("null".to_string(), |v| v.to_string());
+        let t_relative = |time: f64| clock.t_zero().map(|t_zero| (time - t_zero) / 1000.0);
+        let mut json = format!(
+            "{{\n  \"session\": {},\n  \"t_zero_ms\": {},\n  \"highlights\": [\n",
+            json_string(&self.session),
+            number(clock.t_zero())
+        );
+        for (i, highlight) in self.entries.iter().enumerate() {
+            let separator = if i + 1 < self.entries.len() { "," } else { "" };
+            json.push_str(&format!(
+                "    {{\"kind\": \"{}\", \"label\": {}, \"time_ms\": {}, \"t\": {}, \
+                 \"end_time_ms\": {}, \"end_t\": {}}}{}\n",
+                highlight.kind.name(),
+                json_string(&highlight.label),
+                highlight.time,
+                number(t_relative(highlight.time)),
+                number(highlight.end),
+                number(highlight.end.and_then(t_relative)),
+                separator
+            ));
+        }
+        json.push_str("  ]\n}\n");
+        json
+    }
+}
+
+/// Quotes and escapes a string for JSON.
+fn json_string(value: &str) -> String {
+    let mut quoted = String::with_capacity(value.len() + 2);
+    quoted.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{:04x}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
@@ -0,0 +1,228 @@
+//! Distribution of one channel's values over a span of the session, for
+//! characterizing sensor noise and comparing repeated cold flows.
+
+use egui_plot::{Bar, BarChart, Legend, Plot};
+use ksi_telemetry::EngineDataPoint;
+
+use crate::config::Settings;
+use crate::widgets;
+
+// Colors of the captured distributions, reused when there are more captures
+const CAPTURE_COLORS: [egui::Color32; 5] = [
+    egui::Color32::LIGHT_BLUE,
+    egui::Color32::from_rgb(255, 140, 0),
+    egui::Color32::GREEN,
+    egui::Color32::YELLOW,
+    egui::Color32::LIGHT_RED,
+];
+const CURRENT_COLOR: egui::Color32 = egui::Color32::WHITE;
+
+/// One channel's values over a span, with summary statistics.
+struct Distribution {
+    label: String,
+    values: Vec<f64>,
+    mean: f64,
+    std_dev: f64,
+    min: f64,
+    max: f64,
+}
+
+impl Distribution {
+    /// None when there are no values to describe.
+    fn new(label: String, values: Vec<f64>) -> Option<Self> {
+        if values.is_empty() {
+            return None;
+        }
+        let count = values.len() as f64;
+        let mean = values.iter().sum::<f64>() / count;
+        let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count;
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        Some(Self {
+            label,
+            values,
+            mean,
+            std_dev: variance.sqrt(),
+            min,
+            max,
+        })
+    }
+
+    /// Fraction of the values in each of `bins` equal bins from `min` to `max`,
+    /// so spans of different lengths compare directly.
+    fn fractions(&self, min: f64, max: f64, bins: usize) -> Vec<f64> {
+        let mut counts = vec![0usize; bins];
+        let width = (max - min) / bins as f64;
+        for value in &self.values {
+            let bin = if width > 0.0 {
+                ((value - min) / width) as usize
+            } else {
+                0
+            };
+            counts[bin.min(bins - 1)] += 1;
+        }
+        let total = self.values.len() as f64;
+        counts.into_iter().map(|c| c as f64 / total).collect()
+    }
+}
+
+/// Histogram window: the selected channel over the span the plots show, and
+/// captured spans kept alongside it for comparison.
+pub struct HistogramTool {
+    // Channel key, as in the dashboard layout
+    channel: String,
+    bins: usize,
+    captures: Vec<Distribution>,
+}
+
+impl Default for HistogramTool {
+    fn default() -> Self {
+        Self {
+            channel: "flow_rate_fuel".to_string(),
+            bins: 30,
+            captures: Vec::new(),
+        }
+    }
+}
+
+impl HistogramTool {
+    /// Draws the form, the histogram of `data_points` (described by `span`), and
+    /// the captured distributions.
+    pub fn show(
+        &mut self,
+        ui: &mut egui::Ui,
+        data_points: &[&EngineDataPoint],
+        span: &str,
+        settings: &Settings,
+    ) {
+        let channels = widgets::channel_names(settings);
+        let groups = widgets::grouped_channel_names(settings);
+        let channel_name = |channel: &str| {
+            channels
+                .iter()
+                .find(|(key, _)| key == channel)
+                .map_or(channel.to_string(), |(_, name)| name.clone())
+        };
+        ui.horizontal(|ui| {
+            let previous = self.channel.clone();
+            egui::ComboBox::from_id_salt("histogram_channel")
+                .selected_text(channel_name(&self.channel))
+                .show_ui(ui, |ui| {
+                    for (group, group_channels) in &groups {
+                        ui.weak(group.name());
+                        for (key, name) in group_channels {
+                            ui.selectable_value(&mut self.channel, key.clone(), name);
+                        }
+                        ui.separator();
+                    }
+                });
+            // Captures of another channel aren't comparable
+            if self.channel != previous {
+                self.captures.clear();
+            }
+            ui.label("Bins");
+            ui.add(egui::DragValue::new(&mut self.bins).range(5..=200));
+        });
+
+        let values: Vec<f64> = match widgets::channel_series(&self.channel, settings) {
+            Some(series) => data_points
+                .iter()
+                .filter_map(|dp| series.value(dp))
+                .collect(),
+            None => Vec::new(),
+        };
+        let current = Distribution::new(span.to_string(), values);
+        ui.horizontal(|ui| {
+            ui.label(format!("Current: {}", span));
+            let capture = ui
+                .add_enabled(current.is_some(), egui::Button::new("Capture"))
+                .on_hover_text(
+                    "Keep this distribution to compare against later spans, \
+                     e.g. the next cold flow",
+                );
+            if capture.clicked() {
+                if let Some(current) = &current {
+                    let label = format!(
+                        "{} {}",
+                        chrono::Local::now().format("%H:%M:%S"),
+                        current.label
+                    );
+                    self.captures
+                        .extend(Distribution::new(label, current.values.clone()));
+                }
+            }
+            if !self.captures.is_empty() && ui.button("Clear captures").clicked() {
+                self.captures.clear();
+            }
+        });
+
+        let shown: Vec<(&Distribution, egui::Color32)> = self
+            .captures
+            .iter()
+            .zip(CAPTURE_COLORS.iter().cycle().copied())
+            .chain(current.as_ref().map(|current| (current, CURRENT_COLOR)))
+            .collect();
+        if shown.is_empty() {
+            ui.label("No samples of this channel in the span");
+            return;
+        }
+
+        // Bins shared by every distribution, so their bars line up
+        let min = shown
+            .iter()
+            .map(|(d, _)| d.min)
+            .fold(f64::INFINITY, f64::min);
+        let max = shown
+            .iter()
+            .map(|(d, _)| d.max)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let width = if max > min {
+            (max - min) / self.bins as f64
+        } else {
+            1.0
+        };
+        Plot::new("histogram")
+            .legend(Legend::default())
+            .x_axis_label(channel_name(&self.channel))
+            .y_axis_label("Fraction of samples")
+            .view_aspect(2.0)
+            .show(ui, |plot_ui| {
+                for (distribution, color) in &shown {
+                    let bars = distribution
+                        .fractions(min, max, self.bins)
+                        .into_iter()
+                        .enumerate()
+                        .map(|(bin, fraction)| {
+                            Bar::new(min + (bin as f64 + 0.5) * width, fraction)
+                                .width(width)
+                                .fill(color.gamma_multiply(0.4))
+                                .stroke(egui::Stroke::new(1.0, *color))
+                        })
+                        .collect();
+                    plot_ui.bar_chart(BarChart::new(bars).color(*color).name(&distribution.label));
+                }
+            });
+
+        egui::Grid::new("histogram_stats")
+            .striped(true)
+            .show(ui, |ui| {
+                for heading in ["Span", "Samples", "Mean", "Std dev", "Min", "Max"] {
+                    ui.strong(heading);
+                }
+                ui.end_row();
+                for (distribution, color) in &shown {
+                    ui.colored_label(*color, &distribution.label);
+                    ui.label(distribution.values.len().to_string());
+                    for value in [
+                        distribution.mean,
+                        distribution.std_dev,
+                        distribution.min,
+                        distribution.max,
+                    ] {
+                        ui.label(format!("{:.4}", value));
+                    }
+                    ui.end_row();
+                }
+            });
+    }
+}
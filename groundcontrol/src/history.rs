@@ -0,0 +1,50 @@
+use ksi_telemetry::EngineDataPoint;
+
+/// Data points that aged out of the full-rate plot store, thinned so the whole
+/// session stays in view in a bounded amount of memory.
+///
+/// Kept points are at least `step_ms` apart. When the history fills, every other
+/// point is dropped and the step doubles, so older data loses resolution evenly
+/// instead of disappearing. A spike shorter than the step can fall between kept
+/// points; data_log.csv still has every sample.
+#[derive(Clone, Default)]
+pub struct History {
+    points: Vec<EngineDataPoint>,
+    step_ms: f64,
+}
+
+impl History {
+    /// Adds the oldest full-rate point, keeping at most `capacity` points.
+    pub fn push(&mut self, data_point: EngineDataPoint, capacity: usize) {
+        // A firmware restart sends time backwards; that point is always kept
+        if self
+            .points
+            .last()
+            .is_some_and(|last| (0.0..self.step_ms).contains(&(data_point.time - last.time)))
+        {
+            return;
+        }
+        self.points.push(data_point);
+        if self.points.len() > capacity.max(2) {
+            let mut index = 0;
+            self.points.retain(|_| {
+                index += 1;
+                index % 2 == 1
+            });
+            let span = match (self.points.first(), self.points.last()) {
+                (Some(first), Some(last)) => last.time - first.time,
+                _ => 0.0,
+            };
+            self.step_ms = (self.step_ms * 2.0).max(span / self.points.len() as f64);
+        }
+    }
+
+    pub fn points(&self) -> &[EngineDataPoint] {
+        &self.points
+    }
+
+    /// Least spacing between kept points in ms; zero until the history first fills.
+    pub fn step_ms(&self) -> f64 {
+        self.step_ms
+    }
+}
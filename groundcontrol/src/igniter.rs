@@ -0,0 +1,307 @@
+//! Igniter channel: an output separate from the valves, armed and fired in two
+//! deliberate steps, behind an interlock on the valve states.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::ValveEcho;
+use serde::{Deserialize, Serialize};
+
+use crate::applog::diag;
+use crate::arming::ArmState;
+
+/// Igniter timing and interlock; the channel is only driven when these are set.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IgniterSettings {
+    /// Seconds an armed igniter waits for FIRE before disarming itself
+    pub arm_timeout_s: f64,
+    /// How long one fire energizes the igniter
+    pub fire_duration_ms: u64,
+    /// Valve states required at the moment of firing
+    pub preignition_fuel_open: bool,
+    pub preignition_oxi_open: bool,
+}
+
+impl Default for IgniterSettings {
+    fn default() -> Self {
+        Self {
+            arm_timeout_s: 10.0,
+            fire_duration_ms: 1500,
+            preignition_fuel_open: false,
+            preignition_oxi_open: false,
+        }
+    }
+}
+
+/// Where the igniter is in its arm and fire sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IgniterState {
+    Safe,
+    Armed,
+    Firing,
+}
+
+impl fmt::Display for IgniterState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            IgniterState::Safe => "SAFE",
+            IgniterState::Armed => "ARMED",
+            IgniterState::Firing => "FIRING",
+        })
+    }
+}
+
+/// The igniter output as the write thread sees it: energized until a deadline,
+/// so a stalled GUI can't leave it on.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct IgniterOutput {
+    fire_until: Option<Instant>,
+}
+
+impl IgniterOutput {
+    pub fn energized(&self, now: Instant) -> bool {
+        self.fire_until.is_some_and(|until| now < until)
+    }
+
+    fn cut(&mut self) {
+        self.fire_until = None;
+    }
+}
+
+/// Why firing is refused with the valves as they are, if it is.
+///
+/// Both the commanded states and, when the firmware echoes them, the reported
+/// states must match the configured pre-ignition states.
+pub fn interlock(
+    settings: &IgniterSettings,
+    commanded: (bool, bool),
+    reported: Option<ValveEcho>,
+) -> Result<(), String> {
+    let required = (
+        settings.preignition_fuel_open,
+        settings.preignition_oxi_open,
+    );
+    let describe = |(fuel_open, oxi_open): (bool, bool)| {
+        let state = |open| if open { "open" } else { "closed" };
+        format!("fuel {}, oxidizer {}", state(fuel_open), state(oxi_open))
+    };
+    if commanded != required {
+        return Err(format!(
+            "valves must be {} (commanded {})",
+            describe(required),
+            describe(commanded)
+        ));
+    }
+    if let Some(echo) = reported {
+        let reported = (echo.fuel_open, echo.oxi_open);
+        if reported != required {
+            return Err(format!(
+                "valves must be {} (firmware reports {})",
+                describe(required),
+                describe(reported)
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Two-step igniter sequence: arm while the stand is armed, then fire within
+/// the timeout. Each fire energizes the output once and returns to SAFE.
+pub struct Igniter {
+    state: IgniterState,
+    since: Instant,
+    /// Whether the cover over the FIRE button is lifted; it drops on leaving ARMED
+    pub guard_open: bool,
+}
+
+impl Default for Igniter {
+    fn default() -> Self {
+        Self {
+            state: IgniterState::Safe,
+            since: Instant::now(),
+            guard_open: false,
+        }
+    }
+}
+
+impl Igniter {
+    pub fn state(&self) -> IgniterState {
+        self.state
+    }
+
+    /// Time left to fire before an armed igniter disarms itself.
+    pub fn arm_remaining(&self, settings: &IgniterSettings) -> Duration {
+        // NaN reads as no wait; an infinite or overlong timeout as the longest one
+        let timeout =
+            Duration::try_from_secs_f64(settings.arm_timeout_s.max(0.0)).unwrap_or(Duration::MAX);
+        timeout.saturating_sub(self.since.elapsed())
+    }
+
+    /// SAFE -> ARMED; the stand must be ARMED or FIRING.
+    pub fn arm(&mut self, stand: ArmState) -> Result<IgniterState, String> {
+        match (self.state, stand) {
+            (IgniterState::Safe, ArmState::Armed | ArmState::Firing) => {
+                Ok(self.enter(IgniterState::Armed))
+            }
+            (IgniterState::Safe, stand) => Err(format!("Can't arm the igniter while {}", stand)),
+            (state, _) => Err(format!("Igniter already {}", state)),
+        }
+    }
+
+    /// ARMED -> FIRING, energizing the output for the fire duration, if the
+    /// interlock passes.
+    pub fn fire(
+        &mut self,
+        settings: &IgniterSettings,
+        interlock: Result<(), String>,
+        output: &mut IgniterOutput,
+    ) -> Result<IgniterState, String> {
+        if self.state != IgniterState::Armed {
+            return Err(format!("Can't fire while the igniter is {}", self.state));
+        }
+        interlock.map_err(|e| format!("Interlock: {}", e))?;
+        output.fire_until = Some(Instant::now() + Duration::from_millis(settings.fire_duration_ms));
+        Ok(self.enter(IgniterState::Firing))
+    }
+
+    /// Cuts the output and returns to SAFE; None if already SAFE.
+    pub fn safe(&mut self, output: &mut IgniterOutput) -> Option<IgniterState> {
+        output.cut();
+        (self.state != IgniterState::Safe).then(|| self.enter(IgniterState::Safe))
+    }
+
+    /// Returns to SAFE when the arm times out, the fire ends, or the stand
+    /// leaves ARMED and FIRING; returns why.
+    pub fn update(
+        &mut self,
+        settings: &IgniterSettings,
+        stand: ArmState,
+        output: &mut IgniterOutput,
+    ) -> Option<String> {
+        let reason = match self.state {
+            IgniterState::Safe => return None,
+            _ if !matches!(stand, ArmState::Armed | ArmState::Firing) => {
+                format!("stand {}", stand)
+            }
+            IgniterState::Armed if self.arm_remaining(settings).is_zero() => {
+                "arm timed out".to_string()
+            }
+            IgniterState::Firing if !output.energized(Instant::now()) => {
+                "fire complete".to_string()
+            }
+            _ => return None,
+        };
+        self.safe(output);
+        Some(reason)
+    }
+
+    fn enter(&mut self, state: IgniterState) -> IgniterState {
+        diag!("Igniter: {} -> {}", self.state, state);
+        self.state = state;
+        self.since = Instant::now();
+        self.guard_open = false;
+        state
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn armed(settings: &IgniterSettings) -> (Igniter, IgniterOutput) {
+        let mut igniter = Igniter::default();
+        let mut output = IgniterOutput::default();
+        igniter.arm(ArmState::Armed).unwrap();
+        assert_eq!(igniter.update(settings, ArmState::Armed, &mut output), None);
+        (igniter, output)
+    }
+
+    #[test]
+    fn arming_needs_an_armed_stand() {
+        let mut igniter = Igniter::default();
+        assert!(igniter.arm(ArmState::Safe).is_err());
+        assert!(igniter.arm(ArmState::Aborted).is_err());
+        assert_eq!(igniter.arm(ArmState::Firing), Ok(IgniterState::Armed));
+        assert!(igniter.arm(ArmState::Armed).is_err(), "already armed");
+    }
+
+    #[test]
+    fn arm_times_out() {
+        let settings = IgniterSettings {
+            arm_timeout_s: 0.0,
+            ..IgniterSettings::default()
+        };
+        let mut igniter = Igniter::default();
+        let mut output = IgniterOutput::default();
+        igniter.arm(ArmState::Armed).unwrap();
+        assert_eq!(
+            igniter.update(&settings, ArmState::Armed, &mut output),
+            Some("arm timed out".to_string())
+        );
+        assert_eq!(igniter.state(), IgniterState::Safe);
+    }
+
+    #[test]
+    fn unrepresentable_timeouts_do_not_panic() {
+        for (timeout, times_out) in [(f64::INFINITY, false), (1e300, false), (f64::NAN, true)] {
+            let settings = IgniterSettings {
+                arm_timeout_s: timeout,
+                ..IgniterSettings::default()
+            };
+            let mut igniter = Igniter::default();
+            igniter.arm(ArmState::Armed).unwrap();
+            assert_eq!(
+                igniter.arm_remaining(&settings).is_zero(),
+                times_out,
+                "{}",
+                timeout
+            );
+        }
+    }
+
+    #[test]
+    fn interlock_refuses_firing() {
+        let settings = IgniterSettings::default();
+        let (mut igniter, mut output) = armed(&settings);
+        let open = interlock(&settings, (true, false), None);
+        assert!(open.is_err());
+        assert!(igniter.fire(&settings, open, &mut output).is_err());
+        assert_eq!(igniter.state(), IgniterState::Armed);
+        assert!(!output.energized(Instant::now()));
+
+        // The firmware's echo must agree with the commanded valves too
+        let echo = ValveEcho {
+            fuel_open: true,
+            oxi_open: false,
+        };
+        assert!(interlock(&settings, (false, false), Some(echo)).is_err());
+
+        let closed = interlock(&settings, (false, false), None);
+        assert_eq!(
+            igniter.fire(&settings, closed, &mut output),
+            Ok(IgniterState::Firing)
+        );
+        assert!(output.energized(Instant::now()));
+    }
+
+    #[test]
+    fn leaving_armed_makes_the_igniter_safe() {
+        let settings = IgniterSettings::default();
+        for stand in [ArmState::Safe, ArmState::Aborted] {
+            let (mut igniter, mut output) = armed(&settings);
+            igniter.guard_open = true;
+            assert_eq!(
+                igniter.update(&settings, stand, &mut output),
+                Some(format!("stand {}", stand))
+            );
+            assert_eq!(igniter.state(), IgniterState::Safe);
+            assert!(!igniter.guard_open);
+        }
+
+        let (mut igniter, mut output) = armed(&settings);
+        igniter.fire(&settings, Ok(()), &mut output).unwrap();
+        igniter.update(&settings, ArmState::Aborted, &mut output);
+        assert!(!output.energized(Instant::now()), "abort cuts a fire short");
+    }
+}
@@ -0,0 +1,216 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, UNIX_EPOCH};
+
+use crate::applog::diag;
+use crate::campaign::{self, SessionRecord, SessionStats, CAMPAIGN_DB_NAME};
+use ksi_telemetry::{data_log, quality, EngineDataPoint, Quality, Relays};
+
+/// Folder polled for externally recorded logs (e.g. copied off the stand SD card).
+pub const IMPORT_WATCH_DIR: &str = "import";
+const IMPORT_POLL_INTERVAL: Duration = Duration::from_secs(5);
+// Optional "field=column" overrides for the default schema mapping
+const MAPPING_FILE_NAME: &str = "schema.map";
+
+/// Default mapping from session fields to foreign CSV header names.
+const DEFAULT_MAPPING: [(&str, &str); 10] = [
+    ("timestamp", "unix_time"),
+    ("time", "time_ms"),
+    ("flow_rate_fuel", "flow_fuel"),
+    ("flow_rate_oxi", "flow_oxi"),
+    ("pulse_count_fuel", "pulses_fuel"),
+    ("pulse_count_oxi", "pulses_oxi"),
+    ("desired_pos_fuel", "pos_fuel"),
+    ("desired_pos_oxi", "pos_oxi"),
+    ("valve_fuel", "valve_fuel"),
+    ("valve_oxi", "valve_oxi"),
+];
+
+/// Starts a background thread that imports CSV logs dropped into the watch folder.
+///
+/// Each file becomes a session folder under `logs_root` and is indexed in the
+/// campaign database; the source is then moved to `processed/` or `failed/`.
+pub fn spawn_watcher(watch_dir: PathBuf, logs_root: PathBuf) {
+    thread::spawn(move || {
+        if let Err(e) = fs::create_dir_all(&watch_dir) {
+            diag!(
+                "Failed to create import folder {}: {}",
+                watch_dir.display(),
+                e
+            );
+            return;
+        }
+        diag!("Watching {} for logs to import", watch_dir.display());
+        loop {
+            for path in pending_files(&watch_dir) {
+                let outcome = import_file(&path, &watch_dir, &logs_root);
+                let destination = match &outcome {
+                    Ok(session_dir) => {
+                        diag!("Imported {} into {}", path.display(), session_dir.display());
+                        "processed"
+                    }
+                    Err(e) => {
+                        diag!("Failed to import {}: {}", path.display(), e);
+                        "failed"
+                    }
+                };
+                if let Err(e) = move_into(&path, &watch_dir.join(destination)) {
+                    diag!("Failed to move {}: {}", path.display(), e);
+                }
+            }
+            thread::sleep(IMPORT_POLL_INTERVAL);
+        }
+    });
+}
+
+fn pending_files(watch_dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(watch_dir) else {
+        return Vec::new();
+    };
+    entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| path.is_file() && path.extension().and_then(|e| e.to_str()) == Some("csv"))
+        .collect()
+}
+
+fn move_into(path: &Path, dir: &Path) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    fs::rename(path, dir.join(path.file_name().unwrap_or_default()))
+}
+
+/// Reads the schema mapping, applying any overrides from the watch folder.
+fn load_mapping(watch_dir: &Path) -> HashMap<String, String> {
+    let mut mapping: HashMap<String, String> = DEFAULT_MAPPING
+        .iter()
+        .map(|(field, column)| (field.to_string(), column.to_string()))
+        .collect();
+    if let Ok(contents) = fs::read_to_string(watch_dir.join(MAPPING_FILE_NAME)) {
+        for line in contents.lines() {
+            if let Some((field, column)) = line.split_once('=') {
+                mapping.insert(field.trim().to_string(), column.trim().to_string());
+            }
+        }
+    }
+    mapping
+}
+
+/// Converts one foreign CSV into a session folder and indexes it.
+fn import_file(path: &Path, watch_dir: &Path, logs_root: &Path) -> Result<PathBuf, String> {
+    let contents = fs::read_to_string(path).map_err(|e| e.to_string())?;
+    let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+    let header: Vec<&str> = lines
+        .next()
+        .ok_or("File is empty")?
+        .split(',')
+        .map(str::trim)
+        .collect();
+
+    // Resolve each session field to a column index in this file
+    let mapping = load_mapping(watch_dir);
+    let column = |field: &str| {
+        mapping
+            .get(field)
+            .and_then(|name| header.iter().position(|h| h == name))
+    };
+    let time_column = column("time").ok_or("No column mapped to 'time'")?;
+    let timestamp_column = column("timestamp");
+    let columns: Vec<Option<usize>> = [
+        "flow_rate_fuel",
+        "flow_rate_oxi",
+        "pulse_count_fuel",
+        "pulse_count_oxi",
+        "desired_pos_fuel",
+        "desired_pos_oxi",
+        "valve_fuel",
+        "valve_oxi",
+    ]
+    .iter()
+    .map(|field| column(field))
+    .collect();
+
+    // Without a wall-clock column, anchor firmware time to the file's modification time
+    let file_time = fs::metadata(path)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |d| d.as_secs());
+
+    let mut data_points = Vec::new();
+    for (i, line) in lines.enumerate() {
+        let values: Vec<&str> = line.split(',').map(str::trim).collect();
+        let number = |index: Option<usize>| -> Result<f64, String> {
+            match index {
+                Some(index) => values
+                    .get(index)
+                    .ok_or(format!("row {}: missing column {}", i + 2, index + 1))?
+                    .parse::<f64>()
+                    .map_err(|e| format!("row {}: {}", i + 2, e)),
+                None => Ok(0.0),
+            }
+        };
+        let time = number(Some(time_column))?;
+        let timestamp = match timestamp_column {
+            Some(_) => number(timestamp_column)? as u64,
+            None => file_time + (time / 1000.0) as u64,
+        };
+        let mut data_point = EngineDataPoint {
+            timestamp,
+            time,
+            flow_rate_fuel: number(columns[0])?,
+            flow_rate_oxi: number(columns[1])?,
+            pulse_count_fuel: number(columns[2])? as i32,
+            pulse_count_oxi: number(columns[3])? as i32,
+            desired_pos_fuel: number(columns[4])? as i32,
+            desired_pos_oxi: number(columns[5])? as i32,
+            fuel_valve_open: number(columns[6])? != 0.0,
+            oxi_valve_open: number(columns[7])? != 0.0,
+            raw_values: line.to_string(),
+            controller: None,
+            emergency: false,
+            pressures: Vec::new(),
+            temperatures: None,
+            thrust: None,
+            quality: Quality::Good,
+            valve_echo: None,
+            relays: Relays::default(),
+            t_time: None,
+            unix_ms: None,
+        };
+        data_point.quality = quality::assess(&data_point);
+        data_points.push(data_point);
+    }
+    if data_points.is_empty() {
+        return Err("No data rows".to_string());
+    }
+
+    // Write the session folder in the same format as live sessions
+    let stem = path.file_stem().and_then(|s| s.to_str()).unwrap_or("log");
+    let session_dir = logs_root.join(format!("KSI_Imported_{}", stem));
+    if session_dir.exists() {
+        return Err(format!("{} already exists", session_dir.display()));
+    }
+    fs::create_dir_all(&session_dir).map_err(|e| e.to_string())?;
+    let mut log_file =
+        data_log::create(&session_dir.join("data_log.csv"), 0).map_err(|e| e.to_string())?;
+    let mut stats = SessionStats::default();
+    for data_point in &data_points {
+        stats.update(data_point);
+        log_file
+            .write_all(data_point.to_log_line().as_bytes())
+            .map_err(|e| e.to_string())?;
+    }
+    fs::copy(path, session_dir.join("source.csv")).map_err(|e| e.to_string())?;
+
+    let record = SessionRecord {
+        log_dir: &session_dir,
+        stats: &stats,
+        extra_metrics: vec![("imported", 1.0)],
+        markers: &[],
+    };
+    campaign::record_session(&logs_root.join(CAMPAIGN_DB_NAME), &record)
+        .map_err(|e| e.to_string())?;
+    Ok(session_dir)
+}
@@ -0,0 +1,38 @@
+use std::time::{Duration, Instant};
+
+/// Command-line flag that starts groundcontrol as a read-only status display,
+/// watching the station given with `--remote`, or else the one on this machine.
+pub const KIOSK_FLAG: &str = "--kiosk";
+/// How long each dashboard widget stays on screen.
+const CYCLE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Dashboard rotation for the full-screen kiosk display.
+///
+/// Kiosk mode never opens a serial port or commands the valves; it only shows
+/// the stand status served to remote viewers.
+pub struct Kiosk {
+    widget_index: usize,
+    shown_since: Instant,
+}
+
+impl Kiosk {
+    pub fn new() -> Self {
+        Self {
+            widget_index: 0,
+            shown_since: Instant::now(),
+        }
+    }
+
+    /// Index of the widget to show, advancing once it has been up for the cycle interval.
+    pub fn current_widget(&mut self, widget_count: usize) -> usize {
+        if widget_count == 0 {
+            return 0;
+        }
+        if self.shown_since.elapsed() >= CYCLE_INTERVAL {
+            self.widget_index += 1;
+            self.shown_since = Instant::now();
+        }
+        self.widget_index %= widget_count;
+        self.widget_index
+    }
+}
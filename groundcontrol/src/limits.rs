@@ -0,0 +1,364 @@
+use serde::{Deserialize, Serialize};
+
+use ksi_telemetry::EngineDataPoint;
+
+use crate::query::Quantity;
+
+/// Operator limits for one quantity.
+///
+/// The same entries decorate the plots and raise the live limit alarms, so the
+/// lines an operator sees are always the ones being checked.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChannelLimits {
+    pub quantity: Quantity,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning_low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub warning_high: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub critical_low: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub critical_high: Option<f64>,
+    /// Acceptable operating range, shaded on the plot as [min, max]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub band: Option<[f64; 2]>,
+    /// Treat the critical limits as redlines that close both valves
+    #[serde(default)]
+    pub safe_on_critical: bool,
+    /// How far back inside a limit a value must come before its alarm clears,
+    /// so a value hovering at the limit doesn't raise it again and again
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub hysteresis: f64,
+    /// Time a value must stay beyond a limit before its alarm is raised, in firmware ms
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub min_duration_ms: f64,
+}
+
+fn is_zero(value: &f64) -> bool {
+    *value == 0.0
+}
+
+/// How far a value is outside its limits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LimitLevel {
+    Normal,
+    Warning,
+    Critical,
+}
+
+impl ChannelLimits {
+    pub fn new(quantity: Quantity) -> Self {
+        Self {
+            quantity,
+            warning_low: None,
+            warning_high: None,
+            critical_low: None,
+            critical_high: None,
+            band: None,
+            safe_on_critical: false,
+            hysteresis: 0.0,
+            min_duration_ms: 0.0,
+        }
+    }
+
+    /// The same limits for values multiplied by `factor`, e.g. in another unit.
+    pub fn scaled(self, factor: f64) -> Self {
+        let scale = |value: Option<f64>| value.map(|v| v * factor);
+        Self {
+            warning_low: scale(self.warning_low),
+            warning_high: scale(self.warning_high),
+            critical_low: scale(self.critical_low),
+            critical_high: scale(self.critical_high),
+            band: self.band.map(|band| band.map(|v| v * factor)),
+            hysteresis: self.hysteresis * factor,
+            ..self
+        }
+    }
+
+    /// The critical limit a value is beyond, described for the operator.
+    fn critical_breach(&self, value: f64) -> Option<String> {
+        match (self.critical_low, self.critical_high) {
+            (Some(low), _) if value < low => Some(format!("below {}", low)),
+            (_, Some(high)) if value > high => Some(format!("above {}", high)),
+            _ => None,
+        }
+    }
+
+    /// Level of a value against these limits; leaving the band alone is not an alarm.
+    pub fn level(&self, value: f64) -> LimitLevel {
+        self.level_within(value, 0.0)
+    }
+
+    /// Level against the limits moved `margin` inward, where a raised alarm clears.
+    fn level_within(&self, value: f64, margin: f64) -> LimitLevel {
+        let beyond = |low: Option<f64>, high: Option<f64>| {
+            low.is_some_and(|low| value < low + margin)
+                || high.is_some_and(|high| value > high - margin)
+        };
+        if beyond(self.critical_low, self.critical_high) {
+            LimitLevel::Critical
+        } else if beyond(self.warning_low, self.warning_high) {
+            LimitLevel::Warning
+        } else {
+            LimitLevel::Normal
+        }
+    }
+}
+
+/// A quantity currently outside its warning or critical limits.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LimitAlarm {
+    pub quantity: Quantity,
+    pub level: LimitLevel,
+    pub value: f64,
+}
+
+impl LimitAlarm {
+    pub fn message(&self) -> String {
+        let level = match self.level {
+            LimitLevel::Critical => "CRITICAL",
+            _ => "warning",
+        };
+        format!("{} {} ({:.2})", self.quantity.name(), level, self.value)
+    }
+}
+
+/// Alarm state of one limits entry.
+#[derive(Clone, Copy)]
+struct AlarmState {
+    level: LimitLevel,
+    // A higher level the value has been at since the given firmware time
+    pending: Option<(LimitLevel, f64)>,
+}
+
+impl Default for AlarmState {
+    fn default() -> Self {
+        Self {
+            level: LimitLevel::Normal,
+            pending: None,
+        }
+    }
+}
+
+/// Raises limit alarms once a value has stayed beyond a limit for the entry's
+/// minimum duration, and clears them once it is back inside by its hysteresis.
+#[derive(Default)]
+pub struct LimitMonitor {
+    // Per limits entry, in settings order
+    states: Vec<AlarmState>,
+}
+
+impl LimitMonitor {
+    /// Limit alarms active after a data point, most severe first.
+    pub fn update(&mut self, limits: &[ChannelLimits], dp: &EngineDataPoint) -> Vec<LimitAlarm> {
+        self.states.resize(limits.len(), AlarmState::default());
+        let mut alarms = Vec::new();
+        for (limits, state) in limits.iter().zip(&mut self.states) {
+            let Some(value) = limits.quantity.value(dp) else {
+                *state = AlarmState::default();
+                continue;
+            };
+            let level = limits.level(value);
+            if level > state.level {
+                let since = match state.pending {
+                    Some((pending, since)) if pending == level => since,
+                    _ => dp.time,
+                };
+                if dp.time - since >= limits.min_duration_ms {
+                    *state = AlarmState {
+                        level,
+                        pending: None,
+                    };
+                } else {
+                    state.pending = Some((level, since));
+                }
+            } else {
+                state.pending = None;
+                state.level = state
+                    .level
+                    .min(limits.level_within(value, limits.hysteresis.abs()));
+            }
+            if state.level != LimitLevel::Normal {
+                alarms.push(LimitAlarm {
+                    quantity: limits.quantity,
+                    level: state.level,
+                    value,
+                });
+            }
+        }
+        alarms.sort_by_key(|alarm| std::cmp::Reverse(alarm.level));
+        alarms
+    }
+}
+
+/// Trips a redline when a safing limit stays critical for consecutive samples.
+///
+/// Requiring several samples keeps a single noisy reading from closing the valves.
+#[derive(Default)]
+pub struct RedlineMonitor {
+    // Consecutive critical samples per limits entry, in settings order
+    counts: Vec<u32>,
+}
+
+impl RedlineMonitor {
+    /// Counts a data point; returns why redlines tripped on this sample.
+    ///
+    /// A breach trips on every sample from its `samples`th on while `armed`, so
+    /// re-arming with a channel still critical trips again at once.
+    pub fn update(
+        &mut self,
+        limits: &[ChannelLimits],
+        samples: u32,
+        armed: bool,
+        dp: &EngineDataPoint,
+    ) -> Vec<String> {
+        self.counts.resize(limits.len(), 0);
+        let mut tripped = Vec::new();
+        for (limits, count) in limits.iter().zip(&mut self.counts) {
+            let breach = limits
+                .safe_on_critical
+                .then(|| limits.quantity.value(dp))
+                .flatten()
+                .and_then(|value| Some((value, limits.critical_breach(value)?)));
+            let Some((value, breach)) = breach else {
+                *count = 0;
+                continue;
+            };
+            *count = count.saturating_add(1);
+            if *count >= samples.max(1) && armed {
+                tripped.push(format!(
+                    "Redline: {} {} for {} samples (last {:.2})",
+                    limits.quantity.name(),
+                    breach,
+                    count,
+                    value
+                ));
+            }
+        }
+        tripped
+    }
+
+    /// Whether any safing limit was critical at the latest sample.
+    pub fn critical(&self) -> bool {
+        self.counts.iter().any(|&count| count > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(time: f64, flow_fuel: f64, flow_oxi: f64) -> EngineDataPoint {
+        let mut dp = ksi_telemetry::parse_line("0,0,0,0,0,0,0,0").unwrap();
+        dp.time = time;
+        dp.flow_rate_fuel = flow_fuel;
+        dp.flow_rate_oxi = flow_oxi;
+        dp
+    }
+
+    fn redline(quantity: Quantity, high: f64) -> ChannelLimits {
+        ChannelLimits {
+            critical_high: Some(high),
+            safe_on_critical: true,
+            ..ChannelLimits::new(quantity)
+        }
+    }
+
+    fn warning(quantity: Quantity, high: f64) -> ChannelLimits {
+        ChannelLimits {
+            warning_high: Some(high),
+            critical_high: Some(high * 2.0),
+            ..ChannelLimits::new(quantity)
+        }
+    }
+
+    fn levels(alarms: &[LimitAlarm]) -> Vec<LimitLevel> {
+        alarms.iter().map(|alarm| alarm.level).collect()
+    }
+
+    #[test]
+    fn alarms_clear_only_past_the_hysteresis() {
+        let limits = [ChannelLimits {
+            hysteresis: 1.0,
+            ..warning(Quantity::FlowFuel, 10.0)
+        }];
+        let mut monitor = LimitMonitor::default();
+        assert!(monitor.update(&limits, &sample(0.0, 9.0, 0.0)).is_empty());
+        let alarms = monitor.update(&limits, &sample(10.0, 10.5, 0.0));
+        assert_eq!(levels(&alarms), [LimitLevel::Warning]);
+        assert_eq!(alarms[0].value, 10.5);
+        // Back under the limit, but not by the hysteresis
+        let alarms = monitor.update(&limits, &sample(20.0, 9.5, 0.0));
+        assert_eq!(levels(&alarms), [LimitLevel::Warning]);
+        assert!(monitor.update(&limits, &sample(30.0, 8.5, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn alarms_wait_for_the_minimum_duration() {
+        let limits = [ChannelLimits {
+            min_duration_ms: 100.0,
+            ..warning(Quantity::FlowFuel, 10.0)
+        }];
+        let mut monitor = LimitMonitor::default();
+        assert!(monitor.update(&limits, &sample(0.0, 11.0, 0.0)).is_empty());
+        assert!(monitor.update(&limits, &sample(50.0, 11.0, 0.0)).is_empty());
+        // A dip back inside restarts the wait
+        assert!(monitor.update(&limits, &sample(60.0, 9.0, 0.0)).is_empty());
+        assert!(monitor
+            .update(&limits, &sample(120.0, 11.0, 0.0))
+            .is_empty());
+        assert!(monitor
+            .update(&limits, &sample(200.0, 11.0, 0.0))
+            .is_empty());
+        let alarms = monitor.update(&limits, &sample(220.0, 11.0, 0.0));
+        assert_eq!(levels(&alarms), [LimitLevel::Warning]);
+        // Clearing is immediate once back inside
+        assert!(monitor.update(&limits, &sample(230.0, 9.0, 0.0)).is_empty());
+    }
+
+    #[test]
+    fn alarms_are_ordered_by_severity() {
+        let limits = [
+            warning(Quantity::FlowFuel, 10.0),
+            warning(Quantity::FlowOxi, 10.0),
+        ];
+        let mut monitor = LimitMonitor::default();
+        let alarms = monitor.update(&limits, &sample(0.0, 11.0, 25.0));
+        assert_eq!(levels(&alarms), [LimitLevel::Critical, LimitLevel::Warning]);
+        assert_eq!(alarms[0].quantity, Quantity::FlowOxi);
+    }
+
+    #[test]
+    fn redline_trips_again_after_rearming() {
+        let limits = [redline(Quantity::FlowFuel, 10.0)];
+        let mut monitor = RedlineMonitor::default();
+        let hot = sample(0.0, 12.0, 0.0);
+        assert!(monitor.update(&limits, 2, true, &hot).is_empty());
+        assert_eq!(monitor.update(&limits, 2, true, &hot).len(), 1);
+        // Aborted: the breach is held, not reported again
+        assert!(monitor.update(&limits, 2, false, &hot).is_empty());
+        assert!(monitor.critical());
+        assert_eq!(
+            monitor.update(&limits, 2, true, &hot).len(),
+            1,
+            "re-armed while still critical"
+        );
+        assert!(monitor
+            .update(&limits, 2, true, &sample(0.0, 5.0, 0.0))
+            .is_empty());
+        assert!(!monitor.critical());
+    }
+
+    #[test]
+    fn redlines_on_the_same_sample_are_all_reported() {
+        let limits = [
+            redline(Quantity::FlowFuel, 10.0),
+            redline(Quantity::FlowOxi, 10.0),
+        ];
+        let mut monitor = RedlineMonitor::default();
+        let tripped = monitor.update(&limits, 1, true, &sample(0.0, 12.0, 15.0));
+        assert_eq!(tripped.len(), 2);
+        assert!(tripped[0].contains("Fuel Flow"));
+        assert!(tripped[1].contains("Oxidizer Flow"));
+    }
+}
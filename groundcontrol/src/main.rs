@@ -1,414 +1,3595 @@
 use eframe::egui;
-use egui_plot::{Legend, Line, Plot, PlotPoints};
 use std::collections::VecDeque;
-use std::fs::{self, File};
-use std::io::{BufRead, Write};
-use std::path::PathBuf;
-use std::sync::mpsc::{self, Receiver, Sender};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::mpsc::{self, Receiver};
 use std::sync::{Arc, Mutex};
-use std::thread;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use std::time::{Duration, Instant};
 
+mod alerts;
+mod applog;
+mod arming;
+mod calibration;
+mod campaign;
+mod clock;
+mod commands;
+mod compression;
+mod config;
+mod console;
+mod countdown;
+mod deadman;
+mod decoding;
+mod devices;
+mod environment;
+mod events;
+mod frozen;
+mod headless;
+mod highlights;
+mod histogram;
+mod history;
+mod igniter;
+mod import;
+mod kiosk;
+mod limits;
+mod mqtt;
+mod network;
+mod parquet_log;
+mod quality;
+mod query;
+mod readouts;
+mod recorder;
+mod relays;
+mod replay;
+mod screenshots;
+mod scripting;
+mod serial;
+mod session_info;
+mod simulator;
+mod sleepwatch;
+mod startup;
+mod timesync;
+mod trends;
+mod valves;
+mod widgets;
+
+use alerts::{AlertEvent, Alerter};
+use applog::diag;
+use arming::{ArmState, Arming};
+use calibration::{CalibrationWizard, FlowUnit};
+use campaign::{SessionRecord, SessionStats, CAMPAIGN_DB_NAME};
+use clock::MissionClock;
+use commands::CommandDialect;
+use compression::{LinkCodec, LinkCompression};
+use config::{EnvironmentSensor, PressureChannel, SerialDevice, Settings};
+use console::FirmwareConsole;
+use countdown::{format_t_time, Countdown};
+use deadman::{DeadmanInput, Gamepads};
+use decoding::{FlowDecoding, FlowDecodingConfig};
+use devices::{DeviceHub, DEVICE_STALE_MS};
+use environment::Environment;
+use events::{EventLog, ANNOTATION_KEY, QUICK_MARKERS};
+use frozen::{Channel, FrozenChannelDetector};
+use highlights::{HighlightKind, Highlights};
+use histogram::HistogramTool;
+use history::History;
+use igniter::{Igniter, IgniterOutput, IgniterSettings, IgniterState};
+use kiosk::Kiosk;
+use ksi_telemetry::packet::{Framing, MAX_PACKET_LEN};
+use ksi_telemetry::{EngineDataPoint, Relays};
+use limits::{ChannelLimits, LimitAlarm, LimitLevel, LimitMonitor, RedlineMonitor};
+use mqtt::{MqttPublisher, MqttSettings};
+use network::{RemoteClient, StandStatus, TelemetryServer, UdpBroadcaster};
+use quality::QualityChecks;
+use query::{PlotFocus, Quantity, QueryTool};
+use recorder::DataRecorder;
+use relays::{BeaconAutomation, RelayChannel};
+use replay::{Replay, REPLAY_SPEEDS};
+use screenshots::{PlotCapture, Screenshots};
+use scripting::{ScriptRequest, ScriptRunner};
+use serial::{ConnectionState, SerialLink, SerialShared, BAUD_RATES};
+use session_info::{SessionInfo, SessionInfoDraft};
+use simulator::Simulator;
+use sleepwatch::SleepWatch;
+use startup::{SelfTest, StartupGate};
+use timesync::{TimeSource, TimeSync};
+use trends::CampaignTrends;
+use valves::{DutyCycleGuard, PositionRamp};
+use widgets::{ChannelGroup, DashboardLayout, DashboardWidget, TimeWindow, WidgetContext};
+
+const WINDOW_TITLE: &str = "Khan Space Industries | Ground Control System";
+// Defaults written to settings.toml on first run
 const PORT_NAME: &str = "/dev/cu.usbserial-10";
 const BAUD_RATE: u32 = 115_200;
-const TIMEOUT_MS: u64 = 100;
 const BROADCAST_INTERVAL_MS: u64 = 100;
-const MAX_DATA_POINTS: usize = 1000;
-
-#[derive(Debug, Clone)]
-struct EngineDataPoint {
-    timestamp: u64, // Real date timestamp in Unix time
-    time: f64,      // Time from the data
-    flow_rate_fuel: f64,
-    flow_rate_oxi: f64,
-    pulse_count_fuel: i32,
-    pulse_count_oxi: i32,
-    desired_pos_fuel: i32,
-    desired_pos_oxi: i32,
-    fuel_valve_open: bool, // Valve states at the time of data point
-    oxi_valve_open: bool,
-    raw_values: String, // Raw decoded values as a string
-}
+const MAX_DATA_POINTS: usize = 30_000; // 5 min at 100 Hz
+const TIMEOUT_MS: u64 = 100;
+const MAX_VALVE_CYCLES_PER_MINUTE: usize = 20;
+// Time the firmware has to echo a valve command before it shows as a mismatch
+const VALVE_ACK_TIMEOUT: Duration = Duration::from_secs(1);
+// Keys held to open the fuel and oxidizer valves in momentary mode
+const MOMENTARY_KEYS: (egui::Key, egui::Key) = (egui::Key::J, egui::Key::K);
+// Valve command broadcast interval while a momentary control is held, so a release closes quickly
+const MOMENTARY_BROADCAST_INTERVAL: Duration = Duration::from_millis(20);
+// Session subfolder for exported plot images
+const PLOT_EXPORT_DIR_NAME: &str = "plots";
 
-#[derive(Default)]
+#[derive(Default, Clone)]
 struct EngineData {
+    // Every recent data point, up to the max_data_points setting
     data_points: VecDeque<EngineDataPoint>,
+    // Older data points, thinned to fit the same number again
+    history: History,
     // Valve states
     fuel_valve_open: bool,
     oxi_valve_open: bool,
 }
 
-struct FlowRateApp {
-    // Receiver for data points
-    data_receiver: Receiver<EngineDataPoint>,
-    // Shared valve states
-    valve_state_sender: Sender<(bool, bool)>,
-    // Local data storage
-    engine_data: EngineData,
-    // Latest raw decoded values
-    latest_raw_values: String,
-    // Log directory path
-    log_dir: PathBuf,
-}
+impl EngineData {
+    /// Adds a data point, moving the oldest full-rate ones into the thinned history.
+    fn push(&mut self, data_point: EngineDataPoint, max_data_points: usize) {
+        self.data_points.push_back(data_point);
+        while self.data_points.len() > max_data_points {
+            if let Some(oldest) = self.data_points.pop_front() {
+                self.history.push(oldest, max_data_points);
+            }
+        }
+    }
+
+    /// Every kept data point, oldest first.
+    fn points(&self) -> impl Iterator<Item = &EngineDataPoint> + Clone {
+        self.history.points().iter().chain(&self.data_points)
+    }
+
+    /// Kept data points within `window` of the latest one.
+    fn visible(&self, window: TimeWindow) -> impl Iterator<Item = &EngineDataPoint> + Clone {
+        let start = window
+            .duration_ms()
+            .zip(self.data_points.back())
+            .map_or(f64::NEG_INFINITY, |(ms, latest)| latest.time - ms);
+        self.points().filter(move |dp| dp.time >= start)
+    }
+}
+
+struct FlowRateApp {
+    // Receiver for data points
+    data_receiver: Receiver<EngineDataPoint>,
+    // State shared with the serial threads
+    serial: SerialShared,
+    // Active serial connection, if any
+    serial_link: Option<SerialLink>,
+    // Synthetic telemetry in place of the serial link, when started with --simulate
+    simulator: Option<Simulator>,
+    // Another ground station's telemetry server, viewed read-only, when started with --remote
+    remote: Option<RemoteClient>,
+    // Connection controls
+    available_ports: Vec<String>,
+    selected_port: String,
+    selected_baud: u32,
+    connection_error: Option<String>,
+    // Local data storage
+    engine_data: EngineData,
+    // History kept for the plots, and the copy they show while frozen for inspection
+    time_window: TimeWindow,
+    frozen_view: Option<EngineData>,
+    // Latest raw decoded values
+    latest_raw_values: String,
+    // Arrival of the latest live data point, or of the link before any arrived
+    last_packet: Instant,
+    // Log directory path
+    log_dir: PathBuf,
+    // Valve actuation counter and duty-cycle limit
+    duty_guard: DutyCycleGuard,
+    // Last refused valve command, shown until the next accepted one
+    duty_warning: Option<String>,
+    // When the valve states last changed, for timing out the firmware's echo
+    valves_commanded_at: Instant,
+    // Deadman switch: valves close as soon as the key is released
+    gamepads: Gamepads,
+    deadman_held: bool,
+    // Momentary mode: valves open only while their button or key is held
+    momentary: bool,
+    // Fuel and oxidizer controls held as of the last frame
+    momentary_held: (bool, bool),
+    // SAFE/ARMED/FIRING/ABORTED; every valve command goes through it
+    arming: Arming,
+    // Emergency flag in the latest firmware line
+    firmware_emergency: bool,
+    // Bring-up gates before arming: the settings file error, link self-test, and ticked checklist items
+    config_error: Option<String>,
+    self_test: SelfTest,
+    checklist_done: Vec<bool>,
+    // Operator event markers
+    event_log: EventLog,
+    // Event log window, and the annotation being typed with the time its key was pressed
+    event_log_open: bool,
+    annotation: String,
+    annotation_time: Option<f64>,
+    focus_annotation: bool,
+    // Time under the pointer on any plot, shared by all plots and the readout
+    plot_cursor: Option<f64>,
+    // Markers, phases, and alarms exported to highlights.json
+    highlights: Highlights,
+    // Per-channel stale value detection
+    frozen_channels: FrozenChannelDetector,
+    // Quantities in alarm at the latest data point, and the hysteresis state behind them
+    limit_alarms: Vec<LimitAlarm>,
+    limit_monitor: LimitMonitor,
+    // Safing limits held critical over consecutive samples, and the unacknowledged trip
+    redline_monitor: RedlineMonitor,
+    redline_alert: Option<String>,
+    // Other boards silent at the last check
+    silent_boards: Vec<String>,
+    // Sounds and desktop notifications for critical events
+    alerter: Alerter,
+    // Window captures at ignition, shutdown, aborts, and alarms, and plot exports
+    screenshots: Screenshots,
+    // Closes the valves when the host wakes from sleep or a stall
+    sleep_watch: SleepWatch,
+    // Dashboard displays, drawn in layout order, and the layout being edited
+    widgets: Vec<Box<dyn DashboardWidget>>,
+    layout: DashboardLayout,
+    layout_draft: Option<DashboardLayout>,
+    // Where each widget was drawn last frame, for plot exports
+    widget_rects: Vec<(String, egui::Rect)>,
+    // T-0 reference for T-relative display
+    clock: MissionClock,
+    // Session statistics recorded in the campaign database on exit
+    session_stats: SessionStats,
+    // Settings in effect, and the copy being edited while the window is open
+    settings: Settings,
+    settings_draft: Option<Settings>,
+    // Test name, propellants, and crew saved to metadata.json, and the copy being edited
+    session_info: SessionInfo,
+    session_info_draft: Option<SessionInfoDraft>,
+    // Campaign trend window, loaded when opened
+    campaign_trends: Option<CampaignTrends>,
+    // Find tool window, and the result the plots were last jumped to
+    query: Option<QueryTool>,
+    // Channel distribution window
+    histogram: Option<HistogramTool>,
+    // Known-volume K-factor measurement window
+    calibration_wizard: Option<CalibrationWizard>,
+    // Igniter arm and fire sequence, when the stand has an igniter channel
+    igniter: Igniter,
+    // Follows the arm state with the warning beacon relay
+    beacon_automation: BeaconAutomation,
+    // Firmware console window
+    console_open: bool,
+    // Scripted sequence window, its source, and the script running, if any
+    script_open: bool,
+    script_path: String,
+    script_source: String,
+    script: Option<ScriptRunner>,
+    // How the last script ended
+    script_status: Option<String>,
+    plot_focus: Option<PlotFocus>,
+    // Read-only full-screen display, when started with --kiosk
+    kiosk: Option<Kiosk>,
+    // Playback of a previous session's log, instead of a serial link
+    replay: Option<Replay>,
+    replay_sessions: Vec<PathBuf>,
+    selected_replay: Option<PathBuf>,
+}
+
+impl FlowRateApp {
+    /// Creates a new FlowRateApp instance.
+    fn new(
+        data_receiver: Receiver<EngineDataPoint>,
+        serial: SerialShared,
+        log_dir: PathBuf,
+        event_log: EventLog,
+        settings: Settings,
+        kiosk: bool,
+        simulator: Option<Simulator>,
+    ) -> Self {
+        let available_ports = serial::available_ports();
+        let selected_port = default_port(&available_ports, &settings.port);
+        let highlights = Highlights::new(&log_dir);
+        let screenshots = Screenshots::new(&log_dir);
+        let sleep_watch = SleepWatch::start(serial.valve_states.clone());
+        Self {
+            data_receiver,
+            serial,
+            serial_link: None,
+            simulator,
+            remote: None,
+            available_ports,
+            selected_port,
+            selected_baud: settings.baud_rate,
+            connection_error: None,
+            engine_data: EngineData::default(),
+            time_window: TimeWindow::Seconds10,
+            frozen_view: None,
+            latest_raw_values: String::new(),
+            last_packet: Instant::now(),
+            log_dir,
+            duty_guard: DutyCycleGuard::new(settings.max_valve_cycles_per_minute),
+            duty_warning: None,
+            valves_commanded_at: Instant::now(),
+            gamepads: Gamepads::new(),
+            deadman_held: false,
+            momentary: false,
+            momentary_held: (false, false),
+            arming: Arming::default(),
+            firmware_emergency: false,
+            config_error: None,
+            self_test: SelfTest::default(),
+            checklist_done: vec![false; settings.checklist.len()],
+            event_log,
+            event_log_open: false,
+            annotation: String::new(),
+            annotation_time: None,
+            focus_annotation: false,
+            plot_cursor: None,
+            highlights,
+            frozen_channels: FrozenChannelDetector::default(),
+            limit_alarms: Vec::new(),
+            limit_monitor: LimitMonitor::default(),
+            redline_monitor: RedlineMonitor::default(),
+            redline_alert: None,
+            // Only a board going silent after it streamed is noted
+            silent_boards: settings.devices.iter().map(|d| d.name.clone()).collect(),
+            alerter: Alerter::default(),
+            screenshots,
+            widget_rects: Vec::new(),
+            sleep_watch,
+            widgets: widgets::dashboard_widgets(&settings),
+            layout: widgets::effective_layout(&settings),
+            layout_draft: None,
+            clock: MissionClock::default(),
+            session_stats: SessionStats::default(),
+            settings,
+            settings_draft: None,
+            session_info: SessionInfo::default(),
+            // Asked for before the test, except on a read-only kiosk display
+            session_info_draft: (!kiosk).then(|| SessionInfoDraft::new(&SessionInfo::default())),
+            campaign_trends: None,
+            query: None,
+            histogram: None,
+            calibration_wizard: None,
+            igniter: Igniter::default(),
+            beacon_automation: BeaconAutomation::default(),
+            console_open: false,
+            script_open: false,
+            script_path: String::new(),
+            script_source: String::new(),
+            script: None,
+            script_status: None,
+            plot_focus: None,
+            kiosk: kiosk.then(Kiosk::new),
+            replay: None,
+            replay_sessions: Vec::new(),
+            selected_replay: None,
+        }
+    }
+
+    /// Drops plotted data so a replay or seek starts from an empty view.
+    fn clear_data(&mut self) {
+        while self.data_receiver.try_recv().is_ok() {}
+        self.engine_data.data_points.clear();
+        self.engine_data.history = History::default();
+        self.frozen_view = None;
+        self.frozen_channels = FrozenChannelDetector::default();
+        self.limit_monitor = LimitMonitor::default();
+        self.latest_raw_values.clear();
+    }
+
+    /// Previous session selection and playback controls for replay.
+    fn replay_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.label("Replay");
+            // Taken out while its controls are drawn; put back unless stopped
+            let Some(replay) = self.replay.take() else {
+                let session_name = |log: &PathBuf| {
+                    log.parent()
+                        .and_then(|dir| dir.file_name())
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default()
+                };
+                let selected_text = self
+                    .selected_replay
+                    .as_ref()
+                    .map(session_name)
+                    .unwrap_or_else(|| "Select session".to_string());
+                let response = egui::ComboBox::from_id_salt("replay_session")
+                    .selected_text(selected_text)
+                    .show_ui(ui, |ui| {
+                        for log in &self.replay_sessions {
+                            ui.selectable_value(
+                                &mut self.selected_replay,
+                                Some(log.clone()),
+                                session_name(log),
+                            );
+                        }
+                    });
+                // Rescan when the list is opened so new sessions show up
+                if response.response.clicked() {
+                    if let Some(logs_root) = self.log_dir.parent() {
+                        self.replay_sessions = replay::previous_sessions(logs_root, &self.log_dir);
+                    }
+                }
+                let can_replay = self.serial_link.is_none()
+                    && self.simulator.is_none()
+                    && self.remote.is_none()
+                    && self.selected_replay.is_some();
+                if ui
+                    .add_enabled(can_replay, egui::Button::new("Start Replay"))
+                    .on_disabled_hover_text("Disconnect and select a session to replay")
+                    .clicked()
+                {
+                    if let Some(log) = &self.selected_replay {
+                        match Replay::start(log, self.serial.data_sender.clone()) {
+                            Ok(replay) => {
+                                self.clear_data();
+                                self.replay = Some(replay);
+                            }
+                            Err(e) => diag!("Failed to start replay: {}", e),
+                        }
+                    }
+                }
+                return;
+            };
+
+            let (start, end) = (replay.start, replay.end);
+            let mut control = replay.control();
+            let finished = control.paused && control.position >= end;
+            let play_label = if control.paused { "Play" } else { "Pause" };
+            if ui.button(play_label).clicked() {
+                if finished {
+                    control.seek(start);
+                    self.clear_data();
+                }
+                control.paused = !control.paused;
+            }
+            egui::ComboBox::from_id_salt("replay_speed")
+                .selected_text(format!("{}x", control.speed))
+                .show_ui(ui, |ui| {
+                    for speed in REPLAY_SPEEDS {
+                        ui.selectable_value(&mut control.speed, speed, format!("{}x", speed));
+                    }
+                });
+            let mut position = control.position;
+            let progress = format!(
+                "{:.1} / {:.1} s",
+                (position - start) / 1000.0,
+                (end - start) / 1000.0
+            );
+            let slider = egui::Slider::new(&mut position, start..=end)
+                .show_value(false)
+                .text(progress);
+            if ui.add(slider).changed() {
+                control.seek(position);
+                self.clear_data();
+            }
+            drop(control);
+
+            if ui.button("Stop Replay").clicked() {
+                drop(replay);
+                self.clear_data();
+                return;
+            }
+            ui.label(replay.path.display().to_string());
+            self.replay = Some(replay);
+        });
+    }
+
+    /// Color and text describing the serial link for the header.
+    fn connection_status(&self) -> (egui::Color32, String) {
+        if self.simulator.is_some() {
+            return (egui::Color32::LIGHT_BLUE, "Simulated telemetry".to_string());
+        }
+        if let Some(remote) = &self.remote {
+            return if remote.connected() {
+                (
+                    egui::Color32::LIGHT_BLUE,
+                    format!("Viewing {} (read-only)", remote.address),
+                )
+            } else {
+                (
+                    egui::Color32::YELLOW,
+                    format!("Waiting for {}", remote.address),
+                )
+            };
+        }
+        match &self.serial_link {
+            Some(link) => match link.state() {
+                ConnectionState::Connected => (
+                    egui::Color32::GREEN,
+                    format!("Connected to {} @ {}", link.port_name, link.baud_rate),
+                ),
+                ConnectionState::Reconnecting { attempt } => (
+                    egui::Color32::YELLOW,
+                    format!("Reconnecting to {} (attempt {})", link.port_name, attempt),
+                ),
+                ConnectionState::Disconnected => (egui::Color32::RED, "Disconnected".to_string()),
+            },
+            None => match &self.connection_error {
+                Some(e) => (egui::Color32::RED, e.clone()),
+                None => (egui::Color32::RED, "Disconnected".to_string()),
+            },
+        }
+    }
+
+    /// Seconds since the last live data point, once past the stale window.
+    ///
+    /// None while nothing is expected: no link, or a replay driving the display.
+    fn stale_seconds(&self) -> Option<f64> {
+        let live = self.serial_link.is_some() || self.simulator.is_some() || self.remote.is_some();
+        let silent = self.last_packet.elapsed().as_secs_f64();
+        (live && self.replay.is_none() && silent > self.settings.stale_after_s).then_some(silent)
+    }
+
+    /// Red-bordered banner shown while telemetry is stale, so frozen plots don't look healthy.
+    fn stale_banner(&self, ui: &mut egui::Ui) {
+        let Some(silent) = self.stale_seconds() else {
+            return;
+        };
+        egui::Frame::none()
+            .stroke(egui::Stroke::new(3.0, egui::Color32::RED))
+            .inner_margin(egui::Margin::symmetric(12.0, 6.0))
+            .show(ui, |ui| {
+                ui.set_width(ui.available_width());
+                ui.colored_label(
+                    egui::Color32::RED,
+                    egui::RichText::new(format!("STALE DATA ({:.1} s)", silent))
+                        .heading()
+                        .strong(),
+                );
+            });
+    }
+
+    /// Opens the selected port, keeping the error for display on failure.
+    fn connect(&mut self) {
+        match SerialLink::connect(
+            &self.selected_port,
+            self.selected_baud,
+            self.settings.telemetry_format(),
+            &self.serial,
+        ) {
+            Ok(link) => {
+                self.serial_link = Some(link);
+                self.last_packet = Instant::now();
+                *self.serial.reported_valves.lock().unwrap() = None;
+                self.connection_error = None;
+            }
+            Err(e) => {
+                diag!("{}", e);
+                self.connection_error = Some(e);
+            }
+        }
+    }
+
+    /// Port and baud selection with connect/disconnect.
+    fn connection_controls(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            let connected = self.serial_link.is_some();
+            ui.add_enabled_ui(!connected, |ui| {
+                ui.label("Port");
+                egui::ComboBox::from_id_salt("serial_port")
+                    .selected_text(&self.selected_port)
+                    .show_ui(ui, |ui| {
+                        for port in &self.available_ports {
+                            ui.selectable_value(&mut self.selected_port, port.clone(), port);
+                        }
+                    });
+                ui.label("Baud");
+                egui::ComboBox::from_id_salt("baud_rate")
+                    .selected_text(self.selected_baud.to_string())
+                    .show_ui(ui, |ui| {
+                        for baud in BAUD_RATES {
+                            ui.selectable_value(&mut self.selected_baud, baud, baud.to_string());
+                        }
+                    });
+                if ui.button("Refresh").clicked() {
+                    self.available_ports = serial::available_ports();
+                }
+            });
+
+            if connected {
+                if ui.button("Disconnect").clicked() {
+                    // Dropping the link stops its threads after a final close command
+                    self.serial_link = None;
+                    self.engine_data.fuel_valve_open = false;
+                    self.engine_data.oxi_valve_open = false;
+                    *self.serial.valve_states.lock().unwrap() = (false, false);
+                    *self.serial.reported_valves.lock().unwrap() = None;
+                }
+            } else {
+                let can_connect = !self.selected_port.is_empty()
+                    && self.replay.is_none()
+                    && self.simulator.is_none()
+                    && self.remote.is_none();
+                if ui
+                    .add_enabled(can_connect, egui::Button::new("Connect"))
+                    .clicked()
+                {
+                    self.connect();
+                }
+            }
+            let (color, status) = self.connection_status();
+            ui.colored_label(color, status);
+            for device in self.serial.devices.readings() {
+                let (color, status) = match device.age() {
+                    Some(age) if age < Duration::from_millis(DEVICE_STALE_MS) => {
+                        (egui::Color32::GREEN, format!("{}: streaming", device.name))
+                    }
+                    Some(age) => (
+                        egui::Color32::RED,
+                        format!("{}: silent {:.1} s", device.name, age.as_secs_f64()),
+                    ),
+                    None => (egui::Color32::RED, format!("{}: no data", device.name)),
+                };
+                ui.separator();
+                ui.colored_label(color, status);
+            }
+        });
+    }
+
+    /// Full-screen status display: no controls, one dashboard at a time.
+    fn show_kiosk(&mut self, ctx: &egui::Context) {
+        let Some(kiosk) = &mut self.kiosk else {
+            return;
+        };
+        let widget_index = kiosk.current_widget(self.widgets.len());
+
+        egui::TopBottomPanel::top("kiosk_status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                let current_time = chrono::Local::now().format("%H:%M:%S").to_string();
+                ui.heading(current_time);
+                ui.separator();
+                let (color, status) = self.connection_status();
+                ui.colored_label(color, egui::RichText::new(status).heading());
+                ui.separator();
+                stand_heading(ui, self.stand_status().as_ref());
+                ui.separator();
+                for (name, open) in [
+                    ("Fuel", self.engine_data.fuel_valve_open),
+                    ("Oxidizer", self.engine_data.oxi_valve_open),
+                ] {
+                    let (color, state) = if open {
+                        (egui::Color32::GREEN, "OPEN")
+                    } else {
+                        (egui::Color32::GRAY, "CLOSED")
+                    };
+                    ui.colored_label(
+                        color,
+                        egui::RichText::new(format!("{} {}", name, state)).heading(),
+                    );
+                }
+                if self.firmware_emergency {
+                    ui.separator();
+                    ui.colored_label(
+                        egui::Color32::RED,
+                        egui::RichText::new("FIRMWARE EMERGENCY").heading().strong(),
+                    );
+                }
+                if self.clock.t_zero().is_some() {
+                    ui.separator();
+                    let latest_time = self
+                        .engine_data
+                        .data_points
+                        .back()
+                        .map_or(0.0, |dp| dp.time);
+                    ui.heading(self.clock.format(latest_time));
+                }
+            });
+            self.stale_banner(ui);
+        });
+
+        egui::CentralPanel::default().show(ctx, |ui| {
+            let widget_ctx = WidgetContext {
+                engine_data: &self.engine_data,
+                frozen_channels: &self.frozen_channels,
+                clock: &self.clock,
+                focus: self.plot_focus,
+                markers: self.event_log.markers(),
+                cursor: self.plot_cursor,
+                window: self.time_window,
+                log_dir: &self.log_dir,
+            };
+            if let Some(widget) = self.widgets.get_mut(widget_index) {
+                widget.show(ui, &widget_ctx);
+            }
+        });
+    }
+
+    /// Settings window; changes apply on Save and are written to the settings file.
+    fn settings_window(&mut self, ctx: &egui::Context) {
+        let Some(draft) = &mut self.settings_draft else {
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new("Settings")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                egui::Grid::new("settings_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Default port");
+                        ui.text_edit_singleline(&mut draft.port);
+                        ui.end_row();
+
+                        ui.label("Default baud");
+                        egui::ComboBox::from_id_salt("settings_baud")
+                            .selected_text(draft.baud_rate.to_string())
+                            .show_ui(ui, |ui| {
+                                for baud in BAUD_RATES {
+                                    ui.selectable_value(
+                                        &mut draft.baud_rate,
+                                        baud,
+                                        baud.to_string(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Broadcast interval");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.broadcast_interval_ms)
+                                .range(10..=1000)
+                                .suffix(" ms"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Valve positions");
+                        ui.horizontal(|ui| {
+                            ui.checkbox(&mut draft.position_control, "Proportional, ramped at");
+                            ui.add_enabled(
+                                draft.position_control,
+                                egui::DragValue::new(&mut draft.position_ramp_pct_per_s)
+                                    .range(1.0..=1000.0)
+                                    .suffix(" %/s"),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Valve duty limit");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.max_valve_cycles_per_minute)
+                                .range(1..=600)
+                                .suffix(" cycles/min"),
+                        )
+                        .on_hover_text("Opens either valve may make in a rolling minute");
+                        ui.end_row();
+
+                        ui.label("Full-rate plot points");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.max_data_points).range(100..=1_000_000),
+                        )
+                        .on_hover_text("Older points are thinned to fit as many again, not dropped");
+                        ui.end_row();
+
+                        ui.label("Log directory");
+                        let mut log_dir = draft.log_dir.display().to_string();
+                        if ui.text_edit_singleline(&mut log_dir).changed() {
+                            draft.log_dir = PathBuf::from(log_dir);
+                        }
+                        ui.end_row();
+
+                        ui.label("Mirror log directory");
+                        optional_path(ui, &mut draft.mirror_log_dir);
+                        ui.end_row();
+
+                        ui.label("Telemetry protocol");
+                        ui.horizontal(|ui| {
+                            ui.add_enabled(
+                                !draft.mixed_telemetry,
+                                egui::Checkbox::new(
+                                    &mut draft.binary_telemetry,
+                                    "Binary frames (COBS + CRC)",
+                                ),
+                            );
+                            ui.checkbox(&mut draft.mixed_telemetry, "Accept both")
+                                .on_hover_text(
+                                    "Tell CSV lines and binary frames apart per message, \
+                                     for firmware partway through the move to binary",
+                                );
+                        });
+                        ui.end_row();
+
+                        ui.label("Packet framing");
+                        framing_editor(ui, "framing", &mut draft.framing);
+                        ui.end_row();
+
+                        for (name, calibration) in [
+                            ("Fuel flow meter", &mut draft.flow_calibration.fuel),
+                            ("Oxidizer flow meter", &mut draft.flow_calibration.oxi),
+                        ] {
+                            ui.label(name);
+                            ui.horizontal(|ui| {
+                                ui.label("K");
+                                ui.add(
+                                    egui::DragValue::new(&mut calibration.k_factor)
+                                        .speed(0.01)
+                                        .range(0.001..=10_000.0),
+                                )
+                                .on_hover_text("Pulses per second per L/min");
+                                ui.label("Offset");
+                                ui.add(
+                                    egui::DragValue::new(&mut calibration.offset)
+                                        .speed(0.01)
+                                        .suffix(" L/min"),
+                                );
+                                egui::ComboBox::from_id_salt(name)
+                                    .selected_text(calibration.unit.label())
+                                    .show_ui(ui, |ui| {
+                                        for unit in FlowUnit::ALL {
+                                            ui.selectable_value(
+                                                &mut calibration.unit,
+                                                unit,
+                                                unit.label(),
+                                            );
+                                        }
+                                    });
+                                if calibration.unit == FlowUnit::KilogramsPerSecond {
+                                    ui.add(
+                                        egui::DragValue::new(&mut calibration.density_kg_per_l)
+                                            .speed(0.001)
+                                            .range(0.001..=25.0)
+                                            .suffix(" kg/L"),
+                                    );
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label("Readout panel");
+                        ui.checkbox(&mut draft.readout_panel, "Show large numeric readouts");
+                        ui.end_row();
+
+                        ui.label("Stand relays");
+                        ui.checkbox(
+                            &mut draft.stand_relays,
+                            "Switch lighting, camera power, and the pad beacon",
+                        );
+                        ui.end_row();
+
+                        ui.label("Beacon automation");
+                        ui.add_enabled(
+                            draft.stand_relays,
+                            egui::Checkbox::new(
+                                &mut draft.beacon_automation,
+                                "Beacon on while ARMED or FIRING, all clear on SAFE",
+                            ),
+                        );
+                        ui.end_row();
+
+                        ui.label("Igniter");
+                        let mut has_igniter = draft.igniter.is_some();
+                        if ui
+                            .checkbox(&mut has_igniter, "Arm and fire an igniter channel")
+                            .changed()
+                        {
+                            draft.igniter = has_igniter.then(IgniterSettings::default);
+                        }
+                        ui.end_row();
+                        if let Some(igniter) = &mut draft.igniter {
+                            ui.label("Igniter timing");
+                            ui.horizontal(|ui| {
+                                ui.label("Disarms after");
+                                ui.add(
+                                    egui::DragValue::new(&mut igniter.arm_timeout_s)
+                                        .range(1.0..=120.0)
+                                        .suffix(" s"),
+                                );
+                                ui.label("Fires for");
+                                ui.add(
+                                    egui::DragValue::new(&mut igniter.fire_duration_ms)
+                                        .range(100..=10_000)
+                                        .suffix(" ms"),
+                                );
+                            });
+                            ui.end_row();
+
+                            ui.label("Pre-ignition valves");
+                            ui.horizontal(|ui| {
+                                ui.checkbox(&mut igniter.preignition_fuel_open, "Fuel open");
+                                ui.checkbox(&mut igniter.preignition_oxi_open, "Oxidizer open");
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label("Parquet log");
+                        ui.checkbox(&mut draft.parquet_log, "Also write data_log.parquet");
+                        ui.end_row();
+
+                        ui.label("Sample database");
+                        ui.checkbox(
+                            &mut draft.sqlite_samples,
+                            "Store samples in the campaign database",
+                        );
+                        ui.end_row();
+
+                        for (name, port, default) in [
+                            (
+                                "WebSocket viewer port",
+                                &mut draft.websocket_port,
+                                config::DEFAULT_WEBSOCKET_PORT,
+                            ),
+                            ("TCP viewer port", &mut draft.tcp_port, config::DEFAULT_TCP_PORT),
+                            (
+                                "UDP broadcast port",
+                                &mut draft.udp_broadcast_port,
+                                config::DEFAULT_UDP_BROADCAST_PORT,
+                            ),
+                        ] {
+                            ui.label(name);
+                            ui.horizontal(|ui| {
+                                let mut enabled = port.is_some();
+                                if ui.checkbox(&mut enabled, "").changed() {
+                                    *port = enabled.then_some(default);
+                                }
+                                if let Some(port) = port {
+                                    ui.add(egui::DragValue::new(port).range(1..=u16::MAX));
+                                }
+                            });
+                            ui.end_row();
+                        }
+
+                        ui.label("Link compression");
+                        ui.horizontal(|ui| {
+                            let mut enabled = draft.link_compression.is_some();
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                draft.link_compression =
+                                    enabled.then(LinkCompression::default);
+                            }
+                            if let Some(compression) = &mut draft.link_compression {
+                                ui.add(
+                                    egui::DragValue::new(&mut compression.level)
+                                        .range(1..=22)
+                                        .prefix("level "),
+                                );
+                            }
+                        })
+                        .response
+                        .on_hover_text(
+                            "zstd-compress WebSocket and UDP telemetry; every station needs the same dictionary",
+                        );
+                        ui.end_row();
+                        if let Some(compression) = &mut draft.link_compression {
+                            ui.label("Link dictionary");
+                            optional_path(ui, &mut compression.dictionary);
+                            ui.end_row();
+                        }
+
+                        ui.label("MQTT publishing");
+                        let mut mqtt_enabled = draft.mqtt.is_some();
+                        if ui.checkbox(&mut mqtt_enabled, "").changed() {
+                            draft.mqtt = mqtt_enabled.then(MqttSettings::default);
+                        }
+                        ui.end_row();
+                        if let Some(mqtt) = &mut draft.mqtt {
+                            ui.label("MQTT broker");
+                            ui.horizontal(|ui| {
+                                ui.text_edit_singleline(&mut mqtt.broker);
+                                ui.add(egui::DragValue::new(&mut mqtt.port).range(1..=u16::MAX));
+                            });
+                            ui.end_row();
+                            ui.label("MQTT topic prefix");
+                            ui.text_edit_singleline(&mut mqtt.topic_prefix);
+                            ui.end_row();
+                            ui.label("MQTT QoS");
+                            ui.add(egui::DragValue::new(&mut mqtt.qos).range(0..=2));
+                            ui.end_row();
+                        }
+
+                        ui.label("Time source");
+                        ui.horizontal(|ui| {
+                            let mut kind = match draft.time_source {
+                                None => "Host clock",
+                                Some(TimeSource::Ntp { .. }) => "NTP",
+                                Some(TimeSource::GpsSerial { .. }) => "GPS serial",
+                            };
+                            let before = kind;
+                            egui::ComboBox::from_id_salt("time_source")
+                                .selected_text(kind)
+                                .show_ui(ui, |ui| {
+                                    for option in ["Host clock", "NTP", "GPS serial"] {
+                                        ui.selectable_value(&mut kind, option, option);
+                                    }
+                                });
+                            if kind != before {
+                                draft.time_source = match kind {
+                                    "NTP" => Some(TimeSource::default()),
+                                    "GPS serial" => Some(TimeSource::GpsSerial {
+                                        port: String::new(),
+                                        baud_rate: 9_600,
+                                    }),
+                                    _ => None,
+                                };
+                            }
+                            match &mut draft.time_source {
+                                Some(TimeSource::Ntp { server, interval_s }) => {
+                                    ui.text_edit_singleline(server);
+                                    ui.add(
+                                        egui::DragValue::new(interval_s)
+                                            .range(1.0..=3600.0)
+                                            .suffix(" s"),
+                                    );
+                                }
+                                Some(TimeSource::GpsSerial { port, baud_rate }) => {
+                                    ui.text_edit_singleline(port);
+                                    egui::ComboBox::from_id_salt("gps_baud")
+                                        .selected_text(baud_rate.to_string())
+                                        .show_ui(ui, |ui| {
+                                            for baud in [4_800, 9_600, 38_400, 115_200] {
+                                                ui.selectable_value(
+                                                    baud_rate,
+                                                    baud,
+                                                    baud.to_string(),
+                                                );
+                                            }
+                                        });
+                                }
+                                None => {}
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Stale data after");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.stale_after_s)
+                                .range(0.1..=60.0)
+                                .speed(0.1)
+                                .suffix(" s"),
+                        );
+                        ui.end_row();
+
+                        ui.label("Countdown from");
+                        ui.add(
+                            egui::DragValue::new(&mut draft.countdown_s)
+                                .range(1.0..=3600.0)
+                                .speed(1.0)
+                                .prefix("T-")
+                                .suffix(" s"),
+                        );
+                        ui.end_row();
+
+                        for (name, redline) in [
+                            ("Nozzle redline (°C)", &mut draft.redline_nozzle_c),
+                            ("Tank redline (°C)", &mut draft.redline_tank_c),
+                            ("Ambient redline (°C)", &mut draft.redline_ambient_c),
+                        ] {
+                            ui.label(name);
+                            optional_value(ui, redline);
+                            ui.end_row();
+                        }
+                    });
+
+                ui.separator();
+                ui.label("Pressure channels, in the order the firmware sends them, and their subsystem");
+                let mut remove = None;
+                for (i, channel) in draft.pressure_channels.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut channel.name).desired_width(140.0));
+                        ui.add(egui::TextEdit::singleline(&mut channel.unit).desired_width(50.0));
+                        egui::ComboBox::from_id_salt(("pressure_group", i))
+                            .selected_text(channel.group.name())
+                            .show_ui(ui, |ui| {
+                                for group in ChannelGroup::ALL {
+                                    ui.selectable_value(&mut channel.group, group, group.name());
+                                }
+                            });
+                        if ui.button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    draft.pressure_channels.remove(i);
+                }
+                if ui.button("Add pressure channel").clicked() {
+                    let number = draft.pressure_channels.len() + 1;
+                    draft.pressure_channels.push(PressureChannel {
+                        name: format!("Pressure {}", number),
+                        unit: "bar".to_string(),
+                        group: ChannelGroup::default(),
+                    });
+                }
+
+                ui.separator();
+                ui.label("Other boards, read-only on their own ports; their channels are named board.channel");
+                let mut remove = None;
+                for (i, device) in draft.devices.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut device.name).desired_width(80.0));
+                        ui.add(
+                            egui::TextEdit::singleline(&mut device.port)
+                                .hint_text("Port")
+                                .desired_width(120.0),
+                        );
+                        egui::ComboBox::from_id_salt(("device_baud", i))
+                            .selected_text(device.baud_rate.to_string())
+                            .show_ui(ui, |ui| {
+                                for baud in BAUD_RATES {
+                                    ui.selectable_value(
+                                        &mut device.baud_rate,
+                                        baud,
+                                        baud.to_string(),
+                                    );
+                                }
+                            });
+                        ui.checkbox(&mut device.binary_telemetry, "Binary frames");
+                        ui.checkbox(&mut device.safe_when_silent, "Redline when silent")
+                            .on_hover_text("Close both valves if it goes silent while armed");
+                        if ui.button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                    ui.indent(("device_channels", i), |ui| {
+                        framing_editor(ui, ("device_framing", i), &mut device.framing);
+                        let mut remove_channel = None;
+                        for (j, channel) in device.channels.iter_mut().enumerate() {
+                            ui.horizontal(|ui| {
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut channel.name)
+                                        .desired_width(140.0),
+                                );
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut channel.unit)
+                                        .desired_width(50.0),
+                                );
+                                egui::ComboBox::from_id_salt(("device_group", i, j))
+                                    .selected_text(channel.group.name())
+                                    .show_ui(ui, |ui| {
+                                        for group in ChannelGroup::ALL {
+                                            ui.selectable_value(
+                                                &mut channel.group,
+                                                group,
+                                                group.name(),
+                                            );
+                                        }
+                                    });
+                                if ui.button("Remove").clicked() {
+                                    remove_channel = Some(j);
+                                }
+                            });
+                        }
+                        if let Some(j) = remove_channel {
+                            device.channels.remove(j);
+                        }
+                        if ui.button("Add channel").clicked() {
+                            let number = device.channels.len() + 1;
+                            device.channels.push(PressureChannel {
+                                name: format!("Pressure {}", number),
+                                unit: "bar".to_string(),
+                                group: ChannelGroup::default(),
+                            });
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    draft.devices.remove(i);
+                }
+                if ui.button("Add board").clicked() {
+                    draft.devices.push(SerialDevice::default());
+                }
+
+                ui.separator();
+                ui.label("Environment sensors: name, unit, sensor number, poll interval (s)");
+                let mut remove = None;
+                for (i, sensor) in draft.environment_sensors.iter_mut().enumerate() {
+                    ui.horizontal(|ui| {
+                        ui.add(egui::TextEdit::singleline(&mut sensor.name).desired_width(140.0));
+                        ui.add(egui::TextEdit::singleline(&mut sensor.unit).desired_width(50.0));
+                        ui.add(egui::DragValue::new(&mut sensor.id));
+                        ui.add(
+                            egui::DragValue::new(&mut sensor.poll_interval_s)
+                                .range(0.1..=3600.0)
+                                .speed(0.5),
+                        );
+                        if ui.button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                    });
+                }
+                if let Some(i) = remove {
+                    draft.environment_sensors.remove(i);
+                }
+                if ui.button("Add environment sensor").clicked() {
+                    let id = draft.environment_sensors.len() as u8 + 1;
+                    draft.environment_sensors.push(EnvironmentSensor {
+                        name: format!("Sensor {}", id),
+                        unit: "°C".to_string(),
+                        id,
+                        poll_interval_s: 5.0,
+                    });
+                }
+
+                ui.separator();
+                ui.label("Limits, drawn on the plots and raised as alarms");
+                let mut remove = None;
+                egui::Grid::new("limits_grid").show(ui, |ui| {
+                    for label in [
+                        "Quantity",
+                        "Warn low",
+                        "Warn high",
+                        "Crit low",
+                        "Crit high",
+                        "Safe",
+                        "Hysteresis",
+                        "Min duration (ms)",
+                        "",
+                    ] {
+                        ui.label(label);
+                    }
+                    ui.end_row();
+                    for (i, limits) in draft.limits.iter_mut().enumerate() {
+                        egui::ComboBox::from_id_salt(("limit_quantity", i))
+                            .selected_text(limits.quantity.name())
+                            .show_ui(ui, |ui| {
+                                for quantity in Quantity::ALL {
+                                    ui.selectable_value(
+                                        &mut limits.quantity,
+                                        quantity,
+                                        quantity.name(),
+                                    );
+                                }
+                            });
+                        optional_value(ui, &mut limits.warning_low);
+                        optional_value(ui, &mut limits.warning_high);
+                        optional_value(ui, &mut limits.critical_low);
+                        optional_value(ui, &mut limits.critical_high);
+                        ui.checkbox(&mut limits.safe_on_critical, "")
+                            .on_hover_text("Close both valves when a critical limit trips");
+                        ui.add(
+                            egui::DragValue::new(&mut limits.hysteresis)
+                                .speed(0.1)
+                                .range(0.0..=f64::MAX),
+                        )
+                        .on_hover_text("How far back inside a limit a value must come to clear");
+                        ui.add(
+                            egui::DragValue::new(&mut limits.min_duration_ms)
+                                .speed(10.0)
+                                .range(0.0..=60_000.0),
+                        )
+                        .on_hover_text("How long a value must stay beyond a limit to alarm");
+                        if ui.button("Remove").clicked() {
+                            remove = Some(i);
+                        }
+                        ui.end_row();
+                        ui.label("");
+                        ui.horizontal(|ui| {
+                            let mut banded = limits.band.is_some();
+                            if ui.checkbox(&mut banded, "Band").changed() {
+                                limits.band = banded.then_some([0.0, 1.0]);
+                            }
+                            if let Some([min, max]) = &mut limits.band {
+                                ui.add(egui::DragValue::new(min).speed(0.1));
+                                ui.add(egui::DragValue::new(max).speed(0.1));
+                            }
+                        });
+                        ui.end_row();
+                    }
+                });
+                if let Some(i) = remove {
+                    draft.limits.remove(i);
+                }
+                if ui.button("Add limit").clicked() {
+                    draft.limits.push(ChannelLimits::new(Quantity::FlowFuel));
+                }
+                ui.horizontal(|ui| {
+                    ui.label("Redline trips after");
+                    ui.add(egui::DragValue::new(&mut draft.redline_samples).range(1..=100));
+                    ui.label("consecutive critical samples");
+                });
+
+                ui.separator();
+                ui.label("Alerts: sound files played per event, blank for none");
+                egui::Grid::new("alerts_grid")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        let alerts = &mut draft.alerts;
+                        for (event, sound) in [
+                            (AlertEvent::Disconnect, &mut alerts.disconnect_sound),
+                            (AlertEvent::Emergency, &mut alerts.emergency_sound),
+                            (AlertEvent::Redline, &mut alerts.redline_sound),
+                            (AlertEvent::Dropout, &mut alerts.dropout_sound),
+                        ] {
+                            ui.label(event.title());
+                            optional_path(ui, sound);
+                            ui.end_row();
+                        }
+
+                        ui.label("Dropout after");
+                        ui.add(
+                            egui::DragValue::new(&mut alerts.dropout_s)
+                                .range(0.0..=60.0)
+                                .speed(0.1)
+                                .suffix(" s"),
+                        )
+                        .on_hover_text("0 disables dropout alerts");
+                        ui.end_row();
+
+                        ui.label("Desktop notifications");
+                        ui.checkbox(&mut alerts.desktop_notifications, "");
+                        ui.end_row();
+                    });
+
+                ui.separator();
+                ui.label(format!("Saved to {}", config::settings_path().display()));
+                ui.label(
+                    "Port, baud, log directories, pressure channels, other boards, environment sensors, redlines, plot limits, Parquet and sample storage, viewer ports, link compression, MQTT, and the time source take effect on the next start.",
+                );
+                ui.label("The telemetry protocol and framing take effect on the next connect.");
+                let problem = draft.validate().err();
+                if let Some(problem) = &problem {
+                    ui.colored_label(egui::Color32::RED, problem);
+                }
+                ui.horizontal(|ui| {
+                    save = ui
+                        .add_enabled(problem.is_none(), egui::Button::new("Save"))
+                        .clicked();
+                    if ui.button("Defaults").clicked() {
+                        *draft = Settings::default();
+                    }
+                });
+            });
+
+        if save {
+            let mut draft = draft.clone();
+            // The layout is edited in its own window
+            draft.layout = self.settings.layout.clone();
+            match draft.save() {
+                Ok(()) => {
+                    diag!("Saved settings to {}", config::settings_path().display());
+                    *self.serial.broadcast_interval.lock().unwrap() =
+                        Duration::from_millis(draft.broadcast_interval_ms);
+                    self.duty_guard
+                        .set_max_cycles_per_minute(draft.max_valve_cycles_per_minute);
+                    if draft.flow_calibration != self.settings.flow_calibration {
+                        self.set_flow_calibration(&draft);
+                    }
+                    {
+                        let mut positions = self.serial.valve_positions.lock().unwrap();
+                        match (draft.position_control, positions.as_mut()) {
+                            (true, Some(ramp)) => ramp.set_rate(draft.position_ramp_pct_per_s),
+                            (true, None) => {
+                                *positions = Some(PositionRamp::new(draft.position_ramp_pct_per_s))
+                            }
+                            (false, _) => *positions = None,
+                        }
+                    }
+                    if draft.igniter.is_some() != self.settings.igniter.is_some() {
+                        let mut igniter = self.serial.igniter.lock().unwrap();
+                        if let Some(output) = igniter.as_mut() {
+                            self.igniter.safe(output);
+                        }
+                        *igniter = draft.igniter.as_ref().map(|_| IgniterOutput::default());
+                    }
+                    if draft.stand_relays != self.settings.stand_relays {
+                        // Relays start off when the board is enabled
+                        *self.serial.relay_states.lock().unwrap() =
+                            draft.stand_relays.then(Relays::default);
+                    }
+                    self.settings = draft;
+                    self.settings_draft = None;
+                    self.config_error = None;
+                }
+                Err(e) => diag!("Failed to save settings: {}", e),
+            }
+        } else if !open {
+            self.settings_draft = None;
+        }
+    }
+
+    /// Saves the deadman switch as set in the control panel, so it's kept across
+    /// restarts and not undone by saving the settings window.
+    fn save_deadman(&mut self) {
+        if let Some(draft) = &mut self.settings_draft {
+            draft.deadman_enabled = self.settings.deadman_enabled;
+            draft.deadman_input = self.settings.deadman_input;
+        }
+        if let Err(e) = self.settings.save() {
+            diag!("Failed to save the deadman setting: {}", e);
+        }
+    }
+
+    /// Applies new flow calibration to decoding and the plots, noting the change in
+    /// the event log and the session metadata since samples before it differ.
+    fn set_flow_calibration(&mut self, settings: &Settings) {
+        self.serial.flow_decoding.lock().unwrap().calibration = settings.flow_calibration.clone();
+        self.widgets = widgets::dashboard_widgets(settings);
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        self.event_log.add(latest_time, "Flow calibration changed");
+        self.session_info.flow_calibration = Some(settings.flow_calibration.clone());
+        if let Err(e) = self.session_info.save(&self.log_dir) {
+            diag!("{}", e);
+        }
+    }
+
+    /// Calibration wizard; an applied K-factor is saved to the settings and used at once.
+    fn calibration_window(&mut self, ctx: &egui::Context) {
+        let Some(wizard) = &mut self.calibration_wizard else {
+            return;
+        };
+        let mut open = true;
+        let mut applied = None;
+        egui::Window::new("Flow Meter Calibration")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                applied = wizard.show(ui, &self.settings.flow_calibration);
+                ui.label("The K-factor decodes channels set to pulse frequency.");
+            });
+        if !open {
+            self.calibration_wizard = None;
+        }
+        let Some((meter, result)) = applied else {
+            return;
+        };
+        let mut settings = self.settings.clone();
+        let calibration = meter.calibration_mut(&mut settings.flow_calibration);
+        calibration.k_factor = result.k_factor;
+        calibration.k_uncertainty = Some(result.uncertainty);
+        calibration.calibrated_at =
+            Some(chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string());
+        match settings.save() {
+            Ok(()) => {
+                diag!(
+                    "{} K-factor set to {:.3} ± {:.3}",
+                    meter.name(),
+                    result.k_factor,
+                    result.uncertainty
+                );
+                self.set_flow_calibration(&settings);
+                if let Some(draft) = &mut self.settings_draft {
+                    draft.flow_calibration = settings.flow_calibration.clone();
+                }
+                self.settings = settings;
+            }
+            Err(e) => diag!("Failed to save settings: {}", e),
+        }
+    }
+
+    /// Plot arrangement editor; applying rebuilds the dashboard and saves the settings.
+    fn layout_window(&mut self, ctx: &egui::Context) {
+        let Some(draft) = &mut self.layout_draft else {
+            return;
+        };
+        let mut open = true;
+        let mut apply = false;
+        let mut standard = false;
+        egui::Window::new("Dashboard Layout")
+            .open(&mut open)
+            .default_width(420.0)
+            .show(ctx, |ui| {
+                draft.edit(ui, &self.settings);
+                ui.separator();
+                ui.horizontal(|ui| {
+                    apply = ui.button("Apply").clicked();
+                    standard = ui.button("Standard Layout").clicked();
+                });
+            });
+        if standard {
+            *draft = DashboardLayout::standard(self.settings.all_pressure_channels().len());
+        }
+
+        if apply {
+            let layout = draft.clone();
+            let mut settings = self.settings.clone();
+            settings.layout = Some(layout.clone());
+            match settings.save() {
+                Ok(()) => {
+                    diag!(
+                        "Saved dashboard layout to {}",
+                        config::settings_path().display()
+                    );
+                    self.widgets = widgets::dashboard_widgets(&settings);
+                    self.settings = settings;
+                    self.layout = layout;
+                    self.layout_draft = None;
+                }
+                Err(e) => diag!("Failed to save dashboard layout: {}", e),
+            }
+        } else if !open {
+            self.layout_draft = None;
+        }
+    }
+
+    /// Pre-test details for metadata.json; the test name is shown in the window title.
+    fn session_info_window(&mut self, ctx: &egui::Context) {
+        let Some(draft) = &mut self.session_info_draft else {
+            return;
+        };
+        let mut open = true;
+        let mut save = false;
+        egui::Window::new("Session Info")
+            .open(&mut open)
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                draft.show(ui);
+                ui.separator();
+                save = ui.button("Save").clicked();
+            });
+
+        if save {
+            if let Some(draft) = self.session_info_draft.take() {
+                let mut info = draft.finish();
+                info.flow_calibration = Some(self.settings.flow_calibration.clone());
+                match info.save(&self.log_dir) {
+                    Ok(()) => diag!("Saved session info for {:?}", info.test_name),
+                    Err(e) => diag!("{}", e),
+                }
+                ctx.send_viewport_cmd(egui::ViewportCommand::Title(
+                    info.window_title(WINDOW_TITLE),
+                ));
+                self.session_info = info;
+            }
+        } else if !open {
+            self.session_info_draft = None;
+        }
+    }
+
+    /// Writes every plot to the session's plots folder, named with the time and the
+    /// test: SVG now, from what was drawn last frame, and PNG from the next frame.
+    fn export_plots(&mut self) {
+        let dir = self.log_dir.join(PLOT_EXPORT_DIR_NAME);
+        let test_name = self.session_info.test_name.trim();
+        let prefix = format!(
+            "{}_{}",
+            chrono::Local::now().format("%Y-%m-%d_%H-%M-%S"),
+            screenshots::file_label(if test_name.is_empty() {
+                "plots"
+            } else {
+                test_name
+            })
+        );
+        for widget in &self.widgets {
+            let Some(svg) = widget.svg() else {
+                continue;
+            };
+            let path = dir.join(format!(
+                "{}_{}.svg",
+                prefix,
+                screenshots::file_label(widget.title())
+            ));
+            if let Err(e) = fs::create_dir_all(&dir).and_then(|()| fs::write(&path, svg)) {
+                diag!("Failed to write {}: {}", path.display(), e);
+            }
+        }
+        self.screenshots.request_plots(PlotCapture {
+            dir: dir.clone(),
+            prefix,
+            plots: self.widget_rects.clone(),
+        });
+        diag!("Exporting plots to {}", dir.display());
+    }
+
+    /// Timestamped markers and free-text operator annotations.
+    fn event_log_window(&mut self, ctx: &egui::Context) {
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        let mut open = self.event_log_open;
+        let mut add = false;
+        egui::Window::new("Event Log")
+            .open(&mut open)
+            .default_width(400.0)
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    let time = self.annotation_time.unwrap_or(latest_time);
+                    ui.label(self.clock.format(time));
+                    let response = ui.add(
+                        egui::TextEdit::singleline(&mut self.annotation)
+                            .hint_text("Annotation, e.g. ignition observed"),
+                    );
+                    // Focused when opened by the hotkey, so typing can start at once
+                    if std::mem::take(&mut self.focus_annotation) {
+                        response.request_focus();
+                    }
+                    let entered =
+                        response.lost_focus() && ui.input(|i| i.key_pressed(egui::Key::Enter));
+                    add = (entered || ui.button("Add").clicked())
+                        && !self.annotation.trim().is_empty();
+                });
+                ui.separator();
+                egui::ScrollArea::vertical()
+                    .max_height(300.0)
+                    .show(ui, |ui| {
+                        for marker in self.event_log.markers().iter().rev() {
+                            ui.label(format!(
+                                "{}  {}",
+                                self.clock.format(marker.time),
+                                marker.label
+                            ));
+                        }
+                    });
+            });
+        if add {
+            let time = self.annotation_time.take().unwrap_or(latest_time);
+            let label = std::mem::take(&mut self.annotation);
+            self.event_log.add(time, label.trim());
+            self.highlights
+                .add(HighlightKind::Marker, time, label.trim());
+        }
+        if !open {
+            self.annotation_time = None;
+        }
+        self.event_log_open = open;
+    }
+
+    /// Values of every plotted series at the linked cursor, while a plot is hovered.
+    fn cursor_readout(&self, ctx: &egui::Context) {
+        let Some(time) = self.plot_cursor else {
+            return;
+        };
+        let engine_data = self.frozen_view.as_ref().unwrap_or(&self.engine_data);
+        // Nearest sample to the cursor, from the history if it's older than the full-rate points
+        let history = engine_data.history.points();
+        let data_points = &engine_data.data_points;
+        let index = data_points.partition_point(|dp| dp.time < time);
+        let history_index = history.partition_point(|dp| dp.time < time);
+        let nearest = [index.checked_sub(1), Some(index)]
+            .into_iter()
+            .flatten()
+            .filter_map(|i| data_points.get(i))
+            .chain(
+                [history_index.checked_sub(1), Some(history_index)]
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|i| history.get(i)),
+            )
+            .min_by(|a, b| (a.time - time).abs().total_cmp(&(b.time - time).abs()));
+        let Some(dp) = nearest else {
+            return;
+        };
+        egui::Window::new("Cursor")
+            .collapsible(false)
+            .resizable(false)
+            .interactable(false)
+            .anchor(egui::Align2::RIGHT_BOTTOM, egui::vec2(-10.0, -40.0))
+            .show(ctx, |ui| {
+                ui.label(self.clock.format(dp.time));
+                egui::Grid::new("cursor_readout")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        for widget in &self.widgets {
+                            let values = widget.readout(dp);
+                            if values.is_empty() {
+                                continue;
+                            }
+                            ui.strong(widget.title());
+                            ui.end_row();
+                            for (name, value) in values {
+                                ui.label(name);
+                                ui.label(format!("{:.2}", value));
+                                ui.end_row();
+                            }
+                        }
+                    });
+            });
+    }
+
+    /// Per-session metric trends across the campaign.
+    fn trends_window(&mut self, ctx: &egui::Context) {
+        let Some(trends) = &mut self.campaign_trends else {
+            return;
+        };
+        let mut open = true;
+        let mut refresh = false;
+        egui::Window::new("Campaign Trends")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                refresh = ui.button("Refresh").clicked();
+                trends.show(ui);
+            });
+        if !open {
+            self.campaign_trends = None;
+        } else if refresh {
+            self.open_campaign_trends();
+        }
+    }
+
+    /// Find tool: searches the plotted data and jumps the plots to a result.
+    fn query_window(&mut self, ctx: &egui::Context) {
+        let Some(query) = &mut self.query else {
+            return;
+        };
+        let mut open = true;
+        let mut jump = None;
+        egui::Window::new("Find")
+            .open(&mut open)
+            .resizable(false)
+            .show(ctx, |ui| {
+                jump = query.show(ui, self.engine_data.points(), &self.clock);
+                if self.plot_focus.is_some() && ui.button("Clear highlight").clicked() {
+                    self.plot_focus = None;
+                }
+            });
+        if let Some(interval) = jump {
+            // Unique per jump, even after the highlight was cleared
+            let id = ctx.cumulative_pass_nr();
+            self.plot_focus = Some(PlotFocus { id, interval });
+        }
+        if !open {
+            self.query = None;
+            self.plot_focus = None;
+        }
+    }
+
+    /// Histogram of a channel over the Find result being highlighted, or else the plotted window.
+    fn histogram_window(&mut self, ctx: &egui::Context) {
+        let Some(histogram) = &mut self.histogram else {
+            return;
+        };
+        let engine_data = self.frozen_view.as_ref().unwrap_or(&self.engine_data);
+        let (data_points, span): (Vec<&EngineDataPoint>, String) = match self.plot_focus {
+            Some(focus) => (
+                engine_data
+                    .points()
+                    .filter(|dp| (focus.interval.start..=focus.interval.end).contains(&dp.time))
+                    .collect(),
+                format!(
+                    "{} to {}",
+                    self.clock.format(focus.interval.start),
+                    self.clock.format(focus.interval.end)
+                ),
+            ),
+            None => (
+                engine_data.visible(self.time_window).collect(),
+                match self.time_window {
+                    TimeWindow::All => "All kept data".to_string(),
+                    window => format!("Last {}", window.name()),
+                },
+            ),
+        };
+        let mut open = true;
+        egui::Window::new("Histogram")
+            .open(&mut open)
+            .default_width(500.0)
+            .show(ctx, |ui| {
+                histogram.show(ui, &data_points, &span, &self.settings);
+                ui.label("Uses the highlighted Find result, or else the plotted time window.");
+            });
+        if !open {
+            self.histogram = None;
+        }
+    }
+
+    /// Modal alert for a redline trip; the abort itself stays latched until reset.
+    fn redline_alert_window(&mut self, ctx: &egui::Context) {
+        let Some(reason) = &self.redline_alert else {
+            return;
+        };
+        let mut acknowledged = false;
+        egui::Window::new("REDLINE TRIPPED")
+            .collapsible(false)
+            .resizable(false)
+            .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+            .show(ctx, |ui| {
+                ui.colored_label(egui::Color32::RED, egui::RichText::new(reason).strong());
+                ui.label("Both valves were commanded closed and the abort is latched.");
+                ui.label("Reset to SAFE from the arming controls once the cause is understood.");
+                acknowledged = ui.button("Acknowledge").clicked();
+            });
+        if acknowledged {
+            self.redline_alert = None;
+        }
+    }
+
+    /// Debug text printed by the firmware, kept out of telemetry parsing.
+    fn console_window(&mut self, ctx: &egui::Context) {
+        let console = &self.serial.console;
+        egui::Window::new("Firmware Console")
+            .open(&mut self.console_open)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                if ui.button("Clear").clicked() {
+                    console.clear();
+                }
+                console.show(ui);
+            });
+    }
+
+    /// Scripted sequence editor, with the line the running script is at.
+    fn script_window(&mut self, ctx: &egui::Context) {
+        let mut open = self.script_open;
+        egui::Window::new("Script")
+            .open(&mut open)
+            .default_width(600.0)
+            .show(ctx, |ui| {
+                let running = self.script.is_some();
+                ui.horizontal(|ui| {
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.script_path)
+                            .hint_text("path/to/sequence.rhai")
+                            .desired_width(300.0),
+                    );
+                    if ui
+                        .add_enabled(!running, egui::Button::new("Load"))
+                        .clicked()
+                    {
+                        match fs::read_to_string(self.script_path.trim()) {
+                            Ok(source) => self.script_source = source,
+                            Err(e) => {
+                                self.script_status =
+                                    Some(format!("Failed to read {}: {}", self.script_path, e))
+                            }
+                        }
+                    }
+                });
+                ui.add_enabled(
+                    !running,
+                    egui::TextEdit::multiline(&mut self.script_source)
+                        .code_editor()
+                        .desired_rows(16)
+                        .desired_width(f32::INFINITY),
+                );
+                ui.horizontal(|ui| {
+                    let can_run = !running && self.remote.is_none();
+                    if ui
+                        .add_enabled(can_run, egui::Button::new("Run"))
+                        .on_hover_text("Valve commands still need the stand ARMED")
+                        .clicked()
+                    {
+                        self.start_script();
+                    }
+                    if ui.add_enabled(running, egui::Button::new("Stop")).clicked() {
+                        if let Some(script) = &self.script {
+                            script.stop();
+                        }
+                    }
+                    match (&self.script, &self.script_status) {
+                        (Some(script), _) => {
+                            let line = script.line().map_or(String::new(), |line| {
+                                let text = self.script_source.lines().nth(line - 1);
+                                format!(", line {}: {}", line, text.unwrap_or("").trim())
+                            });
+                            ui.colored_label(egui::Color32::YELLOW, format!("Running{}", line));
+                        }
+                        (None, Some(status)) => {
+                            ui.label(status);
+                        }
+                        (None, None) => {}
+                    }
+                });
+            });
+        self.script_open = open;
+    }
+
+    /// Starts the editor's script, keeping a copy of it in the session folder.
+    fn start_script(&mut self) {
+        let file_name = format!("script_{}.rhai", chrono::Local::now().format("%H-%M-%S"));
+        if let Err(e) = fs::write(self.log_dir.join(&file_name), &self.script_source) {
+            diag!("Failed to save {}: {}", file_name, e);
+        }
+        match ScriptRunner::start(&self.script_source) {
+            Ok(script) => {
+                self.script = Some(script);
+                self.script_status = None;
+                self.log_phase(&format!("Script started ({})", file_name));
+            }
+            Err(e) => self.script_status = Some(e),
+        }
+    }
+
+    /// Applies what the running script asked for, and notes when it ends.
+    fn update_script(&mut self) {
+        let Some(script) = &mut self.script else {
+            return;
+        };
+        // Checked first, so requests made just before it ended are still applied
+        let finished = script.finished();
+        for request in script.requests() {
+            match request {
+                ScriptRequest::Valves {
+                    fuel_open,
+                    oxi_open,
+                } => {
+                    self.command_valves(fuel_open, oxi_open);
+                    let applied = (
+                        self.engine_data.fuel_valve_open,
+                        self.engine_data.oxi_valve_open,
+                    ) == (fuel_open, oxi_open);
+                    if !applied {
+                        if let Some(script) = &self.script {
+                            script.stop();
+                        }
+                        let reason = self.duty_warning.clone().unwrap_or_default();
+                        self.log_phase(&format!("Script valve command refused: {}", reason));
+                        break;
+                    }
+                }
+                ScriptRequest::Abort(reason) => {
+                    self.trigger_abort(&format!("Script: {}", reason));
+                }
+                ScriptRequest::Log(text) => {
+                    let latest_time = self
+                        .engine_data
+                        .data_points
+                        .back()
+                        .map_or(0.0, |dp| dp.time);
+                    self.event_log.add(latest_time, &text);
+                }
+            }
+        }
+        if let Some(result) = finished {
+            self.script = None;
+            let status = match result {
+                Ok(()) => "Script finished".to_string(),
+                Err(e) => format!("Script ended: {}", e),
+            };
+            self.log_phase(&status);
+            self.script_status = Some(status);
+        }
+    }
+
+    fn open_campaign_trends(&mut self) {
+        let logs_root = self.log_dir.parent().unwrap_or(&self.log_dir);
+        self.campaign_trends = Some(CampaignTrends::load(&logs_root.join(CAMPAIGN_DB_NAME)));
+    }
+
+    /// Formats current channel values, valve states, and warnings for the clipboard.
+    fn snapshot_text(&self) -> String {
+        let mut text = format!(
+            "KSI snapshot {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S")
+        );
+        match self.engine_data.data_points.back() {
+            Some(dp) => {
+                let rows = [
+                    ("Time (ms)", dp.time.to_string()),
+                    ("Fuel flow (L/min)", format!("{:.2}", dp.flow_rate_fuel)),
+                    ("Oxidizer flow (L/min)", format!("{:.2}", dp.flow_rate_oxi)),
+                    ("Fuel pulses", dp.pulse_count_fuel.to_string()),
+                    ("Oxidizer pulses", dp.pulse_count_oxi.to_string()),
+                    ("Fuel position", dp.desired_pos_fuel.to_string()),
+                    ("Oxidizer position", dp.desired_pos_oxi.to_string()),
+                ];
+                for (name, value) in rows {
+                    text.push_str(&format!("{:<22} {}\n", name, value));
+                }
+                for (channel, pressure) in self
+                    .settings
+                    .all_pressure_channels()
+                    .iter()
+                    .zip(&dp.pressures)
+                {
+                    let name = format!("{} ({})", channel.name, channel.unit);
+                    text.push_str(&format!("{:<22} {:.2}\n", name, pressure));
+                }
+                if let Some(t) = dp.temperatures {
+                    for (name, value) in [
+                        ("Nozzle temp (°C)", t.nozzle),
+                        ("Tank temp (°C)", t.tank),
+                        ("Ambient temp (°C)", t.ambient),
+                    ] {
+                        text.push_str(&format!("{:<22} {:.1}\n", name, value));
+                    }
+                }
+                if let Some(thrust) = dp.thrust {
+                    text.push_str(&format!("{:<22} {:.1}\n", "Thrust (N)", thrust));
+                }
+            }
+            None => text.push_str("No telemetry received\n"),
+        }
+        let open_closed = |open: bool| if open { "OPEN" } else { "CLOSED" };
+        text.push_str(&format!("{:<22} {}\n", "State", self.arming.state()));
+        text.push_str(&format!(
+            "{:<22} {}\n{:<22} {}\n",
+            "Fuel valve",
+            open_closed(self.engine_data.fuel_valve_open),
+            "Oxidizer valve",
+            open_closed(self.engine_data.oxi_valve_open)
+        ));
+
+        let mut warnings: Vec<String> = self
+            .frozen_channels
+            .frozen_names(&Channel::ALL)
+            .iter()
+            .map(|name| format!("{} stale", name))
+            .collect();
+        warnings.extend(self.limit_alarms.iter().map(LimitAlarm::message));
+        warnings.extend(self.duty_warning.clone());
+        if warnings.is_empty() {
+            text.push_str("Warnings: none\n");
+        } else {
+            text.push_str(&format!("Warnings: {}\n", warnings.join(", ")));
+        }
+        text
+    }
+
+    /// Sends new valve states to the write thread if the arming state and the
+    /// duty-cycle guard allow it.
+    ///
+    /// Opening is refused unless ARMED or FIRING, while the firmware reports
+    /// an emergency, and while an enabled deadman input isn't held.
+    fn command_valves(&mut self, fuel_open: bool, oxi_open: bool) {
+        if let Err(e) = self.arming.permits(fuel_open || oxi_open) {
+            self.duty_warning = Some(e);
+            return;
+        }
+        if self.firmware_emergency && (fuel_open || oxi_open) {
+            self.duty_warning = Some("Firmware emergency: wait for it to clear".to_string());
+            return;
+        }
+        if self.settings.deadman_enabled && !self.deadman_held && (fuel_open || oxi_open) {
+            self.duty_warning = Some(format!(
+                "Deadman: hold {} to open valves",
+                self.settings.deadman_input.name()
+            ));
+            return;
+        }
+        match self.duty_guard.request(fuel_open, oxi_open) {
+            Ok(()) => {
+                self.duty_warning = None;
+                match self.arming.valves_commanded(fuel_open || oxi_open) {
+                    Some(ArmState::Firing) => self.screenshots.request("ignition"),
+                    Some(ArmState::Armed) => self.screenshots.request("shutdown"),
+                    _ => {}
+                }
+                if (fuel_open, oxi_open)
+                    != (
+                        self.engine_data.fuel_valve_open,
+                        self.engine_data.oxi_valve_open,
+                    )
+                {
+                    self.valves_commanded_at = Instant::now();
+                }
+                self.engine_data.fuel_valve_open = fuel_open;
+                self.engine_data.oxi_valve_open = oxi_open;
+                // Update the states broadcast by the write thread
+                *self.serial.valve_states.lock().unwrap() = (fuel_open, oxi_open);
+            }
+            Err(e) => self.duty_warning = Some(e),
+        }
+    }
+
+    /// Opens a valve when its momentary control is pressed and closes it on
+    /// release, with valve commands broadcast more often while anything is held.
+    /// Only presses and releases command the valves, so a script or countdown
+    /// isn't overridden while nothing changes.
+    fn hold_valves(&mut self, fuel_held: bool, oxi_held: bool) {
+        let held = (fuel_held, oxi_held);
+        if held == self.momentary_held {
+            return;
+        }
+        let was_held = self.momentary_held;
+        self.momentary_held = held;
+        self.command_valves(
+            if fuel_held != was_held.0 {
+                fuel_held
+            } else {
+                self.engine_data.fuel_valve_open
+            },
+            if oxi_held != was_held.1 {
+                oxi_held
+            } else {
+                self.engine_data.oxi_valve_open
+            },
+        );
+        if (fuel_held || oxi_held) != (was_held.0 || was_held.1) {
+            *self.serial.broadcast_interval.lock().unwrap() = if fuel_held || oxi_held {
+                MOMENTARY_BROADCAST_INTERVAL
+            } else {
+                Duration::from_millis(self.settings.broadcast_interval_ms)
+            };
+        }
+    }
+
+    /// Notes other boards going silent, and trips a redline while armed when a
+    /// board redlined when silent is, since its NaN channels never breach a limit.
+    fn check_boards(&mut self) {
+        let devices = self.serial.devices.clone();
+        let silent = devices.silent(Instant::now());
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        for (name, _) in &silent {
+            if !self.silent_boards.iter().any(|board| board == name) {
+                let label = format!("{} silent", name);
+                diag!("{}", label);
+                self.event_log.add(latest_time, &label);
+                self.highlights
+                    .add(HighlightKind::Alarm, latest_time, &label);
+            }
+        }
+        let redlined: Vec<&str> = silent
+            .iter()
+            .filter(|(_, safe_when_silent)| *safe_when_silent)
+            .map(|(name, _)| *name)
+            .collect();
+        if !redlined.is_empty() && self.arming.permits(true).is_ok() {
+            let reason = format!("Redline: {} silent", redlined.join(", "));
+            self.trigger_abort(&reason);
+            self.hold_countdown(&format!("redline ({})", reason));
+            self.alerter
+                .raise(&self.settings.alerts, AlertEvent::Redline, &reason);
+            self.redline_alert = Some(reason);
+        }
+        self.silent_boards = silent.iter().map(|(name, _)| name.to_string()).collect();
+    }
+
+    /// Closes both valves and latches the abort, marking it in the event log.
+    fn trigger_abort(&mut self, reason: &str) {
+        if !self.arming.abort(reason) {
+            return;
+        }
+        // Closing is never refused by the duty guard
+        self.command_valves(false, false);
+        if let Some(output) = self.serial.igniter.lock().unwrap().as_mut() {
+            self.igniter.safe(output);
+        }
+        if let Some(script) = &self.script {
+            script.stop();
+        }
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        let label = format!("ABORT: {}", reason);
+        self.screenshots.request(&label);
+        self.event_log.add(latest_time, &label);
+        self.highlights
+            .add(HighlightKind::Alarm, latest_time, &label);
+    }
+
+    /// Brings the GUI in line with the safe state the sleep watch already commanded,
+    /// disarms, and marks the gap in the event log and highlights.
+    fn host_woke(&mut self, gap: Duration) {
+        self.command_valves(false, false);
+        if self.arming.state() == ArmState::Armed {
+            let result = self.arming.disarm();
+            self.log_transition(result);
+        }
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        let label = format!(
+            "Host slept or stalled for {:.1} s; valves closed",
+            gap.as_secs_f64()
+        );
+        self.event_log.add(latest_time, &label);
+        self.highlights
+            .add(HighlightKind::Alarm, latest_time, &label);
+    }
+
+    /// Commanded versus firmware-reported valve states, once the firmware echoes them.
+    fn valve_echo_status(&self, ui: &mut egui::Ui) {
+        let Some(echo) = *self.serial.reported_valves.lock().unwrap() else {
+            return;
+        };
+        let commanded = (
+            self.engine_data.fuel_valve_open,
+            self.engine_data.oxi_valve_open,
+        );
+        let open_closed = |open: bool| if open { "OPEN" } else { "CLOSED" };
+        let reported = format!(
+            "Reported: Fuel {}, Oxidizer {}",
+            open_closed(echo.fuel_open),
+            open_closed(echo.oxi_open)
+        );
+        let resends = self.serial.valve_resends.load(Ordering::Relaxed);
+        if (echo.fuel_open, echo.oxi_open) == commanded {
+            ui.colored_label(egui::Color32::GREEN, reported)
+                .on_hover_text(format!("Commands re-sent this session: {}", resends));
+        } else if self.valves_commanded_at.elapsed() < VALVE_ACK_TIMEOUT {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!("{} (awaiting ack)", reported),
+            );
+        } else {
+            ui.colored_label(
+                egui::Color32::RED,
+                egui::RichText::new(format!("MISMATCH {} (re-sending)", reported)).strong(),
+            )
+            .on_hover_text(format!("Commands re-sent this session: {}", resends));
+        }
+    }
+
+    /// Marks an operator arming transition in the event log.
+    fn log_transition(&mut self, result: Result<ArmState, String>) {
+        match result {
+            Ok(state) => {
+                let latest_time = self
+                    .engine_data
+                    .data_points
+                    .back()
+                    .map_or(0.0, |dp| dp.time);
+                self.event_log.add(latest_time, &format!("State {}", state));
+            }
+            Err(e) => self.duty_warning = Some(e),
+        }
+    }
+
+    /// The stand's arm state: the serving station's when viewing remotely, once
+    /// it has sent one.
+    fn stand_status(&self) -> Option<StandStatus> {
+        match &self.remote {
+            Some(remote) => remote.stand(),
+            None => Some(StandStatus {
+                state: self.arming.state(),
+                abort_reason: self.arming.abort_record().map(|r| r.reason.clone()),
+            }),
+        }
+    }
+
+    /// Serial link up, or synthetic telemetry standing in for it.
+    fn port_connected(&self) -> bool {
+        self.simulator.is_some()
+            || self
+                .serial_link
+                .as_ref()
+                .is_some_and(|link| link.state() == ConnectionState::Connected)
+    }
+
+    /// The first configured bring-up gate not yet met, or None once arming is available.
+    fn current_gate(&self) -> Option<(StartupGate, String)> {
+        startup::current_gate(&self.settings.startup_gates, |gate| match gate {
+            StartupGate::ConfigLoaded => match &self.config_error {
+                Some(e) => Err(format!("{}; fix it, or save from Settings", e)),
+                None => Ok(()),
+            },
+            StartupGate::PortConnected => {
+                if self.port_connected() {
+                    Ok(())
+                } else {
+                    Err("Connect the serial port".to_string())
+                }
+            }
+            StartupGate::SelfTest => self.self_test.status(),
+            StartupGate::Checklist => {
+                let left = self.checklist_done.iter().filter(|done| !**done).count();
+                if left == 0 {
+                    Ok(())
+                } else {
+                    Err(format!("{} checklist items left", left))
+                }
+            }
+        })
+    }
+
+    /// Bring-up gates in their enforced order, and the checklist once its gate is reached.
+    fn startup_status(&mut self, ui: &mut egui::Ui) {
+        if self.remote.is_some() || self.settings.startup_gates.is_empty() {
+            return;
+        }
+        self.checklist_done
+            .resize(self.settings.checklist.len(), false);
+        let current = self.current_gate();
+        let position =
+            |gate: StartupGate| self.settings.startup_gates.iter().position(|&g| g == gate);
+        let current_position = current.as_ref().and_then(|(gate, _)| position(*gate));
+        ui.horizontal_wrapped(|ui| {
+            ui.label("Startup:");
+            for (i, gate) in self.settings.startup_gates.iter().enumerate() {
+                match current_position {
+                    Some(p) if i == p => {
+                        ui.colored_label(egui::Color32::YELLOW, format!("▶ {}", gate));
+                    }
+                    Some(p) if i > p => {
+                        ui.weak(gate.to_string());
+                    }
+                    _ => {
+                        ui.colored_label(egui::Color32::GREEN, format!("✔ {}", gate));
+                    }
+                }
+            }
+            match &current {
+                Some((_, reason)) => ui.label(reason),
+                None => ui.colored_label(egui::Color32::GREEN, "Arming available"),
+            };
+        });
+
+        // Ticking starts once every earlier gate has passed
+        let Some(checklist) = position(StartupGate::Checklist) else {
+            return;
+        };
+        let reached = current_position.is_none_or(|p| p >= checklist);
+        ui.horizontal_wrapped(|ui| {
+            for (item, done) in self.settings.checklist.iter().zip(&mut self.checklist_done) {
+                ui.add_enabled(reached, egui::Checkbox::new(done, item));
+            }
+        });
+    }
+
+    /// Igniter arm, guarded FIRE, and disarm controls with the interlock status,
+    /// when the stand has an igniter channel.
+    fn igniter_controls(&mut self, ui: &mut egui::Ui) {
+        let Some(settings) = self.settings.igniter.clone() else {
+            return;
+        };
+        // Remote viewers are told in the abort controls that they don't drive the stand
+        if self.remote.is_some() {
+            return;
+        }
+        let stand = self.arming.state();
+        let interlock = igniter::interlock(
+            &settings,
+            (
+                self.engine_data.fuel_valve_open,
+                self.engine_data.oxi_valve_open,
+            ),
+            *self.serial.reported_valves.lock().unwrap(),
+        );
+        let mut igniter_output = self.serial.igniter.lock().unwrap();
+        let Some(output) = igniter_output.as_mut() else {
+            return;
+        };
+        let mut result = None;
+        ui.horizontal(|ui| {
+            if self.replay.is_some() {
+                ui.disable();
+            }
+            ui.label("Igniter:");
+            let state = self.igniter.state();
+            let color = match state {
+                IgniterState::Safe => egui::Color32::GREEN,
+                IgniterState::Armed => egui::Color32::YELLOW,
+                IgniterState::Firing => egui::Color32::RED,
+            };
+            ui.colored_label(color, egui::RichText::new(state.to_string()).strong());
+            match state {
+                IgniterState::Safe => {
+                    let armable = matches!(stand, ArmState::Armed | ArmState::Firing);
+                    if ui
+                        .add_enabled(armable, egui::Button::new("Arm Igniter"))
+                        .on_disabled_hover_text("Arm the stand first")
+                        .clicked()
+                    {
+                        result = Some(self.igniter.arm(stand));
+                    }
+                }
+                IgniterState::Armed => {
+                    ui.label(format!(
+                        "{:.0} s to fire",
+                        self.igniter.arm_remaining(&settings).as_secs_f64().ceil()
+                    ));
+                    // The FIRE button sits under a cover that has to be lifted first
+                    let cover = if self.igniter.guard_open {
+                        "Close Guard"
+                    } else {
+                        "Lift Guard"
+                    };
+                    let cover_button =
+                        egui::Button::new(egui::RichText::new(cover).color(egui::Color32::BLACK))
+                            .fill(egui::Color32::from_rgb(255, 200, 0));
+                    if ui.add(cover_button).clicked() {
+                        self.igniter.guard_open = !self.igniter.guard_open;
+                    }
+                    if self.igniter.guard_open {
+                        let fire_button = egui::Button::new(
+                            egui::RichText::new("FIRE")
+                                .size(20.0)
+                                .strong()
+                                .color(egui::Color32::WHITE),
+                        )
+                        .fill(egui::Color32::from_rgb(200, 0, 0));
+                        let fire = ui.add_enabled(interlock.is_ok(), fire_button);
+                        let fire = match &interlock {
+                            Err(e) => fire.on_disabled_hover_text(format!("Interlock: {}", e)),
+                            Ok(()) => fire,
+                        };
+                        if fire.clicked() {
+                            result = Some(self.igniter.fire(&settings, interlock.clone(), output));
+                        }
+                    }
+                    if ui.button("Disarm Igniter").clicked() {
+                        self.igniter.safe(output);
+                        result = Some(Ok(IgniterState::Safe));
+                    }
+                }
+                IgniterState::Firing => {
+                    ui.colored_label(egui::Color32::RED, "Igniter energized");
+                }
+            }
+            if let Err(e) = &interlock {
+                ui.colored_label(egui::Color32::YELLOW, format!("Interlock: {}", e));
+            }
+        });
+        drop(igniter_output);
+        match result {
+            Some(Ok(IgniterState::Firing)) => {
+                self.screenshots.request("igniter fired");
+                self.log_phase("Igniter FIRED");
+            }
+            Some(Ok(state)) => self.log_phase(&format!("Igniter {}", state)),
+            Some(Err(e)) => diag!("{}", e),
+            None => {}
+        }
+    }
+
+    /// Marks an igniter, countdown, or script transition in the event log and highlights.
+    fn log_phase(&mut self, label: &str) {
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        self.event_log.add(latest_time, label);
+        self.highlights
+            .add(HighlightKind::Phase, latest_time, label);
+    }
+
+    /// Holds the countdown and logs why; does nothing unless it is counting down.
+    fn hold_countdown(&mut self, reason: &str) {
+        if self.serial.countdown.lock().unwrap().hold(reason) {
+            self.log_phase(&format!("HOLD: {}", reason));
+        }
+    }
+
+    /// Sets the plots' T-0 at the latest data point and marks it.
+    fn mark_t_zero(&mut self) {
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        self.clock.set_t_zero(latest_time);
+        self.event_log.add(latest_time, "T-0");
+        self.highlights
+            .add(HighlightKind::Marker, latest_time, "T-0");
+    }
+
+    /// T-time display with the countdown's start, hold, resume, and recycle controls.
+    fn countdown_controls(&mut self, ui: &mut egui::Ui) {
+        let now = Instant::now();
+        let (t_time, hold_reason, counting) = {
+            let countdown = self.serial.countdown.lock().unwrap();
+            (
+                countdown.t_time(now),
+                countdown.hold_reason().map(str::to_string),
+                countdown.counting(now),
+            )
+        };
+        ui.horizontal(|ui| {
+            match t_time {
+                Some(t_time) => {
+                    let color = if hold_reason.is_some() {
+                        egui::Color32::YELLOW
+                    } else {
+                        ui.visuals().strong_text_color()
+                    };
+                    ui.colored_label(
+                        color,
+                        egui::RichText::new(format_t_time(t_time))
+                            .monospace()
+                            .heading()
+                            .strong(),
+                    );
+                }
+                None => {
+                    ui.label("No countdown");
+                }
+            }
+            if let Some(reason) = &hold_reason {
+                ui.colored_label(egui::Color32::YELLOW, format!("HOLD: {}", reason));
+                if ui.button("Resume").clicked() && self.serial.countdown.lock().unwrap().resume() {
+                    self.log_phase("Countdown resumed");
+                }
+            } else if counting {
+                if ui.button("Hold").clicked() {
+                    self.hold_countdown("operator");
+                }
+            } else if t_time.is_none()
+                && ui
+                    .button(format!("Start T-{:.0} s", self.settings.countdown_s))
+                    .on_hover_text("Holds itself on a redline or when telemetry is lost")
+                    .clicked()
+            {
+                self.serial
+                    .countdown
+                    .lock()
+                    .unwrap()
+                    .start(Duration::from_secs_f64(self.settings.countdown_s));
+                self.clock.clear_t_zero();
+                self.highlights.touch();
+                self.log_phase(&format!(
+                    "Countdown started at T-{:.0} s",
+                    self.settings.countdown_s
+                ));
+            }
+            if (counting || hold_reason.is_some()) && ui.button("Recycle").clicked() {
+                self.serial.countdown.lock().unwrap().recycle();
+                self.log_phase("Countdown recycled");
+            }
+        });
+    }
+
+    /// Target position sliders with the commanded and reported positions, when
+    /// the firmware takes proportional positions. Targets apply while a valve is open.
+    fn position_controls(&mut self, ui: &mut egui::Ui) {
+        let mut positions = self.serial.valve_positions.lock().unwrap();
+        let Some(ramp) = positions.as_mut() else {
+            return;
+        };
+        let commanded = ramp.commanded();
+        let latest = self.engine_data.data_points.back();
+        let mut targets_set = Vec::new();
+        ui.horizontal(|ui| {
+            if self.replay.is_some() || self.remote.is_some() {
+                ui.disable();
+            }
+            for (name, target, commanded, reported) in [
+                (
+                    "Fuel",
+                    &mut ramp.target.0,
+                    commanded.0,
+                    latest.map(|dp| dp.desired_pos_fuel),
+                ),
+                (
+                    "Oxidizer",
+                    &mut ramp.target.1,
+                    commanded.1,
+                    latest.map(|dp| dp.desired_pos_oxi),
+                ),
+            ] {
+                ui.label(format!("{} position", name));
+                let slider = ui.add(egui::Slider::new(target, 0.0..=100.0).integer().suffix("%"));
+                // Noted once the slider is let go, not at every step of a drag
+                if slider.drag_stopped() || (slider.changed() && !slider.dragged()) {
+                    targets_set.push((name, *target));
+                }
+                let reported = reported.map_or("--".to_string(), |deg| {
+                    format!("{:.0}%", valves::position_percent(deg))
+                });
+                ui.label(format!(
+                    "commanded {:.0}%, reported {}",
+                    commanded, reported
+                ));
+                ui.separator();
+            }
+        });
+        drop(positions);
+        let latest_time = latest.map_or(0.0, |dp| dp.time);
+        for (name, target) in targets_set {
+            self.event_log.add(
+                latest_time,
+                &format!("{} position target {:.0}%", name, target),
+            );
+        }
+    }
+
+    /// Switches for the stand's auxiliary relays, when it has them, with each
+    /// change marked in the event log.
+    fn relay_controls(&mut self, ui: &mut egui::Ui) {
+        let Some(mut relays) = *self.serial.relay_states.lock().unwrap() else {
+            return;
+        };
+        let mut switched = Vec::new();
+        ui.horizontal(|ui| {
+            // Like the valves, only the station driving the stand switches them
+            if self.replay.is_some() || self.remote.is_some() {
+                ui.disable();
+            }
+            ui.label("Relays:");
+            for channel in RelayChannel::ALL {
+                let on = channel.state_mut(&mut relays);
+                if ui.toggle_value(on, channel.name()).changed() {
+                    switched.push((channel, *on));
+                }
+            }
+        });
+        if switched.is_empty() {
+            return;
+        }
+        *self.serial.relay_states.lock().unwrap() = Some(relays);
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        for (channel, on) in switched {
+            let label = format!("{} {}", channel.name(), if on { "on" } else { "off" });
+            diag!("{}", label);
+            self.event_log.add(latest_time, &label);
+        }
+    }
 
-impl FlowRateApp {
-    /// Creates a new FlowRateApp instance.
-    fn new(
-        data_receiver: Receiver<EngineDataPoint>,
-        valve_state_sender: Sender<(bool, bool)>,
-        log_dir: PathBuf,
-    ) -> Self {
-        Self {
-            data_receiver,
-            valve_state_sender,
-            engine_data: EngineData::default(),
-            latest_raw_values: String::new(),
-            log_dir,
+    /// ABORT button, arming state with its transitions, and the firmware emergency flag.
+    fn abort_controls(&mut self, ui: &mut egui::Ui) {
+        if self.remote.is_some() {
+            ui.horizontal(|ui| {
+                stand_heading(ui, self.stand_status().as_ref());
+                ui.separator();
+                ui.label(
+                    "Remote view: valves are controlled by the station serving this telemetry.",
+                );
+            });
+            return;
         }
+        let gate = self.current_gate();
+        ui.horizontal(|ui| {
+            let abort_button = egui::Button::new(
+                egui::RichText::new("ABORT")
+                    .size(28.0)
+                    .strong()
+                    .color(egui::Color32::WHITE),
+            )
+            .fill(egui::Color32::from_rgb(200, 0, 0));
+            if ui
+                .add_sized([160.0, 48.0], abort_button)
+                .on_hover_text("Close both valves and latch (Esc)")
+                .clicked()
+            {
+                self.trigger_abort("Operator");
+            }
+
+            ui.separator();
+            let state = self.arming.state();
+            let color = arm_state_color(state);
+            match self.arming.abort_record() {
+                Some(record) => {
+                    ui.colored_label(
+                        color,
+                        egui::RichText::new(format!(
+                            "ABORTED ({}, {:.0} s ago)",
+                            record.reason,
+                            record.at.elapsed().as_secs_f64()
+                        ))
+                        .heading()
+                        .strong(),
+                    );
+                }
+                None => {
+                    let text = format!(
+                        "{} ({:.0} s)",
+                        state,
+                        self.arming.since().elapsed().as_secs_f64()
+                    );
+                    ui.colored_label(color, egui::RichText::new(text).heading());
+                }
+            }
+            match state {
+                ArmState::Safe => {
+                    let redline = self.redline_monitor.critical();
+                    let emergency = self.firmware_emergency;
+                    let arm = ui.add_enabled(
+                        gate.is_none() && !redline && !emergency,
+                        egui::Button::new("Arm"),
+                    );
+                    let arm =
+                        match &gate {
+                            Some((gate, reason)) => arm
+                                .on_disabled_hover_text(format!("Waiting on {}: {}", gate, reason)),
+                            None if redline => {
+                                arm.on_disabled_hover_text("A redline is still critical")
+                            }
+                            None if emergency => arm
+                                .on_disabled_hover_text("The firmware emergency flag is still set"),
+                            None => arm,
+                        };
+                    if arm.clicked() {
+                        let result = self.arming.arm();
+                        self.log_transition(result);
+                    }
+                }
+                ArmState::Armed => {
+                    if ui.button("Disarm").clicked() {
+                        let result = self.arming.disarm();
+                        self.log_transition(result);
+                    }
+                }
+                ArmState::Firing => {
+                    ui.add_enabled(false, egui::Button::new("Disarm"))
+                        .on_disabled_hover_text("Close both valves first");
+                }
+                ArmState::Aborted => {
+                    if ui
+                        .add_enabled(!self.firmware_emergency, egui::Button::new("Reset to SAFE"))
+                        .on_disabled_hover_text("The firmware emergency flag is still set")
+                        .clicked()
+                    {
+                        let result = self.arming.reset();
+                        self.log_transition(result);
+                    }
+                }
+            }
+
+            if self.arming.abort_count() > 0 {
+                ui.label(format!(
+                    "Aborts this session: {}",
+                    self.arming.abort_count()
+                ));
+            }
+
+            ui.separator();
+            if self.firmware_emergency {
+                ui.colored_label(
+                    egui::Color32::RED,
+                    egui::RichText::new("FIRMWARE EMERGENCY").heading().strong(),
+                );
+            } else {
+                ui.label("Firmware emergency: clear");
+            }
+        });
     }
 }
 
 impl eframe::App for FlowRateApp {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        self.plot_cursor = widgets::take_cursor(ctx);
         // Receive new data points
         while let Ok(data_point) = self.data_receiver.try_recv() {
             self.latest_raw_values = data_point.raw_values.clone(); // Update latest raw values
-            self.engine_data.data_points.push_back(data_point);
-            if self.engine_data.data_points.len() > MAX_DATA_POINTS {
-                self.engine_data.data_points.pop_front();
+            self.frozen_channels.update(&data_point);
+            let alarms = self
+                .limit_monitor
+                .update(&self.settings.limits, &data_point);
+            for alarm in &alarms {
+                let already = self
+                    .limit_alarms
+                    .iter()
+                    .any(|a| a.quantity == alarm.quantity && a.level >= alarm.level);
+                if !already {
+                    diag!("Limit {}", alarm.message());
+                    if self.replay.is_none() {
+                        let label = format!("Limit {}", alarm.message());
+                        self.highlights
+                            .add(HighlightKind::Alarm, data_point.time, &label);
+                        self.screenshots.request(&label);
+                    }
+                }
+            }
+            self.limit_alarms = alarms;
+            let tripped = self.redline_monitor.update(
+                &self.settings.limits,
+                self.settings.redline_samples,
+                self.arming.permits(true).is_ok(),
+                &data_point,
+            );
+            if !tripped.is_empty() && self.replay.is_none() {
+                let reason = tripped.join("; ");
+                self.trigger_abort(&reason);
+                self.hold_countdown(&format!("redline ({})", reason));
+                self.alerter
+                    .raise(&self.settings.alerts, AlertEvent::Redline, &reason);
+                self.redline_alert = Some(reason);
+            }
+            // A firmware emergency latches an abort as if the operator had pressed it
+            if data_point.emergency && !self.firmware_emergency && self.replay.is_none() {
+                self.trigger_abort("Firmware emergency");
+                self.alerter.raise(
+                    &self.settings.alerts,
+                    AlertEvent::Emergency,
+                    "Emergency flag set by the firmware",
+                );
+            }
+            self.firmware_emergency = data_point.emergency;
+            // Replayed data belongs to its own session, not this one
+            if self.replay.is_none() {
+                self.last_packet = Instant::now();
+                self.alerter.data_received();
+                self.session_stats.update(&data_point);
+                self.self_test.update(&data_point);
+                if let Some(wizard) = &mut self.calibration_wizard {
+                    wizard.update(&data_point);
+                }
+                if let Some(script) = &self.script {
+                    script.telemetry(&data_point);
+                }
+                if data_point.fuel_valve_open || data_point.oxi_valve_open {
+                    self.highlights.start_phase(data_point.time, "Valves open");
+                } else {
+                    self.highlights.end_phase(data_point.time);
+                }
+            }
+            self.engine_data
+                .push(data_point, self.settings.max_data_points);
+        }
+
+        // On the clock, as a silent board stops the samples its values would ride on
+        if self.replay.is_none() && self.remote.is_none() && self.kiosk.is_none() {
+            self.check_boards();
+        }
+
+        // Only a link that dropped on its own raises an alert, not an operator disconnect
+        let link_up = self
+            .serial_link
+            .as_ref()
+            .map(|link| link.state() == ConnectionState::Connected);
+        self.alerter.watch(&self.settings.alerts, link_up);
+        if link_up == Some(false) || self.stale_seconds().is_some() {
+            self.hold_countdown("telemetry lost");
+        }
+        if self
+            .serial
+            .countdown
+            .lock()
+            .unwrap()
+            .take_t_zero(Instant::now())
+        {
+            self.mark_t_zero();
+        }
+        if !self.port_connected() {
+            self.self_test.reset();
+        }
+        if let Some(gap) = self.sleep_watch.take_wake() {
+            self.host_woke(gap);
+        }
+        if let Some(settings) = &self.settings.igniter {
+            let reason = self
+                .serial
+                .igniter
+                .lock()
+                .unwrap()
+                .as_mut()
+                .and_then(|output| self.igniter.update(settings, self.arming.state(), output));
+            if let Some(reason) = reason {
+                self.log_phase(&format!("Igniter SAFE ({})", reason));
+            }
+        }
+        if self.settings.beacon_automation && self.remote.is_none() {
+            if let Some(on) = self
+                .beacon_automation
+                .update(self.arming.state(), Instant::now())
+            {
+                if let Some(relays) = self.serial.relay_states.lock().unwrap().as_mut() {
+                    relays.beacon = on;
+                }
+            }
+        }
+        self.screenshots.update(ctx);
+
+        if self.kiosk.is_some() {
+            self.show_kiosk(ctx);
+            ctx.request_repaint();
+            return;
+        }
+
+        if self.remote.is_none() {
+            if let Some(stand) = self.stand_status() {
+                *self.serial.stand.lock().unwrap() = stand;
+            }
+        }
+
+        // Read before anything commands valves this frame
+        let deadman_input = self.settings.deadman_input;
+        self.deadman_held = match (deadman_input.key(), deadman_input.trigger()) {
+            (Some(key), _) => ctx.input(|i| i.focused && i.key_down(key)),
+            (_, Some(trigger)) => self.gamepads.held(trigger),
+            (None, None) => false,
+        };
+        self.update_script();
+
+        // Esc is left to text fields and popups while they have focus
+        let esc_free = !ctx.wants_keyboard_input() && !ctx.memory(|m| m.any_popup_open());
+        if self.remote.is_none() && esc_free && ctx.input(|i| i.key_pressed(egui::Key::Escape)) {
+            self.trigger_abort("Operator (Esc)");
+        }
+
+        // Deadman switch: close both valves the moment the input is released,
+        // the window loses focus with a key, or the gamepad disconnects
+        if self.settings.deadman_enabled
+            && !self.deadman_held
+            && (self.engine_data.fuel_valve_open || self.engine_data.oxi_valve_open)
+        {
+            self.command_valves(false, false);
+        }
+
+        // Quick event markers from the number keys
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        // Keys typed into a text field are not markers
+        let typing = ctx.wants_keyboard_input();
+        for (key, label) in QUICK_MARKERS {
+            if !typing && ctx.input(|i| i.key_pressed(key)) {
+                self.event_log.add(latest_time, label);
+                self.highlights
+                    .add(HighlightKind::Marker, latest_time, label);
             }
         }
 
+        if !typing && ctx.input(|i| i.key_pressed(ANNOTATION_KEY)) {
+            self.event_log_open = true;
+            self.annotation_time = Some(latest_time);
+            self.focus_annotation = true;
+        }
+
         // Update the UI controls
         egui::TopBottomPanel::top("controls").show(ctx, |ui| {
             // Display current system time
             let current_time = chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
             ui.label(format!("Current Time: {}", current_time));
 
+            self.connection_controls(ui);
+            self.replay_controls(ui);
+            self.stale_banner(ui);
+            self.abort_controls(ui);
+            self.startup_status(ui);
+
             ui.horizontal(|ui| {
-                let mut fuel_valve_open = self.engine_data.fuel_valve_open;
-                let mut oxi_valve_open = self.engine_data.oxi_valve_open;
+                // Valves can't be commanded while a replay or a remote station drives the display
+                let view_only = self.replay.is_some() || self.remote.is_some();
+                if view_only {
+                    ui.disable();
+                }
+                if self.momentary {
+                    // Released the moment the pointer or key lets go, or the window loses focus
+                    let hold = |ui: &mut egui::Ui, label: &str, open: bool| {
+                        ui.add(egui::Button::new(label).selected(open))
+                            .is_pointer_button_down_on()
+                    };
+                    let fuel_button = hold(ui, "Hold Fuel", self.engine_data.fuel_valve_open);
+                    let oxi_button = hold(ui, "Hold Oxidizer", self.engine_data.oxi_valve_open);
+                    let both_button = hold(
+                        ui,
+                        "Hold Both",
+                        self.engine_data.fuel_valve_open && self.engine_data.oxi_valve_open,
+                    );
+                    let (fuel_key, oxi_key) = ctx.input(|i| {
+                        let held = |key| i.focused && !typing && !view_only && i.key_down(key);
+                        (held(MOMENTARY_KEYS.0), held(MOMENTARY_KEYS.1))
+                    });
+                    self.hold_valves(
+                        fuel_button || both_button || fuel_key,
+                        oxi_button || both_button || oxi_key,
+                    );
+                } else {
+                    let mut fuel_valve_open = self.engine_data.fuel_valve_open;
+                    let mut oxi_valve_open = self.engine_data.oxi_valve_open;
+
+                    let fuel_changed = ui
+                        .toggle_value(&mut fuel_valve_open, "Fuel Valve")
+                        .changed();
+                    let oxi_changed = ui
+                        .toggle_value(&mut oxi_valve_open, "Oxidizer Valve")
+                        .changed();
+                    if fuel_changed || oxi_changed {
+                        self.command_valves(fuel_valve_open, oxi_valve_open);
+                    }
 
+                    if ui.button("Both On").clicked() {
+                        self.command_valves(true, true);
+                    }
+                    if ui.button("Both Off").clicked() {
+                        self.command_valves(false, false);
+                    }
+                }
+                self.valve_echo_status(ui);
                 if ui
-                    .toggle_value(&mut fuel_valve_open, "Fuel Valve")
+                    .checkbox(&mut self.momentary, "Momentary")
+                    .on_hover_text(format!(
+                        "Valves open only while held: the Hold buttons, or {} for fuel and {} for oxidizer",
+                        MOMENTARY_KEYS.0.name(),
+                        MOMENTARY_KEYS.1.name()
+                    ))
                     .changed()
-                    || ui
-                        .toggle_value(&mut oxi_valve_open, "Oxidizer Valve")
-                        .changed()
                 {
-                    self.engine_data.fuel_valve_open = fuel_valve_open;
-                    self.engine_data.oxi_valve_open = oxi_valve_open;
-                    // Send updated valve states
-                    let _ = self
-                        .valve_state_sender
-                        .send((fuel_valve_open, oxi_valve_open));
+                    // Either way the valves start closed, as nothing is held yet
+                    self.hold_valves(false, false);
+                    self.command_valves(false, false);
                 }
 
-                if ui.button("Both On").clicked() {
-                    self.engine_data.fuel_valve_open = true;
-                    self.engine_data.oxi_valve_open = true;
-                    // Send updated valve states
-                    let _ = self.valve_state_sender.send((true, true));
+                ui.separator();
+                let mut deadman_changed = ui
+                    .checkbox(&mut self.settings.deadman_enabled, "Deadman")
+                    .changed();
+                egui::ComboBox::from_id_salt("deadman_input")
+                    .selected_text(self.settings.deadman_input.name())
+                    .show_ui(ui, |ui| {
+                        for input in DeadmanInput::ALL {
+                            deadman_changed |= ui
+                                .selectable_value(
+                                    &mut self.settings.deadman_input,
+                                    input,
+                                    input.name(),
+                                )
+                                .changed();
+                        }
+                    });
+                if deadman_changed {
+                    self.save_deadman();
                 }
-                if ui.button("Both Off").clicked() {
-                    self.engine_data.fuel_valve_open = false;
-                    self.engine_data.oxi_valve_open = false;
-                    // Send updated valve states
-                    let _ = self.valve_state_sender.send((false, false));
+                if self.settings.deadman_enabled {
+                    if self.deadman_held {
+                        ui.colored_label(egui::Color32::GREEN, "HELD");
+                    } else {
+                        ui.colored_label(egui::Color32::YELLOW, "RELEASED");
+                    }
                 }
             });
-        });
-
-        // Always build and render the plots
-        let data_points = &self.engine_data.data_points;
-
-        // Build PlotPoints from the data
-        let (fuel_flow_points, oxi_flow_points): (Vec<_>, Vec<_>) = data_points
-            .iter()
-            .map(|dp| ([dp.time, dp.flow_rate_fuel], [dp.time, dp.flow_rate_oxi]))
-            .unzip();
-
-        let (fuel_pulse_points, oxi_pulse_points): (Vec<_>, Vec<_>) = data_points
-            .iter()
-            .map(|dp| {
-                (
-                    [dp.time, dp.pulse_count_fuel as f64],
-                    [dp.time, dp.pulse_count_oxi as f64],
-                )
-            })
-            .unzip();
 
-        let (desired_pos_fuel_points, desired_pos_oxi_points): (Vec<_>, Vec<_>) = data_points
-            .iter()
-            .map(|dp| {
-                (
-                    [dp.time, dp.desired_pos_fuel as f64],
-                    [dp.time, dp.desired_pos_oxi as f64],
-                )
-            })
-            .unzip();
+            self.igniter_controls(ui);
+            self.position_controls(ui);
+            self.relay_controls(ui);
 
-        // Valve states over time
-        let fuel_valve_points: Vec<_> = data_points
-            .iter()
-            .map(|dp| ([dp.time, if dp.fuel_valve_open { 1.0 } else { 0.0 }]))
-            .collect();
+            ui.horizontal(|ui| {
+                let (fuel_actuations, oxi_actuations) = self.duty_guard.actuations();
+                let (fuel_cycles, oxi_cycles) = self.duty_guard.cycles_last_minute();
+                ui.label(format!(
+                    "Actuations: Fuel {} ({}/min) | Oxidizer {} ({}/min) | Limit {}/min",
+                    fuel_actuations,
+                    fuel_cycles,
+                    oxi_actuations,
+                    oxi_cycles,
+                    self.duty_guard.max_cycles_per_minute()
+                ));
+                ui.checkbox(&mut self.duty_guard.override_limit, "Override duty limit");
+                if let Some(warning) = &self.duty_warning {
+                    ui.colored_label(egui::Color32::RED, warning);
+                }
+            });
 
-        let oxi_valve_points: Vec<_> = data_points
-            .iter()
-            .map(|dp| ([dp.time, if dp.oxi_valve_open { 1.0 } else { 0.0 }]))
-            .collect();
+            self.serial.environment.show_strip(ui);
 
-        // Render the plots without ScrollArea
-        egui::CentralPanel::default().show(ctx, |ui| {
-            ui.heading("Engine Data");
+            if !self.limit_alarms.is_empty() {
+                ui.horizontal(|ui| {
+                    ui.label("Limits:");
+                    for alarm in &self.limit_alarms {
+                        let color = match alarm.level {
+                            LimitLevel::Critical => egui::Color32::RED,
+                            _ => egui::Color32::from_rgb(255, 165, 0),
+                        };
+                        ui.colored_label(color, alarm.message());
+                    }
+                });
+            }
 
-            // First Row: Flow Rates and Pulse Counts
-            ui.columns(2, |columns| {
-                // Flow Rates Plot
-                columns[0].heading("Flow Rates");
-                Plot::new("Flow Rates")
-                    .view_aspect(2.0)
-                    .legend(Legend::default())
-                    .allow_double_click_reset(true)
-                    .show(&mut columns[0], |plot_ui| {
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(fuel_flow_points.clone()))
-                                .color(egui::Color32::RED)
-                                .name("Fuel Flow Rate"),
-                        );
+            ui.horizontal(|ui| {
+                let hotkeys: Vec<String> = QUICK_MARKERS
+                    .iter()
+                    .map(|(key, label)| format!("{} {}", key.name(), label))
+                    .collect();
+                ui.label(format!(
+                    "Markers: {} | {} annotate",
+                    hotkeys.join(" | "),
+                    ANNOTATION_KEY.name()
+                ));
+                if let Some(marker) = self.event_log.markers().last() {
+                    ui.label(format!(
+                        "Last: \"{}\" at {}",
+                        marker.label,
+                        self.clock.format(marker.time)
+                    ));
+                }
+            });
 
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(oxi_flow_points.clone()))
-                                .color(egui::Color32::BLUE)
-                                .name("Oxidizer Flow Rate"),
-                        );
+            ui.horizontal(|ui| {
+                ui.label("Plot window");
+                egui::ComboBox::from_id_salt("time_window")
+                    .selected_text(self.time_window.name())
+                    .show_ui(ui, |ui| {
+                        for window in TimeWindow::ALL {
+                            ui.selectable_value(&mut self.time_window, window, window.name());
+                        }
                     });
-
-                // Pulse Counts Plot
-                columns[1].heading("Pulse Counts");
-                Plot::new("Pulse Counts")
-                    .view_aspect(2.0)
-                    .legend(Legend::default())
-                    .allow_double_click_reset(true)
-                    .show(&mut columns[1], |plot_ui| {
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(fuel_pulse_points.clone()))
-                                .color(egui::Color32::RED)
-                                .name("Fuel Pulse Count"),
+                let freeze_label = if self.frozen_view.is_some() {
+                    "Resume"
+                } else {
+                    "Freeze"
+                };
+                if ui
+                    .button(freeze_label)
+                    .on_hover_text("Hold the plots still; data keeps being logged")
+                    .clicked()
+                {
+                    self.frozen_view = match self.frozen_view {
+                        Some(_) => None,
+                        None => Some(self.engine_data.clone()),
+                    };
+                }
+                if self.frozen_view.is_some() {
+                    ui.colored_label(egui::Color32::LIGHT_BLUE, "Plots frozen");
+                }
+                // Say so when the window reaches back into the thinned history
+                let engine_data = self.frozen_view.as_ref().unwrap_or(&self.engine_data);
+                let data_points = &engine_data.data_points;
+                if let (Some(first), Some(last)) = (data_points.front(), data_points.back()) {
+                    let full_rate_ms = last.time - first.time;
+                    let thinned = self
+                        .time_window
+                        .duration_ms()
+                        .is_none_or(|window| full_rate_ms < window);
+                    if thinned && !engine_data.history.points().is_empty() {
+                        ui.label(format!(
+                            "Older than {:.0} s shown at one point per {:.0} ms",
+                            full_rate_ms / 1000.0,
+                            engine_data.history.step_ms().max(1.0)
+                        ))
+                        .on_hover_text(
+                            "Every sample is in data_log.csv; Settings sets how many are kept at full rate",
                         );
+                    }
+                }
+            });
 
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(oxi_pulse_points.clone()))
-                                .color(egui::Color32::BLUE)
-                                .name("Oxidizer Pulse Count"),
-                        );
-                    });
+            self.countdown_controls(ui);
+            ui.horizontal(|ui| {
+                if ui.button("Set T-0").clicked() {
+                    self.serial.countdown.lock().unwrap().mark_t_zero();
+                    self.mark_t_zero();
+                }
+                if self.clock.t_zero().is_some() {
+                    if ui.button("Clear T-0").clicked() {
+                        self.serial.countdown.lock().unwrap().recycle();
+                        self.clock.clear_t_zero();
+                        self.highlights.touch();
+                    }
+                    ui.label(format!("Now: {}", self.clock.format(latest_time)));
+                } else {
+                    ui.label("T-0 not set");
+                }
             });
 
-            // Second Row: Valve States and Desired Positions
-            ui.columns(2, |columns| {
-                // Valve States Plot
-                columns[0].heading("Valve States");
-                Plot::new("Valve States")
-                    .view_aspect(2.0)
-                    .allow_double_click_reset(true)
-                    .show(&mut columns[0], |plot_ui| {
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(fuel_valve_points.clone()))
-                                .color(egui::Color32::RED)
-                                .name("Fuel Valve Open"),
-                        );
+            ui.horizontal(|ui| {
+                let mut flow_decoding = self.serial.flow_decoding.lock().unwrap();
+                let flow_decoding = &mut *flow_decoding;
+                for (label, decoding) in [
+                    ("Fuel decoding", &mut flow_decoding.fuel),
+                    ("Oxidizer decoding", &mut flow_decoding.oxi),
+                ] {
+                    ui.label(label);
+                    egui::ComboBox::from_id_salt(label)
+                        .selected_text(decoding.name())
+                        .show_ui(ui, |ui| {
+                            for mode in FlowDecoding::ALL {
+                                ui.selectable_value(decoding, mode, mode.name());
+                            }
+                        });
+                }
 
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(oxi_valve_points.clone()))
-                                .color(egui::Color32::BLUE)
-                                .name("Oxidizer Valve Open"),
-                        );
+                ui.separator();
+                ui.label("Command dialect");
+                let mut command_dialect = self.serial.command_dialect.lock().unwrap();
+                egui::ComboBox::from_id_salt("command_dialect")
+                    .selected_text(command_dialect.name())
+                    .show_ui(ui, |ui| {
+                        for dialect in CommandDialect::ALL {
+                            ui.selectable_value(&mut *command_dialect, dialect, dialect.name());
+                        }
                     });
+            });
+        });
 
-                // Desired Positions Plot
-                columns[1].heading("Desired Positions");
-                Plot::new("Desired Positions")
-                    .view_aspect(2.0)
-                    .legend(Legend::default())
-                    .allow_double_click_reset(true)
-                    .show(&mut columns[1], |plot_ui| {
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(desired_pos_fuel_points.clone()))
-                                .color(egui::Color32::RED)
-                                .name("Desired Position Fuel"),
-                        );
-
-                        plot_ui.line(
-                            Line::new(PlotPoints::from(desired_pos_oxi_points.clone()))
-                                .color(egui::Color32::BLUE)
-                                .name("Desired Position Oxidizer"),
-                        );
-                    });
+        if self.settings.readout_panel {
+            egui::SidePanel::right("readouts").show(ctx, |ui| {
+                // Always live, even while the plots are frozen for inspection
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    readouts::show(
+                        ui,
+                        self.engine_data.data_points.back(),
+                        &self.settings,
+                        &self.limit_alarms,
+                    );
+                });
             });
+        }
+
+        // Render the registered dashboard widgets two per row
+        egui::CentralPanel::default().show(ctx, |ui| {
+            ui.heading("Engine Data");
+
+            let widget_ctx = WidgetContext {
+                engine_data: self.frozen_view.as_ref().unwrap_or(&self.engine_data),
+                frozen_channels: &self.frozen_channels,
+                clock: &self.clock,
+                focus: self.plot_focus,
+                markers: self.event_log.markers(),
+                cursor: self.plot_cursor,
+                window: self.time_window,
+                log_dir: &self.log_dir,
+            };
+            let mut widget_rects = Vec::new();
+            let columns = self.layout.columns.max(1);
+            for row in self.widgets.chunks_mut(columns) {
+                ui.columns(columns, |columns| {
+                    for (column, widget) in columns.iter_mut().zip(row.iter_mut()) {
+                        let title = widget.title().to_string();
+                        // Titles needn't be unique in a custom layout
+                        let id = (widget_rects.len(), title.clone());
+                        let drawn = column.push_id(id, |ui| widget.show(ui, &widget_ctx));
+                        widget_rects.push((title, drawn.response.rect));
+                    }
+                });
+            }
+            self.widget_rects = widget_rects;
         });
 
         // Display latest raw decoded values at the bottom
         egui::TopBottomPanel::bottom("raw_values").show(ctx, |ui| {
             ui.horizontal(|ui| {
                 ui.label(format!("Latest Raw Values: {}", self.latest_raw_values));
+                let corrupt_frames = self.serial.corrupt_frames.load(Ordering::Relaxed);
+                let corrupt_text = format!("Corrupt frames: {}", corrupt_frames);
+                if corrupt_frames > 0 {
+                    ui.colored_label(egui::Color32::RED, corrupt_text);
+                } else {
+                    ui.label(corrupt_text);
+                }
+                if let Some(server) = &self.serial.telemetry_server {
+                    ui.label(format!("Remote viewers: {}", server.viewer_count()));
+                }
+                if let Some(time_sync) = &self.serial.time_sync {
+                    match time_sync.offset_ms() {
+                        Some(offset) => ui.colored_label(
+                            egui::Color32::GREEN,
+                            format!("Time: {} ({:+} ms)", time_sync.description(), offset),
+                        ),
+                        None => ui.colored_label(
+                            egui::Color32::YELLOW,
+                            format!("Time: {} not synced", time_sync.description()),
+                        ),
+                    };
+                }
+                if let Some(mqtt) = &self.serial.mqtt {
+                    let (color, text) = if mqtt.connected() {
+                        (egui::Color32::GREEN, "MQTT connected")
+                    } else {
+                        (egui::Color32::YELLOW, "MQTT disconnected")
+                    };
+                    ui.colored_label(color, text);
+                    if mqtt.dropped() > 0 {
+                        ui.label(format!("MQTT dropped: {}", mqtt.dropped()));
+                    }
+                }
 
                 // Allocate remaining space with right-to-left layout
                 ui.allocate_ui_with_layout(
                     egui::Vec2::new(ui.available_width(), ui.available_height()),
                     egui::Layout::right_to_left(egui::Align::Center),
                     |ui| {
+                        if ui.button("Settings").clicked() && self.settings_draft.is_none() {
+                            self.settings_draft = Some(self.settings.clone());
+                        }
+                        if ui.button("Layout").clicked() && self.layout_draft.is_none() {
+                            self.layout_draft = Some(self.layout.clone());
+                        }
+                        if ui.button("Session Info").clicked() && self.session_info_draft.is_none()
+                        {
+                            self.session_info_draft =
+                                Some(SessionInfoDraft::new(&self.session_info));
+                        }
+                        if ui.button("Script").clicked() {
+                            self.script_open = true;
+                        }
+                        let console_label = format!("Console ({})", self.serial.console.len());
+                        if ui.button(console_label).clicked() {
+                            self.console_open = true;
+                        }
+                        if ui
+                            .button("Export Plots")
+                            .on_hover_text("Save every plot as PNG and SVG in the session folder")
+                            .clicked()
+                        {
+                            self.export_plots();
+                        }
+                        if ui.button("Event Log").clicked() {
+                            self.event_log_open = true;
+                        }
+                        if ui.button("Find").clicked() && self.query.is_none() {
+                            self.query = Some(QueryTool::default());
+                        }
+                        if ui.button("Histogram").clicked() && self.histogram.is_none() {
+                            self.histogram = Some(HistogramTool::default());
+                        }
+                        if ui.button("Calibrate").clicked() && self.calibration_wizard.is_none() {
+                            self.calibration_wizard = Some(CalibrationWizard::default());
+                        }
+                        if ui.button("Campaign Trends").clicked() && self.campaign_trends.is_none()
+                        {
+                            self.open_campaign_trends();
+                        }
                         if ui.button("Open Data Folder").clicked() {
                             if let Err(e) = open::that(&self.log_dir) {
-                                eprintln!("Failed to open folder: {}", e);
+                                diag!("Failed to open folder: {}", e);
                             }
                         }
+                        if ui.button("Copy Snapshot").clicked() {
+                            ctx.copy_text(self.snapshot_text());
+                        }
 
                         ui.label(self.log_dir.display().to_string());
+                        for (i, destination) in
+                            self.serial.recorder.destinations().iter().enumerate()
+                        {
+                            if !destination.is_healthy() {
+                                ui.colored_label(
+                                    egui::Color32::RED,
+                                    format!("Logging failed: {}", destination.path.display()),
+                                );
+                            } else if i > 0 {
+                                ui.label(format!("Mirror: {}", destination.path.display()));
+                            }
+                        }
                     },
                 );
             });
         });
 
+        self.settings_window(ctx);
+        self.layout_window(ctx);
+        self.session_info_window(ctx);
+        self.event_log_window(ctx);
+        self.cursor_readout(ctx);
+        self.trends_window(ctx);
+        self.query_window(ctx);
+        self.histogram_window(ctx);
+        self.calibration_window(ctx);
+        self.console_window(ctx);
+        self.script_window(ctx);
+        self.redline_alert_window(ctx);
+
+        if let Err(e) = self.highlights.flush(&self.clock) {
+            diag!("{}", e);
+        }
+
         // Request repaint unconditionally
         ctx.request_repaint();
     }
+
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.serial.recorder.finish();
+        let latest_time = self
+            .engine_data
+            .data_points
+            .back()
+            .map_or(0.0, |dp| dp.time);
+        self.highlights.end_phase(latest_time);
+        if let Err(e) = self.highlights.flush(&self.clock) {
+            diag!("{}", e);
+        }
+
+        // Index the session in the campaign database next to the session folders
+        let Some(logs_root) = self.log_dir.parent() else {
+            return;
+        };
+        let (actuations_fuel, actuations_oxi) = self.duty_guard.actuations();
+        let record = SessionRecord {
+            log_dir: &self.log_dir,
+            stats: &self.session_stats,
+            extra_metrics: vec![
+                ("actuations_fuel", actuations_fuel as f64),
+                ("actuations_oxi", actuations_oxi as f64),
+                ("aborts", self.arming.abort_count() as f64),
+            ],
+            markers: self.event_log.markers(),
+        };
+        match campaign::record_session(&logs_root.join(CAMPAIGN_DB_NAME), &record) {
+            Ok(()) => diag!("Recorded session in campaign database"),
+            Err(e) => diag!("Failed to update campaign database: {}", e),
+        }
+    }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    // Channels for communication
+    // Channel for data points from the serial read thread
     let (data_sender, data_receiver) = mpsc::channel::<EngineDataPoint>();
-    let (valve_state_sender, valve_state_receiver) = mpsc::channel::<(bool, bool)>();
 
-    // Shared valve states between GUI and serial read thread
-    let shared_valve_states = Arc::new(Mutex::new((false, false)));
+    let args: Vec<String> = std::env::args().collect();
+    if let Some(index) = args
+        .iter()
+        .position(|arg| arg == compression::TRAIN_DICTIONARY_FLAG)
+    {
+        let Some((output, logs)) = args[index + 1..].split_first() else {
+            return Err(format!(
+                "Usage: {} <dictionary> <data_log.csv>...",
+                compression::TRAIN_DICTIONARY_FLAG
+            )
+            .into());
+        };
+        let logs: Vec<PathBuf> = logs.iter().map(PathBuf::from).collect();
+        compression::train_dictionary(Path::new(output), &logs)?;
+        return Ok(());
+    }
 
-    // Initialize serial port
-    let port = serialport::new(PORT_NAME, BAUD_RATE)
-        .timeout(Duration::from_millis(TIMEOUT_MS))
-        .open()
-        .expect("Failed to open port");
-    let port_clone = port.try_clone().expect("Failed to clone port");
+    let (settings, config_error) = Settings::load();
+    // A kiosk only watches a station's viewer server; it opens no ports of its own
+    let kiosk = args.iter().any(|arg| arg == kiosk::KIOSK_FLAG);
 
     // Create logging directory and file
-    let log_dir = create_log_directory()?;
+    let log_dir = create_log_directory(&settings.log_dir)?;
     let log_file_path = log_dir.join("data_log.csv");
-    let log_file = Arc::new(Mutex::new(File::create(&log_file_path)?));
+    let mirror_path = settings.mirror_log_dir.as_ref().and_then(|mirror_root| {
+        let session_name = log_dir.file_name()?;
+        Some(mirror_root.join(session_name).join("data_log.csv"))
+    });
+    let mut recorder = DataRecorder::open(
+        &log_file_path,
+        mirror_path.as_deref(),
+        settings.all_pressure_channels().len(),
+    )?;
+    if settings.parquet_log {
+        recorder.enable_parquet(
+            &log_dir.join("data_log.parquet"),
+            settings.all_pressure_channels().len(),
+        );
+    }
+    if settings.sqlite_samples {
+        let logs_root = log_dir.parent().unwrap_or(&log_dir);
+        recorder.enable_samples(&logs_root.join(CAMPAIGN_DB_NAME), &log_dir);
+    }
+    let recorder = Arc::new(recorder);
+    applog::init(&log_dir)?;
+    diag!("Logging session to {}", log_dir.display());
+    // Shared so samples and events are stamped with the same T-time
+    let countdown = Arc::new(Mutex::new(Countdown::default()));
+    let event_log = EventLog::new(&log_dir, countdown.clone())?;
 
-    // Serial read thread
-    {
-        let data_sender = data_sender.clone();
-        let shared_valve_states = shared_valve_states.clone();
-        let log_file = log_file.clone();
-
-        thread::spawn(move || {
-            let mut reader = std::io::BufReader::new(port);
-            loop {
-                let mut line = String::new();
-                match reader.read_line(&mut line) {
-                    Ok(bytes_read) => {
-                        if bytes_read > 0 {
-                            let raw_values = line.trim().to_string();
-                            let values: Vec<&str> = line.trim().split(',').collect();
-                            if values.len() == 8 {
-                                match parse_engine_data_point(&values) {
-                                    Ok(mut data_point) => {
-                                        // Get the current timestamp
-                                        let timestamp = SystemTime::now()
-                                            .duration_since(UNIX_EPOCH)
-                                            .unwrap()
-                                            .as_secs();
-                                        data_point.timestamp = timestamp;
-
-                                        // Get current valve states
-                                        let valve_states = shared_valve_states.lock().unwrap();
-                                        data_point.fuel_valve_open = valve_states.0;
-                                        data_point.oxi_valve_open = valve_states.1;
-
-                                        // Store raw values
-                                        data_point.raw_values = raw_values.clone();
-
-                                        // Send data point to GUI
-                                        let _ = data_sender.send(data_point.clone());
-
-                                        // Log data point
-                                        let mut log_file = log_file.lock().unwrap();
-                                        let log_line = format!(
-                                            "{},{},{},{},{},{},{},{},{},{}\n",
-                                            timestamp,
-                                            data_point.time,
-                                            data_point.flow_rate_fuel,
-                                            data_point.flow_rate_oxi,
-                                            data_point.pulse_count_fuel,
-                                            data_point.pulse_count_oxi,
-                                            data_point.desired_pos_fuel,
-                                            data_point.desired_pos_oxi,
-                                            data_point.fuel_valve_open,
-                                            data_point.oxi_valve_open,
-                                        );
-                                        let _ = log_file.write_all(log_line.as_bytes());
-                                    }
-                                    Err(e) => {
-                                        eprintln!("Error parsing data: {}", e);
-                                    }
-                                }
-                            } else {
-                                eprintln!("Received unexpected number of values: {}", values.len());
-                            }
-                        }
-                    }
-                    Err(ref e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
-                    Err(e) => eprintln!("Error reading from serial port: {:?}", e),
+    // Import externally recorded logs into the campaign alongside live sessions
+    if let Some(logs_root) = log_dir.parent() {
+        import::spawn_watcher(
+            PathBuf::from(import::IMPORT_WATCH_DIR),
+            logs_root.to_path_buf(),
+        );
+    }
+
+    let mut serial_shared = SerialShared {
+        data_sender,
+        valve_states: Arc::new(Mutex::new((false, false))),
+        reported_valves: Arc::new(Mutex::new(None)),
+        valve_positions: Arc::new(Mutex::new(
+            settings
+                .position_control
+                .then(|| PositionRamp::new(settings.position_ramp_pct_per_s)),
+        )),
+        igniter: Arc::new(Mutex::new(
+            settings.igniter.as_ref().map(|_| IgniterOutput::default()),
+        )),
+        relay_states: Arc::new(Mutex::new(settings.stand_relays.then(Relays::default))),
+        valve_resends: Arc::new(AtomicU64::new(0)),
+        recorder,
+        flow_decoding: Arc::new(Mutex::new(FlowDecodingConfig {
+            calibration: settings.flow_calibration.clone(),
+            ..FlowDecodingConfig::default()
+        })),
+        command_dialect: Arc::new(Mutex::new(CommandDialect::default())),
+        broadcast_interval: Arc::new(Mutex::new(Duration::from_millis(
+            settings.broadcast_interval_ms,
+        ))),
+        corrupt_frames: Arc::new(AtomicU64::new(0)),
+        environment: Arc::new(Environment::new(
+            settings.environment_sensors.clone(),
+            &log_dir,
+        )?),
+        console: Arc::new(FirmwareConsole::new(&log_dir)?),
+        quality_checks: Arc::new(Mutex::new(QualityChecks::default())),
+        telemetry_server: None,
+        udp_broadcaster: None,
+        stand: Arc::new(Mutex::new(StandStatus::default())),
+        mqtt: None,
+        time_sync: None,
+        countdown,
+        devices: Arc::new(DeviceHub::new(&settings.devices, Some(&log_dir))?),
+    };
+    let link_codec =
+        settings.link_compression.as_ref().and_then(|compression| {
+            match LinkCodec::load(compression) {
+                Ok(codec) => Some(Arc::new(codec)),
+                Err(e) => {
+                    diag!("{}", e);
+                    None
                 }
             }
         });
+    if !kiosk && (settings.websocket_port.is_some() || settings.tcp_port.is_some()) {
+        match TelemetryServer::start(
+            settings.websocket_port,
+            settings.tcp_port,
+            link_codec.clone(),
+        ) {
+            Ok(server) => serial_shared.telemetry_server = Some(server),
+            Err(e) => diag!("{}", e),
+        }
+    }
+    if let Some(port) = settings.udp_broadcast_port.filter(|_| !kiosk) {
+        match UdpBroadcaster::start(port, link_codec.clone()) {
+            Ok(udp) => serial_shared.udp_broadcaster = Some(Arc::new(udp)),
+            Err(e) => diag!("{}", e),
+        }
+    }
+    if let Some(source) = &settings.time_source {
+        match TimeSync::start(source, &log_dir) {
+            Ok(time_sync) => serial_shared.time_sync = Some(time_sync),
+            Err(e) => diag!("{}", e),
+        }
+    }
+    if let Some(mqtt) = &settings.mqtt {
+        let names = settings
+            .all_pressure_channels()
+            .iter()
+            .map(|c| c.name.clone())
+            .collect();
+        match MqttPublisher::start(mqtt, names) {
+            Ok(publisher) => serial_shared.mqtt = Some(Arc::new(publisher)),
+            Err(e) => diag!("{}", e),
+        }
     }
 
-    // Serial write thread
-    {
-        let shared_valve_states = shared_valve_states.clone();
-        thread::spawn(move || {
-            let mut port = port_clone;
-            let mut last_sent_state = (false, false);
-
-            loop {
-                // Check for updated valve states
-                match valve_state_receiver.try_recv() {
-                    Ok(valve_states) => {
-                        last_sent_state = valve_states;
-                        // Update shared valve states
-                        let mut shared_states = shared_valve_states.lock().unwrap();
-                        *shared_states = last_sent_state;
-                    }
-                    Err(mpsc::TryRecvError::Empty) => {}
-                    Err(e) => {
-                        eprintln!("Error receiving valve state: {:?}", e);
-                    }
-                }
-
-                let msg = format!(
-                    "{},{}\n",
-                    if last_sent_state.0 { 1 } else { 0 },
-                    if last_sent_state.1 { 1 } else { 0 }
-                );
+    // Other boards are read and logged for the whole session, whether or not the
+    // engine controller is connected; only its samples carry their values to the plots
+    let _device_links = if kiosk {
+        Vec::new()
+    } else {
+        devices::connect_all(&settings.devices, &serial_shared)
+    };
 
-                if let Err(e) = port.write_all(msg.as_bytes()) {
-                    eprintln!("Failed to write to serial port: {:?}", e);
-                }
-                // else {
-                //     println!("Sent: {}", msg.trim());
-                // }
-                thread::sleep(Duration::from_millis(BROADCAST_INTERVAL_MS));
-            }
-        });
+    let simulator = std::env::args()
+        .any(|arg| arg == simulator::SIMULATE_FLAG)
+        .then(|| Simulator::start(&serial_shared, settings.pressure_channels.len()));
+    if std::env::args().any(|arg| arg == headless::HEADLESS_FLAG) {
+        headless::run(
+            &settings,
+            &serial_shared,
+            data_receiver,
+            &log_dir,
+            simulator,
+        );
+        return Ok(());
     }
 
     // Run the GUI application
-    let native_options = eframe::NativeOptions::default();
-    let app = FlowRateApp::new(data_receiver, valve_state_sender, log_dir.clone());
+    let native_options = if kiosk {
+        eframe::NativeOptions {
+            viewport: egui::ViewportBuilder::default().with_fullscreen(true),
+            ..Default::default()
+        }
+    } else {
+        eframe::NativeOptions::default()
+    };
+    let mut remote = args
+        .windows(2)
+        .find(|pair| pair[0] == network::REMOTE_FLAG)
+        .map(|pair| {
+            RemoteClient::start(
+                &pair[1],
+                serial_shared.data_sender.clone(),
+                link_codec.clone(),
+            )
+        });
+    if let Some(pair) = args
+        .windows(2)
+        .find(|pair| pair[0] == network::UDP_RECEIVE_FLAG)
+    {
+        match pair[1].parse::<u16>() {
+            Ok(port) => {
+                match RemoteClient::listen_udp(
+                    port,
+                    serial_shared.data_sender.clone(),
+                    link_codec.clone(),
+                ) {
+                    Ok(client) => remote = Some(client),
+                    Err(e) => diag!("{}", e),
+                }
+            }
+            Err(_) => diag!("Invalid UDP port: {}", pair[1]),
+        }
+    }
+    if kiosk && remote.is_none() {
+        // Without --remote, the kiosk watches the station on this machine
+        let port = settings
+            .websocket_port
+            .unwrap_or(config::DEFAULT_WEBSOCKET_PORT);
+        remote = Some(RemoteClient::start(
+            &format!("127.0.0.1:{}", port),
+            serial_shared.data_sender.clone(),
+            link_codec,
+        ));
+    }
+    let mut app = FlowRateApp::new(
+        data_receiver,
+        serial_shared,
+        log_dir.clone(),
+        event_log,
+        settings,
+        kiosk,
+        simulator,
+    );
+    app.remote = remote;
+    app.config_error = config_error;
     eframe::run_native(
-        "Khan Space Industries | Ground Control System",
+        WINDOW_TITLE,
         native_options,
         Box::new(move |_cc| Ok(Box::new(app))),
     )
@@ -417,60 +3598,122 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-/// Parses a slice of string values into an EngineDataPoint.
-fn parse_engine_data_point(values: &[&str]) -> Result<EngineDataPoint, String> {
-    if values.len() != 8 {
-        return Err("Invalid number of values".to_string());
-    }
-
-    let time = values[0]
-        .parse::<f64>()
-        .map_err(|e| format!("Time parse error: {}", e))?;
-    let flow_fuel = values[1]
-        .parse::<f64>()
-        .map_err(|e| format!("Flow fuel parse error: {}", e))?;
-    let flow_oxi = values[2]
-        .parse::<f64>()
-        .map_err(|e| format!("Flow oxi parse error: {}", e))?;
-    let pulse_fuel = values[3]
-        .parse::<i32>()
-        .map_err(|e| format!("Pulse fuel parse error: {}", e))?;
-    let pulse_oxi = values[4]
-        .parse::<i32>()
-        .map_err(|e| format!("Pulse oxi parse error: {}", e))?;
-    let pos_fuel = values[5]
-        .parse::<i32>()
-        .map_err(|e| format!("Pos fuel parse error: {}", e))?;
-    let pos_oxi = values[6]
-        .parse::<i32>()
-        .map_err(|e| format!("Pos oxi parse error: {}", e))?;
-    let _emergency = match values[7].parse::<i32>() {
-        Ok(1) => true,
-        Ok(0) => false,
-        Ok(_) => return Err("Emergency value must be 0 or 1".to_string()),
-        Err(e) => return Err(format!("Emergency parse error: {}", e)),
+/// Port selected by default: the configured adapter if it's plugged in, else the first found.
+fn default_port(available_ports: &[String], preferred: &str) -> String {
+    available_ports
+        .iter()
+        .find(|p| p.as_str() == preferred)
+        .or(available_ports.first())
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// A checkbox enabling an optional setting, with its value beside it when enabled.
+fn optional_value(ui: &mut egui::Ui, value: &mut Option<f64>) {
+    ui.horizontal(|ui| {
+        let mut enabled = value.is_some();
+        if ui.checkbox(&mut enabled, "").changed() {
+            *value = enabled.then_some(0.0);
+        }
+        if let Some(value) = value {
+            ui.add(egui::DragValue::new(value).speed(0.1));
+        }
+    });
+}
+
+/// Chooses how messages are cut from a board's stream, with the packet length or prefix.
+fn framing_editor(ui: &mut egui::Ui, id_salt: impl std::hash::Hash, framing: &mut Framing) {
+    ui.horizontal(|ui| {
+        let fixed = matches!(framing, Framing::FixedLength(_));
+        let prefixed = matches!(framing, Framing::LengthPrefixed { .. });
+        if ui
+            .selectable_label(*framing == Framing::Delimited, "Delimited")
+            .on_hover_text("Newlines for CSV, zero bytes for binary frames")
+            .clicked()
+        {
+            *framing = Framing::Delimited;
+        }
+        if ui.selectable_label(fixed, "Fixed length").clicked() && !fixed {
+            *framing = Framing::FixedLength(32);
+        }
+        if ui
+            .selectable_label(prefixed, "Length prefixed")
+            .on_hover_text("Binary packets carry records without COBS encoding")
+            .clicked()
+            && !prefixed
+        {
+            *framing = Framing::LengthPrefixed {
+                width: 1,
+                big_endian: false,
+            };
+        }
+        match framing {
+            Framing::Delimited => {}
+            Framing::FixedLength(len) => {
+                ui.add(
+                    egui::DragValue::new(len)
+                        .range(1..=MAX_PACKET_LEN)
+                        .suffix(" bytes"),
+                );
+            }
+            Framing::LengthPrefixed { width, big_endian } => {
+                egui::ComboBox::from_id_salt(id_salt)
+                    .selected_text(format!("{}-byte length", width))
+                    .show_ui(ui, |ui| {
+                        for option in [1, 2, 4] {
+                            ui.selectable_value(width, option, format!("{}-byte length", option));
+                        }
+                    });
+                ui.checkbox(big_endian, "Big-endian");
+            }
+        }
+    });
+}
+
+/// The stand's arm state as a heading, with the abort reason while aborted.
+fn stand_heading(ui: &mut egui::Ui, stand: Option<&StandStatus>) {
+    let Some(stand) = stand else {
+        ui.colored_label(
+            egui::Color32::GRAY,
+            egui::RichText::new("State unknown").heading(),
+        );
+        return;
     };
+    let text = match &stand.abort_reason {
+        Some(reason) => format!("{} ({})", stand.state, reason),
+        None => stand.state.to_string(),
+    };
+    ui.colored_label(
+        arm_state_color(stand.state),
+        egui::RichText::new(text).heading().strong(),
+    );
+}
 
-    Ok(EngineDataPoint {
-        timestamp: 0, // Will be set later
-        time,
-        flow_rate_fuel: flow_fuel,
-        flow_rate_oxi: flow_oxi,
-        pulse_count_fuel: pulse_fuel,
-        pulse_count_oxi: pulse_oxi,
-        desired_pos_fuel: pos_fuel,
-        desired_pos_oxi: pos_oxi,
-        fuel_valve_open: false, // Will be set later
-        oxi_valve_open: false,  // Will be set later
-        raw_values: String::new(),
-    })
+fn arm_state_color(state: ArmState) -> egui::Color32 {
+    match state {
+        ArmState::Safe => egui::Color32::GREEN,
+        ArmState::Armed => egui::Color32::YELLOW,
+        ArmState::Firing => egui::Color32::from_rgb(255, 140, 0),
+        ArmState::Aborted => egui::Color32::RED,
+    }
+}
+
+/// A path text field where blank means none.
+fn optional_path(ui: &mut egui::Ui, path: &mut Option<PathBuf>) {
+    let mut text = path
+        .as_ref()
+        .map(|path| path.display().to_string())
+        .unwrap_or_default();
+    if ui.text_edit_singleline(&mut text).changed() {
+        *path = (!text.trim().is_empty()).then(|| PathBuf::from(text.trim()));
+    }
 }
 
-/// Creates a logging directory inside 'logs/' with a date-timestamped name.
-fn create_log_directory() -> std::io::Result<PathBuf> {
+/// Creates a logging directory inside `logs_root` with a date-timestamped name.
+fn create_log_directory(logs_root: &Path) -> std::io::Result<PathBuf> {
     let timestamp = chrono::Local::now().format("%Y-%m-%d_%H-%M-%S").to_string();
     let dir_name = format!("KSI_Ground_Control_{}", timestamp);
-    let dir_path = std::path::Path::new("logs").join(dir_name);
+    let dir_path = logs_root.join(dir_name);
     fs::create_dir_all(&dir_path)?;
     Ok(dir_path)
 }
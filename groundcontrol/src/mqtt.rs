@@ -0,0 +1,174 @@
+//! Publishes live telemetry to an MQTT broker, one topic per channel, so test-stand
+//! data can be aggregated alongside other sources.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::Duration;
+
+use ksi_telemetry::EngineDataPoint;
+use rumqttc::{Client, Event, MqttOptions, Packet, QoS};
+use serde::{Deserialize, Serialize};
+
+use crate::applog::diag;
+
+// Messages queued for the broker before new ones are dropped
+const QUEUE_CAPACITY: usize = 1000;
+const KEEP_ALIVE: Duration = Duration::from_secs(5);
+const RECONNECT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Broker connection and topic layout; publishing is off without an `[mqtt]` table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MqttSettings {
+    pub broker: String,
+    pub port: u16,
+    pub client_id: String,
+    /// 0 (at most once), 1 (at least once), or 2 (exactly once)
+    pub qos: u8,
+    /// Topics are this prefix, a slash, and the channel, e.g. ksi/engine/flow_fuel
+    pub topic_prefix: String,
+}
+
+impl Default for MqttSettings {
+    fn default() -> Self {
+        Self {
+            broker: "localhost".to_string(),
+            port: 1883,
+            client_id: "ksi-groundcontrol".to_string(),
+            qos: 0,
+            topic_prefix: "ksi/engine".to_string(),
+        }
+    }
+}
+
+/// Queues every live data point for the broker without blocking the serial thread.
+pub struct MqttPublisher {
+    client: Client,
+    qos: QoS,
+    topic_prefix: String,
+    // Topic names for the configured pressure channels, in order
+    pressure_names: Vec<String>,
+    connected: Arc<AtomicBool>,
+    dropped: AtomicU64,
+}
+
+impl MqttPublisher {
+    pub fn start(settings: &MqttSettings, pressure_names: Vec<String>) -> Result<Self, String> {
+        let qos = match settings.qos {
+            0 => QoS::AtMostOnce,
+            1 => QoS::AtLeastOnce,
+            2 => QoS::ExactlyOnce,
+            other => return Err(format!("Invalid MQTT QoS {}; expected 0, 1, or 2", other)),
+        };
+        let mut options =
+            MqttOptions::new(&settings.client_id, settings.broker.as_str(), settings.port);
+        options.set_keep_alive(KEEP_ALIVE);
+        let (client, mut connection) = Client::new(options, QUEUE_CAPACITY);
+        let connected = Arc::new(AtomicBool::new(false));
+        {
+            let connected = connected.clone();
+            let broker = format!("{}:{}", settings.broker, settings.port);
+            // Driving the connection sends queued messages and reconnects after failures
+            thread::spawn(move || {
+                for event in connection.iter() {
+                    match event {
+                        Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                            diag!("Publishing telemetry to MQTT broker {}", broker);
+                            connected.store(true, Ordering::Relaxed);
+                        }
+                        Ok(_) => {}
+                        Err(e) => {
+                            if connected.swap(false, Ordering::Relaxed) {
+                                diag!("MQTT broker {} connection lost: {}", broker, e);
+                            }
+                            thread::sleep(RECONNECT_INTERVAL);
+                        }
+                    }
+                }
+            });
+        }
+        Ok(Self {
+            client,
+            qos,
+            topic_prefix: settings.topic_prefix.trim_end_matches('/').to_string(),
+            pressure_names: pressure_names.iter().map(|n| topic_name(n)).collect(),
+            connected,
+            dropped: AtomicU64::new(0),
+        })
+    }
+
+    /// Publishes each channel of the data point to its own topic.
+    pub fn publish(&self, data_point: &EngineDataPoint) {
+        if !self.connected.load(Ordering::Relaxed) {
+            return;
+        }
+        let mut values = vec![
+            ("time".to_string(), data_point.time.to_string()),
+            (
+                "flow_fuel".to_string(),
+                data_point.flow_rate_fuel.to_string(),
+            ),
+            ("flow_oxi".to_string(), data_point.flow_rate_oxi.to_string()),
+            (
+                "fuel_valve_open".to_string(),
+                data_point.fuel_valve_open.to_string(),
+            ),
+            (
+                "oxi_valve_open".to_string(),
+                data_point.oxi_valve_open.to_string(),
+            ),
+            (
+                "quality".to_string(),
+                data_point.quality.as_str().to_string(),
+            ),
+        ];
+        if let Some(thrust) = data_point.thrust {
+            values.push(("thrust".to_string(), thrust.to_string()));
+        }
+        if let Some(t) = data_point.temperatures {
+            values.push(("temp_nozzle".to_string(), t.nozzle.to_string()));
+            values.push(("temp_tank".to_string(), t.tank.to_string()));
+            values.push(("temp_ambient".to_string(), t.ambient.to_string()));
+        }
+        for (name, pressure) in self.pressure_names.iter().zip(&data_point.pressures) {
+            values.push((format!("pressure/{}", name), pressure.to_string()));
+        }
+
+        for (channel, payload) in values {
+            let topic = format!("{}/{}", self.topic_prefix, channel);
+            if self
+                .client
+                .try_publish(topic, self.qos, false, payload)
+                .is_err()
+            {
+                // A full queue means the broker can't keep up; count the loss and move on
+                if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                    diag!("MQTT queue full; dropping telemetry messages");
+                }
+            }
+        }
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    pub fn dropped(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// A pressure channel name as a single topic level, e.g. "Chamber P" becomes chamber_p.
+fn topic_name(name: &str) -> String {
+    name.trim()
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() {
+                c.to_ascii_lowercase()
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,460 @@
+//! Live telemetry for viewers on other machines.
+//!
+//! Each data point is sent as one JSON object: a text message to WebSocket
+//! clients, a newline-terminated line to raw TCP clients, or one UDP datagram
+//! broadcast on the LAN. Viewers only receive; nothing they send is acted on.
+//!
+//! The object holds the data point's fields and a `stand` object with the
+//! serving station's arm state and abort reason, so viewers show the stand's
+//! state rather than their own.
+//!
+//! With link compression configured, WebSocket messages and UDP datagrams are
+//! zstd-compressed instead (WebSocket messages then go out as binary).
+
+use std::io::{ErrorKind, Write};
+use std::net::{Ipv4Addr, TcpListener, TcpStream, UdpSocket};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::EngineDataPoint;
+use serde::{Deserialize, Serialize};
+use tungstenite::{Message, WebSocket};
+
+use crate::applog::diag;
+use crate::arming::ArmState;
+use crate::compression::{self, LinkCodec};
+
+/// Command-line flag, followed by `host:port`, that views a remote server's telemetry.
+pub const REMOTE_FLAG: &str = "--remote";
+/// Command-line flag, followed by a port, that views telemetry broadcast over UDP.
+pub const UDP_RECEIVE_FLAG: &str = "--udp-receive";
+
+// A viewer that can't take a message this quickly is dropped rather than stalling the link
+const CLIENT_WRITE_TIMEOUT: Duration = Duration::from_millis(50);
+// Remote client read timeout, so it notices being stopped
+const REMOTE_READ_TIMEOUT: Duration = Duration::from_millis(250);
+const REMOTE_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+// Largest datagram the UDP receiver accepts
+const MAX_DATAGRAM: usize = 65_507;
+
+/// The serving station's arming state, sent with every data point.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StandStatus {
+    pub state: ArmState,
+    pub abort_reason: Option<String>,
+}
+
+impl Default for StandStatus {
+    fn default() -> Self {
+        Self {
+            state: ArmState::Safe,
+            abort_reason: None,
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct OutgoingMessage<'a> {
+    #[serde(flatten)]
+    data_point: &'a EngineDataPoint,
+    stand: &'a StandStatus,
+}
+
+#[derive(Deserialize)]
+struct IncomingMessage {
+    #[serde(flatten)]
+    data_point: EngineDataPoint,
+    // Absent from stations that predate it
+    #[serde(default)]
+    stand: Option<StandStatus>,
+}
+
+fn encode(data_point: &EngineDataPoint, stand: &StandStatus) -> Result<String, String> {
+    serde_json::to_string(&OutgoingMessage { data_point, stand })
+        .map_err(|e| format!("Failed to encode data point: {}", e))
+}
+
+fn decode(json: &[u8]) -> Result<(EngineDataPoint, Option<StandStatus>), String> {
+    let message: IncomingMessage = serde_json::from_slice(json).map_err(|e| e.to_string())?;
+    Ok((message.data_point, message.stand))
+}
+
+enum Viewer {
+    WebSocket(Box<WebSocket<TcpStream>>),
+    Tcp(TcpStream),
+}
+
+impl Viewer {
+    fn send(&mut self, json: &str, compressed: Option<&[u8]>) -> Result<(), String> {
+        match self {
+            Viewer::WebSocket(socket) => {
+                let message = match compressed {
+                    Some(bytes) => Message::binary(bytes),
+                    None => Message::text(json),
+                };
+                socket.send(message).map_err(|e| e.to_string())
+            }
+            Viewer::Tcp(stream) => stream
+                .write_all(format!("{}\n", json).as_bytes())
+                .map_err(|e| e.to_string()),
+        }
+    }
+}
+
+/// Serves every live data point to connected viewers.
+pub struct TelemetryServer {
+    viewers: Mutex<Vec<Viewer>>,
+    codec: Option<Arc<LinkCodec>>,
+}
+
+impl TelemetryServer {
+    /// Listens on all interfaces: WebSocket viewers on `websocket_port`, raw TCP
+    /// viewers on `tcp_port`, each when set. WebSocket messages are compressed with `codec`.
+    ///
+    /// A port that can't be opened is left out; it's an error only if none can.
+    pub fn start(
+        websocket_port: Option<u16>,
+        tcp_port: Option<u16>,
+        codec: Option<Arc<LinkCodec>>,
+    ) -> Result<Arc<Self>, String> {
+        let server = Arc::new(Self {
+            viewers: Mutex::new(Vec::new()),
+            codec,
+        });
+        let mut listening = false;
+        for (port, websocket) in [(websocket_port, true), (tcp_port, false)] {
+            let Some(port) = port else {
+                continue;
+            };
+            match server.listen(port, websocket) {
+                Ok(()) => listening = true,
+                Err(e) => diag!("{}", e),
+            }
+        }
+        if !listening {
+            return Err("No telemetry viewer port could be opened".to_string());
+        }
+        Ok(server)
+    }
+
+    fn listen(self: &Arc<Self>, port: u16, websocket: bool) -> Result<(), String> {
+        let listener = TcpListener::bind(("0.0.0.0", port))
+            .map_err(|e| format!("Failed to listen on port {}: {}", port, e))?;
+        let kind = if websocket { "WebSocket" } else { "TCP" };
+        diag!("Serving telemetry to {} viewers on port {}", kind, port);
+        let server = Arc::downgrade(self);
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let Some(server) = server.upgrade() else {
+                    return;
+                };
+                let stream = match stream {
+                    Ok(stream) => stream,
+                    Err(e) => {
+                        diag!("Failed to accept {} viewer: {}", kind, e);
+                        continue;
+                    }
+                };
+                let peer = stream
+                    .peer_addr()
+                    .map_or_else(|_| "unknown".to_string(), |a| a.to_string());
+                let _ = stream.set_write_timeout(Some(CLIENT_WRITE_TIMEOUT));
+                let _ = stream.set_nodelay(true);
+                // The handshake waits on the viewer, so it gets its own thread
+                thread::spawn(move || {
+                    let viewer = if websocket {
+                        match tungstenite::accept(stream) {
+                            Ok(socket) => Viewer::WebSocket(Box::new(socket)),
+                            Err(e) => {
+                                diag!("WebSocket handshake with {} failed: {}", peer, e);
+                                return;
+                            }
+                        }
+                    } else {
+                        Viewer::Tcp(stream)
+                    };
+                    diag!("{} viewer connected from {}", kind, peer);
+                    server.viewers.lock().unwrap().push(viewer);
+                });
+            }
+        });
+        Ok(())
+    }
+
+    /// Sends a data point and the stand's state to every viewer, dropping any that fail.
+    pub fn broadcast(&self, data_point: &EngineDataPoint, stand: &StandStatus) {
+        let mut viewers = self.viewers.lock().unwrap();
+        if viewers.is_empty() {
+            return;
+        }
+        let json = match encode(data_point, stand) {
+            Ok(json) => json,
+            Err(e) => {
+                diag!("{}", e);
+                return;
+            }
+        };
+        let compressed = match &self.codec {
+            Some(codec) => match codec.compress(json.as_bytes()) {
+                Ok(bytes) => Some(bytes),
+                Err(e) => {
+                    diag!("{}", e);
+                    return;
+                }
+            },
+            None => None,
+        };
+        viewers.retain_mut(|viewer| match viewer.send(&json, compressed.as_deref()) {
+            Ok(()) => true,
+            Err(e) => {
+                diag!("Dropped telemetry viewer: {}", e);
+                false
+            }
+        });
+    }
+
+    pub fn viewer_count(&self) -> usize {
+        self.viewers.lock().unwrap().len()
+    }
+}
+
+/// Broadcasts every live data point as a UDP datagram, for LAN tools that want the
+/// lowest latency and can tolerate loss.
+pub struct UdpBroadcaster {
+    socket: UdpSocket,
+    port: u16,
+    codec: Option<Arc<LinkCodec>>,
+}
+
+impl UdpBroadcaster {
+    pub fn start(port: u16, codec: Option<Arc<LinkCodec>>) -> Result<Self, String> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))
+            .and_then(|socket| socket.set_broadcast(true).map(|()| socket))
+            .and_then(|socket| socket.set_nonblocking(true).map(|()| socket))
+            .map_err(|e| format!("Failed to open UDP broadcast socket: {}", e))?;
+        diag!("Broadcasting telemetry over UDP on port {}", port);
+        Ok(Self {
+            socket,
+            port,
+            codec,
+        })
+    }
+
+    /// Sends a data point and the stand's state without waiting; a datagram the
+    /// OS can't take is lost.
+    pub fn broadcast(&self, data_point: &EngineDataPoint, stand: &StandStatus) {
+        let datagram = encode(data_point, stand).and_then(|json| match &self.codec {
+            Some(codec) => codec.compress(json.as_bytes()),
+            None => Ok(json.into_bytes()),
+        });
+        match datagram {
+            Ok(datagram) => {
+                let _ = self
+                    .socket
+                    .send_to(&datagram, (Ipv4Addr::BROADCAST, self.port));
+            }
+            Err(e) => diag!("{}", e),
+        }
+    }
+}
+
+/// Read-only view of another ground station's telemetry, from its WebSocket server
+/// or its UDP broadcast, in place of a serial link.
+pub struct RemoteClient {
+    pub address: String,
+    connected: Arc<AtomicBool>,
+    stop: Arc<AtomicBool>,
+    // The serving station's state, as of its latest message that carried one
+    stand: Arc<Mutex<Option<StandStatus>>>,
+}
+
+impl RemoteClient {
+    /// Connects to `address` (`host:port`), retrying until stopped, and forwards data points.
+    ///
+    /// Compressed messages are decoded with `codec`, which must use the sender's dictionary.
+    pub fn start(
+        address: &str,
+        data_sender: Sender<EngineDataPoint>,
+        codec: Option<Arc<LinkCodec>>,
+    ) -> Self {
+        let connected = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        let stand = Arc::new(Mutex::new(None));
+        {
+            let address = address.to_string();
+            let connected = connected.clone();
+            let stop = stop.clone();
+            let stand = stand.clone();
+            thread::spawn(move || {
+                while !stop.load(Ordering::Relaxed) {
+                    match open_remote(&address) {
+                        Ok(mut socket) => {
+                            diag!("Viewing remote telemetry from {}", address);
+                            connected.store(true, Ordering::Relaxed);
+                            receive(&mut socket, &data_sender, codec.as_deref(), &stand, &stop);
+                            connected.store(false, Ordering::Relaxed);
+                        }
+                        Err(e) => diag!("{}", e),
+                    }
+                    thread::sleep(REMOTE_RETRY_INTERVAL);
+                }
+            });
+        }
+        Self {
+            address: address.to_string(),
+            connected,
+            stop,
+            stand,
+        }
+    }
+
+    /// Receives datagrams broadcast on `port`; connected while they keep arriving.
+    pub fn listen_udp(
+        port: u16,
+        data_sender: Sender<EngineDataPoint>,
+        codec: Option<Arc<LinkCodec>>,
+    ) -> Result<Self, String> {
+        let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))
+            .map_err(|e| format!("Failed to listen for UDP telemetry on port {}: {}", port, e))?;
+        socket
+            .set_read_timeout(Some(REMOTE_READ_TIMEOUT))
+            .map_err(|e| e.to_string())?;
+        let connected = Arc::new(AtomicBool::new(false));
+        let stop = Arc::new(AtomicBool::new(false));
+        let stand = Arc::new(Mutex::new(None));
+        {
+            let connected = connected.clone();
+            let stop = stop.clone();
+            let stand = stand.clone();
+            thread::spawn(move || {
+                let mut buffer = vec![0; MAX_DATAGRAM];
+                let mut last_datagram = Instant::now();
+                while !stop.load(Ordering::Relaxed) {
+                    match socket.recv_from(&mut buffer) {
+                        Ok((length, _)) => {
+                            match compression::decode(codec.as_deref(), &buffer[..length])
+                                .and_then(|json| decode(&json))
+                            {
+                                Ok((data_point, status)) => {
+                                    last_datagram = Instant::now();
+                                    connected.store(true, Ordering::Relaxed);
+                                    *stand.lock().unwrap() = status;
+                                    if data_sender.send(data_point).is_err() {
+                                        return;
+                                    }
+                                }
+                                Err(e) => diag!("Invalid UDP data point: {}", e),
+                            }
+                        }
+                        Err(e)
+                            if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) => {}
+                        Err(e) => diag!("UDP telemetry receive failed: {}", e),
+                    }
+                    if last_datagram.elapsed() > REMOTE_RETRY_INTERVAL {
+                        connected.store(false, Ordering::Relaxed);
+                    }
+                }
+            });
+        }
+        Ok(Self {
+            address: format!("UDP port {}", port),
+            connected,
+            stop,
+            stand,
+        })
+    }
+
+    pub fn connected(&self) -> bool {
+        self.connected.load(Ordering::Relaxed)
+    }
+
+    /// The serving station's arm state, once it has sent one.
+    pub fn stand(&self) -> Option<StandStatus> {
+        self.stand.lock().unwrap().clone()
+    }
+}
+
+impl Drop for RemoteClient {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn open_remote(address: &str) -> Result<WebSocket<TcpStream>, String> {
+    let stream = TcpStream::connect(address)
+        .map_err(|e| format!("Failed to connect to {}: {}", address, e))?;
+    let (socket, _) = tungstenite::client(format!("ws://{}/", address), stream)
+        .map_err(|e| format!("WebSocket handshake with {} failed: {}", address, e))?;
+    socket
+        .get_ref()
+        .set_read_timeout(Some(REMOTE_READ_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    Ok(socket)
+}
+
+/// Forwards data points until the connection fails or `stop` is set.
+fn receive(
+    socket: &mut WebSocket<TcpStream>,
+    data_sender: &Sender<EngineDataPoint>,
+    codec: Option<&LinkCodec>,
+    stand: &Mutex<Option<StandStatus>>,
+    stop: &AtomicBool,
+) {
+    while !stop.load(Ordering::Relaxed) {
+        let message = match socket.read() {
+            Ok(Message::Text(json)) => json.into_bytes(),
+            Ok(Message::Binary(bytes)) => bytes,
+            Ok(_) => continue,
+            Err(tungstenite::Error::Io(e))
+                if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut) =>
+            {
+                continue
+            }
+            Err(e) => {
+                diag!("Remote telemetry connection lost: {}", e);
+                return;
+            }
+        };
+        match compression::decode(codec, &message).and_then(|json| decode(&json)) {
+            Ok((data_point, status)) => {
+                *stand.lock().unwrap() = status;
+                if data_sender.send(data_point).is_err() {
+                    return;
+                }
+            }
+            Err(e) => diag!("Invalid remote data point: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn messages_carry_the_stand_state() {
+        let mut data_point = ksi_telemetry::parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        data_point.pressures = vec![3.5, f64::NAN];
+        let stand = StandStatus {
+            state: ArmState::Aborted,
+            abort_reason: Some("Operator".to_string()),
+        };
+        let json = encode(&data_point, &stand).unwrap();
+        assert!(json.contains("\"state\":\"ABORTED\""));
+        let (parsed, status) = decode(json.as_bytes()).unwrap();
+        assert_eq!(parsed.to_log_line(), data_point.to_log_line());
+        assert_eq!(status, Some(stand));
+    }
+
+    #[test]
+    fn messages_without_a_stand_state_still_decode() {
+        let data_point = ksi_telemetry::parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        let json = serde_json::to_string(&data_point).unwrap();
+        let (parsed, status) = decode(json.as_bytes()).unwrap();
+        assert_eq!(parsed.to_log_line(), data_point.to_log_line());
+        assert_eq!(status, None);
+    }
+}
@@ -0,0 +1,101 @@
+use std::fs::File;
+use std::path::Path;
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+
+use arrow_schema::Schema;
+use ksi_telemetry::{parquet_log, EngineDataPoint};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::applog::diag;
+
+/// Rows buffered before they are written out as one row group; 10 s at 100 Hz.
+const ROWS_PER_GROUP: usize = 1000;
+
+/// Typed, columnar copy of the data log in data_log.parquet, laid out by
+/// `ksi_telemetry::parquet_log` with the same column names as the CSV.
+///
+/// Rows are written in row groups as they fill, but the file is only readable
+/// once `finish` writes its footer; data_log.csv stays complete either way.
+pub struct ParquetLog {
+    sender: Mutex<Option<Sender<EngineDataPoint>>>,
+    writer_thread: Mutex<Option<JoinHandle<()>>>,
+}
+
+impl ParquetLog {
+    pub fn create(path: &Path, pressure_channels: usize) -> Result<Self, String> {
+        let schema = Arc::new(parquet_log::schema(pressure_channels));
+        let file = File::create(path)
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let properties = WriterProperties::builder()
+            .set_compression(Compression::SNAPPY)
+            .build();
+        let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+            .map_err(|e| format!("Failed to start {}: {}", path.display(), e))?;
+
+        let (sender, receiver) = mpsc::channel::<EngineDataPoint>();
+        let writer_thread = {
+            let path = path.to_path_buf();
+            thread::spawn(move || {
+                let mut rows = Vec::with_capacity(ROWS_PER_GROUP);
+                let mut failed = false;
+                // Ends when `finish` drops the sender
+                for data_point in receiver {
+                    rows.push(data_point);
+                    if rows.len() >= ROWS_PER_GROUP && !failed {
+                        failed = !write_group(&mut writer, &schema, &rows, &path);
+                        rows.clear();
+                    }
+                }
+                if !failed && write_group(&mut writer, &schema, &rows, &path) {
+                    match writer.close() {
+                        Ok(_) => diag!("Closed {}", path.display()),
+                        Err(e) => diag!("Failed to close {}: {}", path.display(), e),
+                    }
+                }
+            })
+        };
+        diag!("Writing Parquet log to {}", path.display());
+        Ok(Self {
+            sender: Mutex::new(Some(sender)),
+            writer_thread: Mutex::new(Some(writer_thread)),
+        })
+    }
+
+    pub fn write(&self, data_point: &EngineDataPoint) {
+        if let Some(sender) = self.sender.lock().unwrap().as_ref() {
+            let _ = sender.send(data_point.clone());
+        }
+    }
+
+    /// Writes the remaining rows and the footer; later data points are ignored.
+    pub fn finish(&self) {
+        self.sender.lock().unwrap().take();
+        if let Some(writer_thread) = self.writer_thread.lock().unwrap().take() {
+            let _ = writer_thread.join();
+        }
+    }
+}
+
+/// Writes the rows as one row group, reporting whether it succeeded.
+fn write_group(
+    writer: &mut ArrowWriter<File>,
+    schema: &Arc<Schema>,
+    rows: &[EngineDataPoint],
+    path: &Path,
+) -> bool {
+    if rows.is_empty() {
+        return true;
+    }
+    let written = parquet_log::record_batch(schema, rows)
+        .and_then(|batch| writer.write(&batch).map_err(|e| e.to_string()))
+        // Ends the row group so its rows are on disk before the next fills
+        .and_then(|()| writer.flush().map_err(|e| e.to_string()));
+    if let Err(e) = &written {
+        diag!("Stopped writing {}: {}", path.display(), e);
+    }
+    written.is_ok()
+}
@@ -0,0 +1,27 @@
+use ksi_telemetry::{EngineDataPoint, Quality};
+
+use crate::frozen::{Channel, FrozenChannelDetector};
+
+/// Plausibility checks run on every live sample after flow decoding, before it is
+/// plotted and logged. Failing samples are flagged suspect rather than dropped.
+#[derive(Default)]
+pub struct QualityChecks {
+    previous_time: Option<f64>,
+    frozen: FrozenChannelDetector,
+}
+
+impl QualityChecks {
+    pub fn check(&mut self, data_point: &mut EngineDataPoint) {
+        self.frozen.update(data_point);
+        // Firmware time only runs backwards across a reset, which leaves the sample unplaceable
+        let time_reversed = self
+            .previous_time
+            .is_some_and(|previous| data_point.time < previous);
+        self.previous_time = Some(data_point.time);
+        let negative_flow = data_point.flow_rate_fuel < 0.0 || data_point.flow_rate_oxi < 0.0;
+        let frozen = !self.frozen.frozen_names(&Channel::ALL).is_empty();
+        if time_reversed || negative_flow || frozen {
+            data_point.degrade(Quality::Suspect);
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use serde::{Deserialize, Serialize};
+
+use ksi_telemetry::EngineDataPoint;
+
+use crate::clock::MissionClock;
+
+/// Quantities the find tool can search, including derived ones.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Quantity {
+    FlowFuel,
+    FlowOxi,
+    MixtureRatio,
+    PulseFuel,
+    PulseOxi,
+    Thrust,
+    NozzleTemp,
+    TankTemp,
+}
+
+impl Quantity {
+    pub const ALL: [Quantity; 8] = [
+        Quantity::FlowFuel,
+        Quantity::FlowOxi,
+        Quantity::MixtureRatio,
+        Quantity::PulseFuel,
+        Quantity::PulseOxi,
+        Quantity::Thrust,
+        Quantity::NozzleTemp,
+        Quantity::TankTemp,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Quantity::FlowFuel => "Fuel Flow",
+            Quantity::FlowOxi => "Oxidizer Flow",
+            Quantity::MixtureRatio => "O/F (volumetric)",
+            Quantity::PulseFuel => "Fuel Pulses",
+            Quantity::PulseOxi => "Oxidizer Pulses",
+            Quantity::Thrust => "Thrust",
+            Quantity::NozzleTemp => "Nozzle Temperature",
+            Quantity::TankTemp => "Tank Temperature",
+        }
+    }
+
+    /// Value at a data point; O/F is undefined without fuel flow, and the
+    /// thrust and temperatures without their sensors.
+    pub fn value(self, dp: &EngineDataPoint) -> Option<f64> {
+        match self {
+            Quantity::FlowFuel => Some(dp.flow_rate_fuel),
+            Quantity::FlowOxi => Some(dp.flow_rate_oxi),
+            Quantity::MixtureRatio => {
+                (dp.flow_rate_fuel > 0.0).then(|| dp.flow_rate_oxi / dp.flow_rate_fuel)
+            }
+            Quantity::PulseFuel => Some(dp.pulse_count_fuel as f64),
+            Quantity::PulseOxi => Some(dp.pulse_count_oxi as f64),
+            Quantity::Thrust => dp.thrust,
+            Quantity::NozzleTemp => dp.temperatures.map(|t| t.nozzle),
+            Quantity::TankTemp => dp.temperatures.map(|t| t.tank),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Above,
+    Below,
+}
+
+impl Comparison {
+    fn holds(self, value: f64, threshold: f64) -> bool {
+        match self {
+            Comparison::Above => value > threshold,
+            Comparison::Below => value < threshold,
+        }
+    }
+}
+
+/// A span of firmware time, in ms.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Interval {
+    pub start: f64,
+    pub end: f64,
+}
+
+/// Every interval where `quantity` satisfies the comparison, in time order.
+///
+/// An interval starts at the first matching point and ends at the last one.
+pub fn find_intervals<'a>(
+    data_points: impl IntoIterator<Item = &'a EngineDataPoint>,
+    quantity: Quantity,
+    comparison: Comparison,
+    threshold: f64,
+) -> Vec<Interval> {
+    let mut intervals = Vec::new();
+    let mut current: Option<Interval> = None;
+    for dp in data_points {
+        let matches = quantity
+            .value(dp)
+            .is_some_and(|value| comparison.holds(value, threshold));
+        match (&mut current, matches) {
+            (Some(interval), true) => interval.end = dp.time,
+            (None, true) => {
+                current = Some(Interval {
+                    start: dp.time,
+                    end: dp.time,
+                })
+            }
+            (Some(_), false) => intervals.extend(current.take()),
+            (None, false) => {}
+        }
+    }
+    intervals.extend(current);
+    intervals
+}
+
+/// Plot range requested by the find tool; `id` changes on every jump.
+#[derive(Debug, Clone, Copy)]
+pub struct PlotFocus {
+    pub id: u64,
+    pub interval: Interval,
+}
+
+/// Search form and results for the "Find" window.
+pub struct QueryTool {
+    quantity: Quantity,
+    comparison: Comparison,
+    threshold: f64,
+    results: Option<Vec<Interval>>,
+}
+
+impl Default for QueryTool {
+    fn default() -> Self {
+        Self {
+            quantity: Quantity::MixtureRatio,
+            comparison: Comparison::Above,
+            threshold: 2.5,
+            results: None,
+        }
+    }
+}
+
+impl QueryTool {
+    /// Draws the form and results; returns the interval the operator chose to jump to.
+    pub fn show<'a>(
+        &mut self,
+        ui: &mut egui::Ui,
+        data_points: impl IntoIterator<Item = &'a EngineDataPoint>,
+        clock: &MissionClock,
+    ) -> Option<Interval> {
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("query_quantity")
+                .selected_text(self.quantity.name())
+                .show_ui(ui, |ui| {
+                    for quantity in Quantity::ALL {
+                        ui.selectable_value(&mut self.quantity, quantity, quantity.name());
+                    }
+                });
+            ui.selectable_value(&mut self.comparison, Comparison::Above, ">");
+            ui.selectable_value(&mut self.comparison, Comparison::Below, "<");
+            ui.add(egui::DragValue::new(&mut self.threshold).speed(0.1));
+            if ui.button("Search").clicked() {
+                self.results = Some(find_intervals(
+                    data_points,
+                    self.quantity,
+                    self.comparison,
+                    self.threshold,
+                ));
+            }
+        });
+        ui.label("Searches the data currently loaded in the plots.");
+
+        let results = self.results.as_ref()?;
+        ui.separator();
+        if results.is_empty() {
+            ui.label("No matches");
+            return None;
+        }
+        ui.label(format!("{} interval(s)", results.len()));
+        let mut jump = None;
+        egui::ScrollArea::vertical()
+            .max_height(240.0)
+            .show(ui, |ui| {
+                for interval in results {
+                    ui.horizontal(|ui| {
+                        if ui.button("Go").clicked() {
+                            jump = Some(*interval);
+                        }
+                        ui.label(format!(
+                            "{} to {} ({:.0} ms)",
+                            clock.format(interval.start),
+                            clock.format(interval.end),
+                            interval.end - interval.start
+                        ));
+                    });
+                }
+            });
+        jump
+    }
+}
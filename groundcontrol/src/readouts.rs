@@ -0,0 +1,125 @@
+//! Large numeric readouts of the latest data point, for reading the stand at a
+//! glance without finding the value on a plot, and every other channel's
+//! latest value under its subsystem.
+
+use eframe::egui;
+use ksi_telemetry::EngineDataPoint;
+
+use crate::config::Settings;
+use crate::limits::{LimitAlarm, LimitLevel};
+use crate::query::Quantity;
+use crate::widgets;
+
+const VALUE_SIZE: f32 = 32.0;
+const ORANGE: egui::Color32 = egui::Color32::from_rgb(255, 165, 0);
+
+/// Color of a value: red or orange while its limit alarm is raised, yellow
+/// outside its acceptable band, green inside it, and plain without limits.
+fn value_color(
+    quantity: Quantity,
+    value: f64,
+    settings: &Settings,
+    alarms: &[LimitAlarm],
+) -> Option<egui::Color32> {
+    let level = alarms
+        .iter()
+        .filter(|alarm| alarm.quantity == quantity)
+        .map(|alarm| alarm.level)
+        .max();
+    match level {
+        Some(LimitLevel::Critical) => return Some(egui::Color32::RED),
+        Some(LimitLevel::Warning) => return Some(ORANGE),
+        _ => {}
+    }
+    let [min, max] = settings.limits_for(quantity)?.band?;
+    Some(if (min..=max).contains(&value) {
+        egui::Color32::GREEN
+    } else {
+        egui::Color32::YELLOW
+    })
+}
+
+fn readout(ui: &mut egui::Ui, name: &str, value: String, color: Option<egui::Color32>) {
+    ui.label(name);
+    let mut text = egui::RichText::new(value)
+        .size(VALUE_SIZE)
+        .monospace()
+        .strong();
+    if let Some(color) = color {
+        text = text.color(color);
+    }
+    ui.label(text);
+    ui.add_space(6.0);
+}
+
+/// Draws the readouts for `latest`, or dashes before any data has arrived.
+pub fn show(
+    ui: &mut egui::Ui,
+    latest: Option<&EngineDataPoint>,
+    settings: &Settings,
+    alarms: &[LimitAlarm],
+) {
+    ui.heading("Readouts");
+    ui.separator();
+    for (quantity, name, calibration) in [
+        (
+            Quantity::FlowFuel,
+            "Fuel flow",
+            &settings.flow_calibration.fuel,
+        ),
+        (
+            Quantity::FlowOxi,
+            "Oxidizer flow",
+            &settings.flow_calibration.oxi,
+        ),
+    ] {
+        let name = format!("{} ({})", name, calibration.unit.label());
+        match latest.and_then(|dp| quantity.value(dp)) {
+            Some(value) => readout(
+                ui,
+                &name,
+                format!("{:.2}", value * calibration.display_scale()),
+                value_color(quantity, value, settings, alarms),
+            ),
+            None => readout(ui, &name, "--".to_string(), None),
+        }
+    }
+    for (name, position) in [
+        ("Fuel position", latest.map(|dp| dp.desired_pos_fuel)),
+        ("Oxidizer position", latest.map(|dp| dp.desired_pos_oxi)),
+    ] {
+        let value = position.map_or("--".to_string(), |p| p.to_string());
+        readout(ui, name, value, None);
+    }
+    for (name, open) in [
+        ("Fuel valve", latest.map(|dp| dp.fuel_valve_open)),
+        ("Oxidizer valve", latest.map(|dp| dp.oxi_valve_open)),
+    ] {
+        let (value, color) = match open {
+            Some(true) => ("OPEN", Some(ORANGE)),
+            Some(false) => ("CLOSED", Some(egui::Color32::GREEN)),
+            None => ("--", None),
+        };
+        readout(ui, name, value.to_string(), color);
+    }
+
+    ui.separator();
+    for (group, channels) in widgets::grouped_channel_names(settings) {
+        egui::CollapsingHeader::new(group.name())
+            .id_salt(("readouts", group.name()))
+            .show(ui, |ui| {
+                egui::Grid::new(("readout_values", group.name()))
+                    .num_columns(2)
+                    .striped(true)
+                    .show(ui, |ui| {
+                        for (key, name) in channels {
+                            let value = latest
+                                .and_then(|dp| widgets::channel_series(&key, settings)?.value(dp));
+                            ui.label(name);
+                            ui.monospace(value.map_or("--".to_string(), |v| format!("{:.2}", v)));
+                            ui.end_row();
+                        }
+                    });
+            });
+    }
+}
@@ -0,0 +1,137 @@
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::Arc;
+use std::thread;
+
+use ksi_telemetry::{data_log, EngineDataPoint};
+
+use crate::applog::diag;
+use crate::campaign::SampleWriter;
+use crate::parquet_log::ParquetLog;
+
+/// One data_log.csv copy, written by its own thread so a slow or failed
+/// drive never holds up the others.
+pub struct Destination {
+    pub path: PathBuf,
+    sender: Sender<String>,
+    healthy: Arc<AtomicBool>,
+}
+
+impl Destination {
+    fn open(path: PathBuf, pressure_channels: usize) -> std::io::Result<Self> {
+        let mut file = data_log::create(&path, pressure_channels)?;
+        let (sender, receiver) = mpsc::channel::<String>();
+        let healthy = Arc::new(AtomicBool::new(true));
+        {
+            let healthy = healthy.clone();
+            let path = path.clone();
+            thread::spawn(move || {
+                for line in receiver {
+                    if let Err(e) = file.write_all(line.as_bytes()) {
+                        diag!("Stopped logging to {}: {}", path.display(), e);
+                        healthy.store(false, Ordering::Relaxed);
+                        return;
+                    }
+                }
+            });
+        }
+        Ok(Self {
+            path,
+            sender,
+            healthy,
+        })
+    }
+
+    pub fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+}
+
+/// Writes every data log row to the session folder and, optionally, a mirror,
+/// a Parquet copy, and the campaign database.
+///
+/// Any destination may fail without affecting the others.
+pub struct DataRecorder {
+    destinations: Vec<Destination>,
+    parquet: Option<ParquetLog>,
+    samples: Option<SampleWriter>,
+}
+
+impl DataRecorder {
+    /// Opens the primary log; a mirror that can't be created is reported and skipped.
+    pub fn open(
+        primary: &Path,
+        mirror: Option<&Path>,
+        pressure_channels: usize,
+    ) -> std::io::Result<Self> {
+        let mut destinations = vec![Destination::open(primary.to_path_buf(), pressure_channels)?];
+        if let Some(mirror) = mirror {
+            let opened = mirror
+                .parent()
+                .map_or(Ok(()), std::fs::create_dir_all)
+                .and_then(|()| Destination::open(mirror.to_path_buf(), pressure_channels));
+            match opened {
+                Ok(destination) => {
+                    diag!("Mirroring data log to {}", mirror.display());
+                    destinations.push(destination);
+                }
+                Err(e) => diag!("Failed to open mirror log {}: {}", mirror.display(), e),
+            }
+        }
+        Ok(Self {
+            destinations,
+            parquet: None,
+            samples: None,
+        })
+    }
+
+    /// Also writes each data point to a Parquet file; a failure is reported and skipped.
+    pub fn enable_parquet(&mut self, path: &Path, pressure_channels: usize) {
+        match ParquetLog::create(path, pressure_channels) {
+            Ok(parquet) => self.parquet = Some(parquet),
+            Err(e) => diag!("{}", e),
+        }
+    }
+
+    /// Also stores each data point in the campaign database's samples table; a failure
+    /// is reported and skipped.
+    pub fn enable_samples(&mut self, db_path: &Path, log_dir: &Path) {
+        match SampleWriter::start(db_path, log_dir) {
+            Ok(samples) => self.samples = Some(samples),
+            Err(e) => diag!("{}", e),
+        }
+    }
+
+    /// Queues a data point for every destination that is still writable.
+    pub fn record(&self, data_point: &EngineDataPoint) {
+        let line = data_point.to_log_line();
+        for destination in &self.destinations {
+            if destination.is_healthy() {
+                let _ = destination.sender.send(line.clone());
+            }
+        }
+        if let Some(parquet) = &self.parquet {
+            parquet.write(data_point);
+        }
+        if let Some(samples) = &self.samples {
+            samples.write(data_point);
+        }
+    }
+
+    /// Completes the Parquet file, which is unreadable until its footer is written,
+    /// and commits the last stored samples.
+    pub fn finish(&self) {
+        if let Some(parquet) = &self.parquet {
+            parquet.finish();
+        }
+        if let Some(samples) = &self.samples {
+            samples.finish();
+        }
+    }
+
+    pub fn destinations(&self) -> &[Destination] {
+        &self.destinations
+    }
+}
@@ -0,0 +1,91 @@
+//! Auxiliary stand power relays (lighting, camera power, the pad warning
+//! beacon), switched from the ground station and logged with every data point.
+
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::Relays;
+
+use crate::applog::diag;
+use crate::arming::ArmState;
+
+/// Beacon steps signalling all clear on return to SAFE, as (on, duration): a
+/// pause, then two long pulses, so it can't be mistaken for the steady warning.
+const ALL_CLEAR_PATTERN: [(bool, Duration); 4] = [
+    (false, Duration::from_millis(500)),
+    (true, Duration::from_millis(1500)),
+    (false, Duration::from_millis(500)),
+    (true, Duration::from_millis(1500)),
+];
+
+/// One relay on the stand's auxiliary board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelayChannel {
+    Lighting,
+    CameraPower,
+    Beacon,
+}
+
+impl RelayChannel {
+    pub const ALL: [RelayChannel; 3] = [
+        RelayChannel::Lighting,
+        RelayChannel::CameraPower,
+        RelayChannel::Beacon,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RelayChannel::Lighting => "Stand Lighting",
+            RelayChannel::CameraPower => "Camera Power",
+            RelayChannel::Beacon => "Warning Beacon",
+        }
+    }
+
+    pub fn state_mut(self, relays: &mut Relays) -> &mut bool {
+        match self {
+            RelayChannel::Lighting => &mut relays.lighting,
+            RelayChannel::CameraPower => &mut relays.camera_power,
+            RelayChannel::Beacon => &mut relays.beacon,
+        }
+    }
+}
+
+/// Drives the pad warning beacon from the arm state: steady on while ARMED or
+/// FIRING, and the all-clear pattern then off on return to SAFE. An abort
+/// leaves the beacon on until the reset back to SAFE.
+#[derive(Default)]
+pub struct BeaconAutomation {
+    state: Option<ArmState>,
+    // Start of the all-clear pattern, while it plays
+    all_clear_since: Option<Instant>,
+}
+
+impl BeaconAutomation {
+    /// The beacon state to command for the arm state at `now`, or None to leave
+    /// it to the operator.
+    pub fn update(&mut self, state: ArmState, now: Instant) -> Option<bool> {
+        let previous = self.state.replace(state);
+        if previous != Some(state) {
+            match state {
+                ArmState::Armed | ArmState::Firing => {
+                    self.all_clear_since = None;
+                    return Some(true);
+                }
+                // Not at startup, which is SAFE without anyone having armed
+                ArmState::Safe if previous.is_some() => {
+                    diag!("Signalling all clear on the warning beacon");
+                    self.all_clear_since = Some(now);
+                }
+                _ => {}
+            }
+        }
+        let mut elapsed = now.duration_since(self.all_clear_since?);
+        for (on, duration) in ALL_CLEAR_PATTERN {
+            if elapsed < duration {
+                return Some(on);
+            }
+            elapsed -= duration;
+        }
+        self.all_clear_since = None;
+        Some(false)
+    }
+}
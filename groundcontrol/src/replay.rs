@@ -0,0 +1,166 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::{data_log, EngineDataPoint};
+
+use crate::applog::diag;
+
+/// Playback speeds offered in the replay controls.
+pub const REPLAY_SPEEDS: [f64; 5] = [0.5, 1.0, 2.0, 5.0, 10.0];
+const PLAYBACK_TICK: Duration = Duration::from_millis(10);
+
+/// Playback state shared between the GUI and the replay thread.
+#[derive(Debug)]
+pub struct ReplayControl {
+    pub paused: bool,
+    pub speed: f64,
+    // Firmware time of the playhead in ms
+    pub position: f64,
+    seek_to: Option<f64>,
+}
+
+/// A previous session's data_log.csv played back through the data channel.
+pub struct Replay {
+    pub path: PathBuf,
+    // Firmware time span of the log in ms
+    pub start: f64,
+    pub end: f64,
+    control: Arc<Mutex<ReplayControl>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Replay {
+    /// Loads the log and starts playing it at real time.
+    pub fn start(path: &Path, data_sender: Sender<EngineDataPoint>) -> Result<Self, String> {
+        let data_points = load(path)?;
+        let (Some(first), Some(last)) = (data_points.first(), data_points.last()) else {
+            return Err(format!("{} has no data rows", path.display()));
+        };
+        let (start, end) = (first.time, last.time);
+        diag!(
+            "Replaying {} ({} rows, {:.1} s)",
+            path.display(),
+            data_points.len(),
+            (end - start) / 1000.0
+        );
+
+        let control = Arc::new(Mutex::new(ReplayControl {
+            paused: false,
+            speed: 1.0,
+            position: start,
+            seek_to: None,
+        }));
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let control = control.clone();
+            let stop = stop.clone();
+            thread::spawn(move || play(data_points, &control, &stop, &data_sender));
+        }
+
+        Ok(Self {
+            path: path.to_path_buf(),
+            start,
+            end,
+            control,
+            stop,
+        })
+    }
+
+    /// Locks the playback state; the replay thread sends nothing while it is held.
+    pub fn control(&self) -> MutexGuard<'_, ReplayControl> {
+        self.control.lock().unwrap()
+    }
+}
+
+impl ReplayControl {
+    /// Moves the playhead; points sent after the lock is released start there.
+    pub fn seek(&mut self, time: f64) {
+        self.seek_to = Some(time);
+        self.position = time;
+    }
+}
+
+impl Drop for Replay {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Reads every data row of a session log.
+pub fn load(path: &Path) -> Result<Vec<EngineDataPoint>, String> {
+    let contents = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut schema = None;
+    let mut data_points = Vec::new();
+    for (i, line) in contents.lines().enumerate() {
+        if let Some(version) = data_log::schema_version(line) {
+            schema = Some(version);
+        }
+        if line.trim().is_empty() || data_log::is_metadata(line) {
+            continue;
+        }
+        data_points.push(
+            data_log::parse_row(line, schema)
+                .map_err(|e| format!("{}:{}: {}", path.display(), i + 1, e))?,
+        );
+    }
+    Ok(data_points)
+}
+
+/// Sends data points as the playhead passes their firmware time.
+fn play(
+    data_points: Vec<EngineDataPoint>,
+    control: &Mutex<ReplayControl>,
+    stop: &AtomicBool,
+    data_sender: &Sender<EngineDataPoint>,
+) {
+    let end = data_points.last().map_or(0.0, |dp| dp.time);
+    let mut index = 0;
+    let mut last_tick = Instant::now();
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(PLAYBACK_TICK);
+        let now = Instant::now();
+        let elapsed_ms = now.duration_since(last_tick).as_secs_f64() * 1000.0;
+        last_tick = now;
+
+        // Send while holding the lock so a seek never interleaves with stale points
+        let mut control = control.lock().unwrap();
+        if let Some(time) = control.seek_to.take() {
+            index = data_points.partition_point(|dp| dp.time < time);
+        }
+        if control.paused {
+            continue;
+        }
+        control.position += elapsed_ms * control.speed;
+        while index < data_points.len() && data_points[index].time <= control.position {
+            if data_sender.send(data_points[index].clone()).is_err() {
+                return;
+            }
+            index += 1;
+        }
+        if index == data_points.len() {
+            control.position = end;
+            control.paused = true;
+        }
+    }
+}
+
+/// Session logs under `logs_root`, newest first, excluding the running session.
+pub fn previous_sessions(logs_root: &Path, current: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(logs_root) else {
+        return Vec::new();
+    };
+    let mut logs: Vec<PathBuf> = entries
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|dir| dir.as_path() != current)
+        .map(|dir| dir.join("data_log.csv"))
+        .filter(|log| log.is_file())
+        .collect();
+    logs.sort_by_key(|log| std::cmp::Reverse(fs::metadata(log).and_then(|m| m.modified()).ok()));
+    logs
+}
@@ -0,0 +1,143 @@
+use eframe::egui;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use crate::applog::diag;
+
+const SCREENSHOT_DIR_NAME: &str = "screenshots";
+
+/// Saves a PNG of the whole window at ignition, shutdown, aborts, and alarms, in
+/// the session folder's screenshots directory.
+///
+/// A screenshot shows the frame after the request, so it includes whatever the
+/// event changed. Requests made before that frame share one image.
+///
+/// Plot exports are cut out of the same screenshots.
+pub struct Screenshots {
+    dir: PathBuf,
+    // Reasons for the screenshot not yet requested from the window
+    queued: Vec<String>,
+    // Reasons for the screenshot requested and not yet received
+    requested: Vec<String>,
+    // Plots to cut out of the next screenshot, and out of the one requested
+    queued_plots: Option<PlotCapture>,
+    requested_plots: Option<PlotCapture>,
+}
+
+/// Areas of the window saved as `<prefix>_<name>.png` in `dir`.
+pub struct PlotCapture {
+    pub dir: PathBuf,
+    pub prefix: String,
+    pub plots: Vec<(String, egui::Rect)>,
+}
+
+impl Screenshots {
+    pub fn new(log_dir: &Path) -> Self {
+        Self {
+            dir: log_dir.join(SCREENSHOT_DIR_NAME),
+            queued: Vec::new(),
+            requested: Vec::new(),
+            queued_plots: None,
+            requested_plots: None,
+        }
+    }
+
+    /// Asks for a screenshot labelled with the given reason on the next frame.
+    pub fn request(&mut self, reason: &str) {
+        self.queued.push(reason.to_string());
+    }
+
+    /// Asks for an image of each plot on the next frame, replacing any not yet requested.
+    pub fn request_plots(&mut self, capture: PlotCapture) {
+        self.queued_plots = Some(capture);
+    }
+
+    /// Saves screenshots the window delivered and sends queued requests.
+    pub fn update(&mut self, ctx: &egui::Context) {
+        let images: Vec<Arc<egui::ColorImage>> = ctx.input(|i| {
+            i.events
+                .iter()
+                .filter_map(|event| match event {
+                    egui::Event::Screenshot { image, .. } => Some(image.clone()),
+                    _ => None,
+                })
+                .collect()
+        });
+        for image in images {
+            let reasons = std::mem::take(&mut self.requested);
+            if !reasons.is_empty() {
+                let name = format!(
+                    "{}_{}.png",
+                    chrono::Local::now().format("%H-%M-%S%.3f"),
+                    file_label(&reasons.join(" "))
+                );
+                save(image.clone(), self.dir.join(name));
+            }
+            if let Some(capture) = self.requested_plots.take() {
+                let pixels_per_point = ctx.pixels_per_point();
+                let [width, height] = image.size;
+                let screen = egui::Rect::from_min_size(
+                    egui::Pos2::ZERO,
+                    egui::vec2(width as f32, height as f32) / pixels_per_point,
+                );
+                for (name, rect) in capture.plots {
+                    let rect = rect.intersect(screen);
+                    if !rect.is_positive() {
+                        continue;
+                    }
+                    let path =
+                        capture
+                            .dir
+                            .join(format!("{}_{}.png", capture.prefix, file_label(&name)));
+                    save(Arc::new(image.region(&rect, Some(pixels_per_point))), path);
+                }
+            }
+        }
+        let idle = self.requested.is_empty() && self.requested_plots.is_none();
+        if idle && (!self.queued.is_empty() || self.queued_plots.is_some()) {
+            self.requested = std::mem::take(&mut self.queued);
+            self.requested_plots = self.queued_plots.take();
+            ctx.send_viewport_cmd(egui::ViewportCommand::Screenshot);
+        }
+    }
+}
+
+/// Encodes and writes an image off the UI thread.
+fn save(image: Arc<egui::ColorImage>, path: PathBuf) {
+    thread::spawn(move || {
+        let [width, height] = image.size;
+        let saved = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .map_err(|e| e.to_string())
+            .and_then(|()| {
+                image::save_buffer(
+                    &path,
+                    image.as_raw(),
+                    width as u32,
+                    height as u32,
+                    image::ExtendedColorType::Rgba8,
+                )
+                .map_err(|e| e.to_string())
+            });
+        match saved {
+            Ok(()) => diag!("Saved {}", path.display()),
+            Err(e) => diag!("Failed to save {}: {}", path.display(), e),
+        }
+    });
+}
+
+/// Text shortened to letters, digits, and underscores for use in a file name.
+pub fn file_label(reason: &str) -> String {
+    let label: String = reason
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    let label = label
+        .split('_')
+        .filter(|part| !part.is_empty())
+        .collect::<Vec<_>>()
+        .join("_");
+    label.chars().take(60).collect()
+}
@@ -0,0 +1,396 @@
+//! Scripted test sequences in Rhai, for conditional logic such as "hold until
+//! chamber pressure > X", run on their own thread.
+//!
+//! Scripts are sandboxed: Rhai has no file, network, or process access, module
+//! imports are disabled, and sizes and nesting are capped. A script only sees
+//! the functions below. Valve commands and aborts are handed to the GUI, which
+//! applies them through the same arming and duty-cycle checks as the buttons.
+//! A refused valve command stops the script.
+//!
+//! | Function                   | Effect                                          |
+//! |----------------------------|-------------------------------------------------|
+//! | `valves(fuel, oxi)`        | Commands both valves open (true) or closed      |
+//! | `close_all()`              | Closes both valves                              |
+//! | `fuel_flow()`, `oxi_flow()`| Latest flows in L/min                           |
+//! | `pressure(i)`              | Latest value of pressure channel i, from 0      |
+//! | `thrust()`                 | Latest thrust in N, or () if not streamed       |
+//! | `time()`                   | Seconds since the script started                |
+//! | `sleep(s)`                 | Waits s seconds, at most an hour                |
+//! | `abort(reason)`            | Aborts the test, as if the operator had         |
+//! | `log(text)`                | Adds an event marker                            |
+//!
+//! Telemetry functions throw while no data point has arrived.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::EngineDataPoint;
+use rhai::{Dynamic, Engine, EvalAltResult, NativeCallContext, INT};
+
+use crate::applog::diag;
+
+/// Longest a `sleep` waits before checking whether the script was stopped.
+const SLEEP_SLICE: Duration = Duration::from_millis(20);
+/// Longest single `sleep`; longer, infinite, or NaN waits are script errors.
+const MAX_SLEEP_S: f64 = 3600.0;
+
+/// What a script asks the GUI to do.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScriptRequest {
+    Valves { fuel_open: bool, oxi_open: bool },
+    Abort(String),
+    Log(String),
+}
+
+/// State shared between the GUI and the script thread.
+struct ScriptShared {
+    stop: AtomicBool,
+    // Line of the latest call into the ground station, or 0 before the first
+    line: AtomicUsize,
+    telemetry: Mutex<Option<EngineDataPoint>>,
+}
+
+/// A script running on its own thread.
+pub struct ScriptRunner {
+    shared: Arc<ScriptShared>,
+    requests: Receiver<ScriptRequest>,
+    thread: Option<JoinHandle<Result<(), String>>>,
+}
+
+impl ScriptRunner {
+    /// Compiles and starts a script; a syntax error is returned without starting it.
+    pub fn start(source: &str) -> Result<Self, String> {
+        let shared = Arc::new(ScriptShared {
+            stop: AtomicBool::new(false),
+            line: AtomicUsize::new(0),
+            telemetry: Mutex::new(None),
+        });
+        let (sender, requests) = mpsc::channel();
+        let engine = sandboxed_engine(&shared, sender);
+        let ast = engine
+            .compile(source)
+            .map_err(|e| format!("Script error: {}", e))?;
+        let thread = thread::spawn(move || {
+            engine.run_ast(&ast).map_err(|e| match *e {
+                EvalAltResult::ErrorTerminated(..) => "Stopped".to_string(),
+                e => format!("Script error: {}", e),
+            })
+        });
+        diag!("Script started");
+        Ok(Self {
+            shared,
+            requests,
+            thread: Some(thread),
+        })
+    }
+
+    /// Makes a live data point the latest telemetry the script reads.
+    pub fn telemetry(&self, data_point: &EngineDataPoint) {
+        *self.shared.telemetry.lock().unwrap() = Some(data_point.clone());
+    }
+
+    /// Requests made since the last call, in order.
+    pub fn requests(&self) -> Vec<ScriptRequest> {
+        self.requests.try_iter().collect()
+    }
+
+    /// Ends the script at its next call or loop iteration.
+    pub fn stop(&self) {
+        self.shared.stop.store(true, Ordering::Relaxed);
+    }
+
+    /// Line of the latest call into the ground station, once there has been one.
+    pub fn line(&self) -> Option<usize> {
+        Some(self.shared.line.load(Ordering::Relaxed)).filter(|&line| line > 0)
+    }
+
+    /// How the script ended, once it has.
+    pub fn finished(&mut self) -> Option<Result<(), String>> {
+        if !self.thread.as_ref()?.is_finished() {
+            return None;
+        }
+        let result = self
+            .thread
+            .take()?
+            .join()
+            .unwrap_or_else(|_| Err("Script thread panicked".to_string()));
+        match &result {
+            Ok(()) => diag!("Script finished"),
+            Err(e) => diag!("Script ended: {}", e),
+        }
+        Some(result)
+    }
+}
+
+impl Drop for ScriptRunner {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+/// An engine that can only reach the ground station through the functions in
+/// the module docs, and stops when asked.
+fn sandboxed_engine(shared: &Arc<ScriptShared>, sender: Sender<ScriptRequest>) -> Engine {
+    let mut engine = Engine::new();
+    engine
+        .set_module_resolver(rhai::module_resolvers::DummyModuleResolver::new())
+        .set_max_call_levels(32)
+        .set_max_expr_depths(64, 32)
+        .set_max_string_size(4096)
+        .set_max_array_size(10_000)
+        .set_max_map_size(1_000)
+        .on_print(|text| diag!("Script: {}", text))
+        .on_debug(|text, _, position| diag!("Script {}: {}", position, text));
+    {
+        let shared = shared.clone();
+        engine.on_progress(move |_| {
+            shared
+                .stop
+                .load(Ordering::Relaxed)
+                .then(|| Dynamic::from("stopped"))
+        });
+    }
+
+    // Records the calling line for the GUI before each call into the station
+    let track = {
+        let shared = shared.clone();
+        move |context: &NativeCallContext| {
+            let line = context.call_position().line().unwrap_or(0);
+            shared.line.store(line, Ordering::Relaxed);
+        }
+    };
+    let stopped = {
+        let shared = shared.clone();
+        move || -> Result<(), Box<EvalAltResult>> {
+            if shared.stop.load(Ordering::Relaxed) {
+                Err(EvalAltResult::ErrorTerminated(Dynamic::UNIT, rhai::Position::NONE).into())
+            } else {
+                Ok(())
+            }
+        }
+    };
+    let latest = {
+        let shared = shared.clone();
+        move |read: fn(&EngineDataPoint) -> Option<f64>| -> Result<Dynamic, Box<EvalAltResult>> {
+            let telemetry = shared.telemetry.lock().unwrap();
+            let data_point = telemetry.as_ref().ok_or("No telemetry yet")?;
+            Ok(read(data_point).map_or(Dynamic::UNIT, Dynamic::from_float))
+        }
+    };
+
+    {
+        let (track, sender) = (track.clone(), sender.clone());
+        engine.register_fn(
+            "valves",
+            move |context: NativeCallContext, fuel_open: bool, oxi_open: bool| {
+                track(&context);
+                let _ = sender.send(ScriptRequest::Valves {
+                    fuel_open,
+                    oxi_open,
+                });
+            },
+        );
+    }
+    {
+        let (track, sender) = (track.clone(), sender.clone());
+        engine.register_fn("close_all", move |context: NativeCallContext| {
+            track(&context);
+            let _ = sender.send(ScriptRequest::Valves {
+                fuel_open: false,
+                oxi_open: false,
+            });
+        });
+    }
+    {
+        let (track, sender) = (track.clone(), sender.clone());
+        engine.register_fn("abort", move |context: NativeCallContext, reason: &str| {
+            track(&context);
+            let _ = sender.send(ScriptRequest::Abort(reason.to_string()));
+        });
+    }
+    {
+        let (track, sender) = (track.clone(), sender);
+        engine.register_fn("log", move |context: NativeCallContext, text: &str| {
+            track(&context);
+            let _ = sender.send(ScriptRequest::Log(text.to_string()));
+        });
+    }
+    for (name, read) in [
+        (
+            "fuel_flow",
+            (|dp| Some(dp.flow_rate_fuel)) as fn(&EngineDataPoint) -> Option<f64>,
+        ),
+        ("oxi_flow", |dp| Some(dp.flow_rate_oxi)),
+        ("thrust", |dp| dp.thrust),
+    ] {
+        let (track, latest) = (track.clone(), latest.clone());
+        engine.register_fn(name, move |context: NativeCallContext| {
+            track(&context);
+            latest(read)
+        });
+    }
+    {
+        let (track, shared) = (track.clone(), shared.clone());
+        engine.register_fn(
+            "pressure",
+            move |context: NativeCallContext, channel: i64| -> Result<f64, Box<EvalAltResult>> {
+                track(&context);
+                let telemetry = shared.telemetry.lock().unwrap();
+                let data_point = telemetry.as_ref().ok_or("No telemetry yet")?;
+                usize::try_from(channel)
+                    .ok()
+                    .and_then(|channel| data_point.pressures.get(channel).copied())
+                    .ok_or_else(|| format!("No pressure channel {}", channel).into())
+            },
+        );
+    }
+    {
+        let started = Instant::now();
+        engine.register_fn("time", move || started.elapsed().as_secs_f64());
+    }
+    let sleep = move |context: NativeCallContext, seconds: f64| -> Result<(), Box<EvalAltResult>> {
+        track(&context);
+        if seconds.is_nan() || seconds > MAX_SLEEP_S {
+            return Err(format!("sleep({}) is longer than {} s", seconds, MAX_SLEEP_S).into());
+        }
+        let until = Instant::now() + Duration::from_secs_f64(seconds.max(0.0));
+        loop {
+            stopped()?;
+            let left = until.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                return Ok(());
+            }
+            thread::sleep(left.min(SLEEP_SLICE));
+        }
+    };
+    // Also replaces the standard library's integer sleep, which can't be stopped
+    {
+        let sleep = sleep.clone();
+        engine.register_fn("sleep", move |context: NativeCallContext, seconds: INT| {
+            sleep(context, seconds as f64)
+        });
+    }
+    engine.register_fn("sleep", sleep);
+    engine
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Waits for a script to end, failing the test if it doesn't within a few seconds
+    fn finish(script: &mut ScriptRunner) -> Result<(), String> {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while Instant::now() < deadline {
+            if let Some(result) = script.finished() {
+                return result;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        panic!("script still running");
+    }
+
+    fn run(source: &str) -> (Result<(), String>, Vec<ScriptRequest>) {
+        let mut script = ScriptRunner::start(source).unwrap();
+        let result = finish(&mut script);
+        (result, script.requests())
+    }
+
+    #[test]
+    fn requests_arrive_in_order() {
+        let (result, requests) = run("valves(true, false); log(\"lit\"); close_all();");
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            requests,
+            vec![
+                ScriptRequest::Valves {
+                    fuel_open: true,
+                    oxi_open: false
+                },
+                ScriptRequest::Log("lit".to_string()),
+                ScriptRequest::Valves {
+                    fuel_open: false,
+                    oxi_open: false
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn syntax_errors_are_returned_without_starting() {
+        assert!(ScriptRunner::start("valves(true,").is_err());
+    }
+
+    #[test]
+    fn telemetry_reads_the_latest_data_point() {
+        let (result, _) = run("fuel_flow()");
+        assert!(result.unwrap_err().contains("No telemetry yet"));
+
+        let mut data_point = ksi_telemetry::parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        data_point.pressures = vec![7.5];
+        let mut script =
+            ScriptRunner::start("sleep(0.1); if pressure(0) > 7.0 { log(\"high\") }").unwrap();
+        script.telemetry(&data_point);
+        assert_eq!(finish(&mut script), Ok(()));
+        assert_eq!(
+            script.requests(),
+            vec![ScriptRequest::Log("high".to_string())]
+        );
+    }
+
+    #[test]
+    fn stop_ends_tight_loops_and_sleeps() {
+        for source in ["loop {}", "sleep(60);", "sleep(60.0);"] {
+            let mut script = ScriptRunner::start(source).unwrap();
+            thread::sleep(Duration::from_millis(50));
+            script.stop();
+            assert_eq!(
+                finish(&mut script),
+                Err("Stopped".to_string()),
+                "{}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn stopping_after_a_refused_command_skips_the_rest() {
+        let mut script =
+            ScriptRunner::start("valves(true, true); sleep(60); log(\"late\");").unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        let mut requests = Vec::new();
+        while requests.is_empty() && Instant::now() < deadline {
+            requests = script.requests();
+            thread::sleep(Duration::from_millis(5));
+        }
+        assert_eq!(script.line(), Some(1));
+        // As the GUI does when it refuses the command
+        script.stop();
+        assert_eq!(finish(&mut script), Err("Stopped".to_string()));
+        assert!(script.requests().is_empty());
+    }
+
+    #[test]
+    fn overlong_sleeps_are_script_errors() {
+        for seconds in ["1e300", "1.0 / 0.0", "0.0 / 0.0"] {
+            let (result, _) = run(&format!("sleep({});", seconds));
+            assert!(result.unwrap_err().contains("sleep"), "{}", seconds);
+        }
+    }
+
+    #[test]
+    fn sandbox_limits_are_enforced() {
+        for source in [
+            "import \"std\" as std;",
+            "fn f(x) { f(x) } f(1);",
+            "let s = \"a\"; loop { s += s; }",
+            "let a = []; loop { a.push(1); }",
+        ] {
+            let (result, _) = run(source);
+            assert!(result.is_err(), "{}", source);
+        }
+    }
+}
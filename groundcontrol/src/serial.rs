@@ -0,0 +1,421 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::Sender;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use crate::applog::diag;
+use crate::commands::CommandDialect;
+use crate::console::FirmwareConsole;
+use crate::countdown::Countdown;
+use crate::decoding::FlowDecodingConfig;
+use crate::devices::{DeviceHub, DeviceReadings};
+use crate::environment::Environment;
+use crate::igniter::IgniterOutput;
+use crate::mqtt::MqttPublisher;
+use crate::network::{StandStatus, TelemetryServer, UdpBroadcaster};
+use crate::quality::QualityChecks;
+use crate::recorder::DataRecorder;
+use crate::timesync::TimeSync;
+use crate::valves::PositionRamp;
+use crate::TIMEOUT_MS;
+use ksi_telemetry::environment;
+use ksi_telemetry::packet::Framing;
+use ksi_telemetry::{
+    EngineDataPoint, Protocol, Relays, SerialTelemetrySource, Telemetry, ValveEcho,
+};
+
+/// Baud rates offered in the connection controls.
+pub const BAUD_RATES: [u32; 5] = [9_600, 19_200, 57_600, 115_200, 230_400];
+
+/// State shared between the GUI and the serial threads; outlives any one connection.
+#[derive(Clone)]
+pub struct SerialShared {
+    pub data_sender: Sender<EngineDataPoint>,
+    // Commanded valve states, broadcast by the write thread
+    pub valve_states: Arc<Mutex<(bool, bool)>>,
+    // Valve states last echoed by the firmware, if it streams them
+    pub reported_valves: Arc<Mutex<Option<ValveEcho>>>,
+    // Ramped valve positions, or None when the firmware only opens and closes
+    pub valve_positions: Arc<Mutex<Option<PositionRamp>>>,
+    // Igniter output, or None when the stand has no igniter channel
+    pub igniter: Arc<Mutex<Option<IgniterOutput>>>,
+    // Commanded auxiliary relay states, or None when the stand has no relay board
+    pub relay_states: Arc<Mutex<Option<Relays>>>,
+    // Commands re-sent early because the echo didn't match
+    pub valve_resends: Arc<AtomicU64>,
+    pub recorder: Arc<DataRecorder>,
+    pub flow_decoding: Arc<Mutex<FlowDecodingConfig>>,
+    pub command_dialect: Arc<Mutex<CommandDialect>>,
+    // Interval between valve command broadcasts, adjustable in the settings
+    pub broadcast_interval: Arc<Mutex<Duration>>,
+    // Lines rejected for a bad checksum or unparseable fields
+    pub corrupt_frames: Arc<AtomicU64>,
+    // Slow sensors polled by the write thread
+    pub environment: Arc<Environment>,
+    // Debug text from the firmware
+    pub console: Arc<FirmwareConsole>,
+    // Plausibility checks that flag suspect samples before they are logged
+    pub quality_checks: Arc<Mutex<QualityChecks>>,
+    // Remote viewers, when serving is enabled in the settings
+    pub telemetry_server: Option<Arc<TelemetryServer>>,
+    pub udp_broadcaster: Option<Arc<UdpBroadcaster>>,
+    // Arm state sent to remote viewers with every sample
+    pub stand: Arc<Mutex<StandStatus>>,
+    pub mqtt: Option<Arc<MqttPublisher>>,
+    // Reference clock for timestamps, when one is configured
+    pub time_sync: Option<Arc<TimeSync>>,
+    // Countdown clock every sample is stamped against
+    pub countdown: Arc<Mutex<Countdown>>,
+    // Latest values from other boards, merged into every sample
+    pub devices: Arc<DeviceHub>,
+}
+
+/// Reconnect backoff bounds; the delay doubles after each failed attempt.
+const RECONNECT_INITIAL_MS: u64 = 250;
+const RECONNECT_MAX_MS: u64 = 5_000;
+/// Interval between valve commands while the firmware's echo disagrees with them.
+const VALVE_RESEND_MS: u64 = 25;
+
+/// What the firmware sends, as configured in the settings.
+#[derive(Debug, Clone, Copy)]
+pub struct TelemetryFormat {
+    pub protocol: Protocol,
+    pub pressure_channels: usize,
+    pub framing: Framing,
+    // Whether CSV lines are bare channel values, as from boards other than the engine controller
+    pub channel_values: bool,
+}
+
+/// Connection health shown in the GUI header.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ConnectionState {
+    Connected,
+    Reconnecting { attempt: u32 },
+    Disconnected,
+}
+
+/// A serial connection that reopens the port with backoff when it fails.
+pub struct SerialLink {
+    pub port_name: String,
+    pub baud_rate: u32,
+    stop: Arc<AtomicBool>,
+    state: Arc<Mutex<ConnectionState>>,
+}
+
+impl SerialLink {
+    /// Opens the port and starts the supervisor that runs and restarts the I/O threads.
+    pub fn connect(
+        port_name: &str,
+        baud_rate: u32,
+        format: TelemetryFormat,
+        shared: &SerialShared,
+    ) -> Result<Self, String> {
+        let shared = shared.clone();
+        Self::supervise(port_name, baud_rate, format, move |source, stop| {
+            run_link(source, format, &shared, stop)
+        })
+    }
+
+    /// Opens a board that only streams, such as the pressurization skid, keeping
+    /// its latest values in `readings`; nothing is written to it.
+    pub fn connect_device(
+        port_name: &str,
+        baud_rate: u32,
+        format: TelemetryFormat,
+        readings: Arc<DeviceReadings>,
+        time_sync: Option<Arc<TimeSync>>,
+    ) -> Result<Self, String> {
+        Self::supervise(port_name, baud_rate, format, move |source, stop| {
+            run_device(source, &readings, time_sync.as_deref(), stop)
+        })
+    }
+
+    /// Opens the port and starts the supervisor that runs the link with `run`
+    /// and reopens the port with backoff when it fails.
+    fn supervise(
+        port_name: &str,
+        baud_rate: u32,
+        format: TelemetryFormat,
+        run: impl Fn(SerialTelemetrySource, &Arc<AtomicBool>) + Send + 'static,
+    ) -> Result<Self, String> {
+        let source = open_port(port_name, baud_rate, format)?;
+        diag!("Opened {} at {} baud", port_name, baud_rate);
+
+        let stop = Arc::new(AtomicBool::new(false));
+        let state = Arc::new(Mutex::new(ConnectionState::Connected));
+
+        // Supervisor thread: runs the link until it breaks, then reopens with backoff
+        {
+            let stop = stop.clone();
+            let state = state.clone();
+            let port_name = port_name.to_string();
+            thread::spawn(move || {
+                let mut source = Some(source);
+                while let Some(current) = source.take() {
+                    run(current, &stop);
+                    if stop.load(Ordering::Relaxed) {
+                        break;
+                    }
+
+                    let mut backoff = RECONNECT_INITIAL_MS;
+                    let mut attempt = 0;
+                    while !stop.load(Ordering::Relaxed) {
+                        attempt += 1;
+                        *state.lock().unwrap() = ConnectionState::Reconnecting { attempt };
+                        thread::sleep(Duration::from_millis(backoff));
+                        match open_port(&port_name, baud_rate, format) {
+                            Ok(reopened) => {
+                                diag!("Reconnected to {} after {} attempt(s)", port_name, attempt);
+                                *state.lock().unwrap() = ConnectionState::Connected;
+                                source = Some(reopened);
+                                break;
+                            }
+                            Err(e) => {
+                                diag!("Reconnect attempt {} failed: {}", attempt, e);
+                                backoff = (backoff * 2).min(RECONNECT_MAX_MS);
+                            }
+                        }
+                    }
+                }
+                *state.lock().unwrap() = ConnectionState::Disconnected;
+            });
+        }
+
+        Ok(Self {
+            port_name: port_name.to_string(),
+            baud_rate,
+            stop,
+            state,
+        })
+    }
+
+    pub fn state(&self) -> ConnectionState {
+        *self.state.lock().unwrap()
+    }
+}
+
+impl Drop for SerialLink {
+    fn drop(&mut self) {
+        // Threads exit within one read timeout / broadcast interval
+        self.stop.store(true, Ordering::Relaxed);
+        diag!("Closed {}", self.port_name);
+    }
+}
+
+fn open_port(
+    port_name: &str,
+    baud_rate: u32,
+    format: TelemetryFormat,
+) -> Result<SerialTelemetrySource, String> {
+    let source = SerialTelemetrySource::open(
+        port_name,
+        baud_rate,
+        Duration::from_millis(TIMEOUT_MS),
+        format.protocol,
+    )?;
+    let source = source
+        .pressure_channels(format.pressure_channels)
+        .framing(format.framing);
+    Ok(if format.channel_values {
+        source.channel_values()
+    } else {
+        source
+    })
+}
+
+/// Runs the read loop and write thread on one open port until it fails or `stop` is set.
+fn run_link(
+    mut source: SerialTelemetrySource,
+    format: TelemetryFormat,
+    shared: &SerialShared,
+    stop: &Arc<AtomicBool>,
+) {
+    let port_clone = match source.try_clone_port() {
+        Ok(port_clone) => port_clone,
+        Err(e) => {
+            diag!("{}", e);
+            return;
+        }
+    };
+    let alive = Arc::new(AtomicBool::new(true));
+    let running =
+        |alive: &AtomicBool| alive.load(Ordering::Relaxed) && !stop.load(Ordering::Relaxed);
+
+    // Serial write thread
+    let writer = {
+        let shared = shared.clone();
+        let stop = stop.clone();
+        let alive = alive.clone();
+        thread::spawn(move || {
+            let mut port = port_clone;
+            // Sensor replies are CSV lines, which a binary-only stream can't carry
+            let mut polls =
+                (format.protocol != Protocol::Binary).then(|| shared.environment.schedule());
+            while alive.load(Ordering::Relaxed) && !stop.load(Ordering::Relaxed) {
+                let (fuel_open, oxi_open) = *shared.valve_states.lock().unwrap();
+                let encoder = shared.command_dialect.lock().unwrap().encoder();
+                let mut msg = encoder.encode(fuel_open, oxi_open);
+                // Positions, relays, and polls follow the valve command in the same
+                // write: firmware that discards the rest of its buffer loses those,
+                // never the valves
+                if let Some(ramp) = shared.valve_positions.lock().unwrap().as_mut() {
+                    let (fuel, oxi) = ramp.step(fuel_open, oxi_open, Instant::now());
+                    msg.push_str(&encoder.encode_positions(fuel, oxi));
+                }
+                if let Some(igniter) = *shared.igniter.lock().unwrap() {
+                    msg.push_str(&encoder.encode_igniter(igniter.energized(Instant::now())));
+                }
+                if let Some(relays) = *shared.relay_states.lock().unwrap() {
+                    msg.push_str(&encoder.encode_relays(relays));
+                }
+                for sensor in polls.iter_mut().flat_map(|p| p.take_due(Instant::now())) {
+                    msg.push_str(&environment::poll_request(sensor));
+                }
+
+                if let Err(e) = port.write_all(msg.as_bytes()) {
+                    diag!("Failed to write to serial port: {:?}", e);
+                    alive.store(false, Ordering::Relaxed);
+                    return;
+                }
+                // Re-send sooner until the firmware echoes the commanded states
+                let acknowledged =
+                    shared.reported_valves.lock().unwrap().is_none_or(|echo| {
+                        (echo.fuel_open, echo.oxi_open) == (fuel_open, oxi_open)
+                    });
+                let interval = if acknowledged {
+                    *shared.broadcast_interval.lock().unwrap()
+                } else {
+                    shared.valve_resends.fetch_add(1, Ordering::Relaxed);
+                    Duration::from_millis(VALVE_RESEND_MS)
+                };
+                thread::sleep(interval);
+            }
+
+            // Leave the firmware in the safe state when disconnecting; the relays
+            // stay as they are, so the pad isn't left dark
+            if stop.load(Ordering::Relaxed) {
+                let encoder = shared.command_dialect.lock().unwrap().encoder();
+                let mut msg = encoder.encode(false, false);
+                if shared.igniter.lock().unwrap().is_some() {
+                    msg.push_str(&encoder.encode_igniter(false));
+                }
+                let _ = port.write_all(msg.as_bytes());
+            }
+        })
+    };
+
+    // Serial read loop
+    while running(&alive) {
+        match source.read() {
+            Ok(Some(Ok(Telemetry::DataPoint(data_point)))) => handle_data_point(data_point, shared),
+            Ok(Some(Ok(Telemetry::Environment(reading)))) => shared.environment.record(reading),
+            Ok(Some(Ok(Telemetry::Console(text)))) => shared.console.push(&text),
+            // Only read from other boards, in `run_device`
+            Ok(Some(Ok(Telemetry::Values(_)))) => {}
+            Ok(Some(Err(e))) => {
+                shared.corrupt_frames.fetch_add(1, Ordering::Relaxed);
+                diag!("Error parsing data: {}", e);
+            }
+            Ok(None) => continue,
+            Err(e) => {
+                diag!("Error reading from serial port: {:?}", e);
+                alive.store(false, Ordering::Relaxed);
+            }
+        }
+    }
+    let _ = writer.join();
+}
+
+/// Runs the read loop of a board that only streams until it fails or `stop` is set.
+fn run_device(
+    mut source: SerialTelemetrySource,
+    readings: &DeviceReadings,
+    time_sync: Option<&TimeSync>,
+    stop: &AtomicBool,
+) {
+    let record = |values| readings.record(values, unix_time_ms(time_sync), Instant::now());
+    while !stop.load(Ordering::Relaxed) {
+        match source.read() {
+            Ok(Some(Ok(Telemetry::Values(values)))) => record(values),
+            Ok(Some(Ok(Telemetry::DataPoint(data_point)))) => record(data_point.pressures),
+            Ok(Some(Ok(Telemetry::Console(text)))) => diag!("{}: {}", readings.name, text),
+            // Only the engine controller is polled for environment sensors
+            Ok(Some(Ok(Telemetry::Environment(_)))) => {}
+            Ok(Some(Err(e))) => diag!("Error parsing {} data: {}", readings.name, e),
+            Ok(None) => continue,
+            Err(e) => {
+                diag!("Error reading from {}: {:?}", readings.name, e);
+                return;
+            }
+        }
+    }
+}
+
+/// Lists the names of serial ports currently present on the system.
+pub fn available_ports() -> Vec<String> {
+    match serialport::available_ports() {
+        Ok(ports) => ports.into_iter().map(|p| p.port_name).collect(),
+        Err(e) => {
+            diag!("Failed to enumerate serial ports: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+/// The current Unix time in ms, from the reference clock when there is one.
+fn unix_time_ms(time_sync: Option<&TimeSync>) -> u64 {
+    match time_sync {
+        Some(time_sync) => time_sync.unix_time_ms(),
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64,
+    }
+}
+
+/// Completes a parsed data point, forwards it to the GUI, and appends it to the log.
+pub fn handle_data_point(mut data_point: EngineDataPoint, shared: &SerialShared) {
+    let unix_ms = unix_time_ms(shared.time_sync.as_deref());
+    data_point.timestamp = unix_ms / 1000;
+    data_point.unix_ms = Some(unix_ms);
+
+    // Convert raw flow channels per their decoding mode
+    let (flow_fuel, flow_oxi) = shared
+        .flow_decoding
+        .lock()
+        .unwrap()
+        .decode(data_point.flow_rate_fuel, data_point.flow_rate_oxi);
+    data_point.flow_rate_fuel = flow_fuel;
+    data_point.flow_rate_oxi = flow_oxi;
+    shared.quality_checks.lock().unwrap().check(&mut data_point);
+    shared.devices.merge(&mut data_point, Instant::now());
+
+    if data_point.valve_echo.is_some() {
+        *shared.reported_valves.lock().unwrap() = data_point.valve_echo;
+    }
+
+    // Get current valve states
+    let valve_states = *shared.valve_states.lock().unwrap();
+    data_point.fuel_valve_open = valve_states.0;
+    data_point.oxi_valve_open = valve_states.1;
+    data_point.relays = shared.relay_states.lock().unwrap().unwrap_or_default();
+    data_point.t_time = shared.countdown.lock().unwrap().t_time(Instant::now());
+
+    // Send data point to GUI, remote viewers, and the MQTT broker
+    let _ = shared.data_sender.send(data_point.clone());
+    let stand = shared.stand.lock().unwrap().clone();
+    if let Some(server) = &shared.telemetry_server {
+        server.broadcast(&data_point, &stand);
+    }
+    if let Some(udp) = &shared.udp_broadcaster {
+        udp.broadcast(&data_point, &stand);
+    }
+    if let Some(mqtt) = &shared.mqtt {
+        mqtt.publish(&data_point);
+    }
+
+    // Log data point
+    shared.recorder.record(&data_point);
+}
@@ -0,0 +1,101 @@
+use eframe::egui;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use crate::calibration::FlowCalibrations;
+
+const SESSION_INFO_FILE_NAME: &str = "metadata.json";
+
+/// What was tested and by whom, entered before the test and kept in the session
+/// folder's metadata.json.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SessionInfo {
+    pub test_name: String,
+    pub fuel: String,
+    pub oxidizer: String,
+    pub nozzle: String,
+    pub operators: Vec<String>,
+    pub notes: String,
+    /// Flow meter calibration in effect, filled in from the settings when saved
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flow_calibration: Option<FlowCalibrations>,
+}
+
+impl SessionInfo {
+    pub fn save(&self, log_dir: &Path) -> Result<(), String> {
+        let path = log_dir.join(SESSION_INFO_FILE_NAME);
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        // Replace the file in one step so readers never see a partial write
+        let temp = path.with_extension("json.tmp");
+        fs::write(&temp, json + "\n")
+            .and_then(|()| fs::rename(&temp, &path))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Window title naming the test, or the plain title before one is entered.
+    pub fn window_title(&self, title: &str) -> String {
+        let test_name = self.test_name.trim();
+        if test_name.is_empty() {
+            title.to_string()
+        } else {
+            format!("{} | {}", test_name, title)
+        }
+    }
+}
+
+/// Session info being edited, with the operators as one comma-separated line.
+pub struct SessionInfoDraft {
+    info: SessionInfo,
+    operators: String,
+}
+
+impl SessionInfoDraft {
+    pub fn new(info: &SessionInfo) -> Self {
+        Self {
+            operators: info.operators.join(", "),
+            info: info.clone(),
+        }
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        egui::Grid::new("session_info_grid")
+            .num_columns(2)
+            .show(ui, |ui| {
+                ui.label("Test name");
+                ui.text_edit_singleline(&mut self.info.test_name);
+                ui.end_row();
+                ui.label("Fuel");
+                ui.text_edit_singleline(&mut self.info.fuel);
+                ui.end_row();
+                ui.label("Oxidizer");
+                ui.text_edit_singleline(&mut self.info.oxidizer);
+                ui.end_row();
+                ui.label("Nozzle configuration");
+                ui.text_edit_singleline(&mut self.info.nozzle);
+                ui.end_row();
+                ui.label("Operators");
+                ui.text_edit_singleline(&mut self.operators)
+                    .on_hover_text("Separate names with commas");
+                ui.end_row();
+                ui.label("Notes");
+                ui.text_edit_multiline(&mut self.info.notes);
+                ui.end_row();
+            });
+    }
+
+    pub fn finish(self) -> SessionInfo {
+        let operators = self
+            .operators
+            .split(',')
+            .map(str::trim)
+            .filter(|name| !name.is_empty())
+            .map(str::to_string)
+            .collect();
+        SessionInfo {
+            operators,
+            ..self.info
+        }
+    }
+}
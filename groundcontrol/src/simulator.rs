@@ -0,0 +1,162 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::applog::diag;
+use crate::decoding::{FLOW_K_FACTOR_FUEL, FLOW_K_FACTOR_OXI};
+use crate::serial::{self, SerialShared};
+use crate::valves;
+
+/// Command-line flag that replaces the serial link with synthetic telemetry.
+pub const SIMULATE_FLAG: &str = "--simulate";
+
+// Mirrors the firmware: one line per pulse counting window
+const WINDOW_MS: u64 = 100;
+// Steady-state flow with the valve open, in L/min
+const OPEN_FLOW_FUEL: f64 = 2.0;
+const OPEN_FLOW_OXI: f64 = 2.6;
+// First-order response of the flow to a valve change
+const FLOW_TIME_CONSTANT_MS: f64 = 300.0;
+// Relative flow noise while a valve is open
+const FLOW_NOISE: f64 = 0.03;
+// Simulated pressure per L/min of total flow, scaled up for each further channel
+const PRESSURE_PER_FLOW: f64 = 2.0;
+// Thermocouples: nozzle heating per L/min of total flow and its time constant, in °C and ms
+const AMBIENT_C: f64 = 20.0;
+const NOZZLE_HEAT_PER_FLOW: f64 = 120.0;
+const NOZZLE_TIME_CONSTANT_MS: f64 = 3000.0;
+// Load cell thrust per L/min of total flow, in N
+const THRUST_PER_FLOW: f64 = 150.0;
+
+/// Synthetic firmware that answers valve commands, for running without hardware.
+///
+/// Generated lines go through the same parsing, decoding, and logging path as
+/// serial telemetry.
+pub struct Simulator {
+    stop: Arc<AtomicBool>,
+}
+
+impl Simulator {
+    /// Starts generating telemetry, ending each line with `pressure_channels` values.
+    pub fn start(shared: &SerialShared, pressure_channels: usize) -> Self {
+        diag!("Simulating telemetry; no serial port is used");
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let shared = shared.clone();
+            let stop = stop.clone();
+            thread::spawn(move || run(&shared, pressure_channels, &stop));
+        }
+        Self { stop }
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+/// One simulated flow meter and its valve.
+struct Meter {
+    open_flow: f64,
+    k_factor: f64,
+    flow: f64,
+    // Fractional pulses carried into the next window
+    pulse_remainder: f64,
+}
+
+impl Meter {
+    fn new(open_flow: f64, k_factor: f64) -> Self {
+        Self {
+            open_flow,
+            k_factor,
+            flow: 0.0,
+            pulse_remainder: 0.0,
+        }
+    }
+
+    /// Advances one window with the valve `opening` from 0 to 1 and returns the
+    /// whole pulses counted in it.
+    fn step(&mut self, opening: f64, noise: f64) -> i32 {
+        let target = if opening > 0.0 {
+            self.open_flow * opening * (1.0 + noise)
+        } else {
+            0.0
+        };
+        let alpha = 1.0 - (-(WINDOW_MS as f64) / FLOW_TIME_CONSTANT_MS).exp();
+        self.flow += (target - self.flow) * alpha;
+
+        // Q = F / K, counted over the window
+        let pulses = self.flow * self.k_factor * WINDOW_MS as f64 / 1000.0 + self.pulse_remainder;
+        let whole = pulses.floor();
+        self.pulse_remainder = pulses - whole;
+        whole as i32
+    }
+}
+
+/// Small xorshift generator for flow noise in [-1, 1).
+struct Noise(u64);
+
+impl Noise {
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+}
+
+fn run(shared: &SerialShared, pressure_channels: usize, stop: &AtomicBool) {
+    let started = Instant::now();
+    let mut fuel = Meter::new(OPEN_FLOW_FUEL, FLOW_K_FACTOR_FUEL);
+    let mut oxi = Meter::new(OPEN_FLOW_OXI, FLOW_K_FACTOR_OXI);
+    let mut noise = Noise(0x2545_f491_4f6c_dd1d);
+    let mut nozzle = AMBIENT_C;
+    while !stop.load(Ordering::Relaxed) {
+        thread::sleep(Duration::from_millis(WINDOW_MS));
+        // Echo the commanded states, as the firmware does on each command
+        let (fuel_open, oxi_open) = *shared.valve_states.lock().unwrap();
+        // Without a write thread, the simulator ramps the positions itself
+        let (fuel_pct, oxi_pct) = match shared.valve_positions.lock().unwrap().as_mut() {
+            Some(ramp) => ramp.step(fuel_open, oxi_open, Instant::now()),
+            None => {
+                let percent = |open: bool| if open { 100.0 } else { 0.0 };
+                (percent(fuel_open), percent(oxi_open))
+            }
+        };
+        let pulses_fuel = fuel.step(fuel_pct / 100.0, FLOW_NOISE * noise.next());
+        let pulses_oxi = oxi.step(oxi_pct / 100.0, FLOW_NOISE * noise.next());
+        let window_hz = 1000.0 / WINDOW_MS as f64;
+
+        let nozzle_target = AMBIENT_C + (fuel.flow + oxi.flow) * NOZZLE_HEAT_PER_FLOW;
+        nozzle += (nozzle_target - nozzle)
+            * (1.0 - (-(WINDOW_MS as f64) / NOZZLE_TIME_CONSTANT_MS).exp());
+
+        // No controller fields, so they are left empty before the temperatures
+        let mut line = format!(
+            "{},{:.2},{:.2},{},{},{},{},0,,,,{:.1},{:.1},{:.1},{:.1},{},{}",
+            started.elapsed().as_millis(),
+            pulses_fuel as f64 * window_hz / FLOW_K_FACTOR_FUEL,
+            pulses_oxi as f64 * window_hz / FLOW_K_FACTOR_OXI,
+            pulses_fuel,
+            pulses_oxi,
+            valves::servo_angle(fuel_pct),
+            valves::servo_angle(oxi_pct),
+            nozzle,
+            AMBIENT_C - oxi.flow,
+            AMBIENT_C,
+            (fuel.flow + oxi.flow) * THRUST_PER_FLOW,
+            fuel_open as u8,
+            oxi_open as u8,
+        );
+        for channel in 0..pressure_channels {
+            let pressure = (fuel.flow + oxi.flow) * PRESSURE_PER_FLOW * (channel + 1) as f64;
+            line.push_str(&format!(",{:.2}", pressure));
+        }
+        match ksi_telemetry::parse_line_with_pressures(&line, pressure_channels) {
+            Ok(data_point) => serial::handle_data_point(data_point, shared),
+            Err(e) => diag!("Simulator produced an invalid line: {}", e),
+        }
+    }
+}
@@ -0,0 +1,70 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+use crate::applog::diag;
+
+// How often the watch thread wakes to compare clocks
+const TICK: Duration = Duration::from_millis(250);
+// A tick this much later than expected means the host slept or the process was stalled
+const WAKE_GAP: Duration = Duration::from_secs(2);
+
+/// Detects host sleep, App Nap stalls, and wall clock jumps, and commands both
+/// valves closed on wake without waiting for the GUI.
+///
+/// A suspended GUI can't close valves itself, so the watch thread writes the safe
+/// state straight into the states the serial write thread broadcasts.
+pub struct SleepWatch {
+    // Length of the last gap, until the GUI takes it
+    gap: Arc<Mutex<Option<Duration>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl SleepWatch {
+    pub fn start(valve_states: Arc<Mutex<(bool, bool)>>) -> Self {
+        let gap = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+        {
+            let gap = gap.clone();
+            let stop = stop.clone();
+            thread::spawn(move || {
+                let mut last_instant = Instant::now();
+                let mut last_wall = SystemTime::now();
+                while !stop.load(Ordering::Relaxed) {
+                    thread::sleep(TICK);
+                    let (now_instant, now_wall) = (Instant::now(), SystemTime::now());
+                    // The monotonic clock stops during sleep on some platforms and the wall
+                    // clock can jump either way, so whichever moved further wins
+                    let monotonic = now_instant - last_instant;
+                    let wall = now_wall
+                        .duration_since(last_wall)
+                        .unwrap_or_else(|e| e.duration());
+                    (last_instant, last_wall) = (now_instant, now_wall);
+
+                    let elapsed = monotonic.max(wall);
+                    if elapsed > TICK + WAKE_GAP {
+                        *valve_states.lock().unwrap() = (false, false);
+                        diag!(
+                            "Host stalled or slept for {:.1} s; commanded valves closed",
+                            elapsed.as_secs_f64()
+                        );
+                        *gap.lock().unwrap() = Some(elapsed);
+                    }
+                }
+            });
+        }
+        Self { gap, stop }
+    }
+
+    /// The gap detected since the last call, if any.
+    pub fn take_wake(&self) -> Option<Duration> {
+        self.gap.lock().unwrap().take()
+    }
+}
+
+impl Drop for SleepWatch {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
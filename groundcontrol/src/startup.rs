@@ -0,0 +1,125 @@
+//! Bring-up order enforced before arming, so the stand isn't live the moment
+//! the window opens.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use ksi_telemetry::{EngineDataPoint, Quality};
+use serde::{Deserialize, Serialize};
+
+use crate::applog::diag;
+
+// Clean telemetry needed before the self-test passes
+const SELF_TEST_DURATION: Duration = Duration::from_secs(2);
+
+/// One step of bring-up; arming is available once every configured gate passes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StartupGate {
+    /// The settings file was read without errors
+    ConfigLoaded,
+    /// The serial link is connected, or the simulator is running
+    PortConnected,
+    /// Telemetry has been clean for a moment with both valves reported closed
+    SelfTest,
+    /// Every checklist item is ticked
+    Checklist,
+}
+
+impl StartupGate {
+    /// The default order.
+    pub const ALL: [StartupGate; 4] = [
+        StartupGate::ConfigLoaded,
+        StartupGate::PortConnected,
+        StartupGate::SelfTest,
+        StartupGate::Checklist,
+    ];
+}
+
+impl fmt::Display for StartupGate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            StartupGate::ConfigLoaded => "Config loaded",
+            StartupGate::PortConnected => "Port connected",
+            StartupGate::SelfTest => "Self-test passed",
+            StartupGate::Checklist => "Checklist complete",
+        })
+    }
+}
+
+/// The first gate in `order` that isn't met, with why, or None once arming is available.
+///
+/// Gates are checked strictly in order, so a later gate that happens to be met
+/// doesn't count until every one before it is.
+pub fn current_gate(
+    order: &[StartupGate],
+    mut check: impl FnMut(StartupGate) -> Result<(), String>,
+) -> Option<(StartupGate, String)> {
+    order
+        .iter()
+        .find_map(|&gate| check(gate).err().map(|reason| (gate, reason)))
+}
+
+/// Automatic check of the link before arming: telemetry must arrive good
+/// quality, without the firmware emergency flag, and with both valves closed
+/// for SELF_TEST_DURATION. Once passed it holds until the link drops.
+#[derive(Default)]
+pub struct SelfTest {
+    passed: bool,
+    clean_since: Option<Instant>,
+    failure: Option<String>,
+}
+
+impl SelfTest {
+    pub fn update(&mut self, data_point: &EngineDataPoint) {
+        if self.passed {
+            return;
+        }
+        let reported_open = data_point.valve_echo.map_or(
+            data_point.fuel_valve_open || data_point.oxi_valve_open,
+            |echo| echo.fuel_open || echo.oxi_open,
+        );
+        let failure = if data_point.emergency {
+            Some("firmware emergency flag is set".to_string())
+        } else if data_point.quality != Quality::Good {
+            Some(format!(
+                "telemetry quality is {}",
+                data_point.quality.as_str()
+            ))
+        } else if reported_open {
+            Some("a valve is reported open".to_string())
+        } else {
+            None
+        };
+        match failure {
+            Some(failure) => {
+                self.clean_since = None;
+                self.failure = Some(failure);
+            }
+            None => {
+                let since = *self.clean_since.get_or_insert_with(Instant::now);
+                if since.elapsed() >= SELF_TEST_DURATION {
+                    diag!("Self-test passed");
+                    self.passed = true;
+                    self.failure = None;
+                }
+            }
+        }
+    }
+
+    /// Starts over, for when the link drops.
+    pub fn reset(&mut self) {
+        *self = Self::default();
+    }
+
+    pub fn status(&self) -> Result<(), String> {
+        if self.passed {
+            return Ok(());
+        }
+        Err(match (&self.failure, self.clean_since) {
+            (Some(failure), _) => format!("Self-test failing: {}", failure),
+            (None, Some(_)) => "Self-test running".to_string(),
+            (None, None) => "Waiting for telemetry".to_string(),
+        })
+    }
+}
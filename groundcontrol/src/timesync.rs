@@ -0,0 +1,285 @@
+//! Disciplines session timestamps against an NTP server or a GPS receiver, so data
+//! from cameras, other DAQs, and range systems can be aligned on one time base.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::net::UdpSocket;
+use std::path::Path;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use chrono::{NaiveDate, NaiveTime};
+use serde::{Deserialize, Serialize};
+
+use crate::applog::diag;
+
+const TIME_SYNC_FILE_NAME: &str = "time_sync.csv";
+const NTP_PORT: u16 = 123;
+const NTP_TIMEOUT: Duration = Duration::from_secs(2);
+// Seconds from the NTP epoch (1900) to the Unix epoch (1970)
+const NTP_UNIX_OFFSET_S: f64 = 2_208_988_800.0;
+// Interval between offsets logged from the GPS, which sends one every second
+const GPS_LOG_INTERVAL: Duration = Duration::from_secs(10);
+// An offset older than this is shown as stale
+const STALE_AFTER: Duration = Duration::from_secs(120);
+
+/// Reference clock; timestamps follow the host clock without a `[time_source]` table.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum TimeSource {
+    /// Queried with SNTP every `interval_s`
+    Ntp { server: String, interval_s: f64 },
+    /// NMEA RMC sentences from a GPS receiver on a serial port.
+    ///
+    /// Sentences arrive some tens of ms after the second they name, and that
+    /// latency ends up in the offset. For PPS accuracy, discipline the host clock
+    /// from the PPS line with chrony or gpsd and point an NTP source at it.
+    GpsSerial { port: String, baud_rate: u32 },
+}
+
+impl Default for TimeSource {
+    fn default() -> Self {
+        TimeSource::Ntp {
+            server: "pool.ntp.org".to_string(),
+            interval_s: 60.0,
+        }
+    }
+}
+
+impl TimeSource {
+    fn describe(&self) -> String {
+        match self {
+            TimeSource::Ntp { server, .. } => format!("NTP {}", server),
+            TimeSource::GpsSerial { port, .. } => format!("GPS {}", port),
+        }
+    }
+}
+
+/// Offset of the reference clock from the host clock, kept up to date by a
+/// background thread and recorded in time_sync.csv in the session folder.
+///
+/// Until the first measurement the offset is zero, i.e. host time.
+pub struct TimeSync {
+    description: String,
+    offset_ms: AtomicI64,
+    last_sync: Mutex<Option<Instant>>,
+}
+
+impl TimeSync {
+    pub fn start(source: &TimeSource, log_dir: &Path) -> Result<Arc<Self>, String> {
+        let path = log_dir.join(TIME_SYNC_FILE_NAME);
+        let mut file = File::create(&path)
+            .and_then(|mut file| {
+                file.write_all(b"system_time_ms,source,offset_ms,delay_ms\n")?;
+                Ok(file)
+            })
+            .map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+        let sync = Arc::new(Self {
+            description: source.describe(),
+            offset_ms: AtomicI64::new(0),
+            last_sync: Mutex::new(None),
+        });
+
+        let mut record = {
+            let sync = sync.clone();
+            move |offset_ms: f64, delay_ms: Option<f64>| {
+                sync.offset_ms
+                    .store(offset_ms.round() as i64, Ordering::Relaxed);
+                *sync.last_sync.lock().unwrap() = Some(Instant::now());
+                let delay = delay_ms.map_or(String::new(), |d| format!("{:.1}", d));
+                let line = format!(
+                    "{:.0},{},{:.1},{}\n",
+                    system_time_ms(),
+                    sync.description,
+                    offset_ms,
+                    delay
+                );
+                if let Err(e) = file.write_all(line.as_bytes()) {
+                    diag!("Failed to write time sync record: {}", e);
+                }
+            }
+        };
+        match source.clone() {
+            TimeSource::Ntp { server, interval_s } => {
+                // At least a second; NaN reads as one and an infinite interval queries once
+                let interval =
+                    Duration::try_from_secs_f64(interval_s.max(1.0)).unwrap_or(Duration::MAX);
+                thread::spawn(move || loop {
+                    match ntp_offset(&server) {
+                        Ok((offset_ms, delay_ms)) => record(offset_ms, Some(delay_ms)),
+                        Err(e) => diag!("NTP query to {} failed: {}", server, e),
+                    }
+                    thread::sleep(interval);
+                });
+            }
+            TimeSource::GpsSerial { port, baud_rate } => {
+                let serial = serialport::new(&port, baud_rate)
+                    .timeout(Duration::from_secs(2))
+                    .open()
+                    .map_err(|e| format!("Failed to open GPS on {}: {}", port, e))?;
+                thread::spawn(move || {
+                    let mut logged: Option<Instant> = None;
+                    for line in BufReader::new(serial).lines() {
+                        let line = match line {
+                            Ok(line) => line,
+                            Err(e) if e.kind() == std::io::ErrorKind::TimedOut => continue,
+                            Err(e) => {
+                                diag!("Stopped reading GPS on {}: {}", port, e);
+                                return;
+                            }
+                        };
+                        let Some(gps_ms) = rmc_time_ms(&line) else {
+                            continue;
+                        };
+                        let offset_ms = gps_ms - system_time_ms();
+                        if logged.is_none_or(|at| at.elapsed() >= GPS_LOG_INTERVAL) {
+                            logged = Some(Instant::now());
+                            record(offset_ms, None);
+                        }
+                    }
+                });
+            }
+        }
+        diag!("Disciplining timestamps against {}", sync.description);
+        Ok(sync)
+    }
+
+    /// Reference time in Unix ms.
+    pub fn unix_time_ms(&self) -> u64 {
+        (system_time_ms() as i64 + self.offset_ms.load(Ordering::Relaxed)).max(0) as u64
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Latest offset in ms, unless none was measured recently.
+    pub fn offset_ms(&self) -> Option<i64> {
+        let last_sync = *self.last_sync.lock().unwrap();
+        last_sync
+            .filter(|at| at.elapsed() < STALE_AFTER)
+            .map(|_| self.offset_ms.load(Ordering::Relaxed))
+    }
+}
+
+fn system_time_ms() -> f64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_secs_f64()
+        * 1000.0
+}
+
+/// Queries an SNTP server; returns its offset from the host clock and the
+/// round-trip delay, both in ms.
+fn ntp_offset(server: &str) -> Result<(f64, f64), String> {
+    let address = if server.contains(':') {
+        server.to_string()
+    } else {
+        format!("{}:{}", server, NTP_PORT)
+    };
+    let socket = UdpSocket::bind("0.0.0.0:0").map_err(|e| e.to_string())?;
+    socket
+        .set_read_timeout(Some(NTP_TIMEOUT))
+        .map_err(|e| e.to_string())?;
+    let mut request = [0u8; 48];
+    request[0] = 0x23; // No leap warning, version 4, client mode
+    let sent = system_time_ms();
+    socket
+        .send_to(&request, &address)
+        .map_err(|e| e.to_string())?;
+    let mut response = [0u8; 48];
+    let (length, _) = socket.recv_from(&mut response).map_err(|e| e.to_string())?;
+    let received = system_time_ms();
+    if length < response.len() || response[0] & 0x07 != 4 {
+        return Err("not an NTP server reply".to_string());
+    }
+    if response[1] == 0 {
+        return Err("server refused the request".to_string());
+    }
+    let server_received = ntp_time_ms(&response[32..40]);
+    let server_sent = ntp_time_ms(&response[40..48]);
+    let offset = ((server_received - sent) + (server_sent - received)) / 2.0;
+    let delay = (received - sent) - (server_sent - server_received);
+    Ok((offset, delay))
+}
+
+/// A 64-bit NTP timestamp as Unix ms.
+fn ntp_time_ms(bytes: &[u8]) -> f64 {
+    let seconds = u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]) as f64;
+    let fraction = u32::from_be_bytes([bytes[4], bytes[5], bytes[6], bytes[7]]) as f64;
+    (seconds - NTP_UNIX_OFFSET_S) * 1000.0 + fraction / 4_294_967_296.0 * 1000.0
+}
+
+/// UTC time of an RMC sentence with a valid fix and checksum, in Unix ms.
+fn rmc_time_ms(sentence: &str) -> Option<f64> {
+    let (body, checksum) = sentence.trim().strip_prefix('$')?.split_once('*')?;
+    let expected = u8::from_str_radix(checksum.get(..2)?, 16).ok()?;
+    if body.bytes().fold(0, |sum, b| sum ^ b) != expected {
+        return None;
+    }
+    let fields: Vec<&str> = body.split(',').collect();
+    if !fields.first()?.ends_with("RMC") || *fields.get(2)? != "A" {
+        return None;
+    }
+    let time = NaiveTime::parse_from_str(fields.get(1)?, "%H%M%S%.f").ok()?;
+    let date = NaiveDate::parse_from_str(fields.get(9)?, "%d%m%y").ok()?;
+    Some(date.and_time(time).and_utc().timestamp_millis() as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Wraps a sentence body with its `$` and checksum
+    fn sentence(body: &str) -> String {
+        let checksum = body.bytes().fold(0, |sum, b| sum ^ b);
+        format!("${}*{:02X}\r\n", body, checksum)
+    }
+
+    const FIX: &str = "GPRMC,123519.25,A,4807.038,N,01131.000,E,022.4,084.4,230394,003.1,W";
+
+    #[test]
+    fn rmc_gives_utc_ms() {
+        // 1994-03-23 12:35:19.25 UTC
+        assert_eq!(rmc_time_ms(&sentence(FIX)), Some(764_426_119_250.0));
+        let gnss = FIX.replacen("GPRMC", "GNRMC", 1);
+        assert_eq!(rmc_time_ms(&sentence(&gnss)), Some(764_426_119_250.0));
+    }
+
+    #[test]
+    fn rmc_needs_a_checksum_and_a_fix() {
+        let good = sentence(FIX);
+        let corrupted = good.replacen("123519", "123518", 1);
+        assert_eq!(rmc_time_ms(&corrupted), None);
+        assert_eq!(rmc_time_ms(&format!("${}", FIX)), None, "no checksum");
+        let void = FIX.replacen(",A,", ",V,", 1);
+        assert_eq!(rmc_time_ms(&sentence(&void)), None);
+        let other = FIX.replacen("GPRMC", "GPGGA", 1);
+        assert_eq!(rmc_time_ms(&sentence(&other)), None);
+    }
+
+    #[test]
+    fn rmc_rolls_over_midnight_and_the_year() {
+        let before = sentence("GPRMC,235959.50,A,4807.038,N,01131.000,E,0,0,311224,,");
+        let after = sentence("GPRMC,000000.00,A,4807.038,N,01131.000,E,0,0,010125,,");
+        let (before, after) = (rmc_time_ms(&before).unwrap(), rmc_time_ms(&after).unwrap());
+        assert_eq!(after - before, 500.0);
+        // 2025-01-01 00:00:00 UTC
+        assert_eq!(after, 1_735_689_600_000.0);
+    }
+
+    #[test]
+    fn ntp_timestamps_convert_to_unix_ms() {
+        // 2025-01-01 00:00:00.5 UTC: seconds since 1900 and half of 2^32
+        let seconds = (1_735_689_600u64 + 2_208_988_800) as u32;
+        let mut bytes = seconds.to_be_bytes().to_vec();
+        bytes.extend(0x8000_0000u32.to_be_bytes());
+        assert_eq!(ntp_time_ms(&bytes), 1_735_689_600_500.0);
+        // The Unix epoch itself
+        let epoch = (2_208_988_800u64 as u32).to_be_bytes();
+        assert_eq!(ntp_time_ms(&[epoch, [0; 4]].concat()), 0.0);
+    }
+}
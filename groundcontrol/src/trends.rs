@@ -0,0 +1,190 @@
+use egui_plot::{Line, Plot, PlotPoints, Points};
+use std::path::{Path, PathBuf};
+
+use crate::campaign::{self, LabNote, SessionMatch, SessionMetrics, SEARCHABLE_COLUMNS};
+
+/// Metrics plotted per session, as (stored name, display name).
+const TREND_METRICS: [(&str, &str); 6] = [
+    ("mean_of_ratio", "Average O/F (volumetric)"),
+    ("aborts", "Aborts"),
+    ("peak_flow_fuel", "Peak Fuel Flow (L/min)"),
+    ("peak_flow_oxi", "Peak Oxidizer Flow (L/min)"),
+    ("volume_fuel", "Fuel Volume (L)"),
+    ("volume_oxi", "Oxidizer Volume (L)"),
+];
+
+/// Per-session metrics across the whole campaign, loaded from the campaign database.
+pub struct CampaignTrends {
+    db_path: PathBuf,
+    sessions: Vec<SessionMetrics>,
+    // Experiments indexed by lab_assist, shown on the timeline with the sessions
+    lab_notes: Vec<LabNote>,
+    error: Option<String>,
+    // Session search over stored samples: column index, threshold, and last result
+    search_column: usize,
+    search_threshold: f64,
+    search_result: Option<Result<Vec<SessionMatch>, String>>,
+}
+
+impl CampaignTrends {
+    pub fn load(db_path: &Path) -> Self {
+        let loaded = campaign::session_metrics(db_path)
+            .and_then(|sessions| Ok((sessions, campaign::lab_notes(db_path)?)));
+        let ((sessions, lab_notes), error) = match loaded {
+            Ok(loaded) => (loaded, None),
+            Err(e) => (
+                (Vec::new(), Vec::new()),
+                Some(format!("Failed to read {}: {}", db_path.display(), e)),
+            ),
+        };
+        Self {
+            db_path: db_path.to_path_buf(),
+            sessions,
+            lab_notes,
+            error,
+            search_column: 0,
+            search_threshold: 0.0,
+            search_result: None,
+        }
+    }
+
+    /// Folder name and start date of the session at a plot x position.
+    fn session_label(&self, x: f64) -> String {
+        let index = x.round().max(0.0) as usize;
+        let Some(session) = self.sessions.get(index) else {
+            return String::new();
+        };
+        session_name(&session.log_dir, session.started_at)
+    }
+
+    /// Finds sessions whose stored samples peaked above a threshold.
+    fn search(&mut self, ui: &mut egui::Ui) {
+        ui.heading("Find Sessions");
+        ui.horizontal(|ui| {
+            egui::ComboBox::from_id_salt("search_column")
+                .selected_text(SEARCHABLE_COLUMNS[self.search_column].1)
+                .show_ui(ui, |ui| {
+                    for (i, (_, name)) in SEARCHABLE_COLUMNS.iter().enumerate() {
+                        ui.selectable_value(&mut self.search_column, i, *name);
+                    }
+                });
+            ui.label("exceeded");
+            ui.add(egui::DragValue::new(&mut self.search_threshold).speed(0.1));
+            if ui.button("Search").clicked() {
+                self.search_result = Some(campaign::sessions_exceeding(
+                    &self.db_path,
+                    SEARCHABLE_COLUMNS[self.search_column].0,
+                    self.search_threshold,
+                ));
+            }
+        });
+        match &self.search_result {
+            None => {}
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, format!("Search failed: {}", e));
+            }
+            Some(Ok(matches)) if matches.is_empty() => {
+                ui.label("No sessions with stored samples match.");
+            }
+            Some(Ok(matches)) => {
+                egui::Grid::new("search_results")
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.strong("Session");
+                        ui.strong("Peak");
+                        ui.end_row();
+                        for session in matches {
+                            ui.label(session_name(&session.log_dir, session.started_at));
+                            ui.label(format!("{:.3}", session.peak));
+                            ui.end_row();
+                        }
+                    });
+            }
+        }
+        ui.separator();
+    }
+
+    /// Sessions and lab notes in date order.
+    fn timeline(&self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(format!(
+            "Timeline ({} sessions, {} lab notes)",
+            self.sessions.len(),
+            self.lab_notes.len()
+        ))
+        .show(ui, |ui| {
+            let mut entries: Vec<(Option<i64>, String, Option<&str>)> = self
+                .sessions
+                .iter()
+                .map(|s| {
+                    let name = session_name(&s.log_dir, s.started_at);
+                    (s.started_at, format!("Session {}", name), None)
+                })
+                .chain(self.lab_notes.iter().map(|note| {
+                    let kind = note.experiment_type.as_deref().unwrap_or("experiment");
+                    (
+                        note.date,
+                        format!("Lab note {}: {} ({})", note.folder, note.transcript, kind),
+                        note.objective.as_deref(),
+                    )
+                }))
+                .collect();
+            // Undated entries sort first
+            entries.sort_by_key(|(time, _, _)| *time);
+            for (_, text, objective) in entries {
+                let label = ui.label(text);
+                if let Some(objective) = objective {
+                    label.on_hover_text(objective);
+                }
+            }
+        });
+        ui.separator();
+    }
+
+    pub fn show(&mut self, ui: &mut egui::Ui) {
+        if let Some(error) = &self.error {
+            ui.colored_label(egui::Color32::RED, error);
+            return;
+        }
+        ui.label(format!(
+            "{} sessions; the running session is added when it closes. \
+             Peak Pc and total impulse need pressure and thrust channels, which are not logged.",
+            self.sessions.len()
+        ));
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            self.timeline(ui);
+            self.search(ui);
+            for (name, title) in TREND_METRICS {
+                let points: Vec<[f64; 2]> = self
+                    .sessions
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(i, session)| Some([i as f64, *session.metrics.get(name)?]))
+                    .collect();
+                ui.heading(title);
+                Plot::new(name)
+                    .height(140.0)
+                    .x_axis_label("Session")
+                    .label_formatter(|_, value| {
+                        format!("{}\n{:.3}", self.session_label(value.x), value.y)
+                    })
+                    .allow_double_click_reset(true)
+                    .show(ui, |plot_ui| {
+                        plot_ui.line(Line::new(PlotPoints::from(points.clone())));
+                        plot_ui.points(Points::new(PlotPoints::from(points)).radius(3.0));
+                    });
+            }
+        });
+    }
+}
+
+/// Folder name of a session, with its start date when known.
+fn session_name(log_dir: &str, started_at: Option<i64>) -> String {
+    let name = Path::new(log_dir)
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or(log_dir);
+    match started_at.and_then(|t| chrono::DateTime::from_timestamp(t, 0)) {
+        Some(started) => format!("{} ({})", name, started.format("%Y-%m-%d")),
+        None => name.to_string(),
+    }
+}
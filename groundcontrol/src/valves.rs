@@ -0,0 +1,236 @@
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+const DUTY_WINDOW: Duration = Duration::from_secs(60);
+
+/// Actuation history for a single valve.
+#[derive(Default)]
+struct ValveCounter {
+    open: bool,
+    actuations: u32,          // Total state changes this session
+    opens: VecDeque<Instant>, // Open commands inside the duty window
+}
+
+impl ValveCounter {
+    fn prune(&mut self, now: Instant) {
+        while let Some(&front) = self.opens.front() {
+            if now.duration_since(front) > DUTY_WINDOW {
+                self.opens.pop_front();
+            } else {
+                break;
+            }
+        }
+    }
+
+    fn record(&mut self, open: bool, now: Instant) {
+        if open == self.open {
+            return;
+        }
+        self.open = open;
+        self.actuations += 1;
+        if open {
+            self.opens.push_back(now);
+        }
+    }
+}
+
+/// Counts valve actuations and enforces a cycles-per-minute duty limit.
+///
+/// A cycle is counted each time a valve is opened. Closing is never refused,
+/// so the guard can't keep a valve open that the operator wants shut.
+pub struct DutyCycleGuard {
+    max_cycles_per_minute: usize,
+    fuel: ValveCounter,
+    oxi: ValveCounter,
+    pub override_limit: bool,
+}
+
+impl DutyCycleGuard {
+    /// Creates a new guard with the given cycles-per-minute limit.
+    pub fn new(max_cycles_per_minute: usize) -> Self {
+        Self {
+            max_cycles_per_minute,
+            fuel: ValveCounter::default(),
+            oxi: ValveCounter::default(),
+            override_limit: false,
+        }
+    }
+
+    /// Checks a requested valve state against the duty limit and records it
+    /// if accepted. Returns a description of the violation if refused.
+    pub fn request(&mut self, fuel_open: bool, oxi_open: bool) -> Result<(), String> {
+        let now = Instant::now();
+        self.fuel.prune(now);
+        self.oxi.prune(now);
+
+        if !self.override_limit {
+            for (name, counter, open) in [
+                ("Fuel", &self.fuel, fuel_open),
+                ("Oxidizer", &self.oxi, oxi_open),
+            ] {
+                if open && !counter.open && counter.opens.len() >= self.max_cycles_per_minute {
+                    return Err(format!(
+                        "{} valve duty limit reached ({} cycles/min)",
+                        name, self.max_cycles_per_minute
+                    ));
+                }
+            }
+        }
+
+        self.fuel.record(fuel_open, now);
+        self.oxi.record(oxi_open, now);
+        Ok(())
+    }
+
+    /// Total actuations this session as (fuel, oxi).
+    pub fn actuations(&self) -> (u32, u32) {
+        (self.fuel.actuations, self.oxi.actuations)
+    }
+
+    /// Cycles inside the current duty window as (fuel, oxi).
+    pub fn cycles_last_minute(&self) -> (usize, usize) {
+        (self.fuel.opens.len(), self.oxi.opens.len())
+    }
+
+    pub fn max_cycles_per_minute(&self) -> usize {
+        self.max_cycles_per_minute
+    }
+
+    /// Changes the limit, keeping the cycles already counted against it.
+    pub fn set_max_cycles_per_minute(&mut self, max_cycles_per_minute: usize) {
+        self.max_cycles_per_minute = max_cycles_per_minute;
+    }
+}
+
+/// Servo angles the firmware reports as the desired position of a closed and a
+/// fully open valve.
+pub const SERVO_CLOSED_DEG: i32 = 180;
+pub const SERVO_OPEN_DEG: i32 = 115;
+
+/// Valve opening in percent for a reported servo angle.
+pub fn position_percent(servo_deg: i32) -> f64 {
+    (SERVO_CLOSED_DEG - servo_deg) as f64 * 100.0 / (SERVO_CLOSED_DEG - SERVO_OPEN_DEG) as f64
+}
+
+/// Servo angle for a valve opening in percent.
+pub fn servo_angle(percent: f64) -> i32 {
+    let span = (SERVO_CLOSED_DEG - SERVO_OPEN_DEG) as f64;
+    SERVO_CLOSED_DEG - (percent.clamp(0.0, 100.0) * span / 100.0).round() as i32
+}
+
+/// Commanded valve positions in percent, ramped toward the operator's targets.
+///
+/// A closed valve holds at 0% and ramps up to its target once opened. Closing
+/// is never ramped, so a close or abort takes effect at once.
+#[derive(Debug, Clone)]
+pub struct PositionRamp {
+    pub target: (f64, f64),
+    commanded: (f64, f64),
+    rate_pct_per_s: f64,
+    last_step: Option<Instant>,
+}
+
+impl PositionRamp {
+    /// Starts with both valves targeting fully open, as plain open commands do.
+    pub fn new(rate_pct_per_s: f64) -> Self {
+        Self {
+            target: (100.0, 100.0),
+            commanded: (0.0, 0.0),
+            rate_pct_per_s,
+            last_step: None,
+        }
+    }
+
+    pub fn set_rate(&mut self, rate_pct_per_s: f64) {
+        self.rate_pct_per_s = rate_pct_per_s;
+    }
+
+    /// Moves each open valve toward its target by at most the ramp rate since
+    /// the last step, and returns the positions to command as (fuel, oxi).
+    pub fn step(&mut self, fuel_open: bool, oxi_open: bool, now: Instant) -> (f64, f64) {
+        let elapsed = self
+            .last_step
+            .map_or(Duration::ZERO, |last| now.duration_since(last));
+        self.last_step = Some(now);
+        // A NaN or negative rate holds the positions rather than panicking in clamp
+        let max_step = (self.rate_pct_per_s * elapsed.as_secs_f64()).max(0.0);
+        let ramp = |commanded: f64, target: f64, open: bool| {
+            if open {
+                commanded + (target - commanded).clamp(-max_step, max_step)
+            } else {
+                0.0
+            }
+        };
+        self.commanded = (
+            ramp(self.commanded.0, self.target.0, fuel_open),
+            ramp(self.commanded.1, self.target.1, oxi_open),
+        );
+        self.commanded
+    }
+
+    /// Positions sent with the latest command as (fuel, oxi).
+    pub fn commanded(&self) -> (f64, f64) {
+        self.commanded
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn duty_limit_refuses_opens_but_never_closes() {
+        let mut guard = DutyCycleGuard::new(2);
+        for _ in 0..2 {
+            guard.request(true, false).unwrap();
+            guard.request(false, false).unwrap();
+        }
+        assert_eq!(guard.cycles_last_minute(), (2, 0));
+        assert!(guard.request(true, false).is_err());
+        // The oxidizer valve has its own count
+        guard.request(false, true).unwrap();
+        assert!(guard.request(true, true).is_err());
+        guard.request(false, false).unwrap();
+        assert_eq!(guard.actuations(), (4, 2));
+
+        guard.override_limit = true;
+        guard.request(true, false).unwrap();
+        guard.override_limit = false;
+        // Already open, so holding it open isn't another cycle
+        guard.request(true, false).unwrap();
+        guard.request(false, false).unwrap();
+        assert_eq!(guard.cycles_last_minute(), (3, 1));
+    }
+
+    #[test]
+    fn changing_the_duty_limit_keeps_the_count() {
+        let mut guard = DutyCycleGuard::new(2);
+        for _ in 0..2 {
+            guard.request(true, false).unwrap();
+            guard.request(false, false).unwrap();
+        }
+        guard.set_max_cycles_per_minute(3);
+        guard.request(true, false).unwrap();
+        guard.request(false, false).unwrap();
+        guard.set_max_cycles_per_minute(1);
+        assert!(guard.request(true, false).is_err());
+        assert_eq!(guard.cycles_last_minute(), (3, 0));
+    }
+
+    #[test]
+    fn bad_ramp_rates_hold_positions() {
+        let start = Instant::now();
+        for rate in [-10.0, f64::NAN] {
+            let mut ramp = PositionRamp::new(rate);
+            ramp.step(true, true, start);
+            let positions = ramp.step(true, true, start + Duration::from_secs(1));
+            assert_eq!(positions, (0.0, 0.0), "{}", rate);
+        }
+        let mut ramp = PositionRamp::new(f64::INFINITY);
+        ramp.step(true, false, start);
+        assert_eq!(
+            ramp.step(true, false, start + Duration::from_millis(10)),
+            (100.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,454 @@
+//! The operator's arrangement of the dashboard: which plots, in what order,
+//! showing which channels, and how tall each row is.
+
+use eframe::egui;
+use ksi_telemetry::data_log;
+use serde::{Deserialize, Serialize};
+
+use super::{DashboardWidget, Series, TimeSeriesPlot, XyPlot, PRESSURE_COLORS};
+use crate::config::Settings;
+use crate::frozen::Channel;
+use crate::query::Quantity;
+
+const ORANGE: egui::Color32 = egui::Color32::from_rgb(255, 140, 0);
+// Tallest row the editor allows
+const MAX_ROW_HEIGHT: f32 = 1200.0;
+
+/// Subsystem a channel belongs to, for grouping the channel lists.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelGroup {
+    Fuel,
+    Oxidizer,
+    #[default]
+    Structure,
+    Environment,
+    /// Flow controller internals and the auxiliary relays
+    Stand,
+}
+
+impl ChannelGroup {
+    pub const ALL: [ChannelGroup; 5] = [
+        ChannelGroup::Fuel,
+        ChannelGroup::Oxidizer,
+        ChannelGroup::Structure,
+        ChannelGroup::Environment,
+        ChannelGroup::Stand,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ChannelGroup::Fuel => "Fuel side",
+            ChannelGroup::Oxidizer => "Oxidizer side",
+            ChannelGroup::Structure => "Structure",
+            ChannelGroup::Environment => "Environment",
+            ChannelGroup::Stand => "Stand control",
+        }
+    }
+}
+
+/// Plot panels in reading order, filled into rows `columns` wide.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DashboardLayout {
+    pub columns: usize,
+    /// Plot height per row in points; 0, or a row past the end, keeps a 2:1 aspect
+    pub row_heights: Vec<f32>,
+    pub panels: Vec<PanelLayout>,
+}
+
+/// One plot and the channels it shows.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PanelLayout {
+    pub title: String,
+    /// Data log column names, e.g. "flow_rate_fuel" or "pressure_1"
+    pub channels: Vec<String>,
+    pub legend: bool,
+    /// Channel the others are plotted against instead of time, for an XY plot
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x_channel: Option<String>,
+}
+
+impl Default for PanelLayout {
+    fn default() -> Self {
+        Self {
+            title: "New Plot".to_string(),
+            channels: Vec::new(),
+            legend: true,
+            x_channel: None,
+        }
+    }
+}
+
+impl Default for DashboardLayout {
+    fn default() -> Self {
+        Self::standard(0)
+    }
+}
+
+impl DashboardLayout {
+    /// The built-in dashboard, with a pressure panel when channels are configured.
+    pub fn standard(pressure_channels: usize) -> Self {
+        let panel = |title: &str, channels: &[&str]| PanelLayout {
+            title: title.to_string(),
+            channels: channels.iter().map(|c| c.to_string()).collect(),
+            legend: true,
+            x_channel: None,
+        };
+        let mut panels = vec![
+            panel("Flow Rates", &["flow_rate_fuel", "flow_rate_oxi"]),
+            panel("Pulse Counts", &["pulse_count_fuel", "pulse_count_oxi"]),
+            PanelLayout {
+                legend: false,
+                ..panel("Valve States", &["fuel_valve_open", "oxi_valve_open"])
+            },
+            panel(
+                "Desired Positions",
+                &["desired_pos_fuel", "desired_pos_oxi"],
+            ),
+            panel(
+                "Controller",
+                &[
+                    "controller_error",
+                    "controller_integrator",
+                    "controller_output",
+                ],
+            ),
+            panel(
+                "Temperatures",
+                &[
+                    "temperature_nozzle",
+                    "temperature_tank",
+                    "temperature_ambient",
+                ],
+            ),
+            panel("Thrust", &["thrust"]),
+        ];
+        if pressure_channels > 0 {
+            panels.push(PanelLayout {
+                title: "Pressures".to_string(),
+                channels: (0..pressure_channels)
+                    .map(data_log::pressure_column)
+                    .collect(),
+                legend: true,
+                x_channel: None,
+            });
+        }
+        Self {
+            columns: 2,
+            row_heights: Vec::new(),
+            panels,
+        }
+    }
+
+    /// Builds the plots, skipping channels this configuration doesn't have.
+    ///
+    /// A panel whose X channel is missing falls back to plotting against time.
+    pub fn build(&self, settings: &Settings) -> Vec<Box<dyn DashboardWidget>> {
+        let columns = self.columns.max(1);
+        self.panels
+            .iter()
+            .enumerate()
+            .map(|(index, panel)| {
+                let height = self
+                    .row_heights
+                    .get(index / columns)
+                    .copied()
+                    .filter(|&height| height > 0.0);
+                let channels = panel
+                    .channels
+                    .iter()
+                    .filter_map(|key| channel_series(key, settings));
+                let x = panel
+                    .x_channel
+                    .as_deref()
+                    .and_then(|key| channel_series(key, settings));
+                if let Some(x) = x {
+                    let mut plot = channels.fold(XyPlot::new(&panel.title, x), XyPlot::series);
+                    if !panel.legend {
+                        plot = plot.without_legend();
+                    }
+                    if let Some(height) = height {
+                        plot = plot.height(height);
+                    }
+                    return Box::new(plot) as Box<dyn DashboardWidget>;
+                }
+                let mut plot =
+                    channels.fold(TimeSeriesPlot::new(&panel.title), TimeSeriesPlot::series);
+                if !panel.legend {
+                    plot = plot.without_legend();
+                }
+                if let Some(height) = height {
+                    plot = plot.height(height);
+                }
+                Box::new(plot)
+            })
+            .collect()
+    }
+
+    /// Editor controls for panels, their channels and order, and row heights.
+    pub fn edit(&mut self, ui: &mut egui::Ui, settings: &Settings) {
+        let channels = channel_names(settings);
+        let groups = grouped_channel_names(settings);
+        ui.horizontal(|ui| {
+            ui.label("Columns");
+            ui.add(egui::DragValue::new(&mut self.columns).range(1..=4));
+        });
+        ui.separator();
+
+        let mut move_up = None;
+        let mut remove = None;
+        let count = self.panels.len();
+        egui::ScrollArea::vertical()
+            .max_height(400.0)
+            .show(ui, |ui| {
+                for (index, panel) in self.panels.iter_mut().enumerate() {
+                    ui.push_id(index, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.text_edit_singleline(&mut panel.title);
+                            if ui.add_enabled(index > 0, egui::Button::new("Up")).clicked() {
+                                move_up = Some(index);
+                            }
+                            if ui
+                                .add_enabled(index + 1 < count, egui::Button::new("Down"))
+                                .clicked()
+                            {
+                                move_up = Some(index + 1);
+                            }
+                            if ui.button("Remove").clicked() {
+                                remove = Some(index);
+                            }
+                            ui.checkbox(&mut panel.legend, "Legend");
+                            let x_name = panel
+                                .x_channel
+                                .as_ref()
+                                .and_then(|x| channels.iter().find(|(key, _)| key == x))
+                                .map_or("Time", |(_, name)| name.as_str());
+                            egui::ComboBox::from_label("X axis")
+                                .selected_text(x_name)
+                                .show_ui(ui, |ui| {
+                                    ui.selectable_value(&mut panel.x_channel, None, "Time");
+                                    for (group, group_channels) in &groups {
+                                        ui.separator();
+                                        ui.weak(group.name());
+                                        for (key, name) in group_channels {
+                                            ui.selectable_value(
+                                                &mut panel.x_channel,
+                                                Some(key.clone()),
+                                                name,
+                                            );
+                                        }
+                                    }
+                                });
+                        });
+                        let heading = format!("Channels ({})", panel.channels.len());
+                        egui::CollapsingHeader::new(heading).show(ui, |ui| {
+                            for (group, group_channels) in &groups {
+                                let shown_count = group_channels
+                                    .iter()
+                                    .filter(|(key, _)| panel.channels.contains(key))
+                                    .count();
+                                let heading = format!(
+                                    "{} ({}/{})",
+                                    group.name(),
+                                    shown_count,
+                                    group_channels.len()
+                                );
+                                egui::CollapsingHeader::new(heading)
+                                    .id_salt(group.name())
+                                    .show(ui, |ui| {
+                                        for (key, name) in group_channels {
+                                            let mut shown = panel.channels.contains(key);
+                                            if ui.checkbox(&mut shown, name).changed() {
+                                                if shown {
+                                                    panel.channels.push(key.clone());
+                                                } else {
+                                                    panel.channels.retain(|k| k != key);
+                                                }
+                                            }
+                                        }
+                                    });
+                            }
+                        });
+                    });
+                }
+            });
+        if let Some(index) = move_up {
+            self.panels.swap(index - 1, index);
+        }
+        if let Some(index) = remove {
+            self.panels.remove(index);
+        }
+        if ui.button("Add Plot").clicked() {
+            self.panels.push(PanelLayout::default());
+        }
+        ui.separator();
+
+        let rows = self.panels.len().div_ceil(self.columns.max(1));
+        self.row_heights.resize(rows, 0.0);
+        ui.label("Row heights in points (0 keeps a 2:1 aspect)");
+        ui.horizontal_wrapped(|ui| {
+            for (row, height) in self.row_heights.iter_mut().enumerate() {
+                ui.label(format!("{}:", row + 1));
+                ui.add(egui::DragValue::new(height).range(0.0..=MAX_ROW_HEIGHT));
+            }
+        });
+    }
+}
+
+/// Every channel a panel can show, as (key, display name).
+pub fn channel_names(settings: &Settings) -> Vec<(String, String)> {
+    data_log::COLUMNS
+        .iter()
+        .map(|key| key.to_string())
+        .chain((0..settings.all_pressure_channels().len()).map(data_log::pressure_column))
+        .filter_map(|key| {
+            let name = channel_series(&key, settings)?.name().to_string();
+            Some((key, name))
+        })
+        .collect()
+}
+
+/// Every channel a panel can show, as (key, display name), under its subsystem.
+/// Subsystems without channels in this configuration are left out.
+pub fn grouped_channel_names(settings: &Settings) -> Vec<(ChannelGroup, Vec<(String, String)>)> {
+    let channels = channel_names(settings);
+    ChannelGroup::ALL
+        .into_iter()
+        .map(|group| {
+            let members: Vec<(String, String)> = channels
+                .iter()
+                .filter(|(key, _)| channel_group(key, settings) == group)
+                .cloned()
+                .collect();
+            (group, members)
+        })
+        .filter(|(_, members)| !members.is_empty())
+        .collect()
+}
+
+/// The subsystem of a channel key; pressures are in the group set for them.
+pub fn channel_group(key: &str, settings: &Settings) -> ChannelGroup {
+    match key {
+        "flow_rate_fuel" | "pulse_count_fuel" | "desired_pos_fuel" | "fuel_valve_open" => {
+            ChannelGroup::Fuel
+        }
+        "flow_rate_oxi" | "pulse_count_oxi" | "desired_pos_oxi" | "oxi_valve_open" => {
+            ChannelGroup::Oxidizer
+        }
+        "temperature_ambient" => ChannelGroup::Environment,
+        _ if key.starts_with("controller_") || key.starts_with("relay_") => ChannelGroup::Stand,
+        _ => {
+            let channels = settings.all_pressure_channels();
+            (0..channels.len())
+                .find(|&i| data_log::pressure_column(i) == key)
+                .map_or(ChannelGroup::Structure, |i| channels[i].group)
+        }
+    }
+}
+
+/// The series for a channel key, with its configured limits and redlines.
+pub fn channel_series(key: &str, settings: &Settings) -> Option<Series> {
+    let red = egui::Color32::RED;
+    let blue = egui::Color32::BLUE;
+    let series = match key {
+        "flow_rate_fuel" => {
+            let calibration = &settings.flow_calibration.fuel;
+            let name = format!("Fuel Flow Rate ({})", calibration.unit.label());
+            Series::new(&name, red, |dp| dp.flow_rate_fuel)
+                .channel(Channel::FlowFuel)
+                .limits(settings.limits_for(Quantity::FlowFuel))
+                .scale(calibration.display_scale())
+        }
+        "flow_rate_oxi" => {
+            let calibration = &settings.flow_calibration.oxi;
+            let name = format!("Oxidizer Flow Rate ({})", calibration.unit.label());
+            Series::new(&name, blue, |dp| dp.flow_rate_oxi)
+                .channel(Channel::FlowOxi)
+                .limits(settings.limits_for(Quantity::FlowOxi))
+                .scale(calibration.display_scale())
+        }
+        "pulse_count_fuel" => Series::new("Fuel Pulse Count", red, |dp| dp.pulse_count_fuel as f64)
+            .channel(Channel::PulseFuel)
+            .limits(settings.limits_for(Quantity::PulseFuel)),
+        "pulse_count_oxi" => {
+            Series::new("Oxidizer Pulse Count", blue, |dp| dp.pulse_count_oxi as f64)
+                .channel(Channel::PulseOxi)
+                .limits(settings.limits_for(Quantity::PulseOxi))
+        }
+        "fuel_valve_open" => Series::new("Fuel Valve Open", red, |dp| {
+            if dp.fuel_valve_open {
+                1.0
+            } else {
+                0.0
+            }
+        }),
+        "oxi_valve_open" => Series::new("Oxidizer Valve Open", blue, |dp| {
+            if dp.oxi_valve_open {
+                1.0
+            } else {
+                0.0
+            }
+        }),
+        "relay_lighting" => Series::new("Stand Lighting", egui::Color32::YELLOW, |dp| {
+            if dp.relays.lighting {
+                1.0
+            } else {
+                0.0
+            }
+        }),
+        "relay_camera_power" => Series::new("Camera Power", egui::Color32::GREEN, |dp| {
+            if dp.relays.camera_power {
+                1.0
+            } else {
+                0.0
+            }
+        }),
+        "relay_beacon" => Series::new("Warning Beacon", ORANGE, |dp| {
+            if dp.relays.beacon {
+                1.0
+            } else {
+                0.0
+            }
+        }),
+        "desired_pos_fuel" => Series::new("Desired Position Fuel", red, |dp| {
+            dp.desired_pos_fuel as f64
+        }),
+        "desired_pos_oxi" => Series::new("Desired Position Oxidizer", blue, |dp| {
+            dp.desired_pos_oxi as f64
+        }),
+        "controller_error" => Series::optional("Error", red, |dp| dp.controller.map(|c| c.error)),
+        "controller_integrator" => Series::optional("Integrator", egui::Color32::YELLOW, |dp| {
+            dp.controller.map(|c| c.integrator)
+        }),
+        "controller_output" => Series::optional("Output", egui::Color32::GREEN, |dp| {
+            dp.controller.map(|c| c.output)
+        }),
+        "temperature_nozzle" => Series::optional("Nozzle (°C)", ORANGE, |dp| {
+            dp.temperatures.map(|t| t.nozzle)
+        })
+        .redline(settings.redline_nozzle_c)
+        .limits(settings.limits_for(Quantity::NozzleTemp)),
+        "temperature_tank" => {
+            Series::optional("Tank (°C)", blue, |dp| dp.temperatures.map(|t| t.tank))
+                .redline(settings.redline_tank_c)
+                .limits(settings.limits_for(Quantity::TankTemp))
+        }
+        "temperature_ambient" => Series::optional("Ambient (°C)", egui::Color32::GREEN, |dp| {
+            dp.temperatures.map(|t| t.ambient)
+        })
+        .redline(settings.redline_ambient_c),
+        "thrust" => Series::optional("Thrust (N)", ORANGE, |dp| dp.thrust)
+            .limits(settings.limits_for(Quantity::Thrust))
+            .annotate_peak_and_average(),
+        _ => {
+            let channels = settings.all_pressure_channels();
+            let index = (0..channels.len()).find(|&i| data_log::pressure_column(i) == key)?;
+            let channel = &channels[index];
+            let name = format!("{} ({})", channel.name, channel.unit);
+            Series::pressure(&name, PRESSURE_COLORS[index % PRESSURE_COLORS.len()], index)
+        }
+    };
+    Some(series)
+}
@@ -0,0 +1,141 @@
+use crate::clock::MissionClock;
+use crate::config::Settings;
+use crate::events::EventMarker;
+use crate::frozen::FrozenChannelDetector;
+use crate::query::PlotFocus;
+use crate::EngineData;
+use ksi_telemetry::EngineDataPoint;
+use std::path::Path;
+
+mod layout;
+mod svg;
+mod time_series;
+mod xy_plot;
+
+pub use layout::{
+    channel_names, channel_series, grouped_channel_names, ChannelGroup, DashboardLayout,
+};
+pub use time_series::{Series, TimeSeriesPlot};
+pub use xy_plot::XyPlot;
+
+/// Read-only state handed to every widget each frame.
+pub struct WidgetContext<'a> {
+    pub engine_data: &'a EngineData,
+    pub frozen_channels: &'a FrozenChannelDetector,
+    pub clock: &'a MissionClock,
+    // Time range to jump the plots to, set by the find tool
+    pub focus: Option<PlotFocus>,
+    // Operator markers and annotations, drawn as vertical lines on every plot
+    pub markers: &'a [EventMarker],
+    // Firmware time under the pointer on any plot last frame, drawn on all of them
+    pub cursor: Option<f64>,
+    // Span of history the plots show
+    pub window: TimeWindow,
+    // Session folder, where plot data is exported
+    pub log_dir: &'a Path,
+}
+
+/// Records the firmware time a plot is hovered at, for every plot's cursor next frame.
+pub fn set_cursor(ctx: &egui::Context, time: f64) {
+    ctx.data_mut(|data| data.insert_temp(egui::Id::new("plot_cursor"), time));
+}
+
+/// Takes the hovered time recorded this frame, if a plot was hovered.
+pub fn take_cursor(ctx: &egui::Context) -> Option<f64> {
+    ctx.data_mut(|data| data.remove_temp(egui::Id::new("plot_cursor")))
+}
+
+/// How much recent history the plots show.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TimeWindow {
+    Seconds10,
+    Seconds60,
+    Minutes5,
+    All,
+}
+
+impl TimeWindow {
+    pub const ALL: [TimeWindow; 4] = [
+        TimeWindow::Seconds10,
+        TimeWindow::Seconds60,
+        TimeWindow::Minutes5,
+        TimeWindow::All,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TimeWindow::Seconds10 => "10 s",
+            TimeWindow::Seconds60 => "60 s",
+            TimeWindow::Minutes5 => "5 min",
+            TimeWindow::All => "All",
+        }
+    }
+
+    /// Span in firmware ms; None shows everything kept.
+    pub fn duration_ms(self) -> Option<f64> {
+        match self {
+            TimeWindow::Seconds10 => Some(10_000.0),
+            TimeWindow::Seconds60 => Some(60_000.0),
+            TimeWindow::Minutes5 => Some(300_000.0),
+            TimeWindow::All => None,
+        }
+    }
+}
+
+/// A self-contained display registered with the dashboard.
+///
+/// New visualizations implement this trait in their own module and are
+/// registered in `dashboard_widgets()` instead of editing update().
+pub trait DashboardWidget {
+    /// Title shown above the widget; also used as its egui id.
+    fn title(&self) -> &str;
+
+    /// Draws the widget into its dashboard cell.
+    fn show(&mut self, ui: &mut egui::Ui, ctx: &WidgetContext);
+
+    /// The widget as drawn last frame, as a standalone SVG document, if it can be.
+    fn svg(&self) -> Option<String> {
+        None
+    }
+
+    /// Named values at a data point, listed in the linked cursor readout.
+    fn readout(&self, _dp: &EngineDataPoint) -> Vec<(String, f64)> {
+        Vec::new()
+    }
+}
+
+// Trace colors for pressure channels, reused when there are more channels
+const PRESSURE_COLORS: [egui::Color32; 6] = [
+    egui::Color32::RED,
+    egui::Color32::BLUE,
+    egui::Color32::GREEN,
+    egui::Color32::YELLOW,
+    egui::Color32::LIGHT_BLUE,
+    egui::Color32::from_rgb(255, 140, 0),
+];
+
+/// The dashboard plots, as arranged in the settings or the standard layout.
+pub fn dashboard_widgets(settings: &Settings) -> Vec<Box<dyn DashboardWidget>> {
+    effective_layout(settings).build(settings)
+}
+
+/// The saved layout, or the standard one when none has been saved.
+pub fn effective_layout(settings: &Settings) -> DashboardLayout {
+    settings
+        .layout
+        .clone()
+        .unwrap_or_else(|| DashboardLayout::standard(settings.all_pressure_channels().len()))
+}
+
+/// Draws a widget heading, badged with any channels that have stopped updating.
+pub fn widget_heading(ui: &mut egui::Ui, title: &str, frozen: &[&str]) {
+    ui.horizontal(|ui| {
+        ui.heading(title);
+        if !frozen.is_empty() {
+            ui.colored_label(
+                egui::Color32::YELLOW,
+                format!("STALE: {}", frozen.join(", ")),
+            );
+        }
+    });
+}
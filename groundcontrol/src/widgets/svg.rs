@@ -0,0 +1,160 @@
+//! Standalone SVG rendering of a time series plot, for test reports.
+
+use egui_plot::PlotBounds;
+
+const WIDTH: f64 = 800.0;
+const HEIGHT: f64 = 400.0;
+// Space around the plot area for the title, tick labels, and axis label
+const LEFT: f64 = 70.0;
+const RIGHT: f64 = 20.0;
+const TOP: f64 = 30.0;
+const BOTTOM: f64 = 45.0;
+const TICKS: f64 = 6.0;
+
+/// What a plot drew last frame, in plot coordinates.
+pub struct DrawnView {
+    pub x_label: String,
+    pub bounds: PlotBounds,
+    pub traces: Vec<(String, egui::Color32, Vec<[f64; 2]>)>,
+    // Limit and redline levels
+    pub levels: Vec<(f64, egui::Color32)>,
+    pub markers: Vec<(f64, String)>,
+}
+
+/// Renders a plot on a white background with axes, ticks, and a legend.
+pub fn render(title: &str, view: &DrawnView) -> String {
+    let [x_min, y_min] = view.bounds.min();
+    let [x_max, y_max] = view.bounds.max();
+    let (plot_width, plot_height) = (WIDTH - LEFT - RIGHT, HEIGHT - TOP - BOTTOM);
+    let sx = |x: f64| LEFT + (x - x_min) / (x_max - x_min) * plot_width;
+    let sy = |y: f64| TOP + (y_max - y) / (y_max - y_min) * plot_height;
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{WIDTH}\" height=\"{HEIGHT}\" \
+         viewBox=\"0 0 {WIDTH} {HEIGHT}\" font-family=\"sans-serif\" font-size=\"11\">\n\
+         <rect width=\"100%\" height=\"100%\" fill=\"white\"/>\n\
+         <clipPath id=\"area\"><rect x=\"{LEFT}\" y=\"{TOP}\" width=\"{plot_width}\" \
+         height=\"{plot_height}\"/></clipPath>\n"
+    );
+    svg.push_str(&format!(
+        "<text x=\"{}\" y=\"18\" text-anchor=\"middle\" font-size=\"14\">{}</text>\n",
+        WIDTH / 2.0,
+        escape(title)
+    ));
+    for x in ticks(x_min, x_max) {
+        svg.push_str(&format!(
+            "<line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\" stroke=\"#ddd\"/>\n\
+             <text x=\"{0:.1}\" y=\"{3}\" text-anchor=\"middle\">{4}</text>\n",
+            sx(x),
+            TOP,
+            TOP + plot_height,
+            TOP + plot_height + 14.0,
+            tick_label(x, x_max - x_min)
+        ));
+    }
+    for y in ticks(y_min, y_max) {
+        svg.push_str(&format!(
+            "<line x1=\"{1}\" y1=\"{0:.1}\" x2=\"{2}\" y2=\"{0:.1}\" stroke=\"#ddd\"/>\n\
+             <text x=\"{3}\" y=\"{0:.1}\" text-anchor=\"end\" dominant-baseline=\"middle\">{4}</text>\n",
+            sy(y),
+            LEFT,
+            LEFT + plot_width,
+            LEFT - 4.0,
+            tick_label(y, y_max - y_min)
+        ));
+    }
+    svg.push_str(&format!(
+        "<rect x=\"{LEFT}\" y=\"{TOP}\" width=\"{plot_width}\" height=\"{plot_height}\" \
+         fill=\"none\" stroke=\"black\"/>\n\
+         <text x=\"{}\" y=\"{}\" text-anchor=\"middle\">{}</text>\n",
+        LEFT + plot_width / 2.0,
+        HEIGHT - 8.0,
+        escape(&view.x_label)
+    ));
+
+    svg.push_str("<g clip-path=\"url(#area)\">\n");
+    for (level, color) in &view.levels {
+        svg.push_str(&format!(
+            "<line x1=\"{0}\" y1=\"{1:.1}\" x2=\"{2}\" y2=\"{1:.1}\" stroke=\"{3}\" stroke-dasharray=\"6 3\"/>\n",
+            LEFT,
+            sy(*level),
+            LEFT + plot_width,
+            hex(*color)
+        ));
+    }
+    for (x, label) in &view.markers {
+        svg.push_str(&format!(
+            "<line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\" stroke=\"#4a90d9\" stroke-dasharray=\"4 4\"/>\n\
+             <text x=\"{3:.1}\" y=\"{4}\" fill=\"#4a90d9\">{5}</text>\n",
+            sx(*x),
+            TOP,
+            TOP + plot_height,
+            sx(*x) + 3.0,
+            TOP + 12.0,
+            escape(label)
+        ));
+    }
+    for (_, color, points) in &view.traces {
+        let path: Vec<String> = points
+            .iter()
+            .map(|[x, y]| format!("{:.1},{:.1}", sx(*x), sy(*y)))
+            .collect();
+        svg.push_str(&format!(
+            "<polyline fill=\"none\" stroke=\"{}\" stroke-width=\"1.5\" points=\"{}\"/>\n",
+            hex(*color),
+            path.join(" ")
+        ));
+    }
+    svg.push_str("</g>\n");
+
+    for (i, (name, color, _)) in view.traces.iter().enumerate() {
+        let y = TOP + 14.0 + i as f64 * 14.0;
+        svg.push_str(&format!(
+            "<text x=\"{}\" y=\"{}\" text-anchor=\"end\" fill=\"{}\">{}</text>\n",
+            LEFT + plot_width - 6.0,
+            y,
+            hex(*color),
+            escape(name)
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+/// Evenly spaced round values across a range, about TICKS of them.
+fn ticks(min: f64, max: f64) -> Vec<f64> {
+    let step = tick_step(max - min);
+    if step.is_nan() || step <= 0.0 {
+        return Vec::new();
+    }
+    let first = (min / step).ceil() as i64;
+    let last = (max / step).floor() as i64;
+    (first..=last).map(|i| i as f64 * step).collect()
+}
+
+/// A 1, 2, or 5 times a power of ten step giving about TICKS ticks over `span`.
+fn tick_step(span: f64) -> f64 {
+    let raw = span / TICKS;
+    let magnitude = 10f64.powf(raw.log10().floor());
+    [1.0, 2.0, 5.0, 10.0]
+        .into_iter()
+        .map(|m| m * magnitude)
+        .find(|&step| step >= raw)
+        .unwrap_or(raw)
+}
+
+fn tick_label(value: f64, span: f64) -> String {
+    let decimals = (-tick_step(span).log10().floor()).max(0.0) as usize;
+    format!("{:.*}", decimals, value)
+}
+
+fn hex(color: egui::Color32) -> String {
+    format!("#{:02x}{:02x}{:02x}", color.r(), color.g(), color.b())
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
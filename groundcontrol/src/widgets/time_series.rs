@@ -0,0 +1,650 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::PathBuf;
+
+use egui_plot::{
+    HLine, Legend, Line, LineStyle, Plot, PlotBounds, PlotPoint, PlotPoints, PlotUi, Points,
+    Polygon, Text, VLine,
+};
+
+use super::svg::{self, DrawnView};
+use super::{set_cursor, widget_heading, DashboardWidget, WidgetContext};
+use crate::events::csv_field;
+use crate::frozen::Channel;
+use crate::limits::ChannelLimits;
+use crate::query::Interval;
+use ksi_telemetry::{EngineDataPoint, Quality};
+
+// Smallest time shown either side of a find-tool result
+const FOCUS_MIN_MARGIN_MS: f64 = 500.0;
+// Limit decoration colors
+const WARNING_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 165, 0);
+const CRITICAL_COLOR: egui::Color32 = egui::Color32::RED;
+const BAND_COLOR: egui::Color32 = egui::Color32::from_rgba_premultiplied(0, 60, 0, 40);
+// Fraction of the peak a sample must reach to count towards the annotated average
+const AVERAGE_MIN_FRACTION_OF_PEAK: f64 = 0.05;
+// Trace color for samples flagged below good quality
+const DEGRADED_COLOR: egui::Color32 = egui::Color32::GRAY;
+const MARKER_COLOR: egui::Color32 = egui::Color32::LIGHT_BLUE;
+const CURSOR_COLOR: egui::Color32 = egui::Color32::WHITE;
+const EXPORT_DIR_NAME: &str = "exports";
+
+enum SeriesValue {
+    Always(fn(&EngineDataPoint) -> f64),
+    Optional(fn(&EngineDataPoint) -> Option<f64>), // Skips points without the channel
+    Pressure(usize),                               // Index into the pressure channels
+}
+
+/// One line on a time series plot.
+pub struct Series {
+    name: String,
+    pub(super) color: egui::Color32,
+    value: SeriesValue,
+    pub(super) channel: Option<Channel>, // Channel checked for stale data, if any
+    redline: Option<f64>,                // Values above this are drawn red
+    limits: Option<ChannelLimits>,
+    annotate: bool, // Marks the peak and the average while above AVERAGE_MIN_FRACTION_OF_PEAK
+    scale: f64,     // Displayed value per logged value
+}
+
+impl Series {
+    pub fn new(name: &str, color: egui::Color32, value: fn(&EngineDataPoint) -> f64) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            value: SeriesValue::Always(value),
+            channel: None,
+            redline: None,
+            limits: None,
+            annotate: false,
+            scale: 1.0,
+        }
+    }
+
+    /// A series for a channel that only some data points carry.
+    pub fn optional(
+        name: &str,
+        color: egui::Color32,
+        value: fn(&EngineDataPoint) -> Option<f64>,
+    ) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            value: SeriesValue::Optional(value),
+            channel: None,
+            redline: None,
+            limits: None,
+            annotate: false,
+            scale: 1.0,
+        }
+    }
+
+    /// A series for one of the configured pressure channels.
+    pub fn pressure(name: &str, color: egui::Color32, index: usize) -> Self {
+        Self {
+            name: name.to_string(),
+            color,
+            value: SeriesValue::Pressure(index),
+            channel: None,
+            redline: None,
+            limits: None,
+            annotate: false,
+            scale: 1.0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn value(&self, dp: &EngineDataPoint) -> Option<f64> {
+        match self.value {
+            SeriesValue::Always(value) => Some(value(dp)),
+            SeriesValue::Optional(value) => value(dp),
+            // NaN where another board had gone quiet; drawn as a gap
+            SeriesValue::Pressure(index) => {
+                dp.pressures.get(index).copied().filter(|p| !p.is_nan())
+            }
+        }
+        .map(|value| value * self.scale)
+    }
+
+    /// Badges the plot when this channel stops updating.
+    pub fn channel(mut self, channel: Channel) -> Self {
+        self.channel = Some(channel);
+        self
+    }
+
+    /// Marks a limit on the plot and highlights the trace wherever it is exceeded.
+    pub fn redline(mut self, redline: Option<f64>) -> Self {
+        self.redline = redline.map(|redline| redline * self.scale);
+        self
+    }
+
+    /// Displays values in another unit, `scale` per logged unit; limits and
+    /// redlines are still given in the logged unit.
+    pub fn scale(mut self, scale: f64) -> Self {
+        let factor = scale / self.scale;
+        self.redline = self.redline.map(|redline| redline * factor);
+        self.limits = self.limits.map(|limits| limits.scaled(factor));
+        self.scale = scale;
+        self
+    }
+}
+
+impl Series {
+    /// Draws configured limit lines and the acceptable band across `x_range`.
+    pub fn limits(mut self, limits: Option<ChannelLimits>) -> Self {
+        self.limits = limits.map(|limits| limits.scaled(self.scale));
+        self
+    }
+
+    /// Marks the peak value and draws the average over the samples near it, e.g. during a burn.
+    pub fn annotate_peak_and_average(mut self) -> Self {
+        self.annotate = true;
+        self
+    }
+
+    fn draw_annotations(&self, plot_ui: &mut PlotUi, points: &[[f64; 2]]) {
+        let Some(peak) = points.iter().copied().max_by(|a, b| a[1].total_cmp(&b[1])) else {
+            return;
+        };
+        let active: Vec<f64> = points
+            .iter()
+            .map(|p| p[1])
+            .filter(|&value| value >= peak[1] * AVERAGE_MIN_FRACTION_OF_PEAK)
+            .collect();
+        let average = active.iter().sum::<f64>() / active.len() as f64;
+        plot_ui.points(Points::new(vec![peak]).radius(4.0).color(self.color));
+        plot_ui.text(
+            Text::new(
+                PlotPoint::new(peak[0], peak[1]),
+                format!("Peak {:.1}", peak[1]),
+            )
+            .anchor(egui::Align2::CENTER_BOTTOM),
+        );
+        plot_ui.hline(
+            HLine::new(average)
+                .color(self.color)
+                .style(LineStyle::dotted_loose())
+                .name(format!("Average {:.1}", average)),
+        );
+    }
+
+    /// Limit and redline levels with their colors.
+    pub(super) fn levels(&self) -> Vec<(f64, egui::Color32)> {
+        let mut levels: Vec<(f64, egui::Color32)> = self
+            .limits
+            .iter()
+            .flat_map(|limits| {
+                [
+                    (limits.warning_low, WARNING_COLOR),
+                    (limits.warning_high, WARNING_COLOR),
+                    (limits.critical_low, CRITICAL_COLOR),
+                    (limits.critical_high, CRITICAL_COLOR),
+                ]
+            })
+            .filter_map(|(value, color)| Some((value?, color)))
+            .collect();
+        levels.extend(self.redline.map(|redline| (redline, egui::Color32::RED)));
+        levels
+    }
+
+    pub(super) fn draw_limits(&self, plot_ui: &mut PlotUi, x_range: Option<(f64, f64)>) {
+        let Some(limits) = &self.limits else {
+            return;
+        };
+        if let (Some([min, max]), Some((start, end))) = (limits.band, x_range) {
+            plot_ui.polygon(
+                Polygon::new(PlotPoints::from(vec![
+                    [start, min],
+                    [end, min],
+                    [end, max],
+                    [start, max],
+                ]))
+                .fill_color(BAND_COLOR)
+                .stroke(egui::Stroke::NONE)
+                .allow_hover(false),
+            );
+        }
+        for (value, color) in [
+            (limits.warning_low, WARNING_COLOR),
+            (limits.warning_high, WARNING_COLOR),
+            (limits.critical_low, CRITICAL_COLOR),
+            (limits.critical_high, CRITICAL_COLOR),
+        ] {
+            if let Some(value) = value {
+                plot_ui.hline(
+                    HLine::new(value)
+                        .color(color)
+                        .style(LineStyle::dashed_dense()),
+                );
+            }
+        }
+    }
+}
+
+/// Runs of consecutive points above the redline, each drawn as its own red segment.
+fn exceeded_runs(points: &[[f64; 2]], redline: f64) -> Vec<Vec<[f64; 2]>> {
+    let mut runs: Vec<Vec<[f64; 2]>> = Vec::new();
+    let mut in_run = false;
+    for point in points {
+        if point[1] > redline {
+            if !in_run {
+                runs.push(Vec::new());
+                in_run = true;
+            }
+            runs.last_mut().unwrap().push(*point);
+        } else {
+            in_run = false;
+        }
+    }
+    runs
+}
+
+/// Splits points into runs of good and degraded samples. Each run starts at the
+/// last point of the one before, so the trace stays continuous.
+fn quality_runs(samples: &[([f64; 2], bool)]) -> Vec<(bool, Vec<[f64; 2]>)> {
+    let mut runs: Vec<(bool, Vec<[f64; 2]>)> = Vec::new();
+    for &(point, degraded) in samples {
+        match runs.last_mut() {
+            Some((run_degraded, run)) if *run_degraded == degraded => run.push(point),
+            last => {
+                let start = last.and_then(|(_, run)| run.last().copied());
+                runs.push((degraded, start.into_iter().chain([point]).collect()));
+            }
+        }
+    }
+    runs
+}
+
+/// Thins samples to the lowest and highest of each of `buckets` equal spans of
+/// time, in order, so a trace keeps its peaks at any sample rate while drawing a
+/// bounded number of points. A bucket containing any degraded sample is drawn
+/// degraded.
+fn decimate(samples: &[([f64; 2], bool)], buckets: usize) -> Vec<([f64; 2], bool)> {
+    let (Some(first), Some(last)) = (samples.first(), samples.last()) else {
+        return Vec::new();
+    };
+    let (start, span) = (first.0[0], last.0[0] - first.0[0]);
+    if samples.len() <= buckets * 2 || span <= 0.0 {
+        return samples.to_vec();
+    }
+    let bucket_of = |x: f64| (((x - start) / span * buckets as f64) as usize).min(buckets - 1);
+    let mut decimated = Vec::with_capacity(buckets * 2);
+    let mut rest = samples;
+    while let Some(&(point, _)) = rest.first() {
+        let bucket = bucket_of(point[0]);
+        let end = rest
+            .iter()
+            .position(|(point, _)| bucket_of(point[0]) != bucket)
+            .unwrap_or(rest.len());
+        let (run, next) = rest.split_at(end);
+        let degraded = run.iter().any(|&(_, degraded)| degraded);
+        let by_value = |&a: &usize, &b: &usize| run[a].0[1].total_cmp(&run[b].0[1]);
+        let low = (0..run.len()).min_by(by_value).unwrap_or(0);
+        let high = (0..run.len()).max_by(by_value).unwrap_or(0);
+        let mut picks = vec![low.min(high), low.max(high)];
+        picks.dedup();
+        decimated.extend(picks.into_iter().map(|i| (run[i].0, degraded)));
+        rest = next;
+    }
+    decimated
+}
+
+/// A plot of one or more channels against firmware time.
+pub struct TimeSeriesPlot {
+    title: String,
+    series: Vec<Series>,
+    legend: bool,
+    // Fixed plot height in points; otherwise a 2:1 aspect
+    height: Option<f32>,
+    // Last find-tool focus applied, so the view is only moved once per jump
+    applied_focus: Option<u64>,
+    // Outcome of the last data export, shown under the heading
+    export_status: Option<Result<String, String>>,
+    // What was drawn last frame, for SVG export
+    last_view: Option<DrawnView>,
+}
+
+impl TimeSeriesPlot {
+    pub fn new(title: &str) -> Self {
+        Self {
+            title: title.to_string(),
+            series: Vec::new(),
+            legend: true,
+            height: None,
+            applied_focus: None,
+            export_status: None,
+            last_view: None,
+        }
+    }
+
+    pub fn series(mut self, series: Series) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    pub fn without_legend(mut self) -> Self {
+        self.legend = false;
+        self
+    }
+
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = Some(height);
+        self
+    }
+}
+
+impl TimeSeriesPlot {
+    /// Bounds framing an interval with some margin around it.
+    fn focus_bounds<'a>(
+        &self,
+        data_points: impl IntoIterator<Item = &'a EngineDataPoint>,
+        interval: Interval,
+        ctx: &WidgetContext,
+    ) -> PlotBounds {
+        let margin = ((interval.end - interval.start) * 0.2).max(FOCUS_MIN_MARGIN_MS);
+        let (start, end) = (interval.start - margin, interval.end + margin);
+        let (mut y_min, mut y_max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for dp in data_points {
+            if dp.time < start || dp.time > end {
+                continue;
+            }
+            for value in self.series.iter().filter_map(|series| series.value(dp)) {
+                y_min = y_min.min(value);
+                y_max = y_max.max(value);
+            }
+        }
+        if y_min > y_max {
+            (y_min, y_max) = (0.0, 1.0);
+        }
+        let y_margin = ((y_max - y_min) * 0.1).max(0.5);
+        PlotBounds::from_min_max(
+            [ctx.clock.plot_x(start), y_min - y_margin],
+            [ctx.clock.plot_x(end), y_max + y_margin],
+        )
+    }
+}
+
+impl TimeSeriesPlot {
+    /// Writes data points to a CSV in the session's exports folder, one column per
+    /// series, after comment lines describing what was selected.
+    fn export(
+        &self,
+        data_points: &[&EngineDataPoint],
+        ctx: &WidgetContext,
+        description: &[String],
+    ) -> Result<PathBuf, String> {
+        let dir = ctx.log_dir.join(EXPORT_DIR_NAME);
+        let name: String = self
+            .title
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() {
+                    c.to_ascii_lowercase()
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        let path = dir.join(format!(
+            "{}_{}.csv",
+            name,
+            chrono::Local::now().format("%H-%M-%S")
+        ));
+        let mut csv: String = description
+            .iter()
+            .map(|line| format!("# {}\n", line))
+            .collect();
+        csv.push_str("time_ms");
+        for series in &self.series {
+            csv.push_str(&format!(",{}", csv_field(&series.name)));
+        }
+        csv.push('\n');
+        for dp in data_points {
+            csv.push_str(&dp.time.to_string());
+            for series in &self.series {
+                csv.push(',');
+                if let Some(value) = series.value(dp) {
+                    csv.push_str(&value.to_string());
+                }
+            }
+            csv.push('\n');
+        }
+        fs::create_dir_all(&dir)
+            .and_then(|()| fs::write(&path, csv))
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+        Ok(path)
+    }
+}
+
+impl DashboardWidget for TimeSeriesPlot {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn svg(&self) -> Option<String> {
+        Some(svg::render(&self.title, self.last_view.as_ref()?))
+    }
+
+    fn readout(&self, dp: &EngineDataPoint) -> Vec<(String, f64)> {
+        self.series
+            .iter()
+            .filter_map(|series| Some((series.name.clone(), series.value(dp)?)))
+            .collect()
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui, ctx: &WidgetContext) {
+        let channels: Vec<Channel> = self.series.iter().filter_map(|s| s.channel).collect();
+        widget_heading(
+            ui,
+            &self.title,
+            &ctx.frozen_channels.frozen_names(&channels),
+        );
+        match &self.export_status {
+            Some(Ok(message)) => {
+                ui.label(message);
+            }
+            Some(Err(e)) => {
+                ui.colored_label(egui::Color32::RED, e);
+            }
+            None => {}
+        }
+
+        let data_points: Vec<&EngineDataPoint> = ctx.engine_data.visible(ctx.window).collect();
+        let mut plot = Plot::new(&self.title)
+            .x_axis_label(ctx.clock.axis_label())
+            .allow_double_click_reset(true);
+        plot = match self.height {
+            Some(height) => plot.height(height),
+            None => plot.view_aspect(2.0),
+        };
+        if self.legend {
+            plot = plot.legend(Legend::default());
+        }
+        let focus_bounds = ctx
+            .focus
+            .filter(|focus| self.applied_focus != Some(focus.id))
+            .map(|focus| self.focus_bounds(ctx.engine_data.points(), focus.interval, ctx));
+        let width = ui.available_width() as f64;
+        // Plot X of every drawn sample, to tell what an export of the drawn points holds
+        let mut drawn = HashSet::new();
+        let response = plot.show(ui, |plot_ui| {
+            if let Some(bounds) = focus_bounds {
+                plot_ui.set_plot_bounds(bounds);
+            }
+            if let Some(focus) = ctx.focus {
+                for time in [focus.interval.start, focus.interval.end] {
+                    plot_ui.vline(VLine::new(ctx.clock.plot_x(time)).color(egui::Color32::GOLD));
+                }
+            }
+            if let Some(pointer) = plot_ui.pointer_coordinate() {
+                set_cursor(plot_ui.ctx(), ctx.clock.time_at(pointer.x));
+            }
+            if let Some(time) = ctx.cursor {
+                plot_ui.vline(VLine::new(ctx.clock.plot_x(time)).color(CURSOR_COLOR));
+            }
+            let top = plot_ui.plot_bounds().max()[1];
+            for marker in ctx.markers {
+                let x = ctx.clock.plot_x(marker.time);
+                plot_ui.vline(
+                    VLine::new(x)
+                        .color(MARKER_COLOR)
+                        .style(LineStyle::dashed_loose()),
+                );
+                plot_ui.text(
+                    Text::new(PlotPoint::new(x, top), &marker.label)
+                        .color(MARKER_COLOR)
+                        .anchor(egui::Align2::LEFT_TOP),
+                );
+            }
+            let x_range = data_points
+                .first()
+                .zip(data_points.last())
+                .map(|(first, last)| (ctx.clock.plot_x(first.time), ctx.clock.plot_x(last.time)));
+            for series in &self.series {
+                series.draw_limits(plot_ui, x_range);
+            }
+            // One bucket per pixel of the visible span, so zooming in shows more detail
+            let visible = plot_ui.plot_bounds().width();
+            let zoom = x_range
+                .map(|(first, last)| last - first)
+                .filter(|_| visible > 0.0)
+                .map_or(1.0, |span| (span / visible).clamp(1.0, 1000.0));
+            let buckets = (width * zoom).max(1.0) as usize;
+            let mut traces = Vec::new();
+            for series in &self.series {
+                let samples: Vec<([f64; 2], bool)> = data_points
+                    .iter()
+                    .filter_map(|dp| {
+                        let point = [ctx.clock.plot_x(dp.time), series.value(dp)?];
+                        Some((point, dp.quality != Quality::Good))
+                    })
+                    .collect();
+                if series.annotate {
+                    let points: Vec<[f64; 2]> = samples.iter().map(|&(point, _)| point).collect();
+                    series.draw_annotations(plot_ui, &points);
+                }
+                // Every sample is logged; only the drawing is thinned
+                let samples = decimate(&samples, buckets);
+                drawn.extend(samples.iter().map(|(point, _)| point[0].to_bits()));
+                let points: Vec<[f64; 2]> = samples.iter().map(|&(point, _)| point).collect();
+                traces.push((series.name.clone(), series.color, points.clone()));
+                let exceeded = series
+                    .redline
+                    .map(|redline| (redline, exceeded_runs(&points, redline)));
+                // Interpolated, suspect, and faulted samples are drawn dashed and grey
+                for (degraded, run) in quality_runs(&samples) {
+                    let line = Line::new(PlotPoints::from(run));
+                    plot_ui.line(if degraded {
+                        line.color(DEGRADED_COLOR).style(LineStyle::dashed_dense())
+                    } else {
+                        line.color(series.color).name(&series.name)
+                    });
+                }
+                let Some((redline, runs)) = exceeded else {
+                    continue;
+                };
+                plot_ui.hline(
+                    HLine::new(redline)
+                        .color(egui::Color32::RED)
+                        .style(LineStyle::dashed_loose()),
+                );
+                for run in runs {
+                    plot_ui.line(
+                        Line::new(PlotPoints::from(run))
+                            .color(egui::Color32::RED)
+                            .width(2.5),
+                    );
+                }
+            }
+            let bounds = plot_ui.plot_bounds();
+            DrawnView {
+                x_label: ctx.clock.axis_label().to_string(),
+                bounds,
+                traces,
+                levels: self.series.iter().flat_map(Series::levels).collect(),
+                markers: ctx
+                    .markers
+                    .iter()
+                    .map(|marker| (ctx.clock.plot_x(marker.time), marker.label.clone()))
+                    .collect(),
+            }
+        });
+        let view = response.inner;
+        let (view_start, view_end) = (view.bounds.min()[0], view.bounds.max()[0]);
+        self.last_view = Some(view);
+        response.response.context_menu(|ui| {
+            let in_view: Vec<&EngineDataPoint> = data_points
+                .iter()
+                .copied()
+                .filter(|dp| (view_start..=view_end).contains(&ctx.clock.plot_x(dp.time)))
+                .collect();
+            let shown: Vec<&EngineDataPoint> = in_view
+                .iter()
+                .copied()
+                .filter(|dp| drawn.contains(&ctx.clock.plot_x(dp.time).to_bits()))
+                .collect();
+            let mut description = vec![
+                format!("{} plot, {}", self.title, ctx.clock.axis_label()),
+                format!(
+                    "View from {} to {}",
+                    ctx.clock.format(ctx.clock.time_at(view_start)),
+                    ctx.clock.format(ctx.clock.time_at(view_end))
+                ),
+            ];
+            // Disclose both kinds of thinning before anything is exported
+            let thinned_before = ctx
+                .engine_data
+                .data_points
+                .front()
+                .map(|first| first.time)
+                .filter(|_| !ctx.engine_data.history.points().is_empty());
+            if let Some(first) =
+                thinned_before.filter(|&first| in_view.iter().any(|dp| dp.time < first))
+            {
+                let note = format!(
+                    "Samples before {} are kept at one per {:.0} ms",
+                    ctx.clock.format(first),
+                    ctx.engine_data.history.step_ms().max(1.0)
+                );
+                ui.label(&note);
+                description.push(note);
+            }
+            let drawn_note = format!(
+                "{} of {} samples in view are drawn (lowest and highest per pixel of each trace)",
+                shown.len(),
+                in_view.len()
+            );
+            ui.label(&drawn_note);
+            let mut export = None;
+            if ui
+                .button("Export visible data")
+                .on_hover_text("The drawn samples, with every series' value at each")
+                .clicked()
+            {
+                let mut description = description.clone();
+                description.push(drawn_note);
+                export = Some((shown, description));
+            } else if in_view.len() > shown.len()
+                && ui
+                    .button("Export every sample in view")
+                    .on_hover_text("All kept samples in view, not just the drawn ones")
+                    .clicked()
+            {
+                description.push(format!("All {} samples in view", in_view.len()));
+                export = Some((in_view, description));
+            }
+            if let Some((points, description)) = export {
+                self.export_status = Some(self.export(&points, ctx, &description).map(|path| {
+                    format!("Exported {} samples to {}", points.len(), path.display())
+                }));
+                ui.close_menu();
+            }
+        });
+        if focus_bounds.is_some() {
+            self.applied_focus = ctx.focus.map(|focus| focus.id);
+        }
+    }
+}
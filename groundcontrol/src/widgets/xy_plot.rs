@@ -0,0 +1,141 @@
+use egui_plot::{Legend, Line, Plot, PlotPoints, Points};
+
+use super::svg::{self, DrawnView};
+use super::{widget_heading, DashboardWidget, Series, WidgetContext};
+use crate::frozen::Channel;
+use ksi_telemetry::EngineDataPoint;
+
+const CURSOR_COLOR: egui::Color32 = egui::Color32::WHITE;
+
+/// One or more channels plotted against another channel instead of time, e.g.
+/// a pressure against flow rate to show the operating map during a throttle sweep.
+///
+/// Each point pairs the two channels' values from the same data point, so they
+/// are always time-aligned; data points missing either channel are skipped.
+/// Points are joined in time order, tracing the path the engine took.
+pub struct XyPlot {
+    title: String,
+    x: Series,
+    series: Vec<Series>,
+    legend: bool,
+    // Fixed plot height in points; otherwise a 1:1 aspect
+    height: Option<f32>,
+    // What was drawn last frame, for SVG export
+    last_view: Option<DrawnView>,
+}
+
+impl XyPlot {
+    pub fn new(title: &str, x: Series) -> Self {
+        Self {
+            title: title.to_string(),
+            x,
+            series: Vec::new(),
+            legend: true,
+            height: None,
+            last_view: None,
+        }
+    }
+
+    pub fn series(mut self, series: Series) -> Self {
+        self.series.push(series);
+        self
+    }
+
+    pub fn without_legend(mut self) -> Self {
+        self.legend = false;
+        self
+    }
+
+    pub fn height(mut self, height: f32) -> Self {
+        self.height = Some(height);
+        self
+    }
+
+    fn point(&self, series: &Series, dp: &EngineDataPoint) -> Option<[f64; 2]> {
+        Some([self.x.value(dp)?, series.value(dp)?])
+    }
+}
+
+impl DashboardWidget for XyPlot {
+    fn title(&self) -> &str {
+        &self.title
+    }
+
+    fn svg(&self) -> Option<String> {
+        Some(svg::render(&self.title, self.last_view.as_ref()?))
+    }
+
+    fn readout(&self, dp: &EngineDataPoint) -> Vec<(String, f64)> {
+        [&self.x]
+            .into_iter()
+            .chain(&self.series)
+            .filter_map(|series| Some((series.name().to_string(), series.value(dp)?)))
+            .collect()
+    }
+
+    fn show(&mut self, ui: &mut egui::Ui, ctx: &WidgetContext) {
+        let channels: Vec<Channel> = [&self.x]
+            .into_iter()
+            .chain(&self.series)
+            .filter_map(|s| s.channel)
+            .collect();
+        widget_heading(
+            ui,
+            &self.title,
+            &ctx.frozen_channels.frozen_names(&channels),
+        );
+
+        let data_points: Vec<&EngineDataPoint> = ctx.engine_data.visible(ctx.window).collect();
+        // The data point under the time cursor from the other plots
+        let cursor_point = ctx.cursor.and_then(|time| {
+            data_points
+                .iter()
+                .min_by(|a, b| (a.time - time).abs().total_cmp(&(b.time - time).abs()))
+        });
+        let mut plot = Plot::new(&self.title)
+            .x_axis_label(self.x.name())
+            .allow_double_click_reset(true);
+        plot = match self.height {
+            Some(height) => plot.height(height),
+            None => plot.view_aspect(1.0),
+        };
+        if self.legend {
+            plot = plot.legend(Legend::default());
+        }
+        let response = plot.show(ui, |plot_ui| {
+            let x_values = data_points.iter().filter_map(|dp| self.x.value(dp));
+            let x_range = x_values
+                .clone()
+                .reduce(f64::min)
+                .zip(x_values.reduce(f64::max));
+            let mut traces = Vec::new();
+            for series in &self.series {
+                series.draw_limits(plot_ui, x_range);
+                let points: Vec<[f64; 2]> = data_points
+                    .iter()
+                    .filter_map(|dp| self.point(series, dp))
+                    .collect();
+                traces.push((series.name().to_string(), series.color, points.clone()));
+                if let Some(&latest) = points.last() {
+                    plot_ui.points(Points::new(vec![latest]).radius(4.0).color(series.color));
+                }
+                plot_ui.line(
+                    Line::new(PlotPoints::from(points))
+                        .color(series.color)
+                        .name(series.name()),
+                );
+                if let Some(point) = cursor_point.and_then(|dp| self.point(series, dp)) {
+                    plot_ui.points(Points::new(vec![point]).radius(4.0).color(CURSOR_COLOR));
+                }
+            }
+            DrawnView {
+                x_label: self.x.name().to_string(),
+                bounds: plot_ui.plot_bounds(),
+                traces,
+                levels: self.series.iter().flat_map(Series::levels).collect(),
+                markers: Vec::new(),
+            }
+        });
+        self.last_view = Some(response.inner);
+    }
+}
@@ -0,0 +1,222 @@
+use anyhow::{anyhow, bail, Context, Result};
+use ksi_telemetry::{data_log, EngineDataPoint};
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::Path;
+
+/// Minimum fraction of the ground log an offset must overlap to be considered.
+const MIN_OVERLAP: f64 = 0.5;
+
+/// A CSV with a header row and numeric columns, e.g. a stand controller SD log.
+pub struct ForeignLog {
+    pub columns: Vec<String>,
+    pub rows: Vec<Vec<f64>>,
+}
+
+impl ForeignLog {
+    pub fn read(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+        let columns: Vec<String> = lines
+            .next()
+            .ok_or_else(|| anyhow!("{} is empty", path.display()))?
+            .split(',')
+            .map(|c| c.trim().to_string())
+            .collect();
+        let mut rows = Vec::new();
+        for (i, line) in lines.enumerate() {
+            let row = line
+                .split(',')
+                .map(|v| v.trim().parse::<f64>())
+                .collect::<Result<Vec<f64>, _>>()
+                .with_context(|| format!("{}:{}: non-numeric value", path.display(), i + 2))?;
+            if row.len() != columns.len() {
+                bail!(
+                    "{}:{}: expected {} values, found {}",
+                    path.display(),
+                    i + 2,
+                    columns.len(),
+                    row.len()
+                );
+            }
+            rows.push(row);
+        }
+        Ok(Self { columns, rows })
+    }
+
+    pub fn column(&self, name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|c| c == name)
+            .ok_or_else(|| anyhow!("No column named '{}'", name))
+    }
+}
+
+/// Linear interpolation of (time, value) samples sorted by time.
+fn interpolate(samples: &[(f64, f64)], time: f64) -> Option<f64> {
+    let index = samples.partition_point(|&(t, _)| t <= time);
+    if index == 0 || index == samples.len() {
+        return (samples.last()?.0 == time).then(|| samples.last().unwrap().1);
+    }
+    let (t0, v0) = samples[index - 1];
+    let (t1, v1) = samples[index];
+    if t1 == t0 {
+        return Some(v0);
+    }
+    Some(v0 + (v1 - v0) * (time - t0) / (t1 - t0))
+}
+
+/// Pearson correlation of two equal-length series.
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let n = a.len() as f64;
+    let mean_a = a.iter().sum::<f64>() / n;
+    let mean_b = b.iter().sum::<f64>() / n;
+    let mut covariance = 0.0;
+    let mut variance_a = 0.0;
+    let mut variance_b = 0.0;
+    for (x, y) in a.iter().zip(b) {
+        covariance += (x - mean_a) * (y - mean_b);
+        variance_a += (x - mean_a).powi(2);
+        variance_b += (y - mean_b).powi(2);
+    }
+    if variance_a == 0.0 || variance_b == 0.0 {
+        return 0.0;
+    }
+    covariance / (variance_a * variance_b).sqrt()
+}
+
+/// Finds the offset (ms) to add to foreign times so the channels best line up.
+///
+/// The search starts from aligning the first samples and scans +/- `max_lag_ms`
+/// in `step_ms` increments, scoring each offset by correlation on a common grid.
+pub fn find_offset(
+    ground: &[(f64, f64)],
+    foreign: &[(f64, f64)],
+    max_lag_ms: f64,
+    step_ms: f64,
+) -> Result<(f64, f64)> {
+    let (Some(ground_first), Some(ground_last)) = (ground.first(), ground.last()) else {
+        bail!("Ground log is empty");
+    };
+    let Some(foreign_first) = foreign.first() else {
+        bail!("Foreign log is empty");
+    };
+    let grid: Vec<f64> = (0..)
+        .map(|i| ground_first.0 + i as f64 * step_ms)
+        .take_while(|&t| t <= ground_last.0)
+        .collect();
+    let ground_values: Vec<f64> = grid
+        .iter()
+        .map(|&t| interpolate(ground, t).unwrap_or(0.0))
+        .collect();
+
+    let coarse_offset = ground_first.0 - foreign_first.0;
+    let lag_steps = (max_lag_ms / step_ms).round() as i64;
+    let mut best: Option<(f64, f64)> = None;
+    for lag in -lag_steps..=lag_steps {
+        let offset = coarse_offset + lag as f64 * step_ms;
+        let (a, b): (Vec<f64>, Vec<f64>) = grid
+            .iter()
+            .zip(&ground_values)
+            .filter_map(|(&t, &g)| interpolate(foreign, t - offset).map(|f| (g, f)))
+            .unzip();
+        if (a.len() as f64) < MIN_OVERLAP * grid.len() as f64 {
+            continue;
+        }
+        let score = correlation(&a, &b);
+        if best.is_none_or(|(_, best_score)| score > best_score) {
+            best = Some((offset, score));
+        }
+    }
+    best.ok_or_else(|| anyhow!("Logs never overlap by {:.0}%", MIN_OVERLAP * 100.0))
+}
+
+/// Writes the foreign rows on the ground timeline, each joined with the latest ground row.
+///
+/// The aligned time comes first, then every ground log column, then the SD columns.
+pub fn write_merged(
+    path: &Path,
+    ground: &[EngineDataPoint],
+    foreign: &ForeignLog,
+    foreign_time_column: usize,
+    offset: f64,
+) -> Result<usize> {
+    let mut file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let pressure_channels = ground.first().map_or(0, |g| g.pressures.len());
+    if ground
+        .iter()
+        .any(|g| g.pressures.len() != pressure_channels)
+    {
+        bail!("Ground rows have different numbers of pressure channels");
+    }
+    // The header's last line is the column row, after the schema comment
+    let header = data_log::header(pressure_channels);
+    let ground_columns = header.lines().last().unwrap_or_default();
+    let foreign_columns: Vec<String> = foreign
+        .columns
+        .iter()
+        .map(|c| format!("sd_{}", c))
+        .collect();
+    writeln!(
+        file,
+        "aligned_time,{},{}",
+        ground_columns,
+        foreign_columns.join(",")
+    )?;
+
+    let mut written = 0;
+    for row in &foreign.rows {
+        let time = row[foreign_time_column] + offset;
+        // Zero-order hold of the ground data; skip samples outside the ground session
+        let index = ground.partition_point(|g| g.time <= time);
+        if index == 0 || index == ground.len() && time > ground[ground.len() - 1].time {
+            continue;
+        }
+        let values: Vec<String> = row.iter().map(|v| v.to_string()).collect();
+        writeln!(
+            file,
+            "{},{},{}",
+            time,
+            ground[index - 1].to_log_line().trim_end(),
+            values.join(",")
+        )?;
+        written += 1;
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A single pulse, so only one offset lines the logs up
+    fn pulse(time: f64) -> f64 {
+        (-((time - 6800.0) / 150.0).powi(2)).exp()
+    }
+
+    #[test]
+    fn finds_a_known_offset() {
+        let ground: Vec<(f64, f64)> = (0..300)
+            .map(|i| 5000.0 + i as f64 * 10.0)
+            .map(|t| (t, pulse(t)))
+            .collect();
+        // The SD clock reads 4870 ms behind the ground one
+        let foreign: Vec<(f64, f64)> = (0..200)
+            .map(|i| 1000.0 + i as f64 * 10.0)
+            .map(|t| (t, pulse(t + 4870.0)))
+            .collect();
+        let (offset, score) = find_offset(&ground, &foreign, 2000.0, 10.0).unwrap();
+        assert!((offset - 4870.0).abs() < 1e-6, "{}", offset);
+        assert!(score > 0.999, "{}", score);
+    }
+
+    #[test]
+    fn logs_that_never_overlap_are_refused() {
+        let ground = [(0.0, 0.0), (1000.0, 1.0)];
+        let foreign = [(0.0, 0.0), (10.0, 1.0)];
+        assert!(find_offset(&ground, &foreign, 0.0, 10.0).is_err());
+        assert!(find_offset(&[], &foreign, 0.0, 10.0).is_err());
+    }
+}
@@ -0,0 +1,108 @@
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use ksi_telemetry::EngineDataPoint;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum FrameFormat {
+    Csv,
+    Json,
+}
+
+/// Channel values held at one video frame.
+pub struct Frame<'a> {
+    pub index: usize,
+    // Seconds since the first frame
+    pub video_time: f64,
+    // Firmware time of the frame in ms
+    pub time: f64,
+    pub record: &'a EngineDataPoint,
+}
+
+/// Samples the log once per frame, holding the latest row at each frame time.
+pub fn frames(records: &[EngineDataPoint], fps: f64) -> Vec<Frame<'_>> {
+    let (Some(first), Some(last)) = (records.first(), records.last()) else {
+        return Vec::new();
+    };
+    let frame_ms = 1000.0 / fps;
+    let mut frames = Vec::new();
+    let mut row = 0;
+    loop {
+        let index = frames.len();
+        let time = first.time + index as f64 * frame_ms;
+        if time > last.time {
+            break;
+        }
+        while row + 1 < records.len() && records[row + 1].time <= time {
+            row += 1;
+        }
+        frames.push(Frame {
+            index,
+            video_time: index as f64 / fps,
+            time,
+            record: &records[row],
+        });
+    }
+    frames
+}
+
+/// Writes one row (CSV) or object (JSON array) per frame.
+pub fn write_frames(path: &Path, frames: &[Frame], format: FrameFormat) -> Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create: {}", path.display()))?;
+    let mut out = BufWriter::new(file);
+    match format {
+        FrameFormat::Csv => {
+            writeln!(
+                out,
+                "frame,video_time,time,flow_rate_fuel,flow_rate_oxi,pulse_count_fuel,\
+                 pulse_count_oxi,fuel_valve_open,oxi_valve_open"
+            )?;
+            for frame in frames {
+                let r = frame.record;
+                writeln!(
+                    out,
+                    "{},{:.6},{:.3},{},{},{},{},{},{}",
+                    frame.index,
+                    frame.video_time,
+                    frame.time,
+                    r.flow_rate_fuel,
+                    r.flow_rate_oxi,
+                    r.pulse_count_fuel,
+                    r.pulse_count_oxi,
+                    r.fuel_valve_open,
+                    r.oxi_valve_open
+                )?;
+            }
+        }
+        FrameFormat::Json => {
+            writeln!(out, "[")?;
+            for (i, frame) in frames.iter().enumerate() {
+                let r = frame.record;
+                let separator = if i + 1 < frames.len() { "," } else { "" };
+                writeln!(
+                    out,
+                    "  {{\"frame\": {}, \"video_time\": {:.6}, \"time\": {:.3}, \
+                     \"flow_rate_fuel\": {}, \"flow_rate_oxi\": {}, \
+                     \"pulse_count_fuel\": {}, \"pulse_count_oxi\": {}, \
+                     \"fuel_valve_open\": {}, \"oxi_valve_open\": {}}}{}",
+                    frame.index,
+                    frame.video_time,
+                    frame.time,
+                    r.flow_rate_fuel,
+                    r.flow_rate_oxi,
+                    r.pulse_count_fuel,
+                    r.pulse_count_oxi,
+                    r.fuel_valve_open,
+                    r.oxi_valve_open,
+                    separator
+                )?;
+            }
+            writeln!(out, "]")?;
+        }
+    }
+    out.flush()?;
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use anyhow::{bail, Context, Result};
+use ksi_telemetry::{data_log, parquet_log, EngineDataPoint};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+
+/// Log file formats, told apart by extension.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Format {
+    Csv,
+    Parquet,
+}
+
+impl Format {
+    pub fn of(path: &Path) -> Result<Self> {
+        let extension = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(|e| e.to_ascii_lowercase());
+        match extension.as_deref() {
+            Some("csv") => Ok(Format::Csv),
+            Some("parquet") => Ok(Format::Parquet),
+            Some("h5" | "hdf5") => bail!(
+                "{}: HDF5 isn't supported; write Parquet instead, which pandas and MATLAB read",
+                path.display()
+            ),
+            _ => bail!(
+                "{}: unknown log format, expected .csv or .parquet",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// Reads every data line of a log, keeping per-line parse results for validation.
+///
+/// The schema comment and header row are skipped; logs from a newer schema are refused.
+/// Parquet logs are typed, so every row reads or the whole file fails.
+pub fn read_lines(path: &Path) -> Result<Vec<Result<EngineDataPoint, String>>> {
+    if Format::of(path)? == Format::Parquet {
+        let rows = parquet_log::read(path).map_err(anyhow::Error::msg)?;
+        return Ok(rows.into_iter().map(Ok).collect());
+    }
+    let file =
+        File::open(path).with_context(|| format!("Failed to open log: {}", path.display()))?;
+    let mut results = Vec::new();
+    let mut schema = None;
+    for line in BufReader::new(file).lines() {
+        let line = line.with_context(|| format!("Failed to read log: {}", path.display()))?;
+        if let Some(version) = data_log::schema_version(&line) {
+            if version > data_log::SCHEMA_VERSION {
+                bail!(
+                    "{} uses log schema {}, but this ksi-log reads up to {}",
+                    path.display(),
+                    version,
+                    data_log::SCHEMA_VERSION
+                );
+            }
+            schema = Some(version);
+        }
+        if line.trim().is_empty() || data_log::is_metadata(&line) {
+            continue;
+        }
+        results.push(data_log::parse_row(&line, schema));
+    }
+    Ok(results)
+}
+
+/// Reads a log, failing on the first malformed row.
+pub fn read_log(path: &Path) -> Result<Vec<EngineDataPoint>> {
+    read_lines(path)?
+        .into_iter()
+        .enumerate()
+        .map(|(i, record)| {
+            record.map_err(|e| anyhow::anyhow!("{}:{}: {}", path.display(), i + 1, e))
+        })
+        .collect()
+}
+
+/// Writes records to a new data_log.csv-format file, with the current schema header,
+/// or to a data_log.parquet-format file.
+///
+/// Every record must carry the same number of pressure channels.
+pub fn write_log(path: &Path, records: &[EngineDataPoint]) -> Result<()> {
+    if Format::of(path)? == Format::Parquet {
+        return parquet_log::write(path, records).map_err(anyhow::Error::msg);
+    }
+    let pressure_channels = records.first().map_or(0, |r| r.pressures.len());
+    if records
+        .iter()
+        .any(|r| r.pressures.len() != pressure_channels)
+    {
+        bail!("Records have different numbers of pressure channels");
+    }
+    let mut file = data_log::create(path, pressure_channels)
+        .with_context(|| format!("Failed to create: {}", path.display()))?;
+    for record in records {
+        file.write_all(record.to_log_line().as_bytes())?;
+    }
+    Ok(())
+}
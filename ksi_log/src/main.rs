@@ -0,0 +1,556 @@
+use anyhow::{bail, Result};
+use clap::{Parser, Subcommand};
+use ksi_telemetry::{EngineDataPoint, Quality};
+use std::path::{Path, PathBuf};
+
+mod align;
+mod frames;
+mod log;
+
+use frames::FrameFormat;
+
+type ChannelAccessor = fn(&EngineDataPoint) -> f64;
+
+/// Numeric log channels by column name.
+const CHANNELS: [(&str, ChannelAccessor); 6] = [
+    ("flow_rate_fuel", |r| r.flow_rate_fuel),
+    ("flow_rate_oxi", |r| r.flow_rate_oxi),
+    ("pulse_count_fuel", |r| r.pulse_count_fuel as f64),
+    ("pulse_count_oxi", |r| r.pulse_count_oxi as f64),
+    ("desired_pos_fuel", |r| r.desired_pos_fuel as f64),
+    ("desired_pos_oxi", |r| r.desired_pos_oxi as f64),
+];
+
+// Firmware pulse counting window (interval in groundcontrol.ino)
+const PULSE_WINDOW_MS: f64 = 100.0;
+
+/// Inspect and batch-process groundcontrol session logs.
+#[derive(Parser)]
+#[command(name = "ksi-log", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print row count, time span, and per-channel statistics
+    Inspect { log: PathBuf },
+    /// Check every row parses and firmware time never runs backwards
+    Validate {
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+    },
+    /// Resample onto a uniform firmware-time grid (zero-order hold)
+    Resample {
+        log: PathBuf,
+        /// Grid spacing in firmware milliseconds
+        #[arg(long)]
+        interval_ms: f64,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Keep only rows within a firmware-time window
+    Trim {
+        log: PathBuf,
+        /// Start of the window in firmware milliseconds
+        #[arg(long)]
+        start: Option<f64>,
+        /// End of the window in firmware milliseconds
+        #[arg(long)]
+        end: Option<f64>,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Combine several logs into one, ordered by wall-clock timestamp
+    Merge {
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Convert a log between CSV and Parquet, by file extension
+    ///
+    /// Every subcommand reads and writes either format; HDF5 isn't supported.
+    Convert {
+        log: PathBuf,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Recompute flow rates from raw pulse counts with new K-factors
+    ///
+    /// Writes `<name>_recalibrated.csv` (or `.parquet`) next to each input; raw logs are never modified.
+    Recalibrate {
+        #[arg(required = true)]
+        logs: Vec<PathBuf>,
+        /// Fuel meter K-factor in pulses per second per L/min
+        #[arg(long)]
+        k_fuel: f64,
+        /// Oxidizer meter K-factor in pulses per second per L/min
+        #[arg(long)]
+        k_oxi: f64,
+    },
+    /// Align a stand controller (SD card) log to a ground log and merge them
+    ///
+    /// The time offset is found by cross-correlating one channel from each log.
+    Align {
+        /// Ground session data_log.csv
+        ground: PathBuf,
+        /// Stand controller CSV with a header row
+        sd: PathBuf,
+        /// SD log column holding time in milliseconds
+        #[arg(long, default_value = "time_ms")]
+        sd_time_column: String,
+        /// SD log column to correlate against the ground channel
+        #[arg(long, default_value = "flow_fuel")]
+        sd_channel: String,
+        /// Ground channel to correlate
+        #[arg(long, default_value = "flow_rate_fuel")]
+        ground_channel: String,
+        /// Largest offset searched around start-aligned logs, in ms
+        #[arg(long, default_value_t = 10_000.0)]
+        max_lag_ms: f64,
+        /// Correlation grid spacing in ms
+        #[arg(long, default_value_t = 10.0)]
+        step_ms: f64,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Export channel values once per video frame for telemetry overlays
+    ///
+    /// Each frame holds the latest logged row (zero-order hold). Frame 0 is the
+    /// first row at or after --start.
+    Frames {
+        log: PathBuf,
+        /// Video frame rate
+        #[arg(long, default_value_t = 60.0)]
+        fps: f64,
+        /// Start of the window in firmware milliseconds
+        #[arg(long)]
+        start: Option<f64>,
+        /// End of the window in firmware milliseconds
+        #[arg(long)]
+        end: Option<f64>,
+        #[arg(long, value_enum, default_value_t = FrameFormat::Csv)]
+        format: FrameFormat,
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+}
+
+fn main() -> Result<()> {
+    match Cli::parse().command {
+        Command::Inspect { log } => inspect(&log),
+        Command::Validate { logs } => validate(&logs),
+        Command::Resample {
+            log,
+            interval_ms,
+            output,
+        } => {
+            if interval_ms <= 0.0 {
+                bail!("--interval-ms must be positive");
+            }
+            let records = resample(&log::read_log(&log)?, interval_ms);
+            log::write_log(&output, &records)?;
+            println!("Wrote {} rows to {}", records.len(), output.display());
+            Ok(())
+        }
+        Command::Trim {
+            log,
+            start,
+            end,
+            output,
+        } => {
+            let records = trim(log::read_log(&log)?, start, end);
+            log::write_log(&output, &records)?;
+            println!("Wrote {} rows to {}", records.len(), output.display());
+            Ok(())
+        }
+        Command::Merge { logs, output } => {
+            let records = merge(
+                logs.iter()
+                    .map(|path| log::read_log(path))
+                    .collect::<Result<_>>()?,
+            );
+            log::write_log(&output, &records)?;
+            println!(
+                "Merged {} logs ({} rows) into {}",
+                logs.len(),
+                records.len(),
+                output.display()
+            );
+            Ok(())
+        }
+        Command::Convert { log, output } => {
+            let records = log::read_log(&log)?;
+            log::write_log(&output, &records)?;
+            println!("Wrote {} rows to {}", records.len(), output.display());
+            Ok(())
+        }
+        Command::Recalibrate {
+            logs,
+            k_fuel,
+            k_oxi,
+        } => {
+            if k_fuel <= 0.0 || k_oxi <= 0.0 {
+                bail!("K-factors must be positive");
+            }
+            for path in &logs {
+                let records = recalibrate(&log::read_log(path)?, k_fuel, k_oxi);
+                let output = recalibrated_path(path)?;
+                log::write_log(&output, &records)?;
+                println!("{} -> {}", path.display(), output.display());
+            }
+            Ok(())
+        }
+        Command::Align {
+            ground,
+            sd,
+            sd_time_column,
+            sd_channel,
+            ground_channel,
+            max_lag_ms,
+            step_ms,
+            output,
+        } => {
+            if step_ms <= 0.0 || max_lag_ms < 0.0 {
+                bail!("--step-ms must be positive and --max-lag-ms non-negative");
+            }
+            let ground_records = log::read_log(&ground)?;
+            let ground_value = CHANNELS
+                .iter()
+                .find(|(name, _)| *name == ground_channel)
+                .map(|(_, value)| *value)
+                .ok_or_else(|| anyhow::anyhow!("Unknown ground channel '{}'", ground_channel))?;
+            let foreign = align::ForeignLog::read(&sd)?;
+            let time_column = foreign.column(&sd_time_column)?;
+            let channel_column = foreign.column(&sd_channel)?;
+
+            let ground_samples: Vec<(f64, f64)> = ground_records
+                .iter()
+                .map(|r| (r.time, ground_value(r)))
+                .collect();
+            let mut foreign_samples: Vec<(f64, f64)> = foreign
+                .rows
+                .iter()
+                .map(|row| (row[time_column], row[channel_column]))
+                .collect();
+            foreign_samples.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            let (offset, score) =
+                align::find_offset(&ground_samples, &foreign_samples, max_lag_ms, step_ms)?;
+            let written =
+                align::write_merged(&output, &ground_records, &foreign, time_column, offset)?;
+            println!(
+                "Offset {:.1} ms (correlation {:.3}); wrote {} rows to {}",
+                offset,
+                score,
+                written,
+                output.display()
+            );
+            Ok(())
+        }
+        Command::Frames {
+            log,
+            fps,
+            start,
+            end,
+            format,
+            output,
+        } => {
+            if fps <= 0.0 {
+                bail!("--fps must be positive");
+            }
+            let records = trim(log::read_log(&log)?, start, end);
+            let frames = frames::frames(&records, fps);
+            frames::write_frames(&output, &frames, format)?;
+            println!(
+                "Wrote {} frames ({:.1} s at {} fps) to {}",
+                frames.len(),
+                frames.len() as f64 / fps,
+                fps,
+                output.display()
+            );
+            Ok(())
+        }
+    }
+}
+
+fn inspect(path: &Path) -> Result<()> {
+    let records = log::read_log(path)?;
+    let (first, last) = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => (first, last),
+        _ => {
+            println!("{}: empty log", path.display());
+            return Ok(());
+        }
+    };
+
+    println!("Log: {}", path.display());
+    println!("Rows: {}", records.len());
+    println!(
+        "Firmware time: {} ms to {} ms ({:.1} s)",
+        first.time,
+        last.time,
+        (last.time - first.time) / 1000.0
+    );
+    println!("Unix time: {} to {}", first.timestamp, last.timestamp);
+
+    println!(
+        "{:<18} {:>12} {:>12} {:>12}",
+        "channel", "min", "max", "mean"
+    );
+    for (name, value) in CHANNELS {
+        let values: Vec<f64> = records.iter().map(value).collect();
+        let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+        let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        println!("{:<18} {:>12.3} {:>12.3} {:>12.3}", name, min, max, mean);
+    }
+
+    // Total impulse integrates thrust over firmware time (trapezoidal), across rows that carry it
+    let thrust: Vec<(f64, f64)> = records
+        .iter()
+        .filter_map(|r| Some((r.time, r.thrust?)))
+        .collect();
+    if !thrust.is_empty() {
+        let peak = thrust
+            .iter()
+            .map(|&(_, f)| f)
+            .fold(f64::NEG_INFINITY, f64::max);
+        let impulse: f64 = thrust
+            .windows(2)
+            .map(|w| (w[1].0 - w[0].0) / 1000.0 * (w[0].1 + w[1].1) / 2.0)
+            .sum();
+        println!(
+            "Thrust: peak {:.1} N, total impulse {:.1} N·s over {} rows",
+            peak,
+            impulse,
+            thrust.len()
+        );
+    }
+
+    let fuel_open = records.iter().filter(|r| r.fuel_valve_open).count();
+    let oxi_open = records.iter().filter(|r| r.oxi_valve_open).count();
+    println!(
+        "Valves open: fuel {:.1}% of rows, oxidizer {:.1}% of rows",
+        100.0 * fuel_open as f64 / records.len() as f64,
+        100.0 * oxi_open as f64 / records.len() as f64
+    );
+
+    let flagged: Vec<String> = [
+        Quality::Interpolated,
+        Quality::Suspect,
+        Quality::SensorFault,
+    ]
+    .iter()
+    .filter_map(|&quality| {
+        let count = records.iter().filter(|r| r.quality == quality).count();
+        (count > 0).then(|| format!("{} {}", count, quality.as_str()))
+    })
+    .collect();
+    if !flagged.is_empty() {
+        println!("Flagged rows: {}", flagged.join(", "));
+    }
+    Ok(())
+}
+
+fn validate(paths: &[PathBuf]) -> Result<()> {
+    let mut invalid_logs = 0;
+    for path in paths {
+        let mut problems = Vec::new();
+        let mut previous_time: Option<f64> = None;
+        let lines = log::read_lines(path)?;
+        for (i, record) in lines.iter().enumerate() {
+            match record {
+                Ok(record) => {
+                    if let Some(previous) = previous_time {
+                        if record.time < previous {
+                            problems.push(format!(
+                                "line {}: firmware time went backwards ({} -> {})",
+                                i + 1,
+                                previous,
+                                record.time
+                            ));
+                        }
+                    }
+                    previous_time = Some(record.time);
+                }
+                Err(e) => problems.push(format!("line {}: {}", i + 1, e)),
+            }
+        }
+
+        if problems.is_empty() {
+            println!("{}: OK ({} rows)", path.display(), lines.len());
+        } else {
+            invalid_logs += 1;
+            println!("{}: {} problem(s)", path.display(), problems.len());
+            for problem in problems {
+                println!("  {}", problem);
+            }
+        }
+    }
+
+    if invalid_logs > 0 {
+        bail!(
+            "{} of {} log(s) failed validation",
+            invalid_logs,
+            paths.len()
+        );
+    }
+    Ok(())
+}
+
+/// Keeps the records within a firmware-time window, open-ended where no bound is given.
+fn trim(
+    records: Vec<EngineDataPoint>,
+    start: Option<f64>,
+    end: Option<f64>,
+) -> Vec<EngineDataPoint> {
+    let start = start.unwrap_or(f64::NEG_INFINITY);
+    let end = end.unwrap_or(f64::INFINITY);
+    records
+        .into_iter()
+        .filter(|r| r.time >= start && r.time <= end)
+        .collect()
+}
+
+/// Combines logs into one ordered by wall-clock timestamp.
+fn merge(logs: Vec<Vec<EngineDataPoint>>) -> Vec<EngineDataPoint> {
+    let mut records: Vec<EngineDataPoint> = logs.into_iter().flatten().collect();
+    // Stable sort keeps firmware order within the same second
+    records.sort_by_key(|r| r.timestamp);
+    records
+}
+
+/// Resamples records onto a uniform grid, holding the latest value at each step.
+///
+/// Held values are flagged as interpolated; samples that land on the grid keep their quality.
+fn resample(records: &[EngineDataPoint], interval_ms: f64) -> Vec<EngineDataPoint> {
+    let (first, last) = match (records.first(), records.last()) {
+        (Some(first), Some(last)) => (first.time, last.time),
+        _ => return Vec::new(),
+    };
+
+    let mut resampled = Vec::new();
+    let mut index = 0;
+    let mut step = 0;
+    loop {
+        let time = first + step as f64 * interval_ms;
+        if time > last {
+            break;
+        }
+        while index + 1 < records.len() && records[index + 1].time <= time {
+            index += 1;
+        }
+        let record = &records[index];
+        let quality = if record.time == time {
+            record.quality
+        } else {
+            record.quality.max(Quality::Interpolated)
+        };
+        resampled.push(EngineDataPoint {
+            time,
+            quality,
+            ..record.clone()
+        });
+        step += 1;
+    }
+    resampled
+}
+
+/// Derives flow rates from pulse counts: Q [L/min] = F [Hz] / K.
+fn recalibrate(records: &[EngineDataPoint], k_fuel: f64, k_oxi: f64) -> Vec<EngineDataPoint> {
+    let pulses_to_hz = 1000.0 / PULSE_WINDOW_MS;
+    records
+        .iter()
+        .map(|r| EngineDataPoint {
+            flow_rate_fuel: r.pulse_count_fuel as f64 * pulses_to_hz / k_fuel,
+            flow_rate_oxi: r.pulse_count_oxi as f64 * pulses_to_hz / k_oxi,
+            ..r.clone()
+        })
+        .collect()
+}
+
+/// Output path for a recalibrated log, refusing to clobber an existing file.
+fn recalibrated_path(path: &Path) -> Result<PathBuf> {
+    let stem = path
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| anyhow::anyhow!("Invalid log file name: {}", path.display()))?;
+    // Same format as the input
+    let extension = path.extension().and_then(|e| e.to_str()).unwrap_or("csv");
+    let output = path.with_file_name(format!("{}_recalibrated.{}", stem, extension));
+    if output.exists() {
+        bail!("Refusing to overwrite {}", output.display());
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(timestamp: u64, time: f64, flow_rate_fuel: f64) -> EngineDataPoint {
+        let mut record = ksi_telemetry::parse_line("0,0,0,0,0,0,0,0").unwrap();
+        record.timestamp = timestamp;
+        record.time = time;
+        record.flow_rate_fuel = flow_rate_fuel;
+        record
+    }
+
+    fn times(records: &[EngineDataPoint]) -> Vec<f64> {
+        records.iter().map(|r| r.time).collect()
+    }
+
+    #[test]
+    fn resample_holds_the_latest_row() {
+        let records = [
+            record(0, 0.0, 1.0),
+            record(0, 25.0, 2.0),
+            record(0, 100.0, 3.0),
+        ];
+        let resampled = resample(&records, 50.0);
+        assert_eq!(times(&resampled), [0.0, 50.0, 100.0]);
+        let flows: Vec<f64> = resampled.iter().map(|r| r.flow_rate_fuel).collect();
+        assert_eq!(flows, [1.0, 2.0, 3.0]);
+        let qualities: Vec<Quality> = resampled.iter().map(|r| r.quality).collect();
+        assert_eq!(
+            qualities,
+            [Quality::Good, Quality::Interpolated, Quality::Good]
+        );
+        assert!(resample(&[], 50.0).is_empty());
+    }
+
+    #[test]
+    fn trim_keeps_an_inclusive_window() {
+        let records: Vec<EngineDataPoint> =
+            (0..4).map(|i| record(0, i as f64 * 10.0, 0.0)).collect();
+        assert_eq!(
+            times(&trim(records.clone(), Some(10.0), Some(20.0))),
+            [10.0, 20.0]
+        );
+        assert_eq!(times(&trim(records.clone(), None, Some(10.0))), [0.0, 10.0]);
+        assert_eq!(times(&trim(records, Some(25.0), None)), [30.0]);
+    }
+
+    #[test]
+    fn merge_orders_by_timestamp_keeping_firmware_order() {
+        let first = vec![record(2, 0.0, 0.0), record(2, 10.0, 0.0)];
+        let second = vec![record(1, 5.0, 0.0), record(2, 20.0, 0.0)];
+        let merged = merge(vec![first, second]);
+        assert_eq!(times(&merged), [5.0, 0.0, 10.0, 20.0]);
+    }
+
+    #[test]
+    fn recalibrate_derives_flow_from_pulses() {
+        let mut raw = record(0, 0.0, 99.0);
+        raw.pulse_count_fuel = 30;
+        raw.pulse_count_oxi = 15;
+        let recalibrated = recalibrate(&[raw], 7.5, 2.5);
+        // 30 pulses in 100 ms is 300 Hz
+        assert_eq!(recalibrated[0].flow_rate_fuel, 40.0);
+        assert_eq!(recalibrated[0].flow_rate_oxi, 60.0);
+        assert_eq!(recalibrated[0].pulse_count_fuel, 30);
+    }
+}
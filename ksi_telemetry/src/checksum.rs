@@ -0,0 +1,93 @@
+//! Optional per-line checksums appended by the firmware as `*` and hex digits.
+//!
+//! Two hex digits carry an XOR of the payload bytes, four a CRC16 of them
+//! (CRC-16/CCITT-FALSE). Lines without a `*` are accepted unchecked so older
+//! firmware keeps working.
+
+/// CRC-16/CCITT-FALSE: polynomial 0x1021, initial value 0xFFFF.
+pub fn crc16(data: &[u8]) -> u16 {
+    let mut crc: u16 = 0xFFFF;
+    for &byte in data {
+        crc ^= (byte as u16) << 8;
+        for _ in 0..8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+        }
+    }
+    crc
+}
+
+/// XOR of every byte, as used by NMEA sentences.
+pub fn xor8(data: &[u8]) -> u8 {
+    data.iter().fold(0, |acc, &byte| acc ^ byte)
+}
+
+/// Checks and strips a trailing checksum, returning the payload before it.
+pub fn verify(line: &str) -> Result<&str, String> {
+    let Some((payload, digits)) = line.rsplit_once('*') else {
+        return Ok(line);
+    };
+    let received =
+        u16::from_str_radix(digits, 16).map_err(|_| format!("Invalid checksum: {:?}", digits))?;
+    let computed = match digits.len() {
+        2 => xor8(payload.as_bytes()) as u16,
+        4 => crc16(payload.as_bytes()),
+        _ => return Err(format!("Invalid checksum length: {}", digits.len())),
+    };
+    if received != computed {
+        return Err(format!(
+            "Checksum mismatch: received {:0width$X}, computed {:0width$X}",
+            received,
+            computed,
+            width = digits.len()
+        ));
+    }
+    Ok(payload)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc16_matches_check_value() {
+        // Standard check input for CRC-16/CCITT-FALSE
+        assert_eq!(crc16(b"123456789"), 0x29B1);
+    }
+
+    #[test]
+    fn accepts_lines_without_checksum() {
+        assert_eq!(verify("1500,2.5"), Ok("1500,2.5"));
+    }
+
+    #[test]
+    fn verifies_xor_and_crc() {
+        let payload = "1500,2.5,1.25,18,9,90,45,0";
+        let xor = format!("{}*{:02X}", payload, xor8(payload.as_bytes()));
+        let crc = format!("{}*{:04x}", payload, crc16(payload.as_bytes()));
+        assert_eq!(verify(&xor), Ok(payload));
+        assert_eq!(verify(&crc), Ok(payload));
+    }
+
+    #[test]
+    fn rejects_corrupted_payload() {
+        let payload = "1500,2.5,1.25,18,9,90,45,0";
+        let line = format!(
+            "{}*{:04X}",
+            payload.replace("2.5", "7.5"),
+            crc16(payload.as_bytes())
+        );
+        let error = verify(&line).unwrap_err();
+        assert!(error.starts_with("Checksum mismatch"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_malformed_checksum() {
+        assert!(verify("1500,2.5*G1").is_err());
+        assert!(verify("1500,2.5*123").is_err());
+        assert!(verify("1500,2.5*").is_err());
+    }
+}
@@ -0,0 +1,29 @@
+//! Free-text debug output from the firmware, kept apart from telemetry.
+//!
+//! A CSV line starting with `#` is console text, so firmware printf debugging
+//! never reaches the telemetry parser:
+//!
+//! `# valve servo attached`
+
+/// First character of a console line.
+pub const PREFIX: char = '#';
+
+/// The text of a console line, or None for any other line.
+pub fn console_text(line: &str) -> Option<&str> {
+    line.trim_end()
+        .strip_prefix(PREFIX)
+        .map(|text| text.trim_start())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separates_console_lines() {
+        assert_eq!(console_text("# servo attached\r\n"), Some("servo attached"));
+        assert_eq!(console_text("#\n"), Some(""));
+        assert_eq!(console_text("1500,2.5,1.25,18,9,90,45,0"), None);
+        assert_eq!(console_text("E,2,18.5"), None);
+    }
+}
@@ -0,0 +1,352 @@
+//! Layout of data_log.csv, shared by the tools that write and read it.
+
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::{ControllerState, EngineDataPoint, Quality, Relays, Temperatures, ValveEcho};
+
+/// Version of the logged columns.
+///
+/// Bump this and extend `COLUMNS` whenever `EngineDataPoint` gains a logged field.
+/// 1: the original ten columns. 2: controller error, integrator, and output.
+/// 3: optional trailing pressure columns. 4: nozzle, tank, and ambient temperatures.
+/// 5: load cell thrust. 6: sample quality. 7: valve states echoed by the firmware.
+/// 8: auxiliary relay states. 9: seconds from T-0. 10: Unix time in ms.
+pub const SCHEMA_VERSION: u32 = 10;
+/// Start of the comment line that records the schema version.
+pub const SCHEMA_PREFIX: &str = "# ksi data_log schema ";
+/// Columns in rows written before the controller fields were added.
+const SCHEMA_1_COLUMN_COUNT: usize = 10;
+/// Columns in schema 2 and 3 rows, before any pressure columns.
+const SCHEMA_2_COLUMN_COUNT: usize = 13;
+/// Columns in schema 4 rows, before any pressure columns.
+const SCHEMA_4_COLUMN_COUNT: usize = 16;
+/// Columns in schema 5 rows, before any pressure columns.
+const SCHEMA_5_COLUMN_COUNT: usize = 17;
+/// Columns in schema 6 rows, before any pressure columns.
+const SCHEMA_6_COLUMN_COUNT: usize = 18;
+/// Columns in schema 7 rows, before any pressure columns.
+const SCHEMA_7_COLUMN_COUNT: usize = 20;
+/// Columns in schema 8 rows, before any pressure columns.
+const SCHEMA_8_COLUMN_COUNT: usize = 23;
+/// Columns in schema 9 rows, before any pressure columns.
+const SCHEMA_9_COLUMN_COUNT: usize = 24;
+
+/// Column names, in the order written by `EngineDataPoint::to_log_line`.
+///
+/// Pressure columns, if any, follow these and are named by `pressure_column`.
+pub const COLUMNS: [&str; 25] = [
+    "timestamp",
+    "time",
+    "flow_rate_fuel",
+    "flow_rate_oxi",
+    "pulse_count_fuel",
+    "pulse_count_oxi",
+    "desired_pos_fuel",
+    "desired_pos_oxi",
+    "fuel_valve_open",
+    "oxi_valve_open",
+    "controller_error",
+    "controller_integrator",
+    "controller_output",
+    "temperature_nozzle",
+    "temperature_tank",
+    "temperature_ambient",
+    "thrust",
+    "quality",
+    "reported_fuel_valve",
+    "reported_oxi_valve",
+    "relay_lighting",
+    "relay_camera_power",
+    "relay_beacon",
+    "t_time",
+    "unix_ms",
+];
+
+/// Header name of a pressure column, counting from zero.
+pub fn pressure_column(index: usize) -> String {
+    format!("pressure_{}", index + 1)
+}
+
+/// The schema comment and header row that start every data log.
+pub fn header(pressure_channels: usize) -> String {
+    let mut columns: Vec<String> = COLUMNS.iter().map(|c| c.to_string()).collect();
+    columns.extend((0..pressure_channels).map(pressure_column));
+    format!(
+        "{}{}\n{}\n",
+        SCHEMA_PREFIX,
+        SCHEMA_VERSION,
+        columns.join(",")
+    )
+}
+
+/// Creates a data log and writes its header.
+pub fn create(path: &Path, pressure_channels: usize) -> io::Result<File> {
+    let mut file = File::create(path)?;
+    file.write_all(header(pressure_channels).as_bytes())?;
+    Ok(file)
+}
+
+/// Schema version recorded on a comment line, if this is one.
+pub fn schema_version(line: &str) -> Option<u32> {
+    line.trim().strip_prefix(SCHEMA_PREFIX)?.parse().ok()
+}
+
+/// Whether a line is a comment or the header row rather than data.
+///
+/// Logs written before versioning have neither and are read as schema 1 or 2
+/// by their column count.
+pub fn is_metadata(line: &str) -> bool {
+    let line = line.trim_start();
+    line.starts_with('#') || line.starts_with(COLUMNS[0])
+}
+
+/// Columns before any pressure columns in a row of the given schema.
+///
+/// Logs without a schema comment predate pressure columns and are told apart
+/// by their width.
+fn fixed_column_count(schema: Option<u32>, width: usize) -> usize {
+    match schema {
+        Some(10..) => COLUMNS.len(),
+        Some(9) => SCHEMA_9_COLUMN_COUNT,
+        Some(8) => SCHEMA_8_COLUMN_COUNT,
+        Some(7) => SCHEMA_7_COLUMN_COUNT,
+        Some(6) => SCHEMA_6_COLUMN_COUNT,
+        Some(5) => SCHEMA_5_COLUMN_COUNT,
+        Some(4) => SCHEMA_4_COLUMN_COUNT,
+        Some(2 | 3) => SCHEMA_2_COLUMN_COUNT,
+        Some(_) => SCHEMA_1_COLUMN_COUNT,
+        None if width == SCHEMA_1_COLUMN_COUNT => SCHEMA_1_COLUMN_COUNT,
+        None => SCHEMA_2_COLUMN_COUNT,
+    }
+}
+
+/// Parses one data row back into a data point, keeping the row as its raw values.
+///
+/// `schema` is the version from the log's schema comment, if it had one.
+/// Columns after the fixed ones are pressures, from schema 3 on.
+pub fn parse_row(line: &str, schema: Option<u32>) -> Result<EngineDataPoint, String> {
+    let line = line.trim();
+    let values: Vec<&str> = line.split(',').collect();
+    let fixed = fixed_column_count(schema, values.len());
+    let pressures_allowed = schema.is_some_and(|version| version >= 3);
+    if values.len() < fixed || (values.len() > fixed && !pressures_allowed) {
+        return Err(format!("Unexpected number of columns: {}", values.len()));
+    }
+    let number = |index: usize, name: &str| {
+        values[index]
+            .parse::<f64>()
+            .map_err(|e| format!("{} parse error: {}", name, e))
+    };
+    let integer = |index: usize, name: &str| {
+        values[index]
+            .parse::<i32>()
+            .map_err(|e| format!("{} parse error: {}", name, e))
+    };
+    let flag = |index: usize, name: &str| {
+        values[index]
+            .parse::<bool>()
+            .map_err(|e| format!("{} parse error: {}", name, e))
+    };
+    // Controller columns are empty when the firmware didn't stream them
+    let controller = match (fixed >= SCHEMA_2_COLUMN_COUNT).then(|| &values[10..13]) {
+        Some(fields) if fields.iter().any(|v| !v.is_empty()) => Some(ControllerState {
+            error: number(10, "Controller error")?,
+            integrator: number(11, "Controller integrator")?,
+            output: number(12, "Controller output")?,
+        }),
+        _ => None,
+    };
+    let temperatures = match (fixed >= SCHEMA_4_COLUMN_COUNT).then(|| &values[13..16]) {
+        Some(fields) if fields.iter().any(|v| !v.is_empty()) => Some(Temperatures {
+            nozzle: number(13, "Nozzle temperature")?,
+            tank: number(14, "Tank temperature")?,
+            ambient: number(15, "Ambient temperature")?,
+        }),
+        _ => None,
+    };
+    let thrust = match (fixed >= SCHEMA_5_COLUMN_COUNT).then(|| values[16]) {
+        Some(value) if !value.is_empty() => Some(number(16, "Thrust")?),
+        _ => None,
+    };
+    // Rows from before quality was logged are taken as good
+    let quality = match (fixed >= SCHEMA_6_COLUMN_COUNT).then(|| values[17]) {
+        Some(value) => Quality::parse(value)?,
+        None => Quality::Good,
+    };
+    let valve_echo = match (fixed >= SCHEMA_7_COLUMN_COUNT).then(|| &values[18..20]) {
+        Some(fields) if fields.iter().any(|v| !v.is_empty()) => Some(ValveEcho {
+            fuel_open: flag(18, "Reported fuel valve")?,
+            oxi_open: flag(19, "Reported oxi valve")?,
+        }),
+        _ => None,
+    };
+    // Rows from before relays were logged are taken as all off
+    let relays = if fixed >= SCHEMA_8_COLUMN_COUNT {
+        Relays {
+            lighting: flag(20, "Lighting relay")?,
+            camera_power: flag(21, "Camera power relay")?,
+            beacon: flag(22, "Beacon relay")?,
+        }
+    } else {
+        Relays::default()
+    };
+    let t_time = match (fixed >= SCHEMA_9_COLUMN_COUNT).then(|| values[23]) {
+        Some(value) if !value.is_empty() => Some(number(23, "T-time")?),
+        _ => None,
+    };
+    let unix_ms = match (fixed >= COLUMNS.len()).then(|| values[24]) {
+        Some(value) if !value.is_empty() => Some(
+            value
+                .parse()
+                .map_err(|e| format!("Unix ms parse error: {}", e))?,
+        ),
+        _ => None,
+    };
+
+    Ok(EngineDataPoint {
+        timestamp: values[0]
+            .parse()
+            .map_err(|e| format!("Timestamp parse error: {}", e))?,
+        time: number(1, "Time")?,
+        flow_rate_fuel: number(2, "Flow fuel")?,
+        flow_rate_oxi: number(3, "Flow oxi")?,
+        pulse_count_fuel: integer(4, "Pulse fuel")?,
+        pulse_count_oxi: integer(5, "Pulse oxi")?,
+        desired_pos_fuel: integer(6, "Pos fuel")?,
+        desired_pos_oxi: integer(7, "Pos oxi")?,
+        fuel_valve_open: flag(8, "Fuel valve")?,
+        oxi_valve_open: flag(9, "Oxi valve")?,
+        raw_values: line.to_string(),
+        controller,
+        emergency: false,
+        pressures: (fixed..values.len())
+            .map(|index| number(index, "Pressure"))
+            .collect::<Result<_, _>>()?,
+        temperatures,
+        thrust,
+        quality,
+        valve_echo,
+        relays,
+        t_time,
+        unix_ms,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_line;
+
+    #[test]
+    fn header_round_trips_schema_version() {
+        let header = header(2);
+        let mut lines = header.lines();
+        assert_eq!(schema_version(lines.next().unwrap()), Some(SCHEMA_VERSION));
+        let columns = lines.next().unwrap();
+        assert!(is_metadata(columns));
+        assert!(columns.ends_with(",relay_beacon,t_time,unix_ms,pressure_1,pressure_2"));
+    }
+
+    #[test]
+    fn columns_match_logged_fields() {
+        let data_point = parse_line("10,0,0,0,0,0,0,0,0.5,-1,42").unwrap();
+        let line = data_point.to_log_line();
+        assert_eq!(line.trim_end().split(',').count(), COLUMNS.len());
+        assert!(!is_metadata(&line));
+    }
+
+    #[test]
+    fn rows_round_trip() {
+        for firmware_line in [
+            "1500,2.5,1.25,18,9,90,45,0",
+            "10,0,0,0,0,0,0,0,0.5,-1,42",
+            "10,0,0,0,0,0,0,0,,,,450,21.5,18",
+            "10,0,0,0,0,0,0,0,0.5,-1,42,,,,812.5",
+            "10,0,0,0,0,0,0,0,,,,,,,,0,1",
+        ] {
+            let mut data_point = parse_line(firmware_line).unwrap();
+            data_point.timestamp = 1_700_000_000;
+            data_point.oxi_valve_open = true;
+            data_point.relays.beacon = true;
+            data_point.t_time = Some(2.5);
+            data_point.unix_ms = Some(1_700_000_000_250);
+            let line = data_point.to_log_line();
+            let parsed = parse_row(&line, Some(SCHEMA_VERSION)).unwrap();
+            assert_eq!(parsed.to_log_line(), line);
+            assert_eq!(parsed.controller, data_point.controller);
+            assert_eq!(parsed.temperatures, data_point.temperatures);
+            assert_eq!(parsed.thrust, data_point.thrust);
+            assert_eq!(parsed.quality, data_point.quality);
+            assert_eq!(parsed.valve_echo, data_point.valve_echo);
+            assert_eq!(parsed.relays, data_point.relays);
+            assert_eq!(parsed.t_time, data_point.t_time);
+            assert_eq!(parsed.unix_ms, data_point.unix_ms);
+        }
+    }
+
+    #[test]
+    fn rows_round_trip_pressures() {
+        let mut data_point = parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        data_point.pressures = vec![12.5, 3.25];
+        let line = data_point.to_log_line();
+        let parsed = parse_row(&line, Some(SCHEMA_VERSION)).unwrap();
+        assert_eq!(parsed.pressures, data_point.pressures);
+        assert_eq!(parsed.to_log_line(), line);
+    }
+
+    #[test]
+    fn parses_schema_1_rows() {
+        let row = "1700000000,1500,2.5,1.25,18,9,90,45,true,false";
+        for schema in [None, Some(1)] {
+            let parsed = parse_row(row, schema).unwrap();
+            assert_eq!(parsed.time, 1500.0);
+            assert!(parsed.fuel_valve_open);
+            assert_eq!(parsed.controller, None);
+        }
+        assert!(parse_row("1700000000,1500,2.5", None).is_err());
+    }
+
+    #[test]
+    fn schema_decides_trailing_columns() {
+        let row = "1700000000,1500,2.5,1.25,18,9,90,45,true,false,,,,450,21.5,18";
+        let schema_3 = parse_row(row, Some(3)).unwrap();
+        assert_eq!(schema_3.pressures, vec![450.0, 21.5, 18.0]);
+        assert_eq!(schema_3.temperatures, None);
+
+        let schema_4 = parse_row(row, Some(4)).unwrap();
+        assert!(schema_4.pressures.is_empty());
+        assert_eq!(schema_4.temperatures.map(|t| t.nozzle), Some(450.0));
+        assert_eq!(schema_4.thrust, None);
+        assert!(parse_row(row, Some(5)).is_err());
+
+        let schema_5 = parse_row(&format!("{},812.5", row), Some(5)).unwrap();
+        assert_eq!(schema_5.thrust, Some(812.5));
+        assert_eq!(schema_5.quality, Quality::Good);
+
+        let schema_6 = parse_row(&format!("{},812.5,suspect", row), Some(6)).unwrap();
+        assert_eq!(schema_6.quality, Quality::Suspect);
+        assert_eq!(schema_6.valve_echo, None);
+
+        let schema_7 = parse_row(&format!("{},812.5,good,true,false", row), Some(7)).unwrap();
+        assert_eq!(schema_7.valve_echo.map(|e| e.fuel_open), Some(true));
+        assert_eq!(schema_7.relays, Relays::default());
+
+        let schema_8 =
+            parse_row(&format!("{},812.5,good,,,true,false,false", row), Some(8)).unwrap();
+        assert!(schema_8.relays.lighting);
+        assert_eq!(schema_8.t_time, None);
+
+        let schema_9 = parse_row(
+            &format!("{},812.5,good,,,true,false,false,-2.5", row),
+            Some(9),
+        )
+        .unwrap();
+        assert_eq!(schema_9.t_time, Some(-2.5));
+        assert_eq!(schema_9.unix_ms, None);
+
+        // Unversioned logs predate pressure columns
+        assert!(parse_row(row, None).is_err());
+    }
+}
@@ -0,0 +1,225 @@
+use crate::quality::Quality;
+
+/// One telemetry sample. Adding a logged field means bumping `data_log::SCHEMA_VERSION`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EngineDataPoint {
+    pub timestamp: u64, // Real date timestamp in Unix time
+    pub time: f64,      // Time from the data
+    pub flow_rate_fuel: f64,
+    pub flow_rate_oxi: f64,
+    pub pulse_count_fuel: i32,
+    pub pulse_count_oxi: i32,
+    pub desired_pos_fuel: i32,
+    pub desired_pos_oxi: i32,
+    pub fuel_valve_open: bool, // Valve states at the time of data point
+    pub oxi_valve_open: bool,
+    pub raw_values: String,                  // Raw decoded values as a string
+    pub controller: Option<ControllerState>, // Firmware PID internals, if streamed
+    pub emergency: bool,                     // Firmware emergency flag; not logged
+    // Analog pressure channels, in configured order; NaN where a channel has no value
+    #[cfg_attr(feature = "serde", serde(with = "nan_as_null"))]
+    pub pressures: Vec<f64>,
+    pub temperatures: Option<Temperatures>, // Thermocouples, if streamed
+    pub thrust: Option<f64>,                // Load cell thrust in N, if streamed
+    pub quality: Quality,                   // Worst quality given by any stage
+    pub valve_echo: Option<ValveEcho>,      // Valve states the firmware reports, if streamed
+    // Commanded auxiliary relay states; absent from stations that predate them
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub relays: Relays,
+    pub t_time: Option<f64>, // Seconds from T-0 on the countdown clock, once one is set
+    // Unix time in ms on the reference clock, where `timestamp` only has seconds
+    pub unix_ms: Option<u64>,
+}
+
+impl EngineDataPoint {
+    /// Lowers the sample's quality; a better one than it already has is ignored.
+    pub fn degrade(&mut self, quality: Quality) {
+        self.quality = self.quality.max(quality);
+    }
+
+    /// Formats the data point as a data_log.csv row, including the trailing newline.
+    pub fn to_log_line(&self) -> String {
+        // Controller columns are left empty when not streamed
+        let controller = self
+            .controller
+            .map(|c| format!("{},{},{}", c.error, c.integrator, c.output))
+            .unwrap_or_else(|| ",,".to_string());
+        let temperatures = self
+            .temperatures
+            .map(|t| format!("{},{},{}", t.nozzle, t.tank, t.ambient))
+            .unwrap_or_else(|| ",,".to_string());
+        let thrust = self.thrust.map(|t| t.to_string()).unwrap_or_default();
+        let valve_echo = self
+            .valve_echo
+            .map(|e| format!("{},{}", e.fuel_open, e.oxi_open))
+            .unwrap_or_else(|| ",".to_string());
+        let t_time = self.t_time.map(|t| t.to_string()).unwrap_or_default();
+        let unix_ms = self.unix_ms.map(|t| t.to_string()).unwrap_or_default();
+        let pressures: String = self.pressures.iter().map(|p| format!(",{}", p)).collect();
+        format!(
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}{}\n",
+            self.timestamp,
+            self.time,
+            self.flow_rate_fuel,
+            self.flow_rate_oxi,
+            self.pulse_count_fuel,
+            self.pulse_count_oxi,
+            self.desired_pos_fuel,
+            self.desired_pos_oxi,
+            self.fuel_valve_open,
+            self.oxi_valve_open,
+            controller,
+            temperatures,
+            thrust,
+            self.quality.as_str(),
+            valve_echo,
+            self.relays.lighting,
+            self.relays.camera_power,
+            self.relays.beacon,
+            t_time,
+            unix_ms,
+            pressures,
+        )
+    }
+}
+
+/// Firmware flow controller internals, sent as optional trailing fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ControllerState {
+    pub error: f64,
+    pub integrator: f64,
+    pub output: f64,
+}
+
+/// Valve states the firmware actually applied, sent after thrust so the ground
+/// station can confirm its commands arrived.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ValveEcho {
+    pub fuel_open: bool,
+    pub oxi_open: bool,
+}
+
+/// Auxiliary stand power relays, commanded from the ground station like the valves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Relays {
+    pub lighting: bool,
+    pub camera_power: bool,
+    pub beacon: bool, // Pad warning beacon
+}
+
+/// Thermocouple readings in °C, sent after the controller fields.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Temperatures {
+    pub nozzle: f64,
+    pub tank: f64,
+    pub ambient: f64,
+}
+
+/// Channel values with NaN written as null, since JSON has no NaN.
+#[cfg(feature = "serde")]
+mod nan_as_null {
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(values: &[f64], serializer: S) -> Result<S::Ok, S::Error> {
+        let values: Vec<Option<f64>> = values.iter().map(|v| (!v.is_nan()).then_some(*v)).collect();
+        values.serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<f64>, D::Error> {
+        let values = Vec::<Option<f64>>::deserialize(deserializer)?;
+        Ok(values.into_iter().map(|v| v.unwrap_or(f64::NAN)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parse_line;
+
+    #[test]
+    fn log_line_leaves_controller_columns_empty() {
+        let mut data_point = parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        data_point.timestamp = 1_700_000_000;
+        data_point.fuel_valve_open = true;
+        assert_eq!(
+            data_point.to_log_line(),
+            "1700000000,1500,2.5,1.25,18,9,90,45,true,false,,,,,,,,good,,,false,false,false,,\n"
+        );
+    }
+
+    #[test]
+    fn log_line_appends_pressures() {
+        let mut data_point = parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        data_point.pressures = vec![12.5, 0.0];
+        assert_eq!(
+            data_point.to_log_line(),
+            "0,1500,2.5,1.25,18,9,90,45,false,false,,,,,,,,good,,,false,false,false,,,12.5,0\n"
+        );
+    }
+
+    #[test]
+    fn log_line_includes_temperatures() {
+        let data_point = parse_line("10,0,0,0,0,0,0,0,,,,450,21.5,18").unwrap();
+        assert_eq!(
+            data_point.to_log_line(),
+            "0,10,0,0,0,0,0,0,false,false,,,,450,21.5,18,,good,,,false,false,false,,\n"
+        );
+    }
+
+    #[test]
+    fn log_line_includes_thrust() {
+        let data_point = parse_line("10,0,0,0,0,0,0,0,,,,,,,812.5").unwrap();
+        assert_eq!(
+            data_point.to_log_line(),
+            "0,10,0,0,0,0,0,0,false,false,,,,,,,812.5,good,,,false,false,false,,\n"
+        );
+    }
+
+    #[test]
+    fn log_line_includes_controller_state() {
+        let data_point = parse_line("10,0,0,0,0,0,0,0,0.5,-1,42").unwrap();
+        assert_eq!(
+            data_point.to_log_line(),
+            "0,10,0,0,0,0,0,0,false,false,0.5,-1,42,,,,,good,,,false,false,false,,\n"
+        );
+    }
+
+    #[test]
+    fn log_line_includes_valve_echo() {
+        let data_point = parse_line("10,0,0,0,0,0,0,0,,,,,,,,1,0").unwrap();
+        assert_eq!(
+            data_point.to_log_line(),
+            "0,10,0,0,0,0,0,0,false,false,,,,,,,,good,true,false,false,false,false,,\n"
+        );
+    }
+
+    #[test]
+    fn log_line_includes_relays_t_time_and_unix_ms() {
+        let mut data_point = parse_line("10,0,0,0,0,0,0,0").unwrap();
+        data_point.relays.lighting = true;
+        data_point.relays.beacon = true;
+        data_point.t_time = Some(-1.5);
+        data_point.unix_ms = Some(1_700_000_000_250);
+        assert_eq!(
+            data_point.to_log_line(),
+            "0,10,0,0,0,0,0,0,false,false,,,,,,,,good,,,true,false,true,-1.5,1700000000250\n"
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn json_round_trips_missing_channel_values() {
+        let mut data_point = parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        data_point.pressures = vec![12.5, f64::NAN];
+        let json = serde_json::to_string(&data_point).unwrap();
+        assert!(json.contains("\"pressures\":[12.5,null]"));
+        let parsed: crate::EngineDataPoint = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed.pressures[0], 12.5);
+        assert!(parsed.pressures[1].is_nan());
+        assert_eq!(parsed.to_log_line(), data_point.to_log_line());
+    }
+}
@@ -0,0 +1,78 @@
+//! Slow environmental sensors (ambient and tank skin temperatures, humidity),
+//! read on request rather than streamed with every telemetry line.
+//!
+//! The ground station sends `?<sensor>` on its own schedule for each sensor and
+//! the firmware answers with one line, optionally checksummed:
+//!
+//! `E,<sensor>,<value>`
+
+use crate::checksum;
+
+/// Start of a reply line, which telemetry lines never begin with.
+pub const REPLY_PREFIX: &str = "E,";
+
+/// One sensor reading, in the sensor's configured unit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EnvironmentReading {
+    pub sensor: u8,
+    pub value: f64,
+}
+
+/// The request that makes the firmware read one sensor, including the newline.
+pub fn poll_request(sensor: u8) -> String {
+    format!("?{}\n", sensor)
+}
+
+/// Whether a line is a sensor reply rather than telemetry.
+pub fn is_reply(line: &str) -> bool {
+    line.trim_start().starts_with(REPLY_PREFIX)
+}
+
+/// Parses a reply line; a trailing checksum is verified as for telemetry.
+pub fn parse_reply(line: &str) -> Result<EnvironmentReading, String> {
+    let payload = checksum::verify(line.trim())?;
+    let fields = payload
+        .strip_prefix(REPLY_PREFIX)
+        .ok_or_else(|| format!("Not a sensor reply: {:?}", payload))?;
+    let Some((sensor, value)) = fields.split_once(',') else {
+        return Err(format!("Sensor reply without a value: {:?}", payload));
+    };
+    Ok(EnvironmentReading {
+        sensor: sensor
+            .parse()
+            .map_err(|e| format!("Sensor id parse error: {}", e))?,
+        value: value
+            .parse()
+            .map_err(|e| format!("Sensor value parse error: {}", e))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_replies() {
+        assert!(is_reply("E,2,18.5\r\n"));
+        assert!(!is_reply("1500,2.5,1.25,18,9,90,45,0"));
+        assert_eq!(
+            parse_reply("E,2,18.5\r\n").unwrap(),
+            EnvironmentReading {
+                sensor: 2,
+                value: 18.5,
+            }
+        );
+
+        let payload = "E,7,-3.25";
+        let line = format!("{}*{:04X}", payload, checksum::crc16(payload.as_bytes()));
+        assert_eq!(parse_reply(&line).unwrap().value, -3.25);
+    }
+
+    #[test]
+    fn rejects_malformed_replies() {
+        assert!(parse_reply("E,2").is_err());
+        assert!(parse_reply("E,x,1").is_err());
+        assert!(parse_reply("E,300,1").is_err());
+        assert!(parse_reply("1500,2.5").is_err());
+    }
+}
@@ -0,0 +1,513 @@
+//! Binary telemetry frames, for sample rates CSV lines can't keep up with.
+//!
+//! Each frame is COBS-encoded and ends with a zero byte. Decoded, it is the
+//! little-endian layout below followed by a CRC16 (`checksum::crc16`) of
+//! everything before it:
+//!
+//! | Bytes | Type | Field                                    |
+//! |-------|------|------------------------------------------|
+//! | 4     | u32  | time (ms)                                |
+//! | 4     | f32  | flow_rate_fuel                           |
+//! | 4     | f32  | flow_rate_oxi                            |
+//! | 4     | i32  | pulse_count_fuel                         |
+//! | 4     | i32  | pulse_count_oxi                          |
+//! | 2     | i16  | desired_pos_fuel                         |
+//! | 2     | i16  | desired_pos_oxi                          |
+//! | 1     | u8   | flags: bit 0 emergency, bit 1 controller, bit 2 pressures, bit 3 temperatures, bit 4 thrust, bit 5 valve echo |
+//! | 12    | f32  | controller error, integrator, output (if flagged) |
+//! | 12    | f32  | nozzle, tank, ambient temperature (if flagged) |
+//! | 4     | f32  | thrust (N, if flagged)                   |
+//! | 1     | u8   | valve echo: bit 0 fuel, bit 1 oxidizer (if flagged) |
+//! | 1     | u8   | pressure count N (if flagged)            |
+//! | 4N    | f32  | pressures (if flagged)                   |
+//! | 2     | u16  | CRC16                                    |
+
+use crate::quality::{self, Quality};
+use crate::{checksum, ControllerState, EngineDataPoint, Relays, Temperatures, ValveEcho};
+
+/// Marks the end of every encoded frame.
+pub const FRAME_DELIMITER: u8 = 0;
+
+const BASE_LEN: usize = 25;
+const CONTROLLER_LEN: usize = 12;
+const CRC_LEN: usize = 2;
+const FLAG_EMERGENCY: u8 = 1 << 0;
+const FLAG_CONTROLLER: u8 = 1 << 1;
+const FLAG_PRESSURES: u8 = 1 << 2;
+const FLAG_TEMPERATURES: u8 = 1 << 3;
+const FLAG_THRUST: u8 = 1 << 4;
+const FLAG_VALVE_ECHO: u8 = 1 << 5;
+const TEMPERATURES_LEN: usize = 12;
+const THRUST_LEN: usize = 4;
+const VALVE_ECHO_LEN: usize = 1;
+
+/// COBS-encodes `data`, without the trailing delimiter.
+pub fn cobs_encode(data: &[u8]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(data.len() + data.len() / 254 + 2);
+    let mut code_index = 0;
+    encoded.push(0);
+    let mut code: u8 = 1;
+    for &byte in data {
+        if byte == 0 {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            encoded.push(0);
+            code = 1;
+            continue;
+        }
+        encoded.push(byte);
+        code += 1;
+        if code == 0xFF {
+            encoded[code_index] = code;
+            code_index = encoded.len();
+            encoded.push(0);
+            code = 1;
+        }
+    }
+    encoded[code_index] = code;
+    encoded
+}
+
+/// Reverses `cobs_encode` on one frame, without its delimiter.
+pub fn cobs_decode(encoded: &[u8]) -> Result<Vec<u8>, String> {
+    let mut decoded = Vec::with_capacity(encoded.len());
+    let mut index = 0;
+    while index < encoded.len() {
+        let code = encoded[index] as usize;
+        if code == 0 {
+            return Err("Zero byte inside COBS frame".to_string());
+        }
+        let end = index + code;
+        if end > encoded.len() {
+            return Err("Truncated COBS frame".to_string());
+        }
+        decoded.extend_from_slice(&encoded[index + 1..end]);
+        index = end;
+        // A full block carries no implied zero, nor does the last block
+        if code < 0xFF && index < encoded.len() {
+            decoded.push(0);
+        }
+    }
+    Ok(decoded)
+}
+
+/// Encodes a data point as a complete frame, including the delimiter.
+///
+/// Values are narrowed to the frame's field types; at most 255 pressures are sent.
+pub fn encode_frame(data_point: &EngineDataPoint) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(BASE_LEN + CONTROLLER_LEN + CRC_LEN);
+    payload.extend_from_slice(&(data_point.time as u32).to_le_bytes());
+    payload.extend_from_slice(&(data_point.flow_rate_fuel as f32).to_le_bytes());
+    payload.extend_from_slice(&(data_point.flow_rate_oxi as f32).to_le_bytes());
+    payload.extend_from_slice(&data_point.pulse_count_fuel.to_le_bytes());
+    payload.extend_from_slice(&data_point.pulse_count_oxi.to_le_bytes());
+    payload.extend_from_slice(&(data_point.desired_pos_fuel as i16).to_le_bytes());
+    payload.extend_from_slice(&(data_point.desired_pos_oxi as i16).to_le_bytes());
+    let mut flags = 0;
+    if data_point.emergency {
+        flags |= FLAG_EMERGENCY;
+    }
+    if data_point.controller.is_some() {
+        flags |= FLAG_CONTROLLER;
+    }
+    if !data_point.pressures.is_empty() {
+        flags |= FLAG_PRESSURES;
+    }
+    if data_point.temperatures.is_some() {
+        flags |= FLAG_TEMPERATURES;
+    }
+    if data_point.thrust.is_some() {
+        flags |= FLAG_THRUST;
+    }
+    if data_point.valve_echo.is_some() {
+        flags |= FLAG_VALVE_ECHO;
+    }
+    payload.push(flags);
+    if let Some(controller) = data_point.controller {
+        for value in [controller.error, controller.integrator, controller.output] {
+            payload.extend_from_slice(&(value as f32).to_le_bytes());
+        }
+    }
+    if let Some(t) = data_point.temperatures {
+        for value in [t.nozzle, t.tank, t.ambient] {
+            payload.extend_from_slice(&(value as f32).to_le_bytes());
+        }
+    }
+    if let Some(thrust) = data_point.thrust {
+        payload.extend_from_slice(&(thrust as f32).to_le_bytes());
+    }
+    if let Some(echo) = data_point.valve_echo {
+        payload.push(echo.fuel_open as u8 | (echo.oxi_open as u8) << 1);
+    }
+    if !data_point.pressures.is_empty() {
+        let pressures = &data_point.pressures[..data_point.pressures.len().min(u8::MAX as usize)];
+        payload.push(pressures.len() as u8);
+        for &pressure in pressures {
+            payload.extend_from_slice(&(pressure as f32).to_le_bytes());
+        }
+    }
+    let crc = checksum::crc16(&payload);
+    payload.extend_from_slice(&crc.to_le_bytes());
+
+    let mut frame = cobs_encode(&payload);
+    frame.push(FRAME_DELIMITER);
+    frame
+}
+
+/// Decodes one frame, without its delimiter, into a data point.
+///
+/// The raw values are set to the equivalent CSV line, so frames display and
+/// log the same way as text telemetry.
+pub fn decode_frame(encoded: &[u8]) -> Result<EngineDataPoint, String> {
+    decode_record(&cobs_decode(encoded)?)
+}
+
+/// Decodes one record as laid out above, already COBS-decoded, into a data point.
+pub fn decode_record(frame: &[u8]) -> Result<EngineDataPoint, String> {
+    if frame.len() < BASE_LEN + CRC_LEN {
+        return Err(format!("Frame too short: {} bytes", frame.len()));
+    }
+    let (payload, crc) = frame.split_at(frame.len() - CRC_LEN);
+    let received = u16::from_le_bytes([crc[0], crc[1]]);
+    let computed = checksum::crc16(payload);
+    if received != computed {
+        return Err(format!(
+            "Frame CRC mismatch: received {:04X}, computed {:04X}",
+            received, computed
+        ));
+    }
+
+    let flags = payload[24];
+    let mut expected_len = BASE_LEN;
+    if flags & FLAG_CONTROLLER != 0 {
+        expected_len += CONTROLLER_LEN;
+    }
+    let temperatures_at = expected_len;
+    if flags & FLAG_TEMPERATURES != 0 {
+        expected_len += TEMPERATURES_LEN;
+    }
+    let thrust_at = expected_len;
+    if flags & FLAG_THRUST != 0 {
+        expected_len += THRUST_LEN;
+    }
+    let valve_echo_at = expected_len;
+    if flags & FLAG_VALVE_ECHO != 0 {
+        expected_len += VALVE_ECHO_LEN;
+    }
+    let pressure_count = if flags & FLAG_PRESSURES != 0 {
+        let count = *payload
+            .get(expected_len)
+            .ok_or("Frame missing pressure count")? as usize;
+        expected_len += 1 + 4 * count;
+        count
+    } else {
+        0
+    };
+    if payload.len() != expected_len {
+        return Err(format!(
+            "Unexpected frame length: {} bytes",
+            payload.len() + CRC_LEN
+        ));
+    }
+
+    let bytes4 = |at: usize| {
+        [
+            payload[at],
+            payload[at + 1],
+            payload[at + 2],
+            payload[at + 3],
+        ]
+    };
+    // Widen via the shortest decimal form so 2.3 logs as 2.3, not 2.299999952316284
+    let float = |at: usize| {
+        let value = f32::from_le_bytes(bytes4(at));
+        value.to_string().parse::<f64>().unwrap_or(value as f64)
+    };
+    let controller = (flags & FLAG_CONTROLLER != 0).then(|| ControllerState {
+        error: float(25),
+        integrator: float(29),
+        output: float(33),
+    });
+    let temperatures = (flags & FLAG_TEMPERATURES != 0).then(|| Temperatures {
+        nozzle: float(temperatures_at),
+        tank: float(temperatures_at + 4),
+        ambient: float(temperatures_at + 8),
+    });
+    let thrust = (flags & FLAG_THRUST != 0).then(|| float(thrust_at));
+    let valve_echo = (flags & FLAG_VALVE_ECHO != 0).then(|| ValveEcho {
+        fuel_open: payload[valve_echo_at] & 1 != 0,
+        oxi_open: payload[valve_echo_at] & 2 != 0,
+    });
+    let pressures_at = expected_len - 4 * pressure_count;
+    let pressures = (0..pressure_count)
+        .map(|i| float(pressures_at + 4 * i))
+        .collect();
+    let mut data_point = EngineDataPoint {
+        timestamp: 0, // Will be set later
+        time: u32::from_le_bytes(bytes4(0)) as f64,
+        flow_rate_fuel: float(4),
+        flow_rate_oxi: float(8),
+        pulse_count_fuel: i32::from_le_bytes(bytes4(12)),
+        pulse_count_oxi: i32::from_le_bytes(bytes4(16)),
+        desired_pos_fuel: i16::from_le_bytes([payload[20], payload[21]]) as i32,
+        desired_pos_oxi: i16::from_le_bytes([payload[22], payload[23]]) as i32,
+        fuel_valve_open: false, // Will be set later
+        oxi_valve_open: false,  // Will be set later
+        raw_values: String::new(),
+        controller,
+        emergency: flags & FLAG_EMERGENCY != 0,
+        pressures,
+        temperatures,
+        thrust,
+        quality: Quality::Good,
+        valve_echo,
+        relays: Relays::default(), // Will be set later
+        t_time: None,              // Will be set later
+        unix_ms: None,             // Will be set later
+    };
+    data_point.raw_values = csv_line(&data_point);
+    data_point.quality = quality::assess(&data_point);
+    Ok(data_point)
+}
+
+/// The firmware CSV line carrying the same values as a frame.
+fn csv_line(data_point: &EngineDataPoint) -> String {
+    let mut line = format!(
+        "{},{},{},{},{},{},{},{}",
+        data_point.time,
+        data_point.flow_rate_fuel,
+        data_point.flow_rate_oxi,
+        data_point.pulse_count_fuel,
+        data_point.pulse_count_oxi,
+        data_point.desired_pos_fuel,
+        data_point.desired_pos_oxi,
+        data_point.emergency as u8
+    );
+    // Each group follows the ones before it, left empty when not streamed
+    let echo = data_point.valve_echo.is_some();
+    let later_groups = data_point.temperatures.is_some() || data_point.thrust.is_some() || echo;
+    match data_point.controller {
+        Some(c) => line.push_str(&format!(",{},{},{}", c.error, c.integrator, c.output)),
+        None if later_groups => line.push_str(",,,"),
+        None => {}
+    }
+    match data_point.temperatures {
+        Some(t) => line.push_str(&format!(",{},{},{}", t.nozzle, t.tank, t.ambient)),
+        None if data_point.thrust.is_some() || echo => line.push_str(",,,"),
+        None => {}
+    }
+    match data_point.thrust {
+        Some(thrust) => line.push_str(&format!(",{}", thrust)),
+        None if echo => line.push(','),
+        None => {}
+    }
+    if let Some(e) = data_point.valve_echo {
+        line.push_str(&format!(",{},{}", e.fuel_open as u8, e.oxi_open as u8));
+    }
+    for pressure in &data_point.pressures {
+        line.push_str(&format!(",{}", pressure));
+    }
+    line
+}
+
+/// One message cut from a stream that mixes CSV lines and binary frames.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MixedMessage {
+    Line(String),
+    /// A COBS-encoded frame, without its delimiter
+    Frame(Vec<u8>),
+}
+
+/// Tells CSV lines and binary frames apart as they arrive on one stream, so
+/// firmware sending either can talk to the same ground station.
+///
+/// A message is a line if it is all printable text when its newline arrives.
+/// Any other byte makes it a frame, which then runs to the next zero byte even
+/// across newline bytes inside it. A frame whose first bytes happen to be text
+/// is cut short at a newline and then fails its CRC rather than being misread.
+#[derive(Debug, Default)]
+pub struct MixedSplitter {
+    pending: Vec<u8>,
+    binary: bool,
+}
+
+impl MixedSplitter {
+    /// Takes bytes up to the end of the next message. Returns how many were
+    /// taken and the message, if one ended; blank lines and empty frames are
+    /// taken without returning a message.
+    pub fn push(&mut self, bytes: &[u8]) -> (usize, Option<MixedMessage>) {
+        for (i, &byte) in bytes.iter().enumerate() {
+            let message = if byte == FRAME_DELIMITER {
+                let encoded = std::mem::take(&mut self.pending);
+                (!encoded.is_empty()).then_some(MixedMessage::Frame(encoded))
+            } else if byte == b'\n' && !self.binary {
+                let line = String::from_utf8_lossy(&self.pending).into_owned();
+                self.pending.clear();
+                (!line.trim().is_empty()).then_some(MixedMessage::Line(line))
+            } else {
+                self.binary |= !(byte.is_ascii_graphic() || matches!(byte, b' ' | b'\t' | b'\r'));
+                self.pending.push(byte);
+                continue;
+            };
+            self.binary = false;
+            if message.is_some() {
+                return (i + 1, message);
+            }
+        }
+        (bytes.len(), None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{parse_line, parse_line_with_pressures};
+
+    fn strip_delimiter(frame: &[u8]) -> &[u8] {
+        assert_eq!(frame.last(), Some(&FRAME_DELIMITER));
+        &frame[..frame.len() - 1]
+    }
+
+    #[test]
+    fn cobs_round_trips() {
+        let cases: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![0],
+            vec![0, 0],
+            vec![1, 2, 0, 3],
+            (1..=254).collect(),
+            (0..=255).cycle().take(700).collect(),
+        ];
+        for data in cases {
+            let encoded = cobs_encode(&data);
+            assert!(!encoded.contains(&0), "{:?}", data);
+            assert_eq!(cobs_decode(&encoded).unwrap(), data);
+        }
+    }
+
+    #[test]
+    fn frame_round_trips_base_values() {
+        let data_point = parse_line("1500,2.3,1.25,18,0,90,-45,1").unwrap();
+        let frame = encode_frame(&data_point);
+        let decoded = decode_frame(strip_delimiter(&frame)).unwrap();
+        assert_eq!(decoded.time, 1500.0);
+        assert_eq!(decoded.flow_rate_fuel, 2.3);
+        assert_eq!(decoded.flow_rate_oxi, 1.25);
+        assert_eq!(decoded.pulse_count_fuel, 18);
+        assert_eq!(decoded.pulse_count_oxi, 0);
+        assert_eq!(decoded.desired_pos_fuel, 90);
+        assert_eq!(decoded.desired_pos_oxi, -45);
+        assert!(decoded.emergency);
+        assert_eq!(decoded.controller, None);
+        assert_eq!(decoded.raw_values, "1500,2.3,1.25,18,0,90,-45,1");
+    }
+
+    #[test]
+    fn record_decodes_without_cobs() {
+        let data_point = parse_line("1500,2.3,1.25,18,0,90,-45,0").unwrap();
+        let record = cobs_decode(strip_delimiter(&encode_frame(&data_point))).unwrap();
+        let decoded = decode_record(&record).unwrap();
+        assert_eq!(decoded.raw_values, "1500,2.3,1.25,18,0,90,-45,0");
+    }
+
+    #[test]
+    fn frame_round_trips_controller_values() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0,0.5,-1.5,42").unwrap();
+        let frame = encode_frame(&data_point);
+        let decoded = decode_frame(strip_delimiter(&frame)).unwrap();
+        assert_eq!(decoded.controller, data_point.controller);
+        assert_eq!(decoded.raw_values, "1500,2.5,1.25,18,9,90,45,0,0.5,-1.5,42");
+    }
+
+    #[test]
+    fn frame_round_trips_pressures() {
+        for firmware_line in [
+            "1500,2.5,1.25,18,9,90,45,0,12.5,3",
+            "1500,2.5,1.25,18,9,90,45,0,0.5,-1.5,42,12.5,3",
+            "1500,2.5,1.25,18,9,90,45,0,,,,450,21.5,18,12.5,3",
+            "1500,2.5,1.25,18,9,90,45,0,0.5,-1.5,42,450,21.5,18,12.5,3",
+            "1500,2.5,1.25,18,9,90,45,0,,,,,,,812.5,12.5,3",
+            "1500,2.5,1.25,18,9,90,45,0,0.5,-1.5,42,,,,812.5,12.5,3",
+            "1500,2.5,1.25,18,9,90,45,0,,,,,,,,1,0,12.5,3",
+            "1500,2.5,1.25,18,9,90,45,0,,,,450,21.5,18,812.5,0,1,12.5,3",
+        ] {
+            let data_point = parse_line_with_pressures(firmware_line, 2).unwrap();
+            let frame = encode_frame(&data_point);
+            let decoded = decode_frame(strip_delimiter(&frame)).unwrap();
+            assert_eq!(decoded.pressures, vec![12.5, 3.0]);
+            assert_eq!(decoded.controller, data_point.controller);
+            assert_eq!(decoded.temperatures, data_point.temperatures);
+            assert_eq!(decoded.thrust, data_point.thrust);
+            assert_eq!(decoded.valve_echo, data_point.valve_echo);
+            assert_eq!(decoded.raw_values, firmware_line);
+        }
+    }
+
+    #[test]
+    fn rejects_corrupted_frame() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap();
+        let frame = encode_frame(&data_point);
+        let mut payload = cobs_decode(strip_delimiter(&frame)).unwrap();
+        payload[5] ^= 0x10;
+        let error = decode_frame(&cobs_encode(&payload)).unwrap_err();
+        assert!(error.starts_with("Frame CRC mismatch"), "{}", error);
+    }
+
+    #[test]
+    fn rejects_short_frame() {
+        assert!(decode_frame(&cobs_encode(&[1, 2, 3])).is_err());
+        assert!(decode_frame(&[]).is_err());
+    }
+
+    /// Every message a splitter cuts from `stream`, fed in chunks of `chunk` bytes.
+    fn split_all(stream: &[u8], chunk: usize) -> Vec<MixedMessage> {
+        let mut splitter = MixedSplitter::default();
+        let mut messages = Vec::new();
+        for mut bytes in stream.chunks(chunk) {
+            while !bytes.is_empty() {
+                let (used, message) = splitter.push(bytes);
+                messages.extend(message);
+                bytes = &bytes[used..];
+            }
+        }
+        messages
+    }
+
+    #[test]
+    fn splits_lines_and_frames_from_one_stream() {
+        let data_point = parse_line("1500,2.3,1.25,18,0,90,-45,0").unwrap();
+        let frame = encode_frame(&data_point);
+        let mut stream = b"1000,1.0,0.5,3,2,90,90,0\r\n".to_vec();
+        stream.extend(&frame);
+        stream.extend(b"\nDEBUG hello\n");
+        stream.extend(&frame);
+        for chunk in [1, 3, stream.len()] {
+            let messages = split_all(&stream, chunk);
+            assert_eq!(messages.len(), 4, "chunk {}", chunk);
+            assert_eq!(
+                messages[0],
+                MixedMessage::Line("1000,1.0,0.5,3,2,90,90,0\r".to_string())
+            );
+            assert_eq!(messages[2], MixedMessage::Line("DEBUG hello".to_string()));
+            for message in [&messages[1], &messages[3]] {
+                let MixedMessage::Frame(encoded) = message else {
+                    panic!("expected a frame, got {:?}", message);
+                };
+                assert_eq!(decode_frame(encoded).unwrap().time, 1500.0);
+            }
+        }
+    }
+
+    #[test]
+    fn keeps_newline_bytes_inside_frames() {
+        // A pulse count of 10 encodes a 0x0A byte inside the frame
+        let data_point = parse_line("1500,2.3,1.25,10,10,90,-45,0").unwrap();
+        let frame = encode_frame(&data_point);
+        assert!(frame.contains(&b'\n'));
+        let messages = split_all(&frame, frame.len());
+        let [MixedMessage::Frame(encoded)] = messages.as_slice() else {
+            panic!("expected one frame, got {:?}", messages);
+        };
+        assert_eq!(decode_frame(encoded).unwrap().pulse_count_fuel, 10);
+    }
+}
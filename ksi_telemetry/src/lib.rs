@@ -0,0 +1,28 @@
+//! Engine telemetry shared by the KSI ground tools: the data point type and its
+//! quality flags, firmware line and binary frame parsing, packet framing, checksums, the
+//! data_log.csv and data_log.parquet layouts, polled environment sensors, firmware console lines, and
+//! a serial port source.
+
+pub mod checksum;
+pub mod console;
+pub mod data_log;
+mod data_point;
+pub mod environment;
+pub mod frame;
+pub mod packet;
+#[cfg(feature = "parquet")]
+pub mod parquet_log;
+mod parse;
+pub mod quality;
+#[cfg(feature = "serial")]
+mod source;
+
+pub use data_point::{ControllerState, EngineDataPoint, Relays, Temperatures, ValveEcho};
+pub use parse::{
+    parse_channel_values, parse_engine_data_point, parse_line, parse_line_with_pressures,
+    BASE_VALUE_COUNT, CONTROLLER_VALUE_COUNT, TEMPERATURE_VALUE_COUNT, THRUST_VALUE_COUNT,
+    VALVE_ECHO_VALUE_COUNT,
+};
+pub use quality::Quality;
+#[cfg(feature = "serial")]
+pub use source::{Protocol, SerialTelemetrySource, Telemetry};
@@ -0,0 +1,211 @@
+//! Packet framing for boards that don't delimit their messages with newlines
+//! or zero bytes: packets of a fixed length, or led by their length.
+//!
+//! A packet carries one message in the stream's protocol: a CSV line, or a
+//! binary record laid out as in `frame` but without COBS encoding, since the
+//! framing already says where it ends.
+
+/// Maximum length a length prefix may announce; longer ones are taken as
+/// corruption, so a bad prefix can't stall the stream waiting for megabytes.
+pub const MAX_PACKET_LEN: usize = 4096;
+
+/// How messages are cut from the byte stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+pub enum Framing {
+    /// The protocol's own delimiters: newlines for CSV, zero bytes for binary frames
+    #[default]
+    Delimited,
+    /// Every packet is this many bytes
+    FixedLength(usize),
+    /// Each packet starts with its length in bytes, not counting the prefix,
+    /// as an unsigned integer of 1, 2, or 4 bytes
+    LengthPrefixed { width: u8, big_endian: bool },
+}
+
+impl Framing {
+    /// Checks the packet length or prefix width can frame anything.
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            Framing::Delimited => Ok(()),
+            Framing::FixedLength(0) => Err("Fixed packet length must be at least 1".to_string()),
+            Framing::FixedLength(len) if len > MAX_PACKET_LEN => Err(format!(
+                "Fixed packet length must be at most {}",
+                MAX_PACKET_LEN
+            )),
+            Framing::FixedLength(_) => Ok(()),
+            Framing::LengthPrefixed { width, .. } if ![1, 2, 4].contains(&width) => Err(format!(
+                "Length prefix must be 1, 2, or 4 bytes, not {}",
+                width
+            )),
+            Framing::LengthPrefixed { .. } => Ok(()),
+        }
+    }
+}
+
+/// Cuts packets from the stream as bytes arrive, keeping partial packets
+/// across reads.
+#[derive(Debug)]
+pub struct PacketSplitter {
+    framing: Framing,
+    pending: Vec<u8>,
+    // Payload length announced by the prefix in `pending`, once it is complete
+    expected: Option<usize>,
+}
+
+impl PacketSplitter {
+    pub fn new(framing: Framing) -> Self {
+        Self {
+            framing,
+            pending: Vec::new(),
+            expected: None,
+        }
+    }
+
+    /// Takes bytes up to the end of the next packet. Returns how many were
+    /// taken and the packet, if one ended; an empty length-prefixed packet is
+    /// taken without returning one.
+    ///
+    /// A prefix announcing more than `MAX_PACKET_LEN` is dropped with an error.
+    /// Delimited framing has no packets to cut here and takes every byte.
+    pub fn push(&mut self, bytes: &[u8]) -> (usize, Option<Result<Vec<u8>, String>>) {
+        for (i, &byte) in bytes.iter().enumerate() {
+            self.pending.push(byte);
+            let packet = match self.framing {
+                Framing::Delimited => {
+                    self.pending.clear();
+                    continue;
+                }
+                Framing::FixedLength(len) if self.pending.len() >= len => {
+                    Ok(std::mem::take(&mut self.pending))
+                }
+                Framing::FixedLength(_) => continue,
+                Framing::LengthPrefixed { width, big_endian } => {
+                    let width = width as usize;
+                    let Some(expected) = self.expected else {
+                        if self.pending.len() < width {
+                            continue;
+                        }
+                        let len = prefix_len(&self.pending, big_endian);
+                        self.pending.clear();
+                        if len > MAX_PACKET_LEN {
+                            return (
+                                i + 1,
+                                Some(Err(format!("Packet length {} exceeds the maximum", len))),
+                            );
+                        }
+                        if len > 0 {
+                            self.expected = Some(len);
+                        }
+                        continue;
+                    };
+                    if self.pending.len() < expected {
+                        continue;
+                    }
+                    self.expected = None;
+                    Ok(std::mem::take(&mut self.pending))
+                }
+            };
+            return (i + 1, Some(packet));
+        }
+        (bytes.len(), None)
+    }
+}
+
+/// Reads a length prefix as an unsigned integer.
+fn prefix_len(prefix: &[u8], big_endian: bool) -> usize {
+    let fold = |len: usize, &byte: &u8| len << 8 | byte as usize;
+    if big_endian {
+        prefix.iter().fold(0, fold)
+    } else {
+        prefix.iter().rev().fold(0, fold)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Feeds the stream one chunk at a time, as reads would deliver it
+    fn split(framing: Framing, chunks: &[&[u8]]) -> Vec<Result<Vec<u8>, String>> {
+        let mut splitter = PacketSplitter::new(framing);
+        let mut packets = Vec::new();
+        for chunk in chunks {
+            let mut rest = *chunk;
+            while !rest.is_empty() {
+                let (used, packet) = splitter.push(rest);
+                packets.extend(packet);
+                rest = &rest[used..];
+            }
+        }
+        packets
+    }
+
+    #[test]
+    fn fixed_length_packets_span_reads() {
+        let packets = split(Framing::FixedLength(3), &[b"ab", b"c\n\0d", b"ef"]);
+        assert_eq!(
+            packets,
+            vec![Ok(b"abc".to_vec()), Ok(b"\n\0d".to_vec())],
+            "newline and zero bytes are data, and the partial packet waits"
+        );
+    }
+
+    #[test]
+    fn length_prefixed_packets_in_either_byte_order() {
+        let little = Framing::LengthPrefixed {
+            width: 2,
+            big_endian: false,
+        };
+        assert_eq!(
+            split(little, &[&[3, 0, 1], &[2, 3, 1], &[0, 9]]),
+            vec![Ok(vec![1, 2, 3]), Ok(vec![9])]
+        );
+        let big = Framing::LengthPrefixed {
+            width: 2,
+            big_endian: true,
+        };
+        assert_eq!(split(big, &[&[0, 2, 7, 8]]), vec![Ok(vec![7, 8])]);
+    }
+
+    #[test]
+    fn empty_length_prefixed_packets_are_skipped() {
+        let framing = Framing::LengthPrefixed {
+            width: 1,
+            big_endian: false,
+        };
+        assert_eq!(split(framing, &[&[0, 0, 1, 5]]), vec![Ok(vec![5])]);
+    }
+
+    #[test]
+    fn oversized_prefix_is_an_error() {
+        let framing = Framing::LengthPrefixed {
+            width: 2,
+            big_endian: true,
+        };
+        let packets = split(framing, &[&[0xFF, 0xFF, 0, 1, 0x42]]);
+        assert!(packets[0].is_err());
+        assert_eq!(
+            packets[1..],
+            [Ok(vec![0x42])],
+            "reads resume after the prefix"
+        );
+    }
+
+    #[test]
+    fn validates_lengths_and_widths() {
+        assert!(Framing::Delimited.validate().is_ok());
+        assert!(Framing::FixedLength(0).validate().is_err());
+        assert!(Framing::FixedLength(32).validate().is_ok());
+        let prefixed = |width| Framing::LengthPrefixed {
+            width,
+            big_endian: false,
+        };
+        assert!(prefixed(3).validate().is_err());
+        assert!(prefixed(4).validate().is_ok());
+    }
+}
@@ -0,0 +1,345 @@
+//! Layout of data_log.parquet: the data_log.csv columns, typed.
+
+use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow_array::cast::AsArray;
+use arrow_array::types::{Float64Type, Int32Type, UInt64Type};
+use arrow_array::{
+    Array, ArrayRef, BooleanArray, Float64Array, Int32Array, RecordBatch, StringArray, UInt64Array,
+};
+use arrow_schema::{DataType, Field, Schema};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use parquet::arrow::ArrowWriter;
+use parquet::basic::Compression;
+use parquet::file::properties::WriterProperties;
+
+use crate::data_log::{self, COLUMNS};
+use crate::{ControllerState, EngineDataPoint, Quality, Relays, Temperatures, ValveEcho};
+
+/// Types of `data_log::COLUMNS`, in order; pressure columns are Float64.
+const TYPES: [DataType; 25] = [
+    DataType::UInt64,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Int32,
+    DataType::Int32,
+    DataType::Int32,
+    DataType::Int32,
+    DataType::Boolean,
+    DataType::Boolean,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Float64,
+    DataType::Utf8,
+    DataType::Boolean,
+    DataType::Boolean,
+    DataType::Boolean,
+    DataType::Boolean,
+    DataType::Boolean,
+    DataType::Float64,
+    DataType::UInt64,
+];
+
+pub fn schema(pressure_channels: usize) -> Schema {
+    // Columns the firmware may not stream, T-time before any countdown, and
+    // Unix ms in converted older logs are nullable, as they are empty in the CSV
+    let nullable = |name: &str| {
+        name.starts_with("controller_")
+            || name.starts_with("temperature_")
+            || name.starts_with("reported_")
+            || name == "thrust"
+            || name == "t_time"
+            || name == "unix_ms"
+    };
+    let mut fields: Vec<Field> = COLUMNS
+        .iter()
+        .zip(TYPES)
+        .map(|(name, data_type)| Field::new(*name, data_type, nullable(name)))
+        .collect();
+    fields.extend(
+        (0..pressure_channels)
+            .map(|i| Field::new(data_log::pressure_column(i), DataType::Float64, true)),
+    );
+    Schema::new(fields)
+}
+
+/// The rows as one batch under `schema`; a row missing a pressure channel has
+/// it null.
+pub fn record_batch(schema: &Arc<Schema>, rows: &[EngineDataPoint]) -> Result<RecordBatch, String> {
+    let f64s = |value: fn(&EngineDataPoint) -> f64| -> ArrayRef {
+        Arc::new(Float64Array::from_iter_values(rows.iter().map(value)))
+    };
+    let optional_f64s = |value: fn(&EngineDataPoint) -> Option<f64>| -> ArrayRef {
+        Arc::new(rows.iter().map(value).collect::<Float64Array>())
+    };
+    let i32s = |value: fn(&EngineDataPoint) -> i32| -> ArrayRef {
+        Arc::new(Int32Array::from_iter_values(rows.iter().map(value)))
+    };
+    let bools = |value: fn(&EngineDataPoint) -> Option<bool>| -> ArrayRef {
+        Arc::new(rows.iter().map(value).collect::<BooleanArray>())
+    };
+    let mut columns: Vec<ArrayRef> = vec![
+        Arc::new(UInt64Array::from_iter_values(
+            rows.iter().map(|r| r.timestamp),
+        )),
+        f64s(|r| r.time),
+        f64s(|r| r.flow_rate_fuel),
+        f64s(|r| r.flow_rate_oxi),
+        i32s(|r| r.pulse_count_fuel),
+        i32s(|r| r.pulse_count_oxi),
+        i32s(|r| r.desired_pos_fuel),
+        i32s(|r| r.desired_pos_oxi),
+        bools(|r| Some(r.fuel_valve_open)),
+        bools(|r| Some(r.oxi_valve_open)),
+        optional_f64s(|r| r.controller.map(|c| c.error)),
+        optional_f64s(|r| r.controller.map(|c| c.integrator)),
+        optional_f64s(|r| r.controller.map(|c| c.output)),
+        optional_f64s(|r| r.temperatures.map(|t| t.nozzle)),
+        optional_f64s(|r| r.temperatures.map(|t| t.tank)),
+        optional_f64s(|r| r.temperatures.map(|t| t.ambient)),
+        optional_f64s(|r| r.thrust),
+        Arc::new(StringArray::from_iter_values(
+            rows.iter().map(|r| r.quality.as_str()),
+        )),
+        bools(|r| r.valve_echo.map(|e| e.fuel_open)),
+        bools(|r| r.valve_echo.map(|e| e.oxi_open)),
+        bools(|r| Some(r.relays.lighting)),
+        bools(|r| Some(r.relays.camera_power)),
+        bools(|r| Some(r.relays.beacon)),
+        optional_f64s(|r| r.t_time),
+        Arc::new(rows.iter().map(|r| r.unix_ms).collect::<UInt64Array>()),
+    ];
+    for i in COLUMNS.len()..schema.fields().len() {
+        let channel = i - COLUMNS.len();
+        columns.push(Arc::new(
+            rows.iter()
+                .map(|r| r.pressures.get(channel).copied())
+                .collect::<Float64Array>(),
+        ));
+    }
+    RecordBatch::try_new(schema.clone(), columns).map_err(|e| e.to_string())
+}
+
+/// Writes the rows to a new Parquet log in one go.
+///
+/// Every row must carry the same number of pressure channels.
+pub fn write(path: &Path, rows: &[EngineDataPoint]) -> Result<(), String> {
+    let pressure_channels = rows.first().map_or(0, |r| r.pressures.len());
+    if rows.iter().any(|r| r.pressures.len() != pressure_channels) {
+        return Err("Rows have different numbers of pressure channels".to_string());
+    }
+    let schema = Arc::new(schema(pressure_channels));
+    let file =
+        File::create(path).map_err(|e| format!("Failed to create {}: {}", path.display(), e))?;
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::SNAPPY)
+        .build();
+    let mut writer = ArrowWriter::try_new(file, schema.clone(), Some(properties))
+        .map_err(|e| format!("Failed to start {}: {}", path.display(), e))?;
+    if !rows.is_empty() {
+        let batch = record_batch(&schema, rows)?;
+        writer
+            .write(&batch)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))?;
+    }
+    writer
+        .close()
+        .map_err(|e| format!("Failed to close {}: {}", path.display(), e))?;
+    Ok(())
+}
+
+/// Reads every row of a Parquet log written with this layout.
+///
+/// A null pressure reads back as NaN, as a channel without a value does in the CSV.
+pub fn read(path: &Path) -> Result<Vec<EngineDataPoint>, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let reader = ParquetRecordBatchReaderBuilder::try_new(file)
+        .and_then(|builder| builder.build())
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let mut rows = Vec::new();
+    for batch in reader {
+        let batch = batch.map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        read_batch(&batch, &mut rows).map_err(|e| format!("{}: {}", path.display(), e))?;
+    }
+    Ok(rows)
+}
+
+fn read_batch(batch: &RecordBatch, rows: &mut Vec<EngineDataPoint>) -> Result<(), String> {
+    let column = |name: &str| {
+        batch
+            .column_by_name(name)
+            .ok_or_else(|| format!("No column named {}", name))
+    };
+    // The casts panic on another type, so each column's type is checked against the layout first
+    for (name, data_type) in COLUMNS.iter().zip(TYPES) {
+        if column(name)?.data_type() != &data_type {
+            return Err(format!("Column {} is not {}", name, data_type));
+        }
+    }
+    let f64s = |name: &str| Ok::<_, String>(column(name)?.as_primitive::<Float64Type>().clone());
+    let i32s = |name: &str| Ok::<_, String>(column(name)?.as_primitive::<Int32Type>().clone());
+    let bools = |name: &str| Ok::<_, String>(column(name)?.as_boolean().clone());
+    let optional = |array: &Float64Array, i: usize| array.is_valid(i).then(|| array.value(i));
+    let optional_bool = |array: &BooleanArray, i: usize| array.is_valid(i).then(|| array.value(i));
+
+    let timestamp = column("timestamp")?.as_primitive::<UInt64Type>().clone();
+    let time = f64s("time")?;
+    let flow_rate_fuel = f64s("flow_rate_fuel")?;
+    let flow_rate_oxi = f64s("flow_rate_oxi")?;
+    let pulse_count_fuel = i32s("pulse_count_fuel")?;
+    let pulse_count_oxi = i32s("pulse_count_oxi")?;
+    let desired_pos_fuel = i32s("desired_pos_fuel")?;
+    let desired_pos_oxi = i32s("desired_pos_oxi")?;
+    let fuel_valve_open = bools("fuel_valve_open")?;
+    let oxi_valve_open = bools("oxi_valve_open")?;
+    let controller_error = f64s("controller_error")?;
+    let controller_integrator = f64s("controller_integrator")?;
+    let controller_output = f64s("controller_output")?;
+    let temperature_nozzle = f64s("temperature_nozzle")?;
+    let temperature_tank = f64s("temperature_tank")?;
+    let temperature_ambient = f64s("temperature_ambient")?;
+    let thrust = f64s("thrust")?;
+    let reported_fuel_valve = bools("reported_fuel_valve")?;
+    let reported_oxi_valve = bools("reported_oxi_valve")?;
+    let relay_lighting = bools("relay_lighting")?;
+    let relay_camera_power = bools("relay_camera_power")?;
+    let relay_beacon = bools("relay_beacon")?;
+    let t_time = f64s("t_time")?;
+    let unix_ms = column("unix_ms")?.as_primitive::<UInt64Type>().clone();
+    let quality = column("quality")?.as_string::<i32>().clone();
+    let pressures = (0..)
+        .map(data_log::pressure_column)
+        .map_while(|name| batch.column_by_name(&name).cloned())
+        .map(|array| match array.data_type() {
+            DataType::Float64 => Ok(array.as_primitive::<Float64Type>().clone()),
+            other => Err(format!("Pressure column is not Float64 but {}", other)),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    for i in 0..batch.num_rows() {
+        let controller = match (
+            optional(&controller_error, i),
+            optional(&controller_integrator, i),
+            optional(&controller_output, i),
+        ) {
+            (Some(error), Some(integrator), Some(output)) => Some(ControllerState {
+                error,
+                integrator,
+                output,
+            }),
+            _ => None,
+        };
+        let temperatures = match (
+            optional(&temperature_nozzle, i),
+            optional(&temperature_tank, i),
+            optional(&temperature_ambient, i),
+        ) {
+            (Some(nozzle), Some(tank), Some(ambient)) => Some(Temperatures {
+                nozzle,
+                tank,
+                ambient,
+            }),
+            _ => None,
+        };
+        let valve_echo = match (
+            optional_bool(&reported_fuel_valve, i),
+            optional_bool(&reported_oxi_valve, i),
+        ) {
+            (Some(fuel_open), Some(oxi_open)) => Some(ValveEcho {
+                fuel_open,
+                oxi_open,
+            }),
+            _ => None,
+        };
+        let mut row = EngineDataPoint {
+            timestamp: timestamp.value(i),
+            time: time.value(i),
+            flow_rate_fuel: flow_rate_fuel.value(i),
+            flow_rate_oxi: flow_rate_oxi.value(i),
+            pulse_count_fuel: pulse_count_fuel.value(i),
+            pulse_count_oxi: pulse_count_oxi.value(i),
+            desired_pos_fuel: desired_pos_fuel.value(i),
+            desired_pos_oxi: desired_pos_oxi.value(i),
+            fuel_valve_open: fuel_valve_open.value(i),
+            oxi_valve_open: oxi_valve_open.value(i),
+            raw_values: String::new(),
+            controller,
+            emergency: false,
+            pressures: pressures
+                .iter()
+                .map(|channel| optional(channel, i).unwrap_or(f64::NAN))
+                .collect(),
+            temperatures,
+            thrust: optional(&thrust, i),
+            quality: Quality::parse(quality.value(i))?,
+            valve_echo,
+            relays: Relays {
+                lighting: relay_lighting.value(i),
+                camera_power: relay_camera_power.value(i),
+                beacon: relay_beacon.value(i),
+            },
+            t_time: optional(&t_time, i),
+            unix_ms: unix_ms.is_valid(i).then(|| unix_ms.value(i)),
+        };
+        // As a CSV row would hold it
+        row.raw_values = row.to_log_line().trim_end().to_string();
+        rows.push(row);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_line;
+
+    #[test]
+    fn rows_round_trip() {
+        let mut rows = Vec::new();
+        for firmware_line in [
+            "1500,2.5,1.25,18,9,90,45,0",
+            "1510,0,0,0,0,0,0,0,0.5,-1,42,450,21.5,18,812.5",
+        ] {
+            let mut row = parse_line(firmware_line).unwrap();
+            row.timestamp = 1_700_000_000;
+            row.fuel_valve_open = true;
+            row.relays.camera_power = true;
+            row.t_time = Some(2.5);
+            row.unix_ms = Some(1_700_000_000_250);
+            rows.push(row);
+        }
+        rows[0].pressures = vec![12.5, f64::NAN];
+        rows[1].pressures = vec![3.25, 7.0];
+        rows[1].quality = Quality::Suspect;
+        rows[1].unix_ms = None;
+
+        let path = std::env::temp_dir().join(format!(
+            "ksi_parquet_round_trip_{}.parquet",
+            std::process::id()
+        ));
+        write(&path, &rows).unwrap();
+        let read_back = read(&path);
+        let _ = std::fs::remove_file(&path);
+        let read_back = read_back.unwrap();
+
+        assert_eq!(read_back.len(), rows.len());
+        for (read, row) in read_back.iter().zip(&rows) {
+            assert_eq!(read.to_log_line(), row.to_log_line());
+        }
+    }
+
+    #[test]
+    fn rows_need_the_same_pressure_channels() {
+        let mut rows = vec![parse_line("0,0,0,0,0,0,0,0").unwrap(); 2];
+        rows[1].pressures = vec![1.0];
+        let path = std::env::temp_dir().join("ksi_parquet_never_written.parquet");
+        assert!(write(&path, &rows).is_err());
+    }
+}
@@ -0,0 +1,357 @@
+use crate::quality::{self, Quality};
+use crate::{checksum, ControllerState, EngineDataPoint, Relays, Temperatures, ValveEcho};
+
+// Values per telemetry line, without and with the controller fields
+pub const BASE_VALUE_COUNT: usize = 8;
+pub const CONTROLLER_VALUE_COUNT: usize = 11;
+// Values when thermocouples follow the controller fields, which may then be empty
+pub const TEMPERATURE_VALUE_COUNT: usize = 14;
+// Values when load cell thrust follows the temperatures, which may then be empty too
+pub const THRUST_VALUE_COUNT: usize = 15;
+// Values when the valve state echo follows thrust
+pub const VALVE_ECHO_VALUE_COUNT: usize = 17;
+
+/// Parses one comma-separated firmware line, keeping it as the raw values.
+///
+/// A trailing checksum is verified and left out of the raw values.
+pub fn parse_line(line: &str) -> Result<EngineDataPoint, String> {
+    parse_line_with_pressures(line, 0)
+}
+
+/// Parses a firmware line that ends with `pressure_channels` pressure values.
+pub fn parse_line_with_pressures(
+    line: &str,
+    pressure_channels: usize,
+) -> Result<EngineDataPoint, String> {
+    let raw_values = checksum::verify(line.trim())?;
+    let values: Vec<&str> = raw_values.split(',').collect();
+    let Some(split) = values.len().checked_sub(pressure_channels) else {
+        return Err(format!("Unexpected number of values: {}", values.len()));
+    };
+    let (values, pressure_values) = values.split_at(split);
+    let mut data_point = parse_engine_data_point(values)?;
+    data_point.pressures = pressure_values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            value
+                .parse::<f64>()
+                .map_err(|e| format!("Pressure {} parse error: {}", i + 1, e))
+        })
+        .collect::<Result<_, _>>()?;
+    data_point.raw_values = raw_values.to_string();
+    data_point.quality = quality::assess(&data_point);
+    Ok(data_point)
+}
+
+/// Parses a line of exactly `count` bare channel values, as sent by boards
+/// other than the engine controller. A trailing checksum is verified.
+pub fn parse_channel_values(line: &str, count: usize) -> Result<Vec<f64>, String> {
+    let raw_values = checksum::verify(line.trim())?;
+    let values: Vec<&str> = raw_values.split(',').collect();
+    if values.len() != count {
+        return Err(format!(
+            "Expected {} channel values, got {}",
+            count,
+            values.len()
+        ));
+    }
+    values
+        .iter()
+        .enumerate()
+        .map(|(i, value)| {
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|e| format!("Channel {} parse error: {}", i + 1, e))
+        })
+        .collect()
+}
+
+/// Parses a slice of string values into an EngineDataPoint.
+///
+/// The wall-clock timestamp and valve states are not part of the firmware
+/// line; callers fill them in.
+pub fn parse_engine_data_point(values: &[&str]) -> Result<EngineDataPoint, String> {
+    if ![
+        BASE_VALUE_COUNT,
+        CONTROLLER_VALUE_COUNT,
+        TEMPERATURE_VALUE_COUNT,
+        THRUST_VALUE_COUNT,
+        VALVE_ECHO_VALUE_COUNT,
+    ]
+    .contains(&values.len())
+    {
+        return Err(format!("Unexpected number of values: {}", values.len()));
+    }
+
+    let time = values[0]
+        .parse::<f64>()
+        .map_err(|e| format!("Time parse error: {}", e))?;
+    let flow_fuel = values[1]
+        .parse::<f64>()
+        .map_err(|e| format!("Flow fuel parse error: {}", e))?;
+    let flow_oxi = values[2]
+        .parse::<f64>()
+        .map_err(|e| format!("Flow oxi parse error: {}", e))?;
+    let pulse_fuel = values[3]
+        .parse::<i32>()
+        .map_err(|e| format!("Pulse fuel parse error: {}", e))?;
+    let pulse_oxi = values[4]
+        .parse::<i32>()
+        .map_err(|e| format!("Pulse oxi parse error: {}", e))?;
+    let pos_fuel = values[5]
+        .parse::<i32>()
+        .map_err(|e| format!("Pos fuel parse error: {}", e))?;
+    let pos_oxi = values[6]
+        .parse::<i32>()
+        .map_err(|e| format!("Pos oxi parse error: {}", e))?;
+    let flag = |index: usize, name: &str| match values[index].parse::<i32>() {
+        Ok(1) => Ok(true),
+        Ok(0) => Ok(false),
+        Ok(_) => Err(format!("{} value must be 0 or 1", name)),
+        Err(e) => Err(format!("{} parse error: {}", name, e)),
+    };
+    let emergency = flag(7, "Emergency")?;
+    // A group ending the line is required; firmware without it leaves its fields
+    // empty when sending a later group
+    let present = |range: std::ops::Range<usize>| {
+        values.len() == range.end
+            || values
+                .get(range)
+                .is_some_and(|fields| fields.iter().any(|v| !v.is_empty()))
+    };
+    let controller = if present(8..CONTROLLER_VALUE_COUNT) {
+        Some(ControllerState {
+            error: values[8]
+                .parse::<f64>()
+                .map_err(|e| format!("Controller error parse error: {}", e))?,
+            integrator: values[9]
+                .parse::<f64>()
+                .map_err(|e| format!("Controller integrator parse error: {}", e))?,
+            output: values[10]
+                .parse::<f64>()
+                .map_err(|e| format!("Controller output parse error: {}", e))?,
+        })
+    } else {
+        None
+    };
+    let temperatures = if present(CONTROLLER_VALUE_COUNT..TEMPERATURE_VALUE_COUNT) {
+        Some(Temperatures {
+            nozzle: values[11]
+                .parse::<f64>()
+                .map_err(|e| format!("Nozzle temperature parse error: {}", e))?,
+            tank: values[12]
+                .parse::<f64>()
+                .map_err(|e| format!("Tank temperature parse error: {}", e))?,
+            ambient: values[13]
+                .parse::<f64>()
+                .map_err(|e| format!("Ambient temperature parse error: {}", e))?,
+        })
+    } else {
+        None
+    };
+    let thrust = if present(TEMPERATURE_VALUE_COUNT..THRUST_VALUE_COUNT) {
+        Some(
+            values[14]
+                .parse::<f64>()
+                .map_err(|e| format!("Thrust parse error: {}", e))?,
+        )
+    } else {
+        None
+    };
+    let valve_echo = if values.len() == VALVE_ECHO_VALUE_COUNT {
+        Some(ValveEcho {
+            fuel_open: flag(15, "Fuel valve echo")?,
+            oxi_open: flag(16, "Oxi valve echo")?,
+        })
+    } else {
+        None
+    };
+
+    Ok(EngineDataPoint {
+        timestamp: 0, // Will be set later
+        time,
+        flow_rate_fuel: flow_fuel,
+        flow_rate_oxi: flow_oxi,
+        pulse_count_fuel: pulse_fuel,
+        pulse_count_oxi: pulse_oxi,
+        desired_pos_fuel: pos_fuel,
+        desired_pos_oxi: pos_oxi,
+        fuel_valve_open: false, // Will be set later
+        oxi_valve_open: false,  // Will be set later
+        raw_values: String::new(),
+        controller,
+        emergency,
+        pressures: Vec::new(), // Set by parse_line_with_pressures
+        temperatures,
+        thrust,
+        quality: Quality::Good, // Set by parse_line_with_pressures
+        valve_echo,
+        relays: Relays::default(), // Will be set later
+        t_time: None,              // Will be set later
+        unix_ms: None,             // Will be set later
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_base_line() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0\r\n").unwrap();
+        assert_eq!(data_point.time, 1500.0);
+        assert_eq!(data_point.flow_rate_fuel, 2.5);
+        assert_eq!(data_point.flow_rate_oxi, 1.25);
+        assert_eq!(data_point.pulse_count_fuel, 18);
+        assert_eq!(data_point.pulse_count_oxi, 9);
+        assert_eq!(data_point.desired_pos_fuel, 90);
+        assert_eq!(data_point.desired_pos_oxi, 45);
+        assert_eq!(data_point.controller, None);
+        assert!(!data_point.emergency);
+        assert_eq!(data_point.raw_values, "1500,2.5,1.25,18,9,90,45,0");
+    }
+
+    #[test]
+    fn parses_controller_fields() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,1,0.5,-1.5,42").unwrap();
+        assert_eq!(
+            data_point.controller,
+            Some(ControllerState {
+                error: 0.5,
+                integrator: -1.5,
+                output: 42.0,
+            })
+        );
+    }
+
+    #[test]
+    fn strips_verified_checksum() {
+        let payload = "1500,2.5,1.25,18,9,90,45,0";
+        let crc = checksum::crc16(payload.as_bytes());
+        let line = format!("{}*{:04X}\r\n", payload, crc);
+        assert_eq!(parse_line(&line).unwrap().raw_values, payload);
+        let corrupted = format!("{}*{:04X}", payload, crc ^ 1);
+        assert!(parse_line(&corrupted).is_err());
+    }
+
+    #[test]
+    fn parses_channel_values() {
+        assert_eq!(
+            parse_channel_values("12.5, 0,-3\r\n", 3).unwrap(),
+            vec![12.5, 0.0, -3.0]
+        );
+        assert!(parse_channel_values("12.5,0", 3).is_err());
+        assert!(parse_channel_values("12.5,x,0", 3).is_err());
+    }
+
+    #[test]
+    fn parses_temperatures() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0,,,,450,21.5,18").unwrap();
+        assert_eq!(data_point.controller, None);
+        assert_eq!(
+            data_point.temperatures,
+            Some(Temperatures {
+                nozzle: 450.0,
+                tank: 21.5,
+                ambient: 18.0,
+            })
+        );
+
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0,0.5,-1.5,42,450,21.5,18").unwrap();
+        assert!(data_point.controller.is_some());
+        assert!(data_point.temperatures.is_some());
+
+        let data_point =
+            parse_line_with_pressures("1500,2.5,1.25,18,9,90,45,0,,,,450,21.5,18,12", 1).unwrap();
+        assert_eq!(data_point.pressures, vec![12.0]);
+        assert_eq!(data_point.temperatures.map(|t| t.ambient), Some(18.0));
+
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,,,,450,,18").is_err());
+    }
+
+    #[test]
+    fn parses_thrust() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0,,,,,,,812.5").unwrap();
+        assert_eq!(data_point.thrust, Some(812.5));
+        assert_eq!(data_point.controller, None);
+        assert_eq!(data_point.temperatures, None);
+
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0,,,,450,21.5,18,812.5").unwrap();
+        assert_eq!(data_point.temperatures.map(|t| t.nozzle), Some(450.0));
+        assert_eq!(
+            parse_line("1500,2.5,1.25,18,9,90,45,0,,,,450,21.5,18")
+                .unwrap()
+                .thrust,
+            None
+        );
+
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,,,,,,,").is_err());
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,,,,450,,,812.5").is_err());
+    }
+
+    #[test]
+    fn parses_valve_echo() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,0,,,,,,,,1,0").unwrap();
+        assert_eq!(
+            data_point.valve_echo,
+            Some(ValveEcho {
+                fuel_open: true,
+                oxi_open: false,
+            })
+        );
+        assert_eq!(data_point.thrust, None);
+        assert_eq!(
+            parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap().valve_echo,
+            None
+        );
+
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,,,,,,,,2,0").is_err());
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,,,,,,,,1").is_err());
+    }
+
+    #[test]
+    fn parses_trailing_pressures() {
+        let data_point =
+            parse_line_with_pressures("1500,2.5,1.25,18,9,90,45,0,0.5,-1.5,42,12.5,3", 2).unwrap();
+        assert_eq!(data_point.pressures, vec![12.5, 3.0]);
+        assert!(data_point.controller.is_some());
+
+        let data_point = parse_line_with_pressures("1500,2.5,1.25,18,9,90,45,0,7", 1).unwrap();
+        assert_eq!(data_point.pressures, vec![7.0]);
+        assert_eq!(data_point.controller, None);
+
+        assert!(parse_line_with_pressures("1500,2.5,1.25,18,9,90,45,0", 1).is_err());
+        assert!(parse_line_with_pressures("1500,2.5,1.25,18,9,90,45,0,x", 1).is_err());
+        assert!(parse_line_with_pressures("1,2", 3).is_err());
+    }
+
+    #[test]
+    fn rejects_wrong_value_count() {
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45").is_err());
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,1,2,3,4").is_err());
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,,,").is_err());
+        assert!(parse_line("1500,2.5,1.25,18,9,90,45,0,1").is_err());
+        assert!(parse_line("").is_err());
+    }
+
+    #[test]
+    fn parses_emergency_flag() {
+        let data_point = parse_line("1500,2.5,1.25,18,9,90,45,1").unwrap();
+        assert!(data_point.emergency);
+    }
+
+    #[test]
+    fn rejects_invalid_emergency_flag() {
+        let error = parse_line("1500,2.5,1.25,18,9,90,45,2").unwrap_err();
+        assert_eq!(error, "Emergency value must be 0 or 1");
+    }
+
+    #[test]
+    fn rejects_malformed_numbers() {
+        let error = parse_line("1500,abc,1.25,18,9,90,45,0").unwrap_err();
+        assert!(error.starts_with("Flow fuel parse error"), "{}", error);
+        assert!(parse_line("1500,2.5,1.25,1.5,9,90,45,0").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+//! Data quality of a sample, set where it's parsed and degraded by later checks.
+//!
+//! Each sample carries the worst quality any stage gave it, and the log keeps it
+//! in the `quality` column so replays and offline tools see the same flags.
+
+use crate::EngineDataPoint;
+
+/// How far a sample can be trusted, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "snake_case"))]
+pub enum Quality {
+    #[default]
+    Good,
+    /// Not measured at this time, but filled in from neighbouring samples
+    Interpolated,
+    /// Measured, but failed a plausibility check
+    Suspect,
+    /// A sensor reported a value that is not a number
+    SensorFault,
+}
+
+impl Quality {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Quality::Good => "good",
+            Quality::Interpolated => "interpolated",
+            Quality::Suspect => "suspect",
+            Quality::SensorFault => "sensor_fault",
+        }
+    }
+
+    /// Parses a logged quality; an empty column reads as good.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value {
+            "" | "good" => Ok(Quality::Good),
+            "interpolated" => Ok(Quality::Interpolated),
+            "suspect" => Ok(Quality::Suspect),
+            "sensor_fault" => Ok(Quality::SensorFault),
+            other => Err(format!("Unknown quality: {:?}", other)),
+        }
+    }
+}
+
+/// Quality of a freshly parsed sample: a sensor fault if any measured value isn't finite.
+pub fn assess(data_point: &EngineDataPoint) -> Quality {
+    let controller = data_point
+        .controller
+        .map(|c| vec![c.error, c.integrator, c.output])
+        .unwrap_or_default();
+    let temperatures = data_point
+        .temperatures
+        .map(|t| vec![t.nozzle, t.tank, t.ambient])
+        .unwrap_or_default();
+    let finite = [
+        data_point.time,
+        data_point.flow_rate_fuel,
+        data_point.flow_rate_oxi,
+    ]
+    .iter()
+    .chain(&controller)
+    .chain(&temperatures)
+    .chain(&data_point.thrust)
+    .chain(&data_point.pressures)
+    .all(|value| value.is_finite());
+    if finite {
+        Quality::Good
+    } else {
+        Quality::SensorFault
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::parse_line;
+
+    #[test]
+    fn non_finite_values_are_sensor_faults() {
+        assert_eq!(
+            parse_line("1500,2.5,1.25,18,9,90,45,0").unwrap().quality,
+            Quality::Good
+        );
+        assert_eq!(
+            parse_line("1500,NaN,1.25,18,9,90,45,0").unwrap().quality,
+            Quality::SensorFault
+        );
+        assert_eq!(
+            parse_line("10,0,0,0,0,0,0,0,,,,inf,21.5,18")
+                .unwrap()
+                .quality,
+            Quality::SensorFault
+        );
+    }
+
+    #[test]
+    fn qualities_round_trip() {
+        for quality in [
+            Quality::Good,
+            Quality::Interpolated,
+            Quality::Suspect,
+            Quality::SensorFault,
+        ] {
+            assert_eq!(Quality::parse(quality.as_str()), Ok(quality));
+        }
+        assert_eq!(Quality::parse(""), Ok(Quality::Good));
+        assert!(Quality::parse("bad").is_err());
+        assert!(Quality::Suspect > Quality::Interpolated);
+    }
+}
@@ -0,0 +1,217 @@
+use std::io::{BufRead, BufReader, ErrorKind};
+use std::time::Duration;
+
+use serialport::SerialPort;
+
+use crate::console;
+use crate::environment::{self, EnvironmentReading};
+use crate::frame::{self, MixedMessage, MixedSplitter, FRAME_DELIMITER};
+use crate::packet::{Framing, PacketSplitter};
+use crate::{parse_channel_values, parse_line_with_pressures, EngineDataPoint};
+
+/// Wire format of the telemetry stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    /// Comma-separated lines, optionally checksummed
+    #[default]
+    Csv,
+    /// COBS-framed binary records, see `frame`
+    Binary,
+    /// Either, told apart per message, while firmware moves from CSV to binary
+    Mixed,
+}
+
+/// One message from the firmware.
+#[derive(Debug, Clone)]
+pub enum Telemetry {
+    DataPoint(EngineDataPoint),
+    /// Reply to an environment sensor poll; only sent as a CSV line
+    Environment(EnvironmentReading),
+    /// Free-text firmware debug output; only sent as a CSV line
+    Console(String),
+    /// Bare channel values from a board other than the engine controller, see
+    /// `SerialTelemetrySource::channel_values`
+    Values(Vec<f64>),
+}
+
+/// Telemetry read from the engine controller's serial port.
+pub struct SerialTelemetrySource {
+    reader: BufReader<Box<dyn SerialPort>>,
+    protocol: Protocol,
+    // Pressure values ending each CSV line; binary frames carry their own count
+    pressure_channels: usize,
+    // Whether CSV lines hold only the channel values
+    channel_values: bool,
+    // Bytes of a binary frame received before a read timed out
+    pending: Vec<u8>,
+    // Partial message in mixed mode
+    mixed: MixedSplitter,
+    // Cuts packets when the board doesn't delimit its messages
+    packets: Option<PacketSplitter>,
+}
+
+impl SerialTelemetrySource {
+    /// Opens the port; reads give up after `timeout` so callers can check for shutdown.
+    pub fn open(
+        port_name: &str,
+        baud_rate: u32,
+        timeout: Duration,
+        protocol: Protocol,
+    ) -> Result<Self, String> {
+        let port = serialport::new(port_name, baud_rate)
+            .timeout(timeout)
+            .open()
+            .map_err(|e| format!("Failed to open {}: {}", port_name, e))?;
+        Ok(Self {
+            reader: BufReader::new(port),
+            protocol,
+            pressure_channels: 0,
+            channel_values: false,
+            pending: Vec::new(),
+            mixed: MixedSplitter::default(),
+            packets: None,
+        })
+    }
+
+    /// Expects CSV lines to end with this many pressure values.
+    pub fn pressure_channels(mut self, count: usize) -> Self {
+        self.pressure_channels = count;
+        self
+    }
+
+    /// Reads CSV lines as bare channel values, `pressure_channels` of them,
+    /// instead of engine controller lines.
+    pub fn channel_values(mut self) -> Self {
+        self.channel_values = true;
+        self
+    }
+
+    /// Cuts messages into packets instead of at the protocol's delimiters.
+    pub fn framing(mut self, framing: Framing) -> Self {
+        self.packets = (framing != Framing::Delimited).then(|| PacketSplitter::new(framing));
+        self
+    }
+
+    /// A second handle to the same port, for sending commands from another thread.
+    pub fn try_clone_port(&self) -> Result<Box<dyn SerialPort>, String> {
+        self.reader
+            .get_ref()
+            .try_clone()
+            .map_err(|e| format!("Failed to clone serial port: {}", e))
+    }
+
+    /// Reads and parses the next line or frame.
+    ///
+    /// Returns `Ok(None)` when nothing arrived before the timeout and an error
+    /// only when the port itself failed.
+    pub fn read(&mut self) -> std::io::Result<Option<Result<Telemetry, String>>> {
+        if self.packets.is_some() {
+            return self.read_packet();
+        }
+        match self.protocol {
+            Protocol::Binary => {
+                return Ok(self
+                    .read_frame()?
+                    .map(|result| result.map(Telemetry::DataPoint)))
+            }
+            Protocol::Mixed => return self.read_mixed(),
+            Protocol::Csv => {}
+        }
+        let mut line = String::new();
+        match self.reader.read_line(&mut line) {
+            Ok(0) => Ok(None),
+            Ok(_) => Ok(Some(self.parse(&line))),
+            Err(e) if e.kind() == ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Sorts a CSV line into console text, a sensor reply, or a data point or
+    /// channel values.
+    fn parse(&self, line: &str) -> Result<Telemetry, String> {
+        if let Some(text) = console::console_text(line) {
+            Ok(Telemetry::Console(text.to_string()))
+        } else if environment::is_reply(line) {
+            environment::parse_reply(line).map(Telemetry::Environment)
+        } else if self.channel_values {
+            parse_channel_values(line, self.pressure_channels).map(Telemetry::Values)
+        } else {
+            parse_line_with_pressures(line, self.pressure_channels).map(Telemetry::DataPoint)
+        }
+    }
+
+    /// Reads the next CSV line or binary frame, whichever arrives.
+    fn read_mixed(&mut self) -> std::io::Result<Option<Result<Telemetry, String>>> {
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok([]) => return Ok(None),
+                Ok(available) => available,
+                Err(e) if e.kind() == ErrorKind::TimedOut => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let (used, message) = self.mixed.push(available);
+            self.reader.consume(used);
+            match message {
+                Some(MixedMessage::Line(line)) => return Ok(Some(self.parse(&line))),
+                Some(MixedMessage::Frame(encoded)) => {
+                    return Ok(Some(
+                        frame::decode_frame(&encoded).map(Telemetry::DataPoint),
+                    ))
+                }
+                None => {}
+            }
+        }
+    }
+
+    /// Reads the next packet and parses it per the protocol: a CSV line, a binary
+    /// record, or in mixed mode whichever it looks like.
+    fn read_packet(&mut self) -> std::io::Result<Option<Result<Telemetry, String>>> {
+        let Some(splitter) = &mut self.packets else {
+            return Ok(None);
+        };
+        loop {
+            let available = match self.reader.fill_buf() {
+                Ok([]) => return Ok(None),
+                Ok(available) => available,
+                Err(e) if e.kind() == ErrorKind::TimedOut => return Ok(None),
+                Err(e) => return Err(e),
+            };
+            let (used, packet) = splitter.push(available);
+            self.reader.consume(used);
+            let packet = match packet {
+                Some(Ok(packet)) => packet,
+                Some(Err(e)) => return Ok(Some(Err(e))),
+                None => continue,
+            };
+            let text = match self.protocol {
+                Protocol::Csv => true,
+                Protocol::Binary => false,
+                Protocol::Mixed => packet
+                    .iter()
+                    .all(|&b| b.is_ascii_graphic() || matches!(b, b' ' | b'\t' | b'\r' | b'\n')),
+            };
+            return Ok(Some(if text {
+                self.parse(&String::from_utf8_lossy(&packet))
+            } else {
+                frame::decode_record(&packet).map(Telemetry::DataPoint)
+            }));
+        }
+    }
+
+    /// Reads up to the next frame delimiter, keeping partial frames across timeouts.
+    fn read_frame(&mut self) -> std::io::Result<Option<Result<EngineDataPoint, String>>> {
+        match self.reader.read_until(FRAME_DELIMITER, &mut self.pending) {
+            Ok(0) => Ok(None),
+            Ok(_) if self.pending.last() == Some(&FRAME_DELIMITER) => {
+                let encoded = &self.pending[..self.pending.len() - 1];
+                // Back-to-back delimiters carry no frame
+                let result = (!encoded.is_empty()).then(|| frame::decode_frame(encoded));
+                self.pending.clear();
+                Ok(result)
+            }
+            Ok(_) => Ok(None),
+            Err(e) if e.kind() == ErrorKind::TimedOut => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
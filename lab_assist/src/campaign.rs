@@ -0,0 +1,93 @@
+use anyhow::{Context, Result};
+use chrono::{Local, NaiveDate, TimeZone};
+use rusqlite::{params, Connection};
+use std::path::Path;
+use std::time::Duration;
+
+use crate::Experiment;
+
+/// Same definition groundcontrol creates, so whichever opens the database first makes it.
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS lab_notes (
+    id INTEGER PRIMARY KEY,
+    folder TEXT NOT NULL,
+    transcript TEXT NOT NULL,
+    date INTEGER,
+    experiment_type TEXT,
+    objective TEXT,
+    summary_path TEXT NOT NULL,
+    metadata TEXT NOT NULL,
+    UNIQUE (folder, transcript)
+);
+";
+
+/// Indexes summarized experiments in groundcontrol's campaign database, so the
+/// campaign timeline shows lab notes alongside telemetry sessions.
+///
+/// Re-exporting a transcript replaces its earlier entry.
+pub fn export(
+    db_path: &Path,
+    folder: &str,
+    summary_path: &Path,
+    experiments: &[Experiment],
+) -> Result<()> {
+    let mut conn = Connection::open(db_path)
+        .with_context(|| format!("Failed to open campaign database: {}", db_path.display()))?;
+    // groundcontrol may be writing samples to the same database
+    conn.busy_timeout(Duration::from_secs(5))?;
+    conn.execute_batch(SCHEMA)?;
+
+    // Folders are named like "Nov 20 2024"; the entry is placed at local midnight
+    let date = NaiveDate::parse_from_str(folder, "%b %d %Y")
+        .ok()
+        .and_then(|day| {
+            Local
+                .from_local_datetime(&day.and_hms_opt(0, 0, 0)?)
+                .earliest()
+        })
+        .map(|midnight| midnight.timestamp());
+    let tx = conn.transaction()?;
+    for experiment in experiments {
+        let metadata: String = experiment
+            .metadata
+            .iter()
+            .map(|(key, value)| format!("{}: {}\n", key, value))
+            .collect();
+        tx.execute(
+            "INSERT INTO lab_notes
+                (folder, transcript, date, experiment_type, objective, summary_path, metadata)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+             ON CONFLICT(folder, transcript) DO UPDATE SET
+                date = ?3, experiment_type = ?4, objective = ?5, summary_path = ?6, metadata = ?7",
+            params![
+                folder,
+                experiment.transcript,
+                date,
+                experiment.metadata.get("type"),
+                objective(&experiment.summary),
+                summary_path.display().to_string(),
+                metadata,
+            ],
+        )?;
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+/// The text of a summary's Objective section, if it has one.
+fn objective(summary: &str) -> Option<String> {
+    let mut lines = summary.lines().skip_while(|line| {
+        !line
+            .trim_start_matches('#')
+            .trim()
+            .eq_ignore_ascii_case("objective")
+    });
+    lines.next()?;
+    let text = lines
+        .take_while(|line| !line.starts_with('#'))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string();
+    (!text.is_empty()).then_some(text)
+}
@@ -0,0 +1,142 @@
+use anyhow::{Context, Result};
+use chrono::Local;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+use crate::Experiment;
+
+/// Hidden line at the end of a daily summary listing the transcripts it covers.
+const TRANSCRIPTS_MARKER: &str = "<!-- lab_assist transcripts: ";
+const REVISION_HEADING: &str = "## Revision History";
+const GENERATED_PREFIX: &str = "*Generated on ";
+
+/// Transcript file names a daily summary already covers.
+///
+/// Summaries written before the list was kept are taken to cover every
+/// transcript that is older than the summary itself.
+pub fn summarized_transcripts(
+    document: &str,
+    summary_path: &Path,
+    transcripts: &[String],
+) -> Result<BTreeSet<String>> {
+    if let Some(line) = document
+        .lines()
+        .find(|line| line.starts_with(TRANSCRIPTS_MARKER))
+    {
+        return Ok(line[TRANSCRIPTS_MARKER.len()..]
+            .trim_end_matches("-->")
+            .split('|')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect());
+    }
+    let modified = |path: &Path| -> Result<_> {
+        fs::metadata(path)
+            .and_then(|m| m.modified())
+            .with_context(|| format!("Failed to read modification time: {}", path.display()))
+    };
+    let summary_modified = modified(summary_path)?;
+    let directory = summary_path.parent().unwrap_or(Path::new("."));
+    let mut covered = BTreeSet::new();
+    for name in transcripts {
+        if modified(&directory.join(name))? <= summary_modified {
+            covered.insert(name.clone());
+        }
+    }
+    Ok(covered)
+}
+
+/// A new daily summary of the given experiments.
+pub fn create(date: &str, experiments: &[Experiment]) -> String {
+    let mut document = format!("# Daily Experiment Summary - {}\n\n", date);
+    document.push_str(&experiment_sections(1, experiments));
+    let today = Local::now().format("%Y-%m-%d");
+    document.push_str(&format!(
+        "{}\n\n- {}: Summarized {}\n\n{}{}*\n",
+        REVISION_HEADING,
+        today,
+        names(experiments),
+        GENERATED_PREFIX,
+        today
+    ));
+    document.push_str(&marker(experiments.iter().map(|e| e.transcript.clone())));
+    document
+}
+
+/// Appends new experiments to an existing daily summary, numbered after the ones
+/// already there, and records the addition in its revision history.
+///
+/// Everything already in the document, including hand edits, is kept.
+pub fn update(existing: &str, covered: &BTreeSet<String>, experiments: &[Experiment]) -> String {
+    let today = Local::now().format("%Y-%m-%d");
+    let existing: String = existing
+        .lines()
+        .filter(|line| !line.starts_with(TRANSCRIPTS_MARKER))
+        .map(|line| format!("{}\n", line))
+        .collect();
+    let experiment_count = existing
+        .lines()
+        .filter(|line| line.starts_with("## Experiment "))
+        .count();
+    let revision = format!("- {}: Added {}\n", today, names(experiments));
+
+    // New experiments go before the revision history, or the footer in older summaries
+    let split = existing
+        .find(REVISION_HEADING)
+        .or_else(|| existing.find(GENERATED_PREFIX))
+        .unwrap_or(existing.len());
+    let (head, tail) = existing.split_at(split);
+    let mut document = head.to_string();
+    document.push_str(&experiment_sections(experiment_count + 1, experiments));
+    if tail.starts_with(REVISION_HEADING) {
+        // The new entry goes after the last one, before the footer
+        let footer = tail.find(GENERATED_PREFIX).unwrap_or(tail.len());
+        let entries = tail[..footer].trim_end();
+        document.push_str(&format!("{}\n{}\n{}", entries, revision, &tail[footer..]));
+    } else {
+        let original = tail
+            .trim()
+            .strip_prefix(GENERATED_PREFIX)
+            .and_then(|rest| rest.split('*').next())
+            .unwrap_or("Earlier");
+        document.push_str(&format!(
+            "{}\n\n- {}: Summarized {} transcript(s)\n{}\n{}",
+            REVISION_HEADING, original, experiment_count, revision, tail
+        ));
+    }
+    if !document.ends_with('\n') {
+        document.push('\n');
+    }
+    let all = covered
+        .iter()
+        .cloned()
+        .chain(experiments.iter().map(|e| e.transcript.clone()));
+    document.push_str(&marker(all));
+    document
+}
+
+fn experiment_sections(first: usize, experiments: &[Experiment]) -> String {
+    experiments
+        .iter()
+        .enumerate()
+        .map(|(i, e)| format!("## Experiment {}\n\n{}\n\n---\n\n", first + i, e.summary))
+        .collect()
+}
+
+fn names(experiments: &[Experiment]) -> String {
+    experiments
+        .iter()
+        .map(|e| e.transcript.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn marker(names: impl Iterator<Item = String>) -> String {
+    let names: BTreeSet<String> = names.collect();
+    format!(
+        "{}{} -->\n",
+        TRANSCRIPTS_MARKER,
+        names.into_iter().collect::<Vec<_>>().join("|")
+    )
+}
@@ -0,0 +1,244 @@
+use anyhow::{bail, Context, Result};
+use async_openai::config::OpenAIConfig;
+use async_openai::types::CreateEmbeddingRequestArgs;
+use async_openai::Client;
+use chrono::Local;
+use regex::Regex;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::redact::Redactor;
+use crate::{complete, generate_summary, SUMMARY_MODEL, TEMPLATE_PATH};
+
+const EVAL_DIRECTORY: &str = "./Evals/";
+const EMBEDDING_MODEL: &str = "text-embedding-3-small";
+const GRADER_MODEL: &str = "gpt-4o-mini";
+const RUBRIC: [&str; 4] = ["Coverage", "Accuracy", "Structure", "Concision"];
+
+/// A transcript and the human-written summary it's scored against.
+struct Case {
+    name: String,
+    transcript: String,
+    reference: String,
+}
+
+/// Scores of one model and template over every case.
+struct Scores {
+    model: String,
+    template: PathBuf,
+    similarity: Vec<f64>,
+    // Per case, one 1-5 score per rubric criterion
+    rubric: Vec<[f64; RUBRIC.len()]>,
+}
+
+/// Scores generated summaries against the reference summaries in ./Evals/, for
+/// every combination of `--model` and `--template` given, and writes a report there.
+///
+/// Each case is `<name>.txt` with its reference in `<name>_reference.md`.
+pub async fn run(
+    args: &[String],
+    client: &Client<OpenAIConfig>,
+    redactor: &Redactor,
+) -> Result<()> {
+    let mut models = Vec::new();
+    let mut templates = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        let value = args
+            .next()
+            .with_context(|| format!("{} needs a value", arg))?;
+        match arg.as_str() {
+            "--model" => models.push(value.clone()),
+            "--template" => templates.push(PathBuf::from(value)),
+            other => bail!(
+                "Unknown eval option: {} (expected --model or --template)",
+                other
+            ),
+        }
+    }
+    if models.is_empty() {
+        models.push(SUMMARY_MODEL.to_string());
+    }
+    if templates.is_empty() {
+        templates.push(PathBuf::from(TEMPLATE_PATH));
+    }
+
+    let cases = load_cases(Path::new(EVAL_DIRECTORY), redactor)?;
+    if cases.is_empty() {
+        bail!(
+            "No eval cases in {}; add <name>.txt with <name>_reference.md",
+            EVAL_DIRECTORY
+        );
+    }
+    println!("Evaluating {} case(s)", cases.len());
+
+    let mut results = Vec::new();
+    for template_path in &templates {
+        let template = fs::read_to_string(template_path)
+            .with_context(|| format!("Failed to read template: {}", template_path.display()))?;
+        for model in &models {
+            let mut scores = Scores {
+                model: model.clone(),
+                template: template_path.clone(),
+                similarity: Vec::new(),
+                rubric: Vec::new(),
+            };
+            for case in &cases {
+                println!(
+                    "Scoring {} with {} and {}",
+                    case.name,
+                    model,
+                    template_path.display()
+                );
+                let summary = generate_summary(&case.transcript, &template, model, client).await?;
+                scores
+                    .similarity
+                    .push(similarity(&summary, &case.reference, client).await?);
+                scores.rubric.push(grade(case, &summary, client).await?);
+            }
+            results.push(scores);
+        }
+    }
+
+    let report = create_report(&cases, &results);
+    println!("\n{}", report);
+    let report_path = Path::new(EVAL_DIRECTORY).join(format!(
+        "eval_{}.md",
+        Local::now().format("%Y-%m-%d_%H%M%S")
+    ));
+    fs::write(&report_path, report)
+        .with_context(|| format!("Failed to write eval report: {}", report_path.display()))?;
+    println!("Wrote {}", report_path.display());
+    Ok(())
+}
+
+fn load_cases(directory: &Path, redactor: &Redactor) -> Result<Vec<Case>> {
+    let mut cases = Vec::new();
+    for entry in fs::read_dir(directory).context("Failed to read eval directory")? {
+        let path = entry.context("Failed to read eval entry")?.path();
+        if path.extension().and_then(|e| e.to_str()) != Some("txt") {
+            continue;
+        }
+        let Some(name) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        let reference_path = directory.join(format!("{}_reference.md", name));
+        if !reference_path.exists() {
+            println!("Skipping {}: no {}", name, reference_path.display());
+            continue;
+        }
+        // Both are sent to the provider, so both are redacted
+        let transcript = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read transcript: {}", path.display()))?;
+        let reference = fs::read_to_string(&reference_path)
+            .with_context(|| format!("Failed to read reference: {}", reference_path.display()))?;
+        cases.push(Case {
+            name: name.to_string(),
+            transcript: redactor.redact(&transcript).0,
+            reference: redactor.redact(&reference).0,
+        });
+    }
+    cases.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(cases)
+}
+
+/// Cosine similarity of the two texts' embeddings.
+async fn similarity(summary: &str, reference: &str, client: &Client<OpenAIConfig>) -> Result<f64> {
+    let request = CreateEmbeddingRequestArgs::default()
+        .model(EMBEDDING_MODEL)
+        .input(vec![summary.to_string(), reference.to_string()])
+        .build()
+        .context("Failed to build embedding request")?;
+    let response = client
+        .embeddings()
+        .create(request)
+        .await
+        .context("Embedding request failed")?;
+    let [a, b] = response.data.as_slice() else {
+        bail!("Expected 2 embeddings, got {}", response.data.len());
+    };
+    let dot: f64 = a
+        .embedding
+        .iter()
+        .zip(&b.embedding)
+        .map(|(x, y)| f64::from(x * y))
+        .sum();
+    let norm = |v: &[f32]| v.iter().map(|x| f64::from(x * x)).sum::<f64>().sqrt();
+    Ok(dot / (norm(&a.embedding) * norm(&b.embedding)))
+}
+
+/// Asks the grader model to score the summary 1-5 on each rubric criterion.
+async fn grade(
+    case: &Case,
+    summary: &str,
+    client: &Client<OpenAIConfig>,
+) -> Result<[f64; RUBRIC.len()]> {
+    let prompt = format!(
+        "You are grading an experiment summary against a reference written by the experimenter. \
+        Score the candidate from 1 (poor) to 5 (excellent) on each criterion:\n\
+        Coverage: includes the objectives, results, and conclusions the reference includes.\n\
+        Accuracy: states nothing the transcript or reference contradicts.\n\
+        Structure: follows the reference's sections and is easy to scan.\n\
+        Concision: no filler or repetition.\n\n\
+        Reply with exactly four lines of the form `Criterion: score`.\n\n\
+        Transcript:\n{}\n\nReference summary:\n{}\n\nCandidate summary:\n{}",
+        case.transcript, case.reference, summary
+    );
+    let reply = complete(&prompt, GRADER_MODEL, client)
+        .await?
+        .context("Grader returned no reply")?;
+    let mut scores = [0.0; RUBRIC.len()];
+    for (score, criterion) in scores.iter_mut().zip(RUBRIC) {
+        let pattern = Regex::new(&format!(r"(?i){}\W*(\d(\.\d+)?)", criterion))?;
+        *score = pattern
+            .captures(&reply)
+            .and_then(|c| c[1].parse().ok())
+            .with_context(|| format!("Grader reply has no {} score: {}", criterion, reply))?;
+    }
+    Ok(scores)
+}
+
+fn mean(values: impl Iterator<Item = f64>) -> f64 {
+    let (sum, count) = values.fold((0.0, 0), |(sum, count), v| (sum + v, count + 1));
+    if count == 0 {
+        0.0
+    } else {
+        sum / count as f64
+    }
+}
+
+fn create_report(cases: &[Case], results: &[Scores]) -> String {
+    let mut report = format!(
+        "# Summary Eval - {}\n\n{} case(s): {}\n\n",
+        Local::now().format("%Y-%m-%d %H:%M"),
+        cases.len(),
+        cases
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    );
+    report.push_str(&format!(
+        "| Model | Template | Similarity | {} | Rubric mean |\n|---|---|---|{}---|\n",
+        RUBRIC.join(" | "),
+        "---|".repeat(RUBRIC.len())
+    ));
+    for scores in results {
+        let criteria: Vec<f64> = (0..RUBRIC.len())
+            .map(|i| mean(scores.rubric.iter().map(|r| r[i])))
+            .collect();
+        report.push_str(&format!(
+            "| {} | {} | {:.3} | {} | {:.2} |\n",
+            scores.model,
+            scores.template.display(),
+            mean(scores.similarity.iter().copied()),
+            criteria
+                .iter()
+                .map(|c| format!("{:.2}", c))
+                .collect::<Vec<_>>()
+                .join(" | "),
+            mean(criteria.iter().copied())
+        ));
+    }
+    report
+}
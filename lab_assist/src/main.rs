@@ -5,22 +5,46 @@ use async_openai::types::{
     ChatCompletionRequestUserMessageContent, CreateChatCompletionRequestArgs,
 };
 use async_openai::Client;
-use chrono::Local;
 use dotenv::dotenv;
 use regex::Regex;
 use std::env;
 use std::fs::{self, File};
 use std::io::{BufReader, Read, Write};
 use std::path::Path;
-use tokio;
+
+mod campaign;
+mod document;
+mod eval;
+mod redact;
+mod templates;
+
+use redact::Redactor;
+
+const SUMMARY_MODEL: &str = "o1-mini";
+const TEMPLATE_PATH: &str = "template.md";
+
+/// One summarized transcript and the metadata its template was filled from.
+pub struct Experiment {
+    pub transcript: String,
+    pub summary: String,
+    pub metadata: templates::Metadata,
+}
 
 #[tokio::main]
 async fn main() -> Result<()> {
     dotenv().ok();
     let api_key = env::var("OPENAI_API_KEY").context("Missing OPENAI_API_KEY")?;
     let base_directory = "./Experiments/";
+    let redactions_file = env::var("REDACTIONS_FILE").unwrap_or_else(|_| "redactions.txt".into());
+    let redactor = Redactor::load(Path::new(&redactions_file))?;
+    // groundcontrol's campaign.sqlite, to index experiments in; unset skips indexing
+    let campaign_db = env::var("CAMPAIGN_DB").ok();
 
     let client = Client::with_config(OpenAIConfig::new().with_api_key(api_key));
+    let args: Vec<String> = env::args().skip(1).collect();
+    if args.first().map(String::as_str) == Some("eval") {
+        return eval::run(&args[1..], &client, &redactor).await;
+    }
     let folder_pattern =
         Regex::new(r"^(Jan|Feb|Mar|Apr|May|Jun|Jul|Aug|Sep|Oct|Nov|Dec) \d{1,2} \d{4}$")?;
 
@@ -31,28 +55,73 @@ async fn main() -> Result<()> {
             if let Some(folder_name) = path.file_name().and_then(|n| n.to_str()) {
                 if folder_pattern.is_match(folder_name) {
                     let markdown_file = path.join(format!("{}_summary.md", folder_name));
-                    if markdown_file.exists() {
-                        println!("Summary already exists for {}", folder_name);
+                    let transcripts = transcript_names(&path)?;
+                    // An existing summary is extended with transcripts added since it was written
+                    let existing = if markdown_file.exists() {
+                        let document = read_file_to_string(&markdown_file)?;
+                        let covered = document::summarized_transcripts(
+                            &document,
+                            &markdown_file,
+                            &transcripts,
+                        )?;
+                        Some((document, covered))
+                    } else {
+                        None
+                    };
+                    let new_transcripts: Vec<String> = transcripts
+                        .into_iter()
+                        .filter(|name| {
+                            existing
+                                .as_ref()
+                                .is_none_or(|(_, covered)| !covered.contains(name))
+                        })
+                        .collect();
+                    if new_transcripts.is_empty() {
+                        if existing.is_some() {
+                            println!("Summary is up to date for {}", folder_name);
+                        } else {
+                            println!("No transcripts found in {}", folder_name);
+                        }
                         continue;
                     }
 
                     println!("Processing folder: {}", folder_name);
-                    let summaries = process_experiment_files(&path, &client).await?;
-                    let experiment_count = summaries.len();
-
-                    if experiment_count > 0 {
-                        println!(
-                            "Found {} experiment transcript(s) in {}. Generating summary...",
-                            experiment_count, folder_name
-                        );
-                        let markdown_content = create_markdown_document(folder_name, &summaries);
-                        let mut file = File::create(&markdown_file).with_context(|| {
-                            format!("Failed to create summary file: {}", markdown_file.display())
-                        })?;
-                        file.write_all(markdown_content.as_bytes())?;
-                        println!("Generated summary for {}", folder_name);
+                    println!(
+                        "Found {} new experiment transcript(s) in {}. Generating summary...",
+                        new_transcripts.len(),
+                        folder_name
+                    );
+                    let experiments = process_experiment_files(
+                        &path,
+                        folder_name,
+                        &new_transcripts,
+                        &client,
+                        &redactor,
+                    )
+                    .await?;
+                    let markdown_content = match &existing {
+                        Some((document, covered)) => {
+                            document::update(document, covered, &experiments)
+                        }
+                        None => document::create(folder_name, &experiments),
+                    };
+                    let mut file = File::create(&markdown_file).with_context(|| {
+                        format!("Failed to create summary file: {}", markdown_file.display())
+                    })?;
+                    file.write_all(markdown_content.as_bytes())?;
+                    if existing.is_some() {
+                        println!("Updated summary for {}", folder_name);
                     } else {
-                        println!("No transcripts found in {}", folder_name);
+                        println!("Generated summary for {}", folder_name);
+                    }
+                    if let Some(db_path) = &campaign_db {
+                        campaign::export(
+                            Path::new(db_path),
+                            folder_name,
+                            &markdown_file,
+                            &experiments,
+                        )?;
+                        println!("Indexed {} experiment(s) in {}", experiments.len(), db_path);
                     }
                 } else {
                     println!(
@@ -69,23 +138,84 @@ async fn main() -> Result<()> {
 
 async fn process_experiment_files(
     directory: &Path,
+    date: &str,
+    transcripts: &[String],
     client: &Client<OpenAIConfig>,
-) -> Result<Vec<String>> {
+    redactor: &Redactor,
+) -> Result<Vec<Experiment>> {
+    let mut folder_metadata = templates::folder_metadata(directory)?;
+    folder_metadata
+        .entry("date".to_string())
+        .or_insert_with(|| date.to_string());
     let mut summaries = Vec::new();
-    for entry in fs::read_dir(directory).context("Failed to read experiment directory")? {
-        let entry = entry.context("Failed to read file entry")?;
-        let path = entry.path();
-        if path.extension().and_then(|e| e.to_str()) == Some("txt") {
-            let transcript = read_file_to_string(&path)?;
+    for name in transcripts {
+        let path = directory.join(name);
+        {
+            let contents = read_file_to_string(&path)?;
+            // Front matter in the transcript overrides the folder's metadata
+            let (front_matter, transcript) =
+                templates::split_front_matter(&contents, &path.display().to_string())?;
+            let mut metadata = folder_metadata.clone();
+            metadata.extend(front_matter);
+            let (template_path, template) = templates::load_template(&metadata)?;
+            println!(
+                "Using template {} for {}",
+                template_path.display(),
+                path.display()
+            );
+            let template = templates::fill(&template, &metadata);
+            // Only redacted text is sent; the report mapping placeholders back stays here
+            let (redacted, redactions) = redactor.redact_all(&[transcript, &template]);
+            let [transcript, template] = [&redacted[0], &redacted[1]];
+            let stem = path
+                .file_stem()
+                .and_then(|s| s.to_str())
+                .unwrap_or("transcript");
+            let report_path = directory.join(format!("{}_redactions.md", stem));
+            fs::write(&report_path, redact::create_report(&path, &redactions)).with_context(
+                || {
+                    format!(
+                        "Failed to write redaction report: {}",
+                        report_path.display()
+                    )
+                },
+            )?;
+            println!(
+                "Redacted {} string(s) from {}",
+                redactions.iter().map(|r| r.count).sum::<usize>(),
+                path.display()
+            );
             println!("Sending request for transcript: {}", path.display());
-            let summary = generate_summary(&transcript, client).await?;
+            let summary = generate_summary(transcript, template, SUMMARY_MODEL, client).await?;
             println!("Received summary for transcript: {}", path.display());
-            summaries.push(summary);
+            summaries.push(Experiment {
+                transcript: name.clone(),
+                summary,
+                metadata,
+            });
         }
     }
     Ok(summaries)
 }
 
+/// Transcript file names in an experiment folder, in name order.
+fn transcript_names(directory: &Path) -> Result<Vec<String>> {
+    let mut names = Vec::new();
+    for entry in fs::read_dir(directory).context("Failed to read experiment directory")? {
+        let path = entry.context("Failed to read file entry")?.path();
+        let Some(name) = path.file_name().and_then(|n| n.to_str()) else {
+            continue;
+        };
+        if path.extension().and_then(|e| e.to_str()) == Some("txt")
+            && name != templates::METADATA_FILE
+        {
+            names.push(name.to_string());
+        }
+    }
+    names.sort();
+    Ok(names)
+}
+
 fn read_file_to_string(path: &Path) -> Result<String> {
     let file =
         File::open(path).with_context(|| format!("Failed to open file: {}", path.display()))?;
@@ -97,10 +227,12 @@ fn read_file_to_string(path: &Path) -> Result<String> {
     Ok(contents)
 }
 
-async fn generate_summary(transcript: &str, client: &Client<OpenAIConfig>) -> Result<String> {
-    let template_path = Path::new("template.md");
-    let template = fs::read_to_string(template_path).context("Failed to read template.md")?;
-
+async fn generate_summary(
+    transcript: &str,
+    template: &str,
+    model: &str,
+    client: &Client<OpenAIConfig>,
+) -> Result<String> {
     let prompt = format!(
         "You are a helpful lab assistant. Your task is to analyze and summarize experiment transcripts. \
         Use the following Markdown template for the summary:\n\n\
@@ -110,16 +242,28 @@ async fn generate_summary(transcript: &str, client: &Client<OpenAIConfig>) -> Re
         template, transcript
     );
 
+    println!("Sending chat completion request...");
+    let summary = complete(&prompt, model, client)
+        .await?
+        .unwrap_or_else(|| "No summary generated.".to_string());
+    Ok(summary.trim().to_string())
+}
+
+/// Sends a single user message and returns the first choice's reply, if any.
+async fn complete(
+    prompt: &str,
+    model: &str,
+    client: &Client<OpenAIConfig>,
+) -> Result<Option<String>> {
     let messages = vec![ChatCompletionRequestMessage::User(
         ChatCompletionRequestUserMessage {
-            content: ChatCompletionRequestUserMessageContent::Text(prompt),
+            content: ChatCompletionRequestUserMessageContent::Text(prompt.to_string()),
             name: None,
         },
     )];
 
-    println!("Sending chat completion request...");
     let request = CreateChatCompletionRequestArgs::default()
-        .model("o1-mini")
+        .model(model)
         .messages(messages)
         .build()
         .context("Failed to build chat completion request")?;
@@ -129,25 +273,8 @@ async fn generate_summary(transcript: &str, client: &Client<OpenAIConfig>) -> Re
         .create(request)
         .await
         .context("API request failed")?;
-    let summary = response
+    Ok(response
         .choices
-        .get(0)
-        .and_then(|choice| choice.message.content.clone())
-        .unwrap_or_else(|| "No summary generated.".to_string());
-
-    Ok(summary.trim().to_string())
-}
-
-fn create_markdown_document(date: &str, summaries: &[String]) -> String {
-    let mut markdown_content = format!("# Daily Experiment Summary - {}\n\n", date);
-    for (i, summary) in summaries.iter().enumerate() {
-        markdown_content.push_str(&format!(
-            "## Experiment {}\n\n{}\n\n---\n\n",
-            i + 1,
-            summary
-        ));
-    }
-    let generation_date = Local::now().format("%Y-%m-%d").to_string();
-    markdown_content.push_str(&format!("*Generated on {}*", generation_date));
-    markdown_content
+        .first()
+        .and_then(|choice| choice.message.content.clone()))
 }
@@ -0,0 +1,244 @@
+use anyhow::{bail, Context, Result};
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+/// Strips names, locations, and other sensitive strings from transcripts before
+/// they leave the machine.
+///
+/// Terms come from the redactions file, one `category: term` per line, with `#`
+/// starting a comment. Email addresses, phone numbers, and GPS coordinates are
+/// always redacted. Each distinct match is replaced by a numbered placeholder
+/// such as `[NAME-1]`, so the summary can still tell people apart.
+pub struct Redactor {
+    matchers: Vec<(String, Regex)>,
+    // Placeholders already written, which later matchers must leave alone
+    placeholder: Regex,
+}
+
+/// One distinct string that was replaced, and how often.
+pub struct Redaction {
+    pub category: String,
+    pub original: String,
+    pub placeholder: String,
+    pub count: usize,
+}
+
+impl Redactor {
+    /// Loads the terms in `path`; a missing file leaves only the built-in patterns.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut terms = Vec::new();
+        if path.exists() {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("Failed to read redactions file: {}", path.display()))?;
+            for (number, line) in contents.lines().enumerate() {
+                let line = line.split('#').next().unwrap_or_default().trim();
+                if line.is_empty() {
+                    continue;
+                }
+                let Some((category, term)) = line.split_once(':') else {
+                    bail!(
+                        "{} line {}: expected `category: term`",
+                        path.display(),
+                        number + 1
+                    );
+                };
+                let (category, term) = (category.trim(), term.trim());
+                if category.is_empty() || term.is_empty() {
+                    bail!(
+                        "{} line {}: expected `category: term`",
+                        path.display(),
+                        number + 1
+                    );
+                }
+                terms.push((category.to_uppercase(), term.to_string()));
+            }
+        } else {
+            println!(
+                "No redactions file at {}; redacting only emails, phone numbers, and coordinates",
+                path.display()
+            );
+        }
+        // Longer terms first, so a full name wins over the first name alone
+        terms.sort_by_key(|(_, term)| std::cmp::Reverse(term.len()));
+
+        let mut matchers = Vec::new();
+        for (category, term) in terms {
+            // Word boundaries only where the term itself starts or ends with a word character
+            let boundary = |c: Option<char>| match c {
+                Some(c) if c.is_alphanumeric() || c == '_' => r"\b",
+                _ => "",
+            };
+            let pattern = format!(
+                r"(?i){}{}{}",
+                boundary(term.chars().next()),
+                regex::escape(&term),
+                boundary(term.chars().last())
+            );
+            matchers.push((category, Regex::new(&pattern)?));
+        }
+        for (category, pattern) in [
+            ("EMAIL", r"[\w.+-]+@[\w-]+(\.[\w-]+)+"),
+            (
+                "PHONE",
+                r"(\+\d{1,3}[\s.-]?)?\(?\b\d{3}\)?[\s.-]\d{3}[\s.-]\d{4}\b",
+            ),
+            ("LOCATION", r"-?\b\d{1,2}\.\d{4,},\s*-?\d{1,3}\.\d{4,}\b"),
+        ] {
+            matchers.push((category.to_string(), Regex::new(pattern)?));
+        }
+        Ok(Self {
+            matchers,
+            placeholder: Regex::new(r"\[[^\[\]]+-\d+\]")?,
+        })
+    }
+
+    /// Returns the redacted text and every replacement made, in order of first appearance.
+    pub fn redact(&self, text: &str) -> (String, Vec<Redaction>) {
+        let (mut redacted, redactions) = self.redact_all(&[text]);
+        (redacted.remove(0), redactions)
+    }
+
+    /// Redacts several texts sent together, so a string gets the same placeholder in each.
+    ///
+    /// Placeholders written by an earlier term or pattern are never matched again.
+    pub fn redact_all(&self, texts: &[&str]) -> (Vec<String>, Vec<Redaction>) {
+        let mut redactions: Vec<Redaction> = Vec::new();
+        // Placeholder index per lowercased original, so case variants share one
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut redacted: Vec<String> = texts.iter().map(|text| text.to_string()).collect();
+        for (category, regex) in &self.matchers {
+            for text in &mut redacted {
+                let placeholders: Vec<_> = self
+                    .placeholder
+                    .find_iter(text)
+                    .map(|m| m.range())
+                    .collect();
+                *text = regex
+                    .replace_all(text, |caps: &Captures| {
+                        let original = &caps[0];
+                        let span = caps.get(0).unwrap().range();
+                        if placeholders
+                            .iter()
+                            .any(|p| span.start < p.end && p.start < span.end)
+                        {
+                            return original.to_string();
+                        }
+                        let index = *seen.entry(original.to_lowercase()).or_insert_with(|| {
+                            let number = redactions
+                                .iter()
+                                .filter(|r| &r.category == category)
+                                .count()
+                                + 1;
+                            redactions.push(Redaction {
+                                category: category.clone(),
+                                original: original.to_string(),
+                                placeholder: format!("[{}-{}]", category, number),
+                                count: 0,
+                            });
+                            redactions.len() - 1
+                        });
+                        redactions[index].count += 1;
+                        redactions[index].placeholder.clone()
+                    })
+                    .into_owned();
+            }
+        }
+        (redacted, redactions)
+    }
+}
+
+/// Formats the replacements made in one transcript as a Markdown report.
+pub fn create_report(transcript: &Path, redactions: &[Redaction]) -> String {
+    let mut report = format!("# Redaction Report - {}\n\n", transcript.display());
+    if redactions.is_empty() {
+        report.push_str("Nothing was redacted.\n");
+        return report;
+    }
+    report.push_str("| Placeholder | Original | Occurrences |\n|---|---|---|\n");
+    for redaction in redactions {
+        report.push_str(&format!(
+            "| {} | {} | {} |\n",
+            redaction.placeholder, redaction.original, redaction.count
+        ));
+    }
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    fn redactor(terms: &str) -> Redactor {
+        static FILES: AtomicUsize = AtomicUsize::new(0);
+        let path = std::env::temp_dir().join(format!(
+            "lab_assist_redactions_{}_{}.txt",
+            std::process::id(),
+            FILES.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::write(&path, terms).unwrap();
+        let redactor = Redactor::load(&path);
+        let _ = fs::remove_file(&path);
+        redactor.unwrap()
+    }
+
+    #[test]
+    fn full_names_win_over_first_names() {
+        let redactor = redactor("name: Sam\nname: Sam Carter\n");
+        let (text, redactions) = redactor.redact("Sam Carter opened the valve, then Sam closed it");
+        assert_eq!(text, "[NAME-1] opened the valve, then [NAME-2] closed it");
+        assert_eq!(redactions[0].original, "Sam Carter");
+        assert_eq!(redactions[1].original, "Sam");
+    }
+
+    #[test]
+    fn case_variants_share_a_placeholder() {
+        let redactor = redactor("# crew\nname: Sam Carter\nsite: Mojave  # test site\n");
+        let (texts, redactions) =
+            redactor.redact_all(&["SAM CARTER at mojave", "sam carter left Mojave"]);
+        assert_eq!(texts, ["[NAME-1] at [SITE-1]", "[NAME-1] left [SITE-1]"]);
+        assert_eq!(redactions.len(), 2);
+        assert!(redactions.iter().all(|r| r.count == 2));
+    }
+
+    #[test]
+    fn built_in_patterns_need_no_terms() {
+        let redactor = redactor("");
+        let (text, redactions) = redactor.redact(
+            "Mail sam.carter+ksi@example.co.uk or call +1 555-123-4567, we're at 35.0527, -117.8122",
+        );
+        assert_eq!(
+            text,
+            "Mail [EMAIL-1] or call [PHONE-1], we're at [LOCATION-1]"
+        );
+        let categories: Vec<&str> = redactions.iter().map(|r| r.category.as_str()).collect();
+        assert_eq!(categories, ["EMAIL", "PHONE", "LOCATION"]);
+        // Test numbers aren't phone numbers or coordinates
+        assert_eq!(
+            redactor.redact("Run 12, 450.5 psi at 3.25 s").0,
+            "Run 12, 450.5 psi at 3.25 s"
+        );
+    }
+
+    #[test]
+    fn placeholders_are_not_redacted_again() {
+        let redactor = redactor("name: Sam Carter\nsite: Name\nsite: Phone\n");
+        let (text, redactions) = redactor.redact("Sam Carter, 555-123-4567, wrote a name");
+        assert_eq!(text, "[NAME-1], [PHONE-1], wrote a [SITE-1]");
+        assert_eq!(redactions.len(), 3);
+    }
+
+    #[test]
+    fn malformed_lines_are_refused() {
+        let path = std::env::temp_dir().join(format!(
+            "lab_assist_bad_redactions_{}.txt",
+            std::process::id()
+        ));
+        fs::write(&path, "Sam Carter\n").unwrap();
+        let result = Redactor::load(&path);
+        let _ = fs::remove_file(&path);
+        assert!(result.is_err());
+    }
+}
@@ -0,0 +1,116 @@
+use anyhow::{bail, Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::TEMPLATE_PATH;
+
+/// Per-folder metadata file; its `type` selects the template for every transcript in the folder.
+pub const METADATA_FILE: &str = "metadata.txt";
+const TEMPLATE_DIRECTORY: &str = "templates";
+
+/// `key: value` pairs describing an experiment, filled into `{{key}}` in its template.
+pub type Metadata = BTreeMap<String, String>;
+
+/// Parses `key: value` lines, ignoring blanks and `#` comments. Keys are lowercased.
+pub fn parse_metadata(text: &str, source: &str) -> Result<Metadata> {
+    let mut metadata = Metadata::new();
+    for (number, line) in text.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or_default().trim();
+        if line.is_empty() {
+            continue;
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            bail!("{} line {}: expected `key: value`", source, number + 1);
+        };
+        metadata.insert(key.trim().to_lowercase(), value.trim().to_string());
+    }
+    Ok(metadata)
+}
+
+/// Metadata from the folder's metadata file, or none if it has no file.
+pub fn folder_metadata(directory: &Path) -> Result<Metadata> {
+    let path = directory.join(METADATA_FILE);
+    if !path.exists() {
+        return Ok(Metadata::new());
+    }
+    let text = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read metadata: {}", path.display()))?;
+    parse_metadata(&text, &path.display().to_string())
+}
+
+/// Splits a `---` delimited front-matter block off the top of a transcript.
+pub fn split_front_matter<'a>(transcript: &'a str, source: &str) -> Result<(Metadata, &'a str)> {
+    let Some(rest) = transcript
+        .strip_prefix("---\n")
+        .or_else(|| transcript.strip_prefix("---\r\n"))
+    else {
+        return Ok((Metadata::new(), transcript));
+    };
+    let Some(end) = rest.find("\n---") else {
+        bail!("{}: front matter has no closing ---", source);
+    };
+    let body = rest[end + 4..].trim_start_matches(['\r', '\n']);
+    Ok((parse_metadata(&rest[..end], source)?, body))
+}
+
+/// The template for the metadata's `type`, e.g. `static_fire` reads
+/// templates/static_fire.md; without a type, the default template.md.
+pub fn load_template(metadata: &Metadata) -> Result<(PathBuf, String)> {
+    let path = match metadata.get("type") {
+        Some(kind) => {
+            let path = Path::new(TEMPLATE_DIRECTORY).join(format!("{}.md", kind));
+            if !path.exists() {
+                bail!(
+                    "No template for experiment type {:?}; expected {} (available: {})",
+                    kind,
+                    path.display(),
+                    available_types().join(", ")
+                );
+            }
+            path
+        }
+        None => PathBuf::from(TEMPLATE_PATH),
+    };
+    let template = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read template: {}", path.display()))?;
+    Ok((path, template))
+}
+
+fn available_types() -> Vec<String> {
+    let mut types: Vec<String> = fs::read_dir(TEMPLATE_DIRECTORY)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .filter_map(|entry| {
+            let path = entry.path();
+            (path.extension().and_then(|e| e.to_str()) == Some("md"))
+                .then(|| path.file_stem()?.to_str().map(str::to_string))
+                .flatten()
+        })
+        .collect();
+    types.sort();
+    types
+}
+
+/// Replaces each `{{key}}` with its metadata value; a key with no value reads "not recorded".
+pub fn fill(template: &str, metadata: &Metadata) -> String {
+    let mut filled = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(end) = rest[start..].find("}}") else {
+            break;
+        };
+        filled.push_str(&rest[..start]);
+        let key = rest[start + 2..start + end].trim().to_lowercase();
+        filled.push_str(
+            metadata
+                .get(&key)
+                .map(String::as_str)
+                .unwrap_or("not recorded"),
+        );
+        rest = &rest[start + end + 2..];
+    }
+    filled.push_str(rest);
+    filled
+}